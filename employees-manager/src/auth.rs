@@ -80,6 +80,53 @@ impl AuthInfo for JWTAuthClaim {
     }
 }
 
+/// Audience of the invite tokens, it prevents using them where other tokens
+/// signed with the same key are expected
+const INVITE_TOKEN_AUDIENCE: &str = "invite";
+
+/// Struct containing information encoded inside the token sent by email
+/// to invite a person that is not registered yet in a Company
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InviteTokenClaim {
+    pub exp: usize,
+    pub aud: String,
+    pub invite_id: DocumentId,
+    pub email: String,
+}
+
+impl InviteTokenClaim {
+    pub fn new(exp: usize, invite_id: DocumentId, email: String) -> Self {
+        Self {
+            exp,
+            aud: INVITE_TOKEN_AUDIENCE.into(),
+            invite_id,
+            email,
+        }
+    }
+
+    pub fn build_token(&self) -> Result<String, AuthError> {
+        encode(
+            &Header::default(),
+            &self,
+            &ENVIRONMENT.authentication.jwt_encoding,
+        )
+        .map_err(|_| AuthError::TokenCreation)
+    }
+
+    /// Decode the token verifying its signature, expiration and audience
+    pub fn from_token(token: &str) -> Result<Self, AuthError> {
+        let mut validation = Validation::default();
+        validation.set_audience(&[INVITE_TOKEN_AUDIENCE]);
+        validation.set_required_spec_claims(&["exp", "aud"]);
+        decode::<InviteTokenClaim>(token, &ENVIRONMENT.authentication.jwt_decoding, &validation)
+            .map(|token_data| token_data.claims)
+            .map_err(|e| {
+                tracing::error!("Got error {}", e);
+                AuthError::InvalidToken
+            })
+    }
+}
+
 /// Struct containing api key authentication
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct APIKeyAuthClaim {
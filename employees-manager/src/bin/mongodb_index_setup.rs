@@ -11,6 +11,18 @@ async fn create_index<T: 'static + DatabaseDocument>(keys: Document) {
     }
 }
 
+async fn create_unique_index<T: 'static + DatabaseDocument>(keys: Document) {
+    info!(
+        "Creating unique indexes for {} collection",
+        T::collection_name()
+    );
+    if let Err(e) = T::set_unique_indexes(keys).await {
+        error!("Got error {}", e);
+    } else {
+        info!("Index created");
+    }
+}
+
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt()
@@ -19,4 +31,17 @@ async fn main() {
         .init();
 
     create_index::<db_entities::UserCompanyAssignment>(doc! {"company_id": 1, "user_id": 1}).await;
+    create_index::<db_entities::InviteAddCompany>(doc! {"status": 1, "expiration_date": 1}).await;
+    create_index::<db_entities::CompanyCalendarDay>(doc! {"company_id": 1, "date": 1}).await;
+    create_index::<db_entities::Invoice>(doc! {"company_id": 1, "client_id": 1}).await;
+    create_unique_index::<db_entities::Invoice>(doc! {"company_id": 1, "number": 1}).await;
+    create_unique_index::<db_entities::InvoiceSequence>(doc! {"company_id": 1}).await;
+    create_index::<db_entities::ProjectRoleAssignment>(doc! {"project_id": 1, "user_id": 1}).await;
+    create_index::<db_entities::CompanyEmployeeRequest>(doc! {"company_id": 1, "date": 1}).await;
+    create_index::<db_entities::CustomCompanyRole>(doc! {"company_id": 1, "user_ids": 1}).await;
+    create_index::<db_entities::ReportingLine>(doc! {"company_id": 1, "user_id": 1}).await;
+    create_index::<db_entities::Department>(doc! {"company_id": 1, "user_ids": 1}).await;
+    create_index::<db_entities::CorporateGroupJoinRequest>(doc! {"group_id": 1, "answer": 1}).await;
+    create_index::<db_entities::CorporateGroupAuditEntry>(doc! {"group_id": 1, "created_at": 1})
+        .await;
 }
@@ -1,8 +1,11 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc, Weekday};
 use serde::Deserialize;
 
 use crate::{
-    enums::{CompanyRole, WorkingDayType},
+    enums::{
+        CompanyRole, EmployeeRequest, InvoiceStatus, LeaveAccrualType, Permission, ProjectRole,
+        TimesheetCopyPeriod, TimesheetExportFormat, WorkingDayType,
+    },
     DocumentId,
 };
 
@@ -68,11 +71,45 @@ pub struct InviteUserToCompany {
     pub project_ids: Vec<DocumentId>,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnershipTransferAnswer {
+    pub accept: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NominateCompanyOwner {
+    pub user_id: DocumentId,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InviteUserToCompanyByEmail {
+    pub email: String,
+    pub role: CompanyRole,
+    pub job_title: String,
+    pub project_ids: Vec<DocumentId>,
+}
+
+/// Registration payload of a person invited by email
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterWithInvite {
+    /// token received inside the invitation email
+    pub token: String,
+    pub username: String,
+    pub password: String,
+    pub name: String,
+    pub surname: String,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateCompanyProject {
     pub name: String,
     pub code: String,
+    pub client_id: Option<DocumentId>,
 }
 
 #[derive(Deserialize)]
@@ -81,6 +118,7 @@ pub struct EditCompanyProject {
     pub name: String,
     pub code: String,
     pub active: bool,
+    pub client_id: Option<DocumentId>,
 }
 
 #[derive(Deserialize)]
@@ -89,6 +127,12 @@ pub struct ChangeProjectAllocations {
     pub user_ids: Vec<DocumentId>,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetProjectRole {
+    pub role: ProjectRole,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChangeProjectAllocationsForUser {
@@ -100,6 +144,8 @@ pub struct ChangeProjectAllocationsForUser {
 pub struct NewProjectActivity {
     pub name: String,
     pub description: String,
+    #[serde(default)]
+    pub billable: bool,
 }
 
 #[derive(Deserialize)]
@@ -107,6 +153,8 @@ pub struct NewProjectActivity {
 pub struct EditProjectActivity {
     pub name: String,
     pub description: String,
+    #[serde(default)]
+    pub billable: bool,
 }
 
 #[derive(Deserialize)]
@@ -138,13 +186,188 @@ pub struct CreateTimesheetDay {
     pub permit_hours: u32,
     pub working_type: WorkingDayType,
     pub activities: Vec<TimesheetActivityHours>,
+    /// allows to log activities on days that are not working days for the company
+    #[serde(default)]
+    pub overtime: bool,
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct GetUserTimesheetDays {
+pub struct GetCompanyCalendarDays {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportNationalHolidays {
+    /// ISO 3166-1 alpha-2 code of a bundled national rule set
+    pub country_code: String,
     pub year: i32,
-    pub month: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportCompanyCalendarIcs {
+    /// working type assigned to the imported days, Holiday when missing
+    pub day_type: Option<WorkingDayType>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddCompanyClosureDay {
+    pub date: NaiveDate,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetLeaveBalance {
+    pub yearly_allowance_hours: u32,
+    pub accrual_type: LeaveAccrualType,
+    pub carry_over_cap_hours: u32,
+    pub carry_over_expiry_month: Option<u32>,
+    pub start_date: NaiveDate,
+    #[serde(default)]
+    pub opening_balance_hours: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateLeaveRequest {
+    pub request: EmployeeRequest,
+    pub date: NaiveDate,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetLeaveRequests {
+    #[serde(default)]
+    pub only_awaiting: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnswerLeaveRequest {
+    pub accepted: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCustomRole {
+    pub name: String,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditCustomRole {
+    pub name: String,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetCustomRoleUsers {
+    pub user_ids: Vec<DocumentId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetUserManager {
+    pub manager_id: Option<DocumentId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUserReports {
+    #[serde(default)]
+    pub indirect: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetTeamLeaveCalendar {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateDepartment {
+    pub name: String,
+    #[serde(default)]
+    pub lead_ids: Vec<DocumentId>,
+    #[serde(default)]
+    pub default_project_ids: Vec<DocumentId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditDepartment {
+    pub name: String,
+    pub lead_ids: Vec<DocumentId>,
+    pub default_project_ids: Vec<DocumentId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetDepartmentMembers {
+    pub user_ids: Vec<DocumentId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetManagementTeam {
+    pub user_ids: Vec<DocumentId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetProjectBudget {
+    pub total_hours: u32,
+    #[serde(default)]
+    pub activity_budgets: Vec<ActivityHourBudget>,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// percentages of the total hours
+    #[serde(default)]
+    pub notification_thresholds: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityHourBudget {
+    pub activity_id: DocumentId,
+    pub hours: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetLeaveBalanceHistory {
+    pub year: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditCompanySettings {
+    pub daily_hours: u32,
+    pub working_weekdays: Vec<Weekday>,
+    pub timezone: String,
+    pub max_daily_hours: u32,
+    pub allowed_working_types: Vec<WorkingDayType>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUserTimesheetDays {
+    /// calendar year of the month, or ISO year of the week
+    pub year: Option<i32>,
+    pub month: Option<u32>,
+    /// ISO week number
+    pub week: Option<u32>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
 }
 
 #[derive(Deserialize)]
@@ -161,9 +384,155 @@ pub struct EditCorporateGroup {
     pub company_ids: Vec<DocumentId>,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetCorporateGroupAdmins {
+    pub user_ids: Vec<DocumentId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCorporateGroupJoinRequest {
+    pub company_id: DocumentId,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnswerCorporateGroupJoinRequest {
+    pub accepted: bool,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetUserTimesheetExport {
     pub year: i32,
     pub month: u32,
+    #[serde(default)]
+    pub format: TimesheetExportFormat,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetCompanyTimesheetExport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub project_id: Option<DocumentId>,
+    pub user_id: Option<DocumentId>,
+    pub department_id: Option<DocumentId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportTimesheet {
+    pub format: TimesheetExportFormat,
+    /// when true the file is only validated
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetTimesheetConsistencyReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub department_id: Option<DocumentId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTimesheetTemplate {
+    pub name: String,
+    pub working_type: WorkingDayType,
+    pub activities: Vec<TimesheetActivityHours>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyTimesheetTemplate {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// when true the existing days are replaced
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyTimesheetDays {
+    pub period: TimesheetCopyPeriod,
+    /// day, or any day of the week, to fill
+    pub date: NaiveDate,
+    /// when true the existing days are replaced
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateClient {
+    pub name: String,
+    pub currency: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditClient {
+    pub name: String,
+    pub currency: String,
+    pub active: bool,
+}
+
+/// Only one of project, activity and job title must be set
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateBillableRate {
+    pub project_id: Option<DocumentId>,
+    pub activity_id: Option<DocumentId>,
+    pub job_title: Option<String>,
+    pub hourly_rate: f64,
+    pub effective_from: NaiveDate,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetBillableAmounts {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub client_id: Option<DocumentId>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateInvoiceDraft {
+    pub client_id: DocumentId,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetInvoices {
+    pub client_id: Option<DocumentId>,
+    pub status: Option<InvoiceStatus>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetInvoiceStatus {
+    pub status: InvoiceStatus,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetGroupReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchGroupDirectory {
+    pub name: Option<String>,
+    pub job_title: Option<String>,
+    pub company_id: Option<DocumentId>,
 }
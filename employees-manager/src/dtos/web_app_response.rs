@@ -1,11 +1,14 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc, Weekday};
 use derive_builder::Builder;
 use mongodb::bson::oid::ObjectId;
 use serde::Serialize;
 
 use crate::{
-    enums::{CompanyRole, NotificationType, WorkingDayType},
-    error::ServiceAppError,
+    enums::{
+        CalendarDaySource, CompanyRole, CorporateGroupAction, EmployeeRequest, InviteStatus,
+        InvoiceStatus, LeaveAccrualType, NotificationType, Permission, ProjectRole, WorkingDayType,
+    },
+    error::{ServiceAppError, ValidationViolation},
     model::{db_entities, internal},
     service::db::DatabaseDocument,
 };
@@ -240,6 +243,67 @@ impl From<internal::InvitedUserInCompanyInfo> for InvitedUserInCompanyInfo {
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvitationHistoryEntry {
+    invite_id: String,
+    invited_user_id: Option<String>,
+    invited_username: String,
+    invited_email: Option<String>,
+    inviting_user_id: String,
+    inviting_username: String,
+    role: CompanyRole,
+    job_title: String,
+    status: InviteStatus,
+    created_at: DateTime<Utc>,
+    expiration_date: DateTime<Utc>,
+    closed_at: Option<DateTime<Utc>>,
+}
+
+impl From<internal::InvitationHistoryEntry> for InvitationHistoryEntry {
+    fn from(value: internal::InvitationHistoryEntry) -> Self {
+        Self {
+            invite_id: value.invite_id.to_hex(),
+            invited_user_id: value.invited_user_id.map(|id| id.to_hex()),
+            invited_username: value.invited_username,
+            invited_email: value.invited_email,
+            inviting_user_id: value.inviting_user_id.to_hex(),
+            inviting_username: value.inviting_username,
+            role: value.role,
+            job_title: value.job_title,
+            status: value.status,
+            created_at: value.created_at,
+            expiration_date: value.expiration_date,
+            closed_at: value.closed_at,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailInviteInfo {
+    email: String,
+    company_id: String,
+    company_name: String,
+    role: CompanyRole,
+    job_title: String,
+    expiration_date: DateTime<Utc>,
+}
+
+impl From<(db_entities::InviteAddCompanyByEmail, String)> for EmailInviteInfo {
+    fn from(value: (db_entities::InviteAddCompanyByEmail, String)) -> Self {
+        let (invite, company_name) = value;
+        Self {
+            email: invite.email().clone(),
+            company_id: invite.company_id().to_hex(),
+            company_name,
+            role: *invite.company_role(),
+            job_title: invite.job_title().clone(),
+            expiration_date: *invite.expiration_date(),
+        }
+    }
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CompanyProjectInfo {
@@ -247,6 +311,7 @@ pub struct CompanyProjectInfo {
     name: String,
     code: String,
     active: bool,
+    client_id: Option<String>,
 }
 
 impl TryFrom<db_entities::CompanyProject> for CompanyProjectInfo {
@@ -259,6 +324,7 @@ impl TryFrom<db_entities::CompanyProject> for CompanyProjectInfo {
                 name: value.name().into(),
                 code: value.code().into(),
                 active: *value.active(),
+                client_id: value.client_id().map(|client_id| client_id.to_hex()),
             })
         } else {
             Err(ServiceAppError::ResponseBuildError(
@@ -274,6 +340,7 @@ pub struct ProjectActivityInfo {
     id: String,
     name: String,
     description: String,
+    billable: bool,
 }
 
 impl TryFrom<db_entities::ProjectActivity> for ProjectActivityInfo {
@@ -285,6 +352,7 @@ impl TryFrom<db_entities::ProjectActivity> for ProjectActivityInfo {
                 id: id.to_hex(),
                 name: value.name().into(),
                 description: value.description().into(),
+                billable: *value.billable(),
             })
         } else {
             Err(ServiceAppError::ResponseBuildError(
@@ -315,6 +383,343 @@ pub struct TimesheetDay {
     pub permit_hours: u32,
     pub working_type: WorkingDayType,
     pub activities: Vec<TimesheetActivityHours>,
+    pub overtime: bool,
+    /// true when the day comes from the company calendar and it has not been saved yet
+    pub prefilled: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaveBalanceMonth {
+    year: i32,
+    month: u32,
+    carried_over: f64,
+    accrued: f64,
+    consumed: f64,
+    expired: f64,
+    balance: f64,
+}
+
+impl From<internal::LeaveBalanceMonth> for LeaveBalanceMonth {
+    fn from(value: internal::LeaveBalanceMonth) -> Self {
+        Self {
+            year: value.year,
+            month: value.month,
+            carried_over: value.carried_over,
+            accrued: value.accrued,
+            consumed: value.consumed,
+            expired: value.expired,
+            balance: value.balance,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepartmentInfo {
+    id: String,
+    name: String,
+    lead_ids: Vec<String>,
+    user_ids: Vec<String>,
+    default_project_ids: Vec<String>,
+}
+
+impl TryFrom<db_entities::Department> for DepartmentInfo {
+    type Error = ServiceAppError;
+
+    fn try_from(value: db_entities::Department) -> Result<Self, Self::Error> {
+        let to_hex = |ids: &Vec<ObjectId>| ids.iter().map(|id| id.to_hex()).collect();
+        if let Some(id) = value.get_id() {
+            Ok(Self {
+                id: id.to_hex(),
+                name: value.name().clone(),
+                lead_ids: to_hex(value.lead_ids()),
+                user_ids: to_hex(value.user_ids()),
+                default_project_ids: to_hex(value.default_project_ids()),
+            })
+        } else {
+            Err(ServiceAppError::ResponseBuildError(
+                "Document Id should exist for Department".into(),
+            ))
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrgChartNode {
+    #[serde(flatten)]
+    user: UserInCompanyInfo,
+    reports: Vec<OrgChartNode>,
+}
+
+impl From<internal::OrgChartNode> for OrgChartNode {
+    fn from(value: internal::OrgChartNode) -> Self {
+        Self {
+            user: UserInCompanyInfo::from(value.user),
+            reports: value.reports.into_iter().map(OrgChartNode::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamLeaveDay {
+    user_id: String,
+    date: NaiveDate,
+    working_type: Option<WorkingDayType>,
+    request: Option<EmployeeRequest>,
+}
+
+impl From<internal::TeamLeaveDay> for TeamLeaveDay {
+    fn from(value: internal::TeamLeaveDay) -> Self {
+        Self {
+            user_id: value.user_id.to_hex(),
+            date: value.date,
+            working_type: value.working_type,
+            request: value.request,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyRoleDefinition {
+    /// None for the built-in roles
+    id: Option<String>,
+    name: String,
+    builtin: bool,
+    permissions: Vec<Permission>,
+    user_ids: Vec<String>,
+}
+
+impl From<internal::CompanyRoleDefinition> for CompanyRoleDefinition {
+    fn from(value: internal::CompanyRoleDefinition) -> Self {
+        Self {
+            id: value.id.map(|id| id.to_hex()),
+            name: value.name,
+            builtin: value.id.is_none(),
+            permissions: value.permissions,
+            user_ids: value.user_ids.iter().map(|id| id.to_hex()).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaveRequest {
+    id: String,
+    user_id: String,
+    request: EmployeeRequest,
+    date: Option<NaiveDate>,
+}
+
+impl TryFrom<db_entities::CompanyEmployeeRequest> for LeaveRequest {
+    type Error = ServiceAppError;
+
+    fn try_from(value: db_entities::CompanyEmployeeRequest) -> Result<Self, Self::Error> {
+        if let Some(id) = value.get_id() {
+            Ok(Self {
+                id: id.to_hex(),
+                user_id: value.user_id().to_hex(),
+                request: *value.request(),
+                date: value.date().map(|date| date.date_naive()),
+            })
+        } else {
+            Err(ServiceAppError::ResponseBuildError(
+                "Document Id should exist for CompanyEmployeeRequest".into(),
+            ))
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaveBalanceHistory {
+    user_id: String,
+    company_id: String,
+    yearly_allowance_hours: u32,
+    accrual_type: LeaveAccrualType,
+    carry_over_cap_hours: u32,
+    carry_over_expiry_month: Option<u32>,
+    start_date: NaiveDate,
+    months: Vec<LeaveBalanceMonth>,
+    projected_end_of_year_hours: f64,
+}
+
+impl
+    From<(
+        db_entities::LeaveBalance,
+        Vec<internal::LeaveBalanceMonth>,
+        f64,
+    )> for LeaveBalanceHistory
+{
+    fn from(
+        value: (
+            db_entities::LeaveBalance,
+            Vec<internal::LeaveBalanceMonth>,
+            f64,
+        ),
+    ) -> Self {
+        let (balance, months, projected_end_of_year_hours) = value;
+        Self {
+            user_id: balance.user_id().to_hex(),
+            company_id: balance.company_id().to_hex(),
+            yearly_allowance_hours: *balance.yearly_allowance_hours(),
+            accrual_type: *balance.accrual_type(),
+            carry_over_cap_hours: *balance.carry_over_cap_hours(),
+            carry_over_expiry_month: *balance.carry_over_expiry_month(),
+            start_date: balance.start_date().date_naive(),
+            months: months.into_iter().map(LeaveBalanceMonth::from).collect(),
+            projected_end_of_year_hours,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectActivityBudgetStatus {
+    activity_id: String,
+    activity_name: String,
+    budget_hours: Option<u32>,
+    logged_hours: u32,
+}
+
+impl From<internal::ProjectActivityBudgetStatus> for ProjectActivityBudgetStatus {
+    fn from(value: internal::ProjectActivityBudgetStatus) -> Self {
+        Self {
+            activity_id: value.activity_id.to_hex(),
+            activity_name: value.activity_name,
+            budget_hours: value.budget_hours,
+            logged_hours: value.logged_hours,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectBurnDownWeek {
+    week_start: NaiveDate,
+    logged_hours: u32,
+    remaining_hours: i64,
+}
+
+impl From<internal::ProjectBurnDownWeek> for ProjectBurnDownWeek {
+    fn from(value: internal::ProjectBurnDownWeek) -> Self {
+        Self {
+            week_start: value.week_start,
+            logged_hours: value.logged_hours,
+            remaining_hours: value.remaining_hours,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectBudgetStatus {
+    project_id: String,
+    total_hours: u32,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    notification_thresholds: Vec<u32>,
+    logged_hours: u32,
+    remaining_hours: i64,
+    activities: Vec<ProjectActivityBudgetStatus>,
+    burn_down: Vec<ProjectBurnDownWeek>,
+    forecast_completion_date: Option<NaiveDate>,
+}
+
+impl From<(db_entities::ProjectBudget, internal::ProjectBudgetStatus)> for ProjectBudgetStatus {
+    fn from(value: (db_entities::ProjectBudget, internal::ProjectBudgetStatus)) -> Self {
+        let (budget, status) = value;
+        Self {
+            project_id: budget.project_id().to_hex(),
+            total_hours: *budget.total_hours(),
+            start_date: budget.start_date().date_naive(),
+            end_date: budget.end_date().date_naive(),
+            notification_thresholds: budget.notification_thresholds().clone(),
+            logged_hours: status.logged_hours,
+            remaining_hours: status.remaining_hours,
+            activities: status
+                .activities
+                .into_iter()
+                .map(ProjectActivityBudgetStatus::from)
+                .collect(),
+            burn_down: status
+                .burn_down
+                .into_iter()
+                .map(ProjectBurnDownWeek::from)
+                .collect(),
+            forecast_completion_date: status.forecast_completion_date,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NationalRuleSet {
+    country_code: String,
+    country_name: String,
+}
+
+impl From<(&str, &str)> for NationalRuleSet {
+    fn from(value: (&str, &str)) -> Self {
+        Self {
+            country_code: value.0.to_string(),
+            country_name: value.1.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanyCalendarDay {
+    id: String,
+    company_id: String,
+    date: NaiveDate,
+    day_type: WorkingDayType,
+    name: String,
+    source: CalendarDaySource,
+}
+
+impl From<db_entities::CompanyCalendarDay> for CompanyCalendarDay {
+    fn from(value: db_entities::CompanyCalendarDay) -> Self {
+        Self {
+            id: value
+                .get_id()
+                .expect("id should exist from document retrieved from db")
+                .to_hex(),
+            company_id: value.company_id().to_hex(),
+            date: value.date().date_naive(),
+            day_type: *value.day_type(),
+            name: value.name().clone(),
+            source: *value.source(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompanySettings {
+    company_id: String,
+    daily_hours: u32,
+    working_weekdays: Vec<Weekday>,
+    timezone: String,
+    max_daily_hours: u32,
+    allowed_working_types: Vec<WorkingDayType>,
+}
+
+impl From<db_entities::CompanySettings> for CompanySettings {
+    fn from(value: db_entities::CompanySettings) -> Self {
+        Self {
+            company_id: value.company_id().to_hex(),
+            daily_hours: *value.daily_hours(),
+            working_weekdays: value.working_weekdays().clone(),
+            timezone: value.timezone().clone(),
+            max_daily_hours: *value.max_daily_hours(),
+            allowed_working_types: value.allowed_working_types().clone(),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -358,4 +763,551 @@ pub struct CorporateGroupInfo {
     pub name: String,
     pub company_ids: Vec<String>,
     pub company_names: Vec<String>,
+    pub owner_id: String,
+    pub admin_ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorporateGroupSummary {
+    id: String,
+    name: String,
+}
+
+impl From<internal::CorporateGroupSummary> for CorporateGroupSummary {
+    fn from(value: internal::CorporateGroupSummary) -> Self {
+        Self {
+            id: value.id.to_hex(),
+            name: value.name,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorporateGroupJoinRequest {
+    pub id: String,
+    pub company_id: String,
+    pub company_name: String,
+    pub requesting_user_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorporateGroupAuditEntry {
+    user_id: String,
+    action: CorporateGroupAction,
+    company_id: Option<String>,
+    target_user_id: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<db_entities::CorporateGroupAuditEntry> for CorporateGroupAuditEntry {
+    fn from(value: db_entities::CorporateGroupAuditEntry) -> Self {
+        Self {
+            user_id: value.user_id().to_hex(),
+            action: *value.action(),
+            company_id: value.company_id().map(|id| id.to_hex()),
+            target_user_id: value.target_user_id().map(|id| id.to_hex()),
+            created_at: *value.created_at(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimesheetImportRowError {
+    row: usize,
+    field: String,
+    rule: String,
+    message: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimesheetImportReport {
+    dry_run: bool,
+    committed: bool,
+    days: usize,
+    activities: usize,
+    errors: Vec<TimesheetImportRowError>,
+}
+
+impl From<internal::TimesheetImportReport> for TimesheetImportReport {
+    fn from(value: internal::TimesheetImportReport) -> Self {
+        Self {
+            dry_run: value.dry_run,
+            committed: value.committed,
+            days: value.days,
+            activities: value.activities,
+            errors: value
+                .errors
+                .into_iter()
+                .map(|error| TimesheetImportRowError {
+                    row: error.row,
+                    field: error.violation.field,
+                    rule: error.violation.rule,
+                    message: error.violation.message,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimesheetConsistencyIssue {
+    day_id: String,
+    user_id: String,
+    username: String,
+    date: DateTime<Utc>,
+    violations: Vec<ValidationViolation>,
+}
+
+impl From<internal::TimesheetConsistencyIssue> for TimesheetConsistencyIssue {
+    fn from(value: internal::TimesheetConsistencyIssue) -> Self {
+        Self {
+            day_id: value.day_id.to_hex(),
+            user_id: value.user_id.to_hex(),
+            username: value.username,
+            date: value.date,
+            violations: value.violations,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimesheetTemplateActivity {
+    company_id: String,
+    project_id: String,
+    activity_id: String,
+    notes: String,
+    hours: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimesheetTemplate {
+    id: String,
+    name: String,
+    working_type: WorkingDayType,
+    activities: Vec<TimesheetTemplateActivity>,
+}
+
+impl TryFrom<db_entities::TimesheetTemplate> for TimesheetTemplate {
+    type Error = ServiceAppError;
+
+    fn try_from(value: db_entities::TimesheetTemplate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value
+                .get_id()
+                .ok_or(ServiceAppError::InternalServerError(
+                    "Timesheet template without id".into(),
+                ))?
+                .to_hex(),
+            name: value.name().clone(),
+            working_type: *value.working_type(),
+            activities: value
+                .activities()
+                .iter()
+                .map(|activity| TimesheetTemplateActivity {
+                    company_id: activity.company_id().to_hex(),
+                    project_id: activity.project_id().to_hex(),
+                    activity_id: activity.activity_id().to_hex(),
+                    notes: activity.notes().clone(),
+                    hours: *activity.hours(),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimesheetSkippedDay {
+    date: NaiveDate,
+    reason: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimesheetBulkReport {
+    created: Vec<NaiveDate>,
+    skipped: Vec<TimesheetSkippedDay>,
+}
+
+impl From<internal::TimesheetBulkReport> for TimesheetBulkReport {
+    fn from(value: internal::TimesheetBulkReport) -> Self {
+        Self {
+            created: value.created,
+            skipped: value
+                .skipped
+                .into_iter()
+                .map(|skipped| TimesheetSkippedDay {
+                    date: skipped.date,
+                    reason: skipped.reason,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectRoleInfo {
+    user_id: String,
+    role: ProjectRole,
+}
+
+impl From<db_entities::ProjectRoleAssignment> for ProjectRoleInfo {
+    fn from(value: db_entities::ProjectRoleAssignment) -> Self {
+        Self {
+            user_id: value.user_id().to_hex(),
+            role: *value.role(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientInfo {
+    id: String,
+    name: String,
+    currency: String,
+    active: bool,
+}
+
+impl TryFrom<db_entities::Client> for ClientInfo {
+    type Error = ServiceAppError;
+
+    fn try_from(value: db_entities::Client) -> Result<Self, Self::Error> {
+        if let Some(id) = value.get_id() {
+            Ok(Self {
+                id: id.to_hex(),
+                name: value.name().clone(),
+                currency: value.currency().clone(),
+                active: *value.active(),
+            })
+        } else {
+            Err(ServiceAppError::ResponseBuildError(
+                "Document Id should exist for Client".into(),
+            ))
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillableRate {
+    id: String,
+    project_id: Option<String>,
+    activity_id: Option<String>,
+    job_title: Option<String>,
+    hourly_rate: f64,
+    effective_from: NaiveDate,
+}
+
+impl TryFrom<db_entities::BillableRate> for BillableRate {
+    type Error = ServiceAppError;
+
+    fn try_from(value: db_entities::BillableRate) -> Result<Self, Self::Error> {
+        if let Some(id) = value.get_id() {
+            Ok(Self {
+                id: id.to_hex(),
+                project_id: value.project_id().map(|project_id| project_id.to_hex()),
+                activity_id: value.activity_id().map(|activity_id| activity_id.to_hex()),
+                job_title: value.job_title().clone(),
+                hourly_rate: *value.hourly_rate(),
+                effective_from: value.effective_from().date_naive(),
+            })
+        } else {
+            Err(ServiceAppError::ResponseBuildError(
+                "Document Id should exist for BillableRate".into(),
+            ))
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillableLine {
+    client_id: String,
+    project_id: String,
+    project_name: String,
+    activity_id: String,
+    activity_name: String,
+    user_id: String,
+    username: String,
+    hourly_rate: Option<f64>,
+    hours: u32,
+    amount: f64,
+}
+
+impl From<&internal::BillableLine> for BillableLine {
+    fn from(value: &internal::BillableLine) -> Self {
+        Self {
+            client_id: value.client_id.to_hex(),
+            project_id: value.project_id.to_hex(),
+            project_name: value.project_name.clone(),
+            activity_id: value.activity_id.to_hex(),
+            activity_name: value.activity_name.clone(),
+            user_id: value.user_id.to_hex(),
+            username: value.username.clone(),
+            hourly_rate: value.hourly_rate,
+            hours: value.hours,
+            amount: value.amount,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientBillableAmount {
+    client_id: String,
+    client_name: String,
+    currency: String,
+    hours: u32,
+    /// hours without a rate, they are not billed
+    unrated_hours: u32,
+    amount: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BillableAmounts {
+    from: NaiveDate,
+    to: NaiveDate,
+    clients: Vec<ClientBillableAmount>,
+    lines: Vec<BillableLine>,
+}
+
+impl From<(NaiveDate, NaiveDate, Vec<internal::BillableLine>)> for BillableAmounts {
+    fn from(value: (NaiveDate, NaiveDate, Vec<internal::BillableLine>)) -> Self {
+        let (from, to, lines) = value;
+        // lines are sorted by client name, so the lines of a client are contiguous
+        let mut clients: Vec<ClientBillableAmount> = vec![];
+        for line in lines.iter() {
+            let client_id = line.client_id.to_hex();
+            if clients
+                .last()
+                .is_none_or(|client| client.client_id != client_id)
+            {
+                clients.push(ClientBillableAmount {
+                    client_id,
+                    client_name: line.client_name.clone(),
+                    currency: line.currency.clone(),
+                    hours: 0,
+                    unrated_hours: 0,
+                    amount: 0.0,
+                });
+            }
+            let client = clients.last_mut().expect("client has just been pushed");
+            client.hours += line.hours;
+            if line.hourly_rate.is_none() {
+                client.unrated_hours += line.hours;
+            }
+            client.amount = ((client.amount + line.amount) * 100.0).round() / 100.0;
+        }
+        Self {
+            from,
+            to,
+            clients,
+            lines: lines.iter().map(BillableLine::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceLine {
+    project_id: String,
+    project_name: String,
+    activity_id: String,
+    activity_name: String,
+    hours: u32,
+    hourly_rate: f64,
+    amount: f64,
+}
+
+impl From<&db_entities::InvoiceLine> for InvoiceLine {
+    fn from(value: &db_entities::InvoiceLine) -> Self {
+        Self {
+            project_id: value.project_id().to_hex(),
+            project_name: value.project_name().clone(),
+            activity_id: value.activity_id().to_hex(),
+            activity_name: value.activity_name().clone(),
+            hours: *value.hours(),
+            hourly_rate: *value.hourly_rate(),
+            amount: *value.amount(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Invoice {
+    id: String,
+    number: u32,
+    client_id: String,
+    status: InvoiceStatus,
+    from: NaiveDate,
+    to: NaiveDate,
+    currency: String,
+    lines: Vec<InvoiceLine>,
+    total_amount: f64,
+    created_at: DateTime<Utc>,
+    issued_at: Option<DateTime<Utc>>,
+    voided_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<db_entities::Invoice> for Invoice {
+    type Error = ServiceAppError;
+
+    fn try_from(value: db_entities::Invoice) -> Result<Self, Self::Error> {
+        if let Some(id) = value.get_id() {
+            Ok(Self {
+                id: id.to_hex(),
+                number: *value.number(),
+                client_id: value.client_id().to_hex(),
+                status: *value.status(),
+                from: value.from_date().date_naive(),
+                to: value.to_date().date_naive(),
+                currency: value.currency().clone(),
+                lines: value.lines().iter().map(InvoiceLine::from).collect(),
+                total_amount: *value.total_amount(),
+                created_at: *value.created_at(),
+                issued_at: *value.issued_at(),
+                voided_at: *value.voided_at(),
+            })
+        } else {
+            Err(ServiceAppError::ResponseBuildError(
+                "Document Id should exist for Invoice".into(),
+            ))
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupStats {
+    headcount: u32,
+    logged_hours: u32,
+    office_days: u32,
+    remote_days: u32,
+    remote_ratio: f64,
+    day_off_days: u32,
+    sick_days: u32,
+    leave_hours: f64,
+}
+
+impl From<internal::GroupStats> for GroupStats {
+    fn from(value: internal::GroupStats) -> Self {
+        Self {
+            headcount: value.headcount,
+            logged_hours: value.logged_hours,
+            office_days: value.office_days,
+            remote_days: value.remote_days,
+            remote_ratio: value.remote_ratio(),
+            day_off_days: value.day_off_days,
+            sick_days: value.sick_days,
+            leave_hours: value.leave_hours,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupCompanyReport {
+    company_id: String,
+    company_name: String,
+    #[serde(flatten)]
+    stats: GroupStats,
+}
+
+impl From<internal::GroupCompanyReport> for GroupCompanyReport {
+    fn from(value: internal::GroupCompanyReport) -> Self {
+        Self {
+            company_id: value.company_id.to_hex(),
+            company_name: value.company_name,
+            stats: value.stats.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupHoursLine {
+    company_id: String,
+    company_name: String,
+    project_id: String,
+    project_name: String,
+    activity_id: String,
+    activity_name: String,
+    hours: u32,
+}
+
+impl From<internal::GroupHoursLine> for GroupHoursLine {
+    fn from(value: internal::GroupHoursLine) -> Self {
+        Self {
+            company_id: value.company_id.to_hex(),
+            company_name: value.company_name,
+            project_id: value.project_id.to_hex(),
+            project_name: value.project_name,
+            activity_id: value.activity_id.to_hex(),
+            activity_name: value.activity_name,
+            hours: value.hours,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupReport {
+    companies: Vec<GroupCompanyReport>,
+    hours: Vec<GroupHoursLine>,
+    totals: GroupStats,
+}
+
+impl From<internal::GroupReport> for GroupReport {
+    fn from(value: internal::GroupReport) -> Self {
+        Self {
+            companies: value
+                .companies
+                .into_iter()
+                .map(GroupCompanyReport::from)
+                .collect(),
+            hours: value.hours.into_iter().map(GroupHoursLine::from).collect(),
+            totals: value.totals.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupDirectoryEntry {
+    user_id: String,
+    username: String,
+    email: Option<String>,
+    name: String,
+    surname: String,
+    company_id: String,
+    company_name: String,
+    job_title: String,
+}
+
+impl From<internal::GroupDirectoryEntry> for GroupDirectoryEntry {
+    fn from(value: internal::GroupDirectoryEntry) -> Self {
+        Self {
+            user_id: value.user_id.to_hex(),
+            username: value.username,
+            email: value.email,
+            name: value.name,
+            surname: value.surname,
+            company_id: value.company_id.to_hex(),
+            company_name: value.company_name,
+            job_title: value.job_title,
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
 use mongodb::bson::Bson;
 use serde::{Deserialize, Serialize};
@@ -73,6 +73,39 @@ impl Ord for CompanyRole {
     }
 }
 
+/// Enumeration with roles assigned to Users for a single Project of a Company
+///
+/// Company Admins and Owners have every role on all the projects of the company.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum ProjectRole {
+    /// Employee allocated to the project
+    Member,
+    /// Project manager
+    ///
+    /// A user that can edit the allocations and the activities of the project
+    /// and view the hours logged on it
+    Manager,
+}
+
+impl Display for ProjectRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ProjectRole::Member => "Member",
+                ProjectRole::Manager => "Manager",
+            }
+        )
+    }
+}
+
+impl From<ProjectRole> for Bson {
+    fn from(value: ProjectRole) -> Self {
+        Bson::String(value.to_string())
+    }
+}
+
 /// Enumeration with employee request for permission or other
 /// it has an outcome which is another enumeration that defines
 /// how the request is
@@ -86,6 +119,25 @@ pub enum EmployeeRequest {
     Permission(EmployeeRequestOutcome),
 }
 
+impl EmployeeRequest {
+    pub fn outcome(&self) -> EmployeeRequestOutcome {
+        match self {
+            EmployeeRequest::Holiday(outcome)
+            | EmployeeRequest::Remote(outcome)
+            | EmployeeRequest::Permission(outcome) => *outcome,
+        }
+    }
+
+    /// Returns the same request with the given outcome
+    pub fn with_outcome(self, outcome: EmployeeRequestOutcome) -> Self {
+        match self {
+            EmployeeRequest::Holiday(_) => EmployeeRequest::Holiday(outcome),
+            EmployeeRequest::Remote(_) => EmployeeRequest::Remote(outcome),
+            EmployeeRequest::Permission(_) => EmployeeRequest::Permission(outcome),
+        }
+    }
+}
+
 /// Enumeration with employee request outcome
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
 pub enum EmployeeRequestOutcome {
@@ -102,6 +154,234 @@ pub enum EmployeeRequestOutcome {
 pub enum NotificationType {
     InviteAddCompany,
     InviteAddCompanyAnswer,
+    ExpiredInvite,
+    OwnershipTransfer,
+    OwnershipTransferAnswer,
+    /// the hours logged on a project have reached a threshold of its budget
+    ProjectBudgetThreshold,
+    /// a leave request has been accepted or refused by the management team
+    LeaveRequestAnswer,
+    /// a company admin asks to add the company to a corporate group
+    CorporateGroupJoinRequest,
+    /// the request to join a corporate group has been approved or refused
+    CorporateGroupJoinRequestAnswer,
+}
+
+/// Enumeration with the membership changes recorded in the audit trail of a corporate group
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum CorporateGroupAction {
+    /// the group has been created
+    Created,
+    /// a company has been added to the group by one of its managers
+    CompanyAdded,
+    /// a company has been removed from the group
+    CompanyRemoved,
+    /// a company admin has asked to add the company to the group
+    JoinRequested,
+    /// the request to join has been approved and the company added to the group
+    JoinApproved,
+    /// the request to join has been refused
+    JoinRefused,
+    /// a user has become admin of the group
+    AdminAdded,
+    /// a user is no longer admin of the group
+    AdminRemoved,
+    /// the group has been deleted by its owner
+    Deleted,
+}
+
+impl Display for CorporateGroupAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                CorporateGroupAction::Created => "Created",
+                CorporateGroupAction::CompanyAdded => "CompanyAdded",
+                CorporateGroupAction::CompanyRemoved => "CompanyRemoved",
+                CorporateGroupAction::JoinRequested => "JoinRequested",
+                CorporateGroupAction::JoinApproved => "JoinApproved",
+                CorporateGroupAction::JoinRefused => "JoinRefused",
+                CorporateGroupAction::AdminAdded => "AdminAdded",
+                CorporateGroupAction::AdminRemoved => "AdminRemoved",
+                CorporateGroupAction::Deleted => "Deleted",
+            }
+        )
+    }
+}
+
+impl From<CorporateGroupAction> for Bson {
+    fn from(value: CorporateGroupAction) -> Self {
+        Bson::String(value.to_string())
+    }
+}
+
+/// Enumeration with the named operations that can be granted to Company employees.
+///
+/// The permissions are bundled in roles: the built-in roles have a fixed set of
+/// permissions while the custom roles are defined by each company.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum Permission {
+    /// see the employees of the company
+    ViewUsers,
+    /// invite and remove employees, change their job title
+    ManageUsers,
+    /// change the role of the employees, manage custom roles and the management team
+    ManageRoles,
+    /// edit company settings and calendar
+    ManageSettings,
+    /// configure the leave balances of the employees
+    ManageLeaveBalances,
+    /// accept or refuse the leave requests of the employees
+    ApproveLeave,
+    /// see projects and their allocations
+    ViewProjects,
+    /// create and edit projects, activities, allocations and budgets
+    ManageProjects,
+    /// manage clients, billable rates and invoices
+    ManageBilling,
+    /// see the timesheets of the employees
+    ViewTeamTimesheets,
+    /// view the reports of the company, e.g. the timesheet consistency report
+    ViewReports,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 11] = [
+        Permission::ViewUsers,
+        Permission::ManageUsers,
+        Permission::ManageRoles,
+        Permission::ManageSettings,
+        Permission::ManageLeaveBalances,
+        Permission::ApproveLeave,
+        Permission::ViewProjects,
+        Permission::ManageProjects,
+        Permission::ManageBilling,
+        Permission::ViewTeamTimesheets,
+        Permission::ViewReports,
+    ];
+}
+
+/// Permissions granted to the members of the Company Management Team
+pub const MANAGEMENT_TEAM_PERMISSIONS: [Permission; 3] = [
+    Permission::ApproveLeave,
+    Permission::ViewTeamTimesheets,
+    Permission::ViewReports,
+];
+
+/// Enumeration with the lifecycle states of an invite to a company
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+pub enum InviteStatus {
+    /// when the invite is waiting for an answer of the invited user
+    #[default]
+    Pending,
+    /// when the invited user has joined the company
+    Accepted,
+    /// when the invited user has refused to join the company
+    Declined,
+    /// when the invite has been withdrawn by a company admin
+    Cancelled,
+    /// when the invite has not been answered before the expiration date
+    Expired,
+}
+
+impl From<InviteStatus> for Bson {
+    fn from(value: InviteStatus) -> Self {
+        match value {
+            InviteStatus::Pending => "Pending".to_string(),
+            InviteStatus::Accepted => "Accepted".to_string(),
+            InviteStatus::Declined => "Declined".to_string(),
+            InviteStatus::Cancelled => "Cancelled".to_string(),
+            InviteStatus::Expired => "Expired".to_string(),
+        }
+        .into()
+    }
+}
+
+/// Enumeration with the lifecycle states of an invoice
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum InvoiceStatus {
+    /// when the invoice has been generated and it can still be reviewed
+    Draft,
+    /// when the invoice has been sent to the client, it can only be voided
+    Issued,
+    /// when the invoice has been cancelled, it keeps its number
+    Void,
+}
+
+impl Display for InvoiceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                InvoiceStatus::Draft => "Draft",
+                InvoiceStatus::Issued => "Issued",
+                InvoiceStatus::Void => "Void",
+            }
+        )
+    }
+}
+
+impl From<InvoiceStatus> for Bson {
+    fn from(value: InvoiceStatus) -> Self {
+        Bson::String(value.to_string())
+    }
+}
+
+/// Enumeration with the ways the yearly leave allowance is granted
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum LeaveAccrualType {
+    /// the whole allowance is granted at the beginning of the year
+    YearlyUpfront,
+    /// a twelfth of the allowance is granted every month
+    Monthly,
+}
+
+/// Enumeration with the origin of a day in the company calendar
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum CalendarDaySource {
+    /// generated from a national holiday rule set bundled with the application
+    NationalRuleSet,
+    /// imported from an ICS file
+    IcsImport,
+    /// declared by a company admin
+    Manual,
+}
+
+/// Enumeration with the file formats of the timesheet export
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TimesheetExportFormat {
+    /// comma separated values, one record per activity
+    Csv,
+    /// excel workbook
+    #[default]
+    Xlsx,
+    /// OpenDocument spreadsheet
+    Ods,
+}
+
+impl TimesheetExportFormat {
+    /// Media type of the exported file
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TimesheetExportFormat::Csv => "text/csv",
+            TimesheetExportFormat::Xlsx => {
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            }
+            TimesheetExportFormat::Ods => "application/vnd.oasis.opendocument.spreadsheet",
+        }
+    }
+}
+
+/// Enumeration with the periods that can be copied in the timesheet
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum TimesheetCopyPeriod {
+    /// the last day logged before the target day
+    Day,
+    /// the seven days before the week of the target day, each one on the same weekday
+    Week,
 }
 
 /// Define the type of work in the timesheet
@@ -154,6 +434,24 @@ impl TryFrom<Bson> for WorkingDayType {
     }
 }
 
+impl FromStr for WorkingDayType {
+    type Err = ServiceAppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "Office" => Ok(Self::Office),
+            "Remote" => Ok(Self::Remote),
+            "DayOff" => Ok(Self::DayOff),
+            "Holiday" => Ok(Self::Holiday),
+            "CompanyClosure" => Ok(Self::CompanyClosure),
+            "Sick" => Ok(Self::Sick),
+            _ => Err(ServiceAppError::InvalidRequest(format!(
+                "Invalid working day type {value}"
+            ))),
+        }
+    }
+}
+
 impl Display for WorkingDayType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
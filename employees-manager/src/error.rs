@@ -34,6 +34,8 @@ pub enum AppError {
     AccessControlError(String),
     /// When the request is not valid due to one of its parameters aka 400
     InvalidRequest(String),
+    /// When one or more fields of the request violate validation rules aka 422
+    ValidationError(Vec<ValidationViolation>),
 }
 
 // Tell axum how to convert `AppError` into a response.
@@ -43,7 +45,10 @@ impl IntoResponse for AppError {
         #[derive(Serialize)]
         struct ErrorResponse {
             message: String,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            violations: Vec<ValidationViolation>,
         }
+        let mut violations = vec![];
         // Define StatusCode and message for every enum variant
         let (status, message) = match self {
             AppError::JsonRejection(rejection) => {
@@ -61,8 +66,22 @@ impl IntoResponse for AppError {
             AppError::DoesNotExist(message) => (StatusCode::NOT_FOUND, message),
             AppError::AccessControlError(message) => (StatusCode::FORBIDDEN, message),
             AppError::InvalidRequest(message) => (StatusCode::BAD_REQUEST, message),
+            AppError::ValidationError(rule_violations) => {
+                violations = rule_violations;
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "The request did not pass validation".into(),
+                )
+            }
         };
-        (status, AppJson(ErrorResponse { message })).into_response()
+        (
+            status,
+            AppJson(ErrorResponse {
+                message,
+                violations,
+            }),
+        )
+            .into_response()
     }
 }
 
@@ -107,6 +126,9 @@ pub enum ServiceAppError {
     InvalidRequest(String),
     /// InternalServerError
     InternalServerError(String),
+    /// Error that can occur when a request contains fields that violate one or more
+    /// validation rules, all of them are reported
+    ValidationError(Vec<ValidationViolation>),
 }
 
 impl Display for ServiceAppError {
@@ -123,6 +145,14 @@ impl Display for ServiceAppError {
                 Self::ResponseBuildError(message) => format!("ResponseBuildError: {message}"),
                 Self::InternalServerError(message) => format!("InternalServerError: {message}"),
                 Self::AccessControlError(message) => format!("AccessControlError: {message}"),
+                Self::ValidationError(violations) => format!(
+                    "ValidationError: {}",
+                    violations
+                        .iter()
+                        .map(|violation| violation.message.clone())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
             }
         )
     }
@@ -178,6 +208,43 @@ impl From<XlsxError> for ServiceAppError {
     }
 }
 
+impl From<csv::Error> for ServiceAppError {
+    fn from(value: csv::Error) -> Self {
+        ServiceAppError::InternalServerError(value.to_string())
+    }
+}
+
+impl From<zip::result::ZipError> for ServiceAppError {
+    fn from(value: zip::result::ZipError) -> Self {
+        ServiceAppError::InternalServerError(value.to_string())
+    }
+}
+
+/// Single validation rule violated by a field of the request
+///
+/// It is returned to the client inside the error response so that
+/// every violated rule can be shown at once.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationViolation {
+    /// name of the request field that is not valid
+    pub field: String,
+    /// identifier of the violated rule
+    pub rule: String,
+    /// human readable description of the violation
+    pub message: String,
+}
+
+impl ValidationViolation {
+    pub fn new(field: &str, rule: &str, message: String) -> Self {
+        Self {
+            field: field.into(),
+            rule: rule.into(),
+            message,
+        }
+    }
+}
+
 /// AuthError is an internal error used by authentication modules to explain why
 /// authentication is failed.
 /// They are translated to `AppError` when exposed to the client
@@ -167,6 +167,7 @@ pub async fn create_user(
     .await
     .map_err(|e| match e {
         ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
         _ => AppError::InternalServerError(e.to_string()),
     })
 }
@@ -187,3 +188,22 @@ pub async fn delete_user(auth_info: impl AuthInfo, user_id: DocumentId) -> Resul
         _ => AppError::InternalServerError(e.to_string()),
     })
 }
+
+pub async fn force_company_ownership_transfer(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::NominateCompanyOwner,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .is_platform_admin()
+        .await?;
+
+    company::force_ownership_transfer(*auth_info.user_id(), company_id, payload.user_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
@@ -56,6 +56,7 @@ pub async fn create_user(
     .await
     .map_err(|e| match e {
         ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
         _ => AppError::InternalServerError(e.to_string()),
     })
 }
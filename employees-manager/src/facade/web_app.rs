@@ -1,18 +1,20 @@
-use std::collections::{hash_map::Entry, HashMap};
-
+use chrono::NaiveDate;
 use jsonwebtoken::Header;
-use mongodb::bson::doc;
 use tracing::debug;
 
 use crate::{
     auth::{AuthInfo, JWTAuthClaim},
     dtos::{web_app_request, web_app_response},
-    enums::{CompanyRole, NotificationType},
+    enums::{
+        CompanyRole, NotificationType, Permission, ProjectRole, TimesheetExportFormat,
+        WorkingDayType,
+    },
     error::{AppError, ServiceAppError},
     model::{db_entities, internal},
     service::{
-        access_control::AccessControl, company, corporate_group, db::DatabaseDocument,
-        notification, timesheet, user,
+        access_control::AccessControl, billing, calendar, company, company_role, corporate_group,
+        db::DatabaseDocument, department, group_report, invoice, leave, notification, org_chart,
+        project_budget, timesheet, user,
     },
     DocumentId,
 };
@@ -117,7 +119,10 @@ pub async fn answer_to_invite_add_company(
         } else {
             notification::answer_to_invite_add_company(notification, payload.accept)
                 .await
-                .map_err(|e| AppError::InternalServerError(e.to_string()))
+                .map_err(|e| match e {
+                    ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+                    _ => AppError::InternalServerError(e.to_string()),
+                })
         }
     } else {
         Err(AppError::DoesNotExist(format!(
@@ -127,6 +132,53 @@ pub async fn answer_to_invite_add_company(
     }
 }
 
+pub async fn answer_to_ownership_transfer(
+    auth_info: impl AuthInfo,
+    notification_id: DocumentId,
+    payload: web_app_request::OwnershipTransferAnswer,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info).await?;
+    match notification::get_notification(&notification_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+    {
+        Some(notification)
+            if *notification.user_id() == *auth_info.user_id()
+                && *notification.notification_type() == NotificationType::OwnershipTransfer =>
+        {
+            notification::answer_to_ownership_transfer(notification, payload.accept)
+                .await
+                .map_err(|e| match e {
+                    ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+                    ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+                    _ => AppError::InternalServerError(e.to_string()),
+                })
+        }
+        _ => Err(AppError::DoesNotExist(format!(
+            "Ownership transfer notification with id {} does not exist",
+            notification_id
+        ))),
+    }
+}
+
+pub async fn nominate_company_owner(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::NominateCompanyOwner,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_company_role_or_higher(&company_id, CompanyRole::Owner)
+        .await?;
+    company::nominate_new_owner(*auth_info.user_id(), company_id, payload.user_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
 pub async fn create_company(
     auth_info: impl AuthInfo,
     payload: web_app_request::CreateCompany,
@@ -147,7 +199,7 @@ pub async fn invite_user_to_company(
 ) -> Result<(), AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageUsers)
         .await?;
 
     company::invite_user(
@@ -167,21 +219,137 @@ pub async fn invite_user_to_company(
     })
 }
 
+pub async fn invite_user_to_company_by_email(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::InviteUserToCompanyByEmail,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageUsers)
+        .await?;
+
+    company::invite_user_by_email(
+        *auth_info.user_id(),
+        company_id,
+        payload.email,
+        payload.role,
+        payload.job_title,
+        payload.project_ids,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::AccessControlError(message) => AppError::AccessControlError(message),
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+/// Returns the invitation identified by the token received by email.
+///
+/// It does not require authentication because the person is not registered yet
+pub async fn get_email_invite(
+    token: String,
+) -> Result<web_app_response::EmailInviteInfo, AppError> {
+    company::get_email_invite_from_token(&token)
+        .await
+        .map(web_app_response::EmailInviteInfo::from)
+        .map_err(|e| match e {
+            ServiceAppError::AuthorizationError(auth_error) => {
+                AppError::AuthorizationError(auth_error)
+            }
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+/// Register a new user through the invitation received by email.
+///
+/// It does not require authentication because the person is not registered yet
+pub async fn register_with_invite(
+    payload: web_app_request::RegisterWithInvite,
+) -> Result<String, AppError> {
+    company::register_invited_user(
+        &payload.token,
+        payload.username,
+        payload.password,
+        payload.name,
+        payload.surname,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::AuthorizationError(auth_error) => AppError::AuthorizationError(auth_error),
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
 pub async fn get_users_to_invite_in_company(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
 ) -> Result<Vec<web_app_response::UserToInviteInCompany>, AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageUsers)
         .await?;
 
-    Ok(company::get_users_to_invite_in_company(company_id)
+    // Only platform admins can look for users outside of the corporate group of the company
+    let platform_admin = *user::get_user(auth_info.user_id())
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })?
+        .platform_admin();
+    Ok(
+        company::get_users_to_invite_in_company(company_id, !platform_admin)
+            .await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .map(|(user_id, username)| {
+                web_app_response::UserToInviteInCompany::new(user_id, username)
+            })
+            .collect(),
+    )
+}
+
+pub async fn search_group_directory(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    params: web_app_request::SearchGroupDirectory,
+) -> Result<Vec<web_app_response::GroupDirectoryEntry>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_company_role_or_higher(&company_id, CompanyRole::User)
+        .await?;
+    // like in the list of the users of the company, emails need the permission to view users
+    let view_users = company_role::get_user_permissions(auth_info.user_id(), &company_id)
         .await
         .map_err(|e| AppError::InternalServerError(e.to_string()))?
-        .into_iter()
-        .map(|(user_id, username)| web_app_response::UserToInviteInCompany::new(user_id, username))
-        .collect())
+        .contains(&Permission::ViewUsers);
+
+    Ok(corporate_group::search_group_directory(
+        &company_id,
+        internal::GroupDirectoryFilter {
+            name: params.name,
+            job_title: params.job_title,
+            company_id: params.company_id,
+        },
+    )
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+    .into_iter()
+    .map(|mut entry| {
+        if !view_users {
+            entry.email = None;
+        }
+        web_app_response::GroupDirectoryEntry::from(entry)
+    })
+    .collect())
 }
 
 pub async fn remove_company_user(
@@ -189,33 +357,23 @@ pub async fn remove_company_user(
     user_id: DocumentId,
     company_id: DocumentId,
 ) -> Result<(), AppError> {
-    AccessControl::new(&auth_info)
+    let access_control = AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageUsers)
         .await?;
     if auth_info.user_id() == &user_id {
         return Err(AppError::InvalidRequest(
             "You cannot remove yourself from the company".into(),
         ));
     }
-    let company_assignment = db_entities::UserCompanyAssignment::find_one(
-        doc! {"company_id": company_id, "user_id": user_id},
-    )
-    .await.map_err(|e| {
-        match e {
-            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
-            _ => AppError::InternalServerError(format!("Error {e} occurred with `find_one` query over `UserCompanyAssignment` for `company_id`: {company_id} and `user_id`: {user_id}"))
-        }
-    })?;
-    if let Some(company_assignment) = company_assignment {
-        company_assignment.delete(None).await.map_err(|e| AppError::InternalServerError(format!("Error {e} occurred with `delete` of company_assignment for `company_id`: {company_id} and `user_id`: {user_id}")))?;
-        Ok(())
-    } else {
-        Err(AppError::InvalidRequest(format!(
-            "User with id {} is not assigned to company with id {}",
-            user_id, company_id
-        )))
-    }
+    // users with the same role or a higher one, and so the Owner, cannot be removed
+    access_control.outranks_user(&company_id, &user_id).await?;
+    company::remove_user_from_company(&user_id, &company_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
 }
 
 pub async fn get_companies_of_user(
@@ -269,7 +427,7 @@ pub async fn get_users_in_company(
 ) -> Result<Vec<web_app_response::UserInCompanyInfo>, AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ViewUsers)
         .await?;
 
     Ok(company::get_users_in_company(&company_id)
@@ -285,14 +443,31 @@ pub async fn change_user_company_role(
     company_id: DocumentId,
     payload: web_app_request::ChangeUserCompanyRole,
 ) -> Result<(), AppError> {
-    AccessControl::new(&auth_info)
+    let access_control = AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageRoles)
         .await?;
+    // only admins can grant the Admin role
+    let access_control = if payload.role == CompanyRole::Admin {
+        access_control
+            .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+            .await?
+    } else {
+        access_control
+    };
     // A user cannot change the role of himself
     if auth_info.user_id() == &payload.user_id {
-        Err(AppError::InvalidRequest(
+        return Err(AppError::InvalidRequest(
             "You cannot change your own role".into(),
+        ));
+    }
+    // nor the role of users with the same role or a higher one
+    access_control
+        .outranks_user(&company_id, &payload.user_id)
+        .await?;
+    if payload.role == CompanyRole::Owner {
+        Err(AppError::InvalidRequest(
+            "The Owner role can be assigned only through an ownership transfer".into(),
         ))
     } else {
         company::update_user_in_company(&payload.user_id, &company_id, Some(payload.role), None)
@@ -309,10 +484,16 @@ pub async fn change_user_company_job_title(
     company_id: DocumentId,
     payload: web_app_request::ChangeUserJobTitle,
 ) -> Result<(), AppError> {
-    AccessControl::new(&auth_info)
+    let access_control = AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageUsers)
         .await?;
+    // the users with the same role or a higher one can be changed only by themselves
+    if auth_info.user_id() != &payload.user_id {
+        access_control
+            .outranks_user(&company_id, &payload.user_id)
+            .await?;
+    }
     company::update_user_in_company(&payload.user_id, &company_id, None, Some(payload.job_title))
         .await
         .map_err(|e| match e {
@@ -326,187 +507,156 @@ pub async fn change_user_company_manager(
     company_id: DocumentId,
     payload: web_app_request::ChangeUserCompanyManager,
 ) -> Result<(), AppError> {
-    AccessControl::new(&auth_info)
+    let access_control = AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageRoles)
         .await?;
+    // the users with the same role or a higher one can be changed only by themselves
+    if auth_info.user_id() != &payload.user_id {
+        access_control
+            .outranks_user(&company_id, &payload.user_id)
+            .await?;
+    }
     company::change_user_company_manager(&payload.user_id, &company_id, payload.manager)
         .await
-        .map_err(|e| AppError::InternalServerError(e.to_string()))
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
 }
 
-pub async fn get_pending_invited_users_in_company(
+pub async fn get_management_team(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
-) -> Result<Vec<web_app_response::InvitedUserInCompanyInfo>, AppError> {
+) -> Result<Vec<String>, AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageRoles)
         .await?;
-    Ok(company::get_pending_invited_users(&company_id)
+
+    Ok(company::get_management_team(&company_id)
         .await
         .map_err(|e| AppError::InternalServerError(e.to_string()))?
         .into_iter()
-        .map(web_app_response::InvitedUserInCompanyInfo::from)
+        .map(|user_id| user_id.to_hex())
         .collect())
 }
 
-pub async fn cancel_invite_user_to_company(
+pub async fn set_management_team(
     auth_info: impl AuthInfo,
-    notification_id: DocumentId,
     company_id: DocumentId,
+    payload: web_app_request::SetManagementTeam,
 ) -> Result<(), AppError> {
+    // roles grant permissions, so only admins can define and assign them
     AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageRoles)
         .await?
         .has_company_role_or_higher(&company_id, CompanyRole::Admin)
         .await?;
-    notification::cancel_invite_user_to_company(notification_id)
+
+    company::set_management_team(&company_id, payload.user_ids)
         .await
         .map_err(|e| match e {
-            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
             _ => AppError::InternalServerError(e.to_string()),
         })
 }
 
-pub async fn get_company_projects(
+/// Returns the permissions of the current user in the company
+pub async fn get_company_permissions(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
-) -> Result<Vec<web_app_response::CompanyProjectInfo>, AppError> {
+) -> Result<Vec<Permission>, AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_company_role_or_higher(&company_id, CompanyRole::User)
         .await?;
 
-    Ok(company::get_company_projects(&company_id)
+    company_role::get_user_permissions(auth_info.user_id(), &company_id)
         .await
-        .map_err(|e| AppError::InternalServerError(e.to_string()))?
-        .into_iter()
-        .flat_map(web_app_response::CompanyProjectInfo::try_from)
-        .collect())
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
 }
 
-pub async fn get_company_project_allocations_by_project(
+/// Returns the built-in roles and the custom roles of the company
+pub async fn get_company_roles(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
-    project_id: DocumentId,
-) -> Result<Vec<String>, AppError> {
+) -> Result<Vec<web_app_response::CompanyRoleDefinition>, AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageRoles)
         .await?;
-    // TODO: optimize this by filtering directly the query
-    let allocation: Option<Vec<String>> = company::get_company_project_allocations(company_id)
-        .await
-        .map_err(|e| AppError::InternalServerError(e.to_string()))?
-        .into_iter()
-        .filter(|(p, _)| p == &project_id)
-        .map(|(_, user_ids)| {
-            user_ids
-                .into_iter()
-                .map(|id| id.to_string())
-                .collect::<Vec<String>>()
-        })
-        .next();
-    Ok(allocation.unwrap_or_default())
-}
 
-pub async fn get_company_project_allocations_by_user(
-    auth_info: impl AuthInfo,
-    company_id: DocumentId,
-    user_id: DocumentId,
-) -> Result<Vec<String>, AppError> {
-    AccessControl::new(&auth_info)
-        .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
-        .await?;
-    // TODO: optimize this by filtering directly the query
-    let allocation: Vec<String> = company::get_company_project_allocations(company_id)
+    Ok(company_role::get_roles(&company_id)
         .await
         .map_err(|e| AppError::InternalServerError(e.to_string()))?
         .into_iter()
-        .filter(|(_, user_ids)| user_ids.contains(&user_id))
-        .map(|(project_id, _)| project_id.to_string())
-        .collect();
-
-    Ok(allocation)
+        .map(web_app_response::CompanyRoleDefinition::from)
+        .collect())
 }
 
-pub async fn create_company_project(
+pub async fn create_custom_role(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
-    payload: web_app_request::CreateCompanyProject,
+    payload: web_app_request::CreateCustomRole,
 ) -> Result<(), AppError> {
+    // roles grant permissions, so only admins can define and assign them
     AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageRoles)
         .await?
         .has_company_role_or_higher(&company_id, CompanyRole::Admin)
         .await?;
 
-    company::create_project(company_id, payload.name, payload.code)
+    company_role::create_custom_role(company_id, payload.name, payload.permissions)
         .await
+        .map(|_| ())
         .map_err(|e| match e {
             ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
             _ => AppError::InternalServerError(e.to_string()),
-        })?;
-    Ok(())
+        })
 }
 
-pub async fn edit_company_project(
+pub async fn edit_custom_role(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
-    project_id: DocumentId,
-    payload: web_app_request::EditCompanyProject,
+    role_id: DocumentId,
+    payload: web_app_request::EditCustomRole,
 ) -> Result<(), AppError> {
+    // roles grant permissions, so only admins can define and assign them
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
-        .await?;
-
-    company::edit_project(
-        company_id,
-        project_id,
-        payload.name,
-        payload.code,
-        payload.active,
-    )
-    .await
-    .map_err(|e| match e {
-        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
-        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
-        _ => AppError::InternalServerError(e.to_string()),
-    })?;
-    Ok(())
-}
-
-pub async fn delete_company_project(
-    auth_info: impl AuthInfo,
-    company_id: DocumentId,
-    project_id: DocumentId,
-) -> Result<(), AppError> {
-    AccessControl::new(&auth_info)
+        .has_permission(&company_id, Permission::ManageRoles)
         .await?
         .has_company_role_or_higher(&company_id, CompanyRole::Admin)
         .await?;
-    company::delete_project(company_id, project_id)
+
+    company_role::edit_custom_role(company_id, role_id, payload.name, payload.permissions)
         .await
         .map_err(|e| match e {
-            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
             ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
             _ => AppError::InternalServerError(e.to_string()),
         })
 }
 
-pub async fn edit_company_project_allocations_by_project(
+pub async fn delete_custom_role(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
-    project_id: DocumentId,
-    payload: web_app_request::ChangeProjectAllocations,
+    role_id: DocumentId,
 ) -> Result<(), AppError> {
+    // roles grant permissions, so only admins can define and assign them
     AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageRoles)
         .await?
         .has_company_role_or_higher(&company_id, CompanyRole::Admin)
         .await?;
 
-    company::edit_company_project_allocations(company_id, project_id, payload.user_ids)
+    company_role::delete_custom_role(company_id, role_id)
         .await
         .map_err(|e| match e {
             ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
@@ -514,50 +664,1181 @@ pub async fn edit_company_project_allocations_by_project(
         })
 }
 
-pub async fn edit_company_project_allocations_by_user(
+pub async fn set_custom_role_users(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
-    user_id: DocumentId,
-    payload: web_app_request::ChangeProjectAllocationsForUser,
+    role_id: DocumentId,
+    payload: web_app_request::SetCustomRoleUsers,
 ) -> Result<(), AppError> {
+    // roles grant permissions, so only admins can define and assign them
     AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageRoles)
         .await?
         .has_company_role_or_higher(&company_id, CompanyRole::Admin)
         .await?;
 
-    company::edit_company_project_allocations_for_user(company_id, user_id, payload.project_ids)
+    company_role::set_custom_role_users(company_id, role_id, payload.user_ids)
         .await
         .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
             ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
             _ => AppError::InternalServerError(e.to_string()),
         })
 }
 
-pub async fn create_project_activity(
+/// Set or remove the manager of the employee
+pub async fn set_user_manager(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
-    payload: web_app_request::NewProjectActivity,
+    user_id: DocumentId,
+    payload: web_app_request::SetUserManager,
 ) -> Result<(), AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageUsers)
         .await?;
 
-    company::create_company_project_activity(company_id, payload.name, payload.description)
+    org_chart::set_user_manager(company_id, user_id, payload.manager_id)
         .await
         .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
             ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
             _ => AppError::InternalServerError(e.to_string()),
         })
 }
 
-pub async fn get_project_activities(
+pub async fn get_org_chart(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
-) -> Result<Vec<web_app_response::ProjectActivityInfo>, AppError> {
+) -> Result<Vec<web_app_response::OrgChartNode>, AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::User)
+        .has_permission(&company_id, Permission::ViewUsers)
+        .await?;
+
+    Ok(org_chart::get_org_chart(&company_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .map(web_app_response::OrgChartNode::from)
+        .collect())
+}
+
+/// Returns the ids of the direct, or also indirect, reports of the employee
+pub async fn get_user_reports(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    user_id: DocumentId,
+    query: web_app_request::GetUserReports,
+) -> Result<Vec<String>, AppError> {
+    // the own reports, or the ones of a managed user, are visible without viewing all the users
+    let access_control = AccessControl::new(&auth_info).await?;
+    if auth_info.user_id() == &user_id {
+        access_control
+            .has_company_role_or_higher(&company_id, CompanyRole::User)
+            .await?;
+    } else {
+        access_control
+            .manages_user_or_has_permission(&company_id, &user_id, Permission::ViewUsers)
+            .await?;
+    }
+
+    Ok(
+        org_chart::get_reports(&company_id, &user_id, query.indirect)
+            .await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .map(|report| report.to_hex())
+            .collect(),
+    )
+}
+
+/// Returns the days of leave of the team of the manager.
+///
+/// Managers can see their own team, the users that can approve leave every team
+pub async fn get_team_leave_calendar(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    manager_id: DocumentId,
+    query: web_app_request::GetTeamLeaveCalendar,
+) -> Result<Vec<web_app_response::TeamLeaveDay>, AppError> {
+    let access_control = AccessControl::new(&auth_info).await?;
+    if *auth_info.user_id() == manager_id {
+        access_control
+            .has_company_role_or_higher(&company_id, CompanyRole::User)
+            .await?;
+    } else {
+        access_control
+            .has_permission(&company_id, Permission::ApproveLeave)
+            .await?;
+    }
+
+    Ok(
+        org_chart::get_team_leave_calendar(&company_id, &manager_id, query.from, query.to)
+            .await
+            .map_err(|e| match e {
+                ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+                _ => AppError::InternalServerError(e.to_string()),
+            })?
+            .into_iter()
+            .map(web_app_response::TeamLeaveDay::from)
+            .collect(),
+    )
+}
+
+pub async fn get_departments(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+) -> Result<Vec<web_app_response::DepartmentInfo>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_company_role_or_higher(&company_id, CompanyRole::User)
+        .await?;
+
+    Ok(department::get_departments(&company_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .flat_map(web_app_response::DepartmentInfo::try_from)
+        .collect())
+}
+
+pub async fn create_department(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::CreateDepartment,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageUsers)
+        .await?;
+
+    department::create_department(
+        company_id,
+        payload.name,
+        payload.lead_ids,
+        payload.default_project_ids,
+    )
+    .await
+    .map(|_| ())
+    .map_err(|e| match e {
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn edit_department(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    department_id: DocumentId,
+    payload: web_app_request::EditDepartment,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageUsers)
+        .await?;
+
+    department::edit_department(
+        company_id,
+        department_id,
+        payload.name,
+        payload.lead_ids,
+        payload.default_project_ids,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn delete_department(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    department_id: DocumentId,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageUsers)
+        .await?;
+
+    department::delete_department(company_id, department_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+/// Replace the members of the department, the new members are allocated
+/// to the default projects of the department
+pub async fn set_department_members(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    department_id: DocumentId,
+    payload: web_app_request::SetDepartmentMembers,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageUsers)
+        .await?;
+
+    department::set_department_members(company_id, department_id, payload.user_ids)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn get_pending_invited_users_in_company(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+) -> Result<Vec<web_app_response::InvitedUserInCompanyInfo>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageUsers)
+        .await?;
+    Ok(company::get_pending_invited_users(&company_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .map(web_app_response::InvitedUserInCompanyInfo::from)
+        .collect())
+}
+
+pub async fn cancel_invite_user_to_company(
+    auth_info: impl AuthInfo,
+    notification_id: DocumentId,
+    company_id: DocumentId,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageUsers)
+        .await?;
+    notification::cancel_invite_user_to_company(notification_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn resend_invite_to_company(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    invite_id: DocumentId,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageUsers)
+        .await?;
+    company::resend_invite(*auth_info.user_id(), company_id, invite_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::AccessControlError(message) => AppError::AccessControlError(message),
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn get_company_invitation_history(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+) -> Result<Vec<web_app_response::InvitationHistoryEntry>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageUsers)
+        .await?;
+    Ok(company::get_invitation_history(&company_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .map(web_app_response::InvitationHistoryEntry::from)
+        .collect())
+}
+
+pub async fn get_company_settings(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+) -> Result<web_app_response::CompanySettings, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_company_role_or_higher(&company_id, CompanyRole::User)
+        .await?;
+    company::get_company_settings(&company_id)
+        .await
+        .map(web_app_response::CompanySettings::from)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+pub async fn edit_company_settings(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::EditCompanySettings,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageSettings)
+        .await?;
+    company::update_company_settings(
+        company_id,
+        payload.daily_hours,
+        payload.working_weekdays,
+        payload.timezone,
+        payload.max_daily_hours,
+        payload.allowed_working_types,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn set_leave_balance(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    user_id: DocumentId,
+    payload: web_app_request::SetLeaveBalance,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageLeaveBalances)
+        .await?;
+    leave::set_leave_balance(
+        user_id,
+        company_id,
+        payload.yearly_allowance_hours,
+        payload.accrual_type,
+        payload.carry_over_cap_hours,
+        payload.carry_over_expiry_month,
+        payload.start_date,
+        payload.opening_balance_hours,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+/// Returns the leave balance history of the user.
+///
+/// Users can see their own balance, the users that can approve leave
+/// the balance of every employee
+pub async fn get_leave_balance_history(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    user_id: DocumentId,
+    year: i32,
+) -> Result<web_app_response::LeaveBalanceHistory, AppError> {
+    let access_control = AccessControl::new(&auth_info).await?;
+    if *auth_info.user_id() == user_id {
+        access_control
+            .has_company_role_or_higher(&company_id, CompanyRole::User)
+            .await?;
+    } else {
+        access_control
+            .has_permission(&company_id, Permission::ApproveLeave)
+            .await?;
+    }
+
+    let map_error = |e: ServiceAppError| match e {
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    };
+    let balance = leave::get_leave_balance(&user_id, &company_id)
+        .await
+        .map_err(map_error)?;
+    let (months, projected_end_of_year) =
+        leave::get_leave_balance_history(&user_id, &company_id, year)
+            .await
+            .map_err(map_error)?;
+    Ok(web_app_response::LeaveBalanceHistory::from((
+        balance,
+        months,
+        projected_end_of_year,
+    )))
+}
+
+/// Submit a leave request of the current user in the company
+pub async fn create_leave_request(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::CreateLeaveRequest,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_company_role_or_higher(&company_id, CompanyRole::User)
+        .await?;
+
+    leave::create_leave_request(
+        *auth_info.user_id(),
+        company_id,
+        payload.request,
+        payload.date,
+    )
+    .await
+    .map(|_| ())
+    .map_err(|e| match e {
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn get_leave_requests(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    query: web_app_request::GetLeaveRequests,
+) -> Result<Vec<web_app_response::LeaveRequest>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ApproveLeave)
+        .await?;
+
+    Ok(leave::get_leave_requests(&company_id, query.only_awaiting)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .flat_map(web_app_response::LeaveRequest::try_from)
+        .collect())
+}
+
+pub async fn answer_leave_request(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    request_id: DocumentId,
+    payload: web_app_request::AnswerLeaveRequest,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ApproveLeave)
+        .await?;
+
+    leave::answer_leave_request(
+        auth_info.user_id(),
+        &company_id,
+        &request_id,
+        payload.accepted,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn get_national_rule_sets(
+    auth_info: impl AuthInfo,
+) -> Result<Vec<web_app_response::NationalRuleSet>, AppError> {
+    AccessControl::new(&auth_info).await?;
+    Ok(calendar::get_national_rule_sets()
+        .into_iter()
+        .map(web_app_response::NationalRuleSet::from)
+        .collect())
+}
+
+pub async fn get_company_calendar_days(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+) -> Result<Vec<web_app_response::CompanyCalendarDay>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_company_role_or_higher(&company_id, CompanyRole::User)
+        .await?;
+    Ok(calendar::get_calendar_days(&company_id, from_date, to_date)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .map(web_app_response::CompanyCalendarDay::from)
+        .collect())
+}
+
+pub async fn import_national_holidays(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::ImportNationalHolidays,
+) -> Result<usize, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageSettings)
+        .await?;
+    calendar::import_national_holidays(company_id, &payload.country_code, payload.year)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn import_company_calendar_ics(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    day_type: WorkingDayType,
+    content: String,
+) -> Result<usize, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageSettings)
+        .await?;
+    calendar::import_ics(company_id, &content, day_type)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn add_company_closure_day(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::AddCompanyClosureDay,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageSettings)
+        .await?;
+    calendar::add_closure_day(company_id, payload.date, payload.name)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn delete_company_calendar_day(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    calendar_day_id: DocumentId,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageSettings)
+        .await?;
+    calendar::delete_calendar_day(company_id, calendar_day_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn get_company_projects(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+) -> Result<Vec<web_app_response::CompanyProjectInfo>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ViewProjects)
+        .await?;
+
+    Ok(company::get_company_projects(&company_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .flat_map(web_app_response::CompanyProjectInfo::try_from)
+        .collect())
+}
+
+pub async fn get_company_project_allocations_by_project(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+) -> Result<Vec<String>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_project_role_or_higher(&company_id, &project_id, ProjectRole::Manager)
+        .await?;
+    // TODO: optimize this by filtering directly the query
+    let allocation: Option<Vec<String>> = company::get_company_project_allocations(company_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .filter(|(p, _)| p == &project_id)
+        .map(|(_, user_ids)| {
+            user_ids
+                .into_iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<String>>()
+        })
+        .next();
+    Ok(allocation.unwrap_or_default())
+}
+
+pub async fn get_company_project_allocations_by_user(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    user_id: DocumentId,
+) -> Result<Vec<String>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ViewProjects)
+        .await?;
+    // TODO: optimize this by filtering directly the query
+    let allocation: Vec<String> = company::get_company_project_allocations(company_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .filter(|(_, user_ids)| user_ids.contains(&user_id))
+        .map(|(project_id, _)| project_id.to_string())
+        .collect();
+
+    Ok(allocation)
+}
+
+pub async fn create_company_project(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::CreateCompanyProject,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageProjects)
+        .await?;
+
+    company::create_project(company_id, payload.name, payload.code, payload.client_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })?;
+    Ok(())
+}
+
+pub async fn edit_company_project(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+    payload: web_app_request::EditCompanyProject,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageProjects)
+        .await?;
+
+    company::edit_project(
+        company_id,
+        project_id,
+        payload.name,
+        payload.code,
+        payload.active,
+        payload.client_id,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    })?;
+    Ok(())
+}
+
+pub async fn delete_company_project(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageProjects)
+        .await?;
+    company::delete_project(company_id, project_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+/// Create or replace the hour budget of the project
+pub async fn set_project_budget(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+    payload: web_app_request::SetProjectBudget,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageProjects)
+        .await?;
+    project_budget::set_project_budget(
+        company_id,
+        project_id,
+        payload.total_hours,
+        payload
+            .activity_budgets
+            .into_iter()
+            .map(|activity_budget| {
+                db_entities::ActivityHourBudget::new(
+                    activity_budget.activity_id,
+                    activity_budget.hours,
+                )
+            })
+            .collect(),
+        payload.start_date,
+        payload.end_date,
+        payload.notification_thresholds,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn delete_project_budget(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageProjects)
+        .await?;
+    project_budget::delete_project_budget(&company_id, &project_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+/// Returns the hours logged on the project against its budget with burn-down and forecast
+pub async fn get_project_budget_status(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+) -> Result<web_app_response::ProjectBudgetStatus, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_project_role_or_higher(&company_id, &project_id, ProjectRole::Manager)
+        .await?;
+
+    let map_error = |e: ServiceAppError| match e {
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    };
+    let budget = project_budget::get_project_budget(&company_id, &project_id)
+        .await
+        .map_err(map_error)?;
+    let status = project_budget::get_project_budget_status(&budget)
+        .await
+        .map_err(map_error)?;
+    Ok(web_app_response::ProjectBudgetStatus::from((
+        budget, status,
+    )))
+}
+
+/// Returns the roles explicitly assigned on the project
+pub async fn get_project_roles(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+) -> Result<Vec<web_app_response::ProjectRoleInfo>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_project_role_or_higher(&company_id, &project_id, ProjectRole::Manager)
+        .await?;
+
+    Ok(company::get_project_roles(&company_id, &project_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .map(web_app_response::ProjectRoleInfo::from)
+        .collect())
+}
+
+pub async fn set_project_role(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+    user_id: DocumentId,
+    payload: web_app_request::SetProjectRole,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageProjects)
+        .await?;
+
+    company::set_project_role(company_id, project_id, user_id, payload.role)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn remove_project_role(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+    user_id: DocumentId,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageProjects)
+        .await?;
+
+    company::remove_project_role(company_id, project_id, user_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn get_clients(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+) -> Result<Vec<web_app_response::ClientInfo>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    Ok(billing::get_clients(&company_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .flat_map(web_app_response::ClientInfo::try_from)
+        .collect())
+}
+
+pub async fn create_client(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::CreateClient,
+) -> Result<String, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    billing::create_client(company_id, payload.name, payload.currency)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn edit_client(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    client_id: DocumentId,
+    payload: web_app_request::EditClient,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    billing::edit_client(
+        company_id,
+        client_id,
+        payload.name,
+        payload.currency,
+        payload.active,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn delete_client(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    client_id: DocumentId,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    billing::delete_client(company_id, client_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn get_billable_rates(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+) -> Result<Vec<web_app_response::BillableRate>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    Ok(billing::get_rates(&company_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .flat_map(web_app_response::BillableRate::try_from)
+        .collect())
+}
+
+pub async fn create_billable_rate(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::CreateBillableRate,
+) -> Result<String, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    billing::create_rate(
+        company_id,
+        payload.project_id,
+        payload.activity_id,
+        payload.job_title,
+        payload.hourly_rate,
+        payload.effective_from,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn delete_billable_rate(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    rate_id: DocumentId,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    billing::delete_rate(company_id, rate_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+/// Returns the amounts billable to the clients of the company in the period
+pub async fn get_billable_amounts(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    query: web_app_request::GetBillableAmounts,
+) -> Result<web_app_response::BillableAmounts, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    let lines = billing::get_billable_amounts(&company_id, query.from, query.to, query.client_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })?;
+    Ok(web_app_response::BillableAmounts::from((
+        query.from, query.to, lines,
+    )))
+}
+
+/// Create the draft invoice of the billable hours of the client in the period, returns its id
+pub async fn create_invoice_draft(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::CreateInvoiceDraft,
+) -> Result<String, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    invoice::create_draft(company_id, payload.client_id, payload.from, payload.to)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn get_invoices(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    query: web_app_request::GetInvoices,
+) -> Result<Vec<web_app_response::Invoice>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    Ok(
+        invoice::get_invoices(&company_id, query.client_id, query.status)
+            .await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .flat_map(web_app_response::Invoice::try_from)
+            .collect(),
+    )
+}
+
+pub async fn get_invoice(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    invoice_id: DocumentId,
+) -> Result<web_app_response::Invoice, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    let invoice = invoice::get_invoice(&company_id, &invoice_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })?;
+    web_app_response::Invoice::try_from(invoice)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+/// Issue or void the invoice
+pub async fn set_invoice_status(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    invoice_id: DocumentId,
+    payload: web_app_request::SetInvoiceStatus,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    invoice::set_invoice_status(&company_id, &invoice_id, payload.status)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn export_invoice(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    invoice_id: DocumentId,
+) -> Result<Vec<u8>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageBilling)
+        .await?;
+
+    invoice::export_as_excel(&company_id, &invoice_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn edit_company_project_allocations_by_project(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+    payload: web_app_request::ChangeProjectAllocations,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_project_role_or_higher(&company_id, &project_id, ProjectRole::Manager)
+        .await?;
+
+    company::edit_company_project_allocations(company_id, project_id, payload.user_ids)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn edit_company_project_allocations_by_user(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    user_id: DocumentId,
+    payload: web_app_request::ChangeProjectAllocationsForUser,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageProjects)
+        .await?;
+
+    company::edit_company_project_allocations_for_user(company_id, user_id, payload.project_ids)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn create_project_activity(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    payload: web_app_request::NewProjectActivity,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ManageProjects)
+        .await?;
+
+    company::create_company_project_activity(
+        company_id,
+        payload.name,
+        payload.description,
+        payload.billable,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn get_project_activities(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+) -> Result<Vec<web_app_response::ProjectActivityInfo>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_company_role_or_higher(&company_id, CompanyRole::User)
         .await?;
 
     Ok(company::get_company_project_activities(company_id)
@@ -576,7 +1857,7 @@ pub async fn edit_project_activity(
 ) -> Result<(), AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageProjects)
         .await?;
 
     company::edit_company_project_activity(
@@ -584,6 +1865,7 @@ pub async fn edit_project_activity(
         activity_id,
         payload.name,
         payload.description,
+        payload.billable,
     )
     .await
     .map_err(|e| match e {
@@ -602,7 +1884,7 @@ pub async fn delete_project_activity(
 ) -> Result<(), AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageProjects)
         .await?;
 
     company::delete_company_project_activity(company_id, activity_id)
@@ -657,47 +1939,161 @@ pub async fn edit_project_activity_assignment_by_activity(
 ) -> Result<(), AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ManageProjects)
+        .await?;
+
+    company::edit_project_activity_assignment_by_activity(activity_id, project_ids)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn edit_project_activity_assignment_by_project(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    project_id: DocumentId,
+    activity_ids: Vec<DocumentId>,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_project_role_or_higher(&company_id, &project_id, ProjectRole::Manager)
         .await?;
 
-    company::edit_project_activity_assignment_by_activity(activity_id, project_ids)
+    company::edit_project_activity_assignment(company_id, project_id, activity_ids)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn create_timesheet_day(
+    auth_info: impl AuthInfo,
+    user_id: DocumentId,
+    payload: web_app_request::CreateTimesheetDay,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info).await?;
+    check_own_timesheet(&auth_info, &user_id)?;
+
+    timesheet::create_day(
+        user_id,
+        payload.date,
+        payload.permit_hours,
+        payload.working_type,
+        payload
+            .activities
+            .into_iter()
+            .map(|e| e.into())
+            .collect::<Vec<internal::TimesheetActivityHours>>(),
+        payload.overtime,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+/// Returns an error when the timesheet of the user is not the one of the logged user
+fn check_own_timesheet(auth_info: &impl AuthInfo, user_id: &DocumentId) -> Result<(), AppError> {
+    if auth_info.user_id() != user_id {
+        return Err(AppError::AccessControlError(
+            "You can change only your own timesheet".into(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn import_timesheet(
+    auth_info: impl AuthInfo,
+    user_id: DocumentId,
+    format: TimesheetExportFormat,
+    dry_run: bool,
+    content: Vec<u8>,
+) -> Result<web_app_response::TimesheetImportReport, AppError> {
+    AccessControl::new(&auth_info).await?;
+    check_own_timesheet(&auth_info, &user_id)?;
+
+    timesheet::import(&user_id, &content, format, dry_run)
         .await
+        .map(web_app_response::TimesheetImportReport::from)
         .map_err(|e| match e {
-            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
             _ => AppError::InternalServerError(e.to_string()),
         })
 }
 
-pub async fn edit_project_activity_assignment_by_project(
+pub async fn get_timesheet_consistency_report(
     auth_info: impl AuthInfo,
     company_id: DocumentId,
-    project_id: DocumentId,
-    activity_ids: Vec<DocumentId>,
-) -> Result<(), AppError> {
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+    department_id: Option<DocumentId>,
+) -> Result<Vec<web_app_response::TimesheetConsistencyIssue>, AppError> {
     AccessControl::new(&auth_info)
         .await?
-        .has_company_role_or_higher(&company_id, CompanyRole::Admin)
+        .has_permission(&company_id, Permission::ViewReports)
         .await?;
 
-    company::edit_project_activity_assignment(company_id, project_id, activity_ids)
+    let user_ids = department_members(&company_id, department_id.as_ref()).await?;
+    Ok(
+        timesheet::get_consistency_report(&company_id, from_date, to_date, user_ids)
+            .await
+            .map_err(|e| match e {
+                ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+                _ => AppError::InternalServerError(e.to_string()),
+            })?
+            .into_iter()
+            .map(web_app_response::TimesheetConsistencyIssue::from)
+            .collect(),
+    )
+}
+
+/// Returns the members of the department used to filter reports and exports, if any
+async fn department_members(
+    company_id: &DocumentId,
+    department_id: Option<&DocumentId>,
+) -> Result<Option<Vec<DocumentId>>, AppError> {
+    let Some(department_id) = department_id else {
+        return Ok(None);
+    };
+    department::get_department_user_ids(company_id, department_id)
         .await
+        .map(Some)
         .map_err(|e| match e {
             ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
             _ => AppError::InternalServerError(e.to_string()),
         })
 }
 
-pub async fn create_timesheet_day(
+pub async fn get_timesheet_templates(
     auth_info: impl AuthInfo,
     user_id: DocumentId,
-    payload: web_app_request::CreateTimesheetDay,
+) -> Result<Vec<web_app_response::TimesheetTemplate>, AppError> {
+    AccessControl::new(&auth_info).await?;
+    check_own_timesheet(&auth_info, &user_id)?;
+
+    Ok(timesheet::get_templates(&user_id)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .into_iter()
+        .flat_map(web_app_response::TimesheetTemplate::try_from)
+        .collect())
+}
+
+pub async fn create_timesheet_template(
+    auth_info: impl AuthInfo,
+    user_id: DocumentId,
+    payload: web_app_request::CreateTimesheetTemplate,
 ) -> Result<(), AppError> {
     AccessControl::new(&auth_info).await?;
+    check_own_timesheet(&auth_info, &user_id)?;
 
-    timesheet::create_day(
+    timesheet::create_template(
         user_id,
-        payload.date,
-        payload.permit_hours,
+        payload.name,
         payload.working_type,
         payload
             .activities
@@ -706,112 +2102,195 @@ pub async fn create_timesheet_day(
             .collect::<Vec<internal::TimesheetActivityHours>>(),
     )
     .await
-    .map_err(|e| AppError::InternalServerError(e.to_string()))
+    .map(|_| ())
+    .map_err(|e| match e {
+        ServiceAppError::ValidationError(violations) => AppError::ValidationError(violations),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn delete_timesheet_template(
+    auth_info: impl AuthInfo,
+    user_id: DocumentId,
+    template_id: DocumentId,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info).await?;
+    check_own_timesheet(&auth_info, &user_id)?;
+
+    timesheet::delete_template(&user_id, &template_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn apply_timesheet_template(
+    auth_info: impl AuthInfo,
+    user_id: DocumentId,
+    template_id: DocumentId,
+    payload: web_app_request::ApplyTimesheetTemplate,
+) -> Result<web_app_response::TimesheetBulkReport, AppError> {
+    AccessControl::new(&auth_info).await?;
+    check_own_timesheet(&auth_info, &user_id)?;
+
+    timesheet::apply_template(
+        &user_id,
+        &template_id,
+        payload.from,
+        payload.to,
+        payload.overwrite,
+    )
+    .await
+    .map(web_app_response::TimesheetBulkReport::from)
+    .map_err(|e| match e {
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
+pub async fn copy_timesheet_days(
+    auth_info: impl AuthInfo,
+    user_id: DocumentId,
+    payload: web_app_request::CopyTimesheetDays,
+) -> Result<web_app_response::TimesheetBulkReport, AppError> {
+    AccessControl::new(&auth_info).await?;
+    check_own_timesheet(&auth_info, &user_id)?;
+
+    timesheet::copy_previous(&user_id, payload.period, payload.date, payload.overwrite)
+        .await
+        .map(web_app_response::TimesheetBulkReport::from)
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
 }
 
 pub async fn get_timesheet_days(
     auth_info: impl AuthInfo,
     user_id: DocumentId,
-    year: i32,
-    month: u32,
+    query: web_app_request::GetUserTimesheetDays,
 ) -> Result<Vec<web_app_response::TimesheetDay>, AppError> {
     AccessControl::new(&auth_info).await?;
+    check_own_timesheet(&auth_info, &user_id)?;
+    build_timesheet_days(user_id, None, query).await
+}
+
+/// Returns the timesheet days of an employee of the company to its managers
+/// and to the users that can see the team timesheets
+pub async fn get_team_timesheet_days(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    user_id: DocumentId,
+    query: web_app_request::GetUserTimesheetDays,
+) -> Result<Vec<web_app_response::TimesheetDay>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .manages_user_or_has_permission(&company_id, &user_id, Permission::ViewTeamTimesheets)
+        .await?;
+    company::get_user_company_role(&user_id, &company_id)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })?;
+
+    build_timesheet_days(user_id, Some(company_id), query).await
+}
+
+/// Build the timesheet days of the user in the period.
+///
+/// When `company_id` is given only the activities and the calendar of that company are
+/// returned, and the days with activities of other companies only are left out
+async fn build_timesheet_days(
+    user_id: DocumentId,
+    company_id: Option<DocumentId>,
+    query: web_app_request::GetUserTimesheetDays,
+) -> Result<Vec<web_app_response::TimesheetDay>, AppError> {
+    let period = internal::TimesheetPeriod::try_from(query).map_err(|e| match e {
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    })?;
 
     /*
     The function does the following:
-    - retrieve all the timesheet days for user, year and month
+    - retrieve all the timesheet days for user in the period, i.e. month, week or date range
     - for each day build the response and store it in the variable timesheets_to_return
 
     The single TimesheetDay struct response is built using its builder and the attributes are retrieved
     from the TimesheetDay document. Activity embedded document needs to be built in a similar way by iterating over
     all the activities the TimesheetDay document has.
-    Since the TimesheetActivityHours response needs additional information that the original embedded document does not have
-    we need to query the database. To avoid useless database query we cache the several documents we use with an HashMap.
+    Since the TimesheetActivityHours response needs the names of company, project and activity that the original
+    embedded document does not have, they are all loaded at once before building the response.
     */
 
-    // create caches to store temporary database documents to avoid reading them multiple times
-    let mut company_cache: HashMap<DocumentId, db_entities::Company> = HashMap::new();
-    let mut project_cache: HashMap<DocumentId, db_entities::CompanyProject> = HashMap::new();
-    let mut activity_cache: HashMap<DocumentId, db_entities::ProjectActivity> = HashMap::new();
-
     // Retrieve all the timesheet days that need to be returned to the client
-    let timesheet_days =
-        timesheet::get_days(&user_id, &year, &month)
+    let mut timesheet_days = timesheet::get_days(&user_id, &period)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })?;
+    if let Some(company_id) = &company_id {
+        timesheet_days.retain_mut(|day| {
+            if day.activities().is_empty() {
+                return true;
+            }
+            let mut activities = day.activities().clone();
+            activities.retain(|activity| activity.company_id() == company_id);
+            day.set_activities(activities);
+            !day.activities().is_empty()
+        });
+    }
+
+    // Holidays and closure days of the companies of the user without a timesheet day yet.
+    // They are not saved, the client shows them pre-filled
+    let prefilled_days =
+        calendar::get_prefilled_days(&user_id, company_id.as_ref(), &period, &timesheet_days)
             .await
             .map_err(|e| match e {
                 ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
                 _ => AppError::InternalServerError(e.to_string()),
             })?;
 
+    // prefilled days have no activities, the names are needed only for the saved days
+    let names = timesheet::TimesheetNames::load(&timesheet_days)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    let name_error = |e: ServiceAppError| match e {
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    };
+
     let mut timesheets_to_return = vec![];
 
     // iterate over all the days and build response structs
-    for timesheet_doc in timesheet_days {
+    for timesheet_doc in timesheet_days.into_iter().chain(prefilled_days) {
         // Use the builder and add first entities that can be retrieved easily from the document
         let mut builder = web_app_response::TimesheetDayBuilder::default();
         builder
             .date(*timesheet_doc.date())
             .permit_hours(*timesheet_doc.permit_hours())
             .user_id(timesheet_doc.user_id().to_hex())
-            .working_type(*timesheet_doc.working_type());
+            .working_type(*timesheet_doc.working_type())
+            .overtime(*timesheet_doc.overtime())
+            .prefilled(timesheet_doc.get_id().is_none());
 
         // iterate over all the activities and build them
         let mut current_activities = vec![];
         for activity_doc in timesheet_doc.activities() {
-            // Here, we cannot use or_insert_with because we cannot use async closure that are considered unsafe
-            // therefore, we check if the entry exist and if not we perform the query
-            if let Entry::Vacant(entry) = activity_cache.entry(*activity_doc.activity_id()) {
-                entry.insert(
-                    db_entities::ProjectActivity::find_one(
-                        doc! {"_id": activity_doc.activity_id()})
-                        .await
-                        .map_err(|e| AppError::InternalServerError(format!("An error occurred when retrieving the activity document with id {}. Got error {}", activity_doc.activity_id(), e)))?
-                        .ok_or(AppError::DoesNotExist(format!("Activity with id {} does not exist.", activity_doc.activity_id())))?
-                );
-            }
-            let activity_name = activity_cache
-                .get(activity_doc.activity_id())
-                .ok_or(AppError::InternalServerError(format!(
-                    "Activity entry with id {} should exist in the cache",
-                    activity_doc.activity_id()
-                )))?
-                .name();
-
-            if let Entry::Vacant(entry) = company_cache.entry(*activity_doc.company_id()) {
-                entry.insert(
-                    db_entities::Company::find_one(
-                        doc! {"_id": activity_doc.company_id()})
-                        .await
-                        .map_err(|e| AppError::InternalServerError(format!("An error occurred when retrieving the company document with id {}. Got error {}", activity_doc.company_id(), e)))?
-                        .ok_or(AppError::DoesNotExist(format!("Company with id {} does not exist.", activity_doc.company_id())))?
-                );
-            }
-            let company_name = company_cache
-                .get(activity_doc.company_id())
-                .ok_or(AppError::InternalServerError(format!(
-                    "Company entry with id {} should exist in the cache",
-                    activity_doc.company_id()
-                )))?
-                .name();
-
-            if let Entry::Vacant(entry) = project_cache.entry(*activity_doc.project_id()) {
-                entry.insert(
-                    db_entities::CompanyProject::find_one(
-                        doc! {"_id": activity_doc.project_id()})
-                        .await
-                        .map_err(|e| AppError::InternalServerError(format!("An error occurred when retrieving the project document with id {}. Got error {}", activity_doc.project_id(), e)))?
-                        .ok_or(AppError::DoesNotExist(format!("Project with id {} does not exist.", activity_doc.project_id())))?
-                );
-            }
-            let project_name = project_cache
-                .get(activity_doc.project_id())
-                .ok_or(AppError::InternalServerError(format!(
-                    "Project entry with id {} should exist in the cache",
-                    activity_doc.project_id()
-                )))?
-                .name();
-
-            // actually build the activity leveraging the cache
+            let activity_name = names
+                .activity_name(activity_doc.activity_id())
+                .map_err(name_error)?;
+            let company_name = names
+                .company_name(activity_doc.company_id())
+                .map_err(name_error)?;
+            let project_name = names
+                .project_name(activity_doc.project_id())
+                .map_err(name_error)?;
+
+            // actually build the activity with the loaded names
             let activity = web_app_response::TimesheetActivityHoursBuilder::default()
                 .activity_id(activity_doc.activity_id().to_hex())
                 .activity_name(activity_name.clone())
@@ -827,6 +2306,7 @@ pub async fn get_timesheet_days(
         let current_timesheet = builder.activities(current_activities).build().map_err(|e| AppError::InternalServerError(format!("An error occurred when building timesheet response for timesheet day with id {:?} with error {}", timesheet_doc.get_id(), e)))?;
         timesheets_to_return.push(current_timesheet);
     }
+    timesheets_to_return.sort_by_key(|day| day.date);
 
     Ok(timesheets_to_return)
 }
@@ -895,14 +2375,50 @@ pub async fn export_personal_timesheet(
     auth_info: impl AuthInfo,
     year: i32,
     month: u32,
+    format: TimesheetExportFormat,
 ) -> Result<Vec<u8>, AppError> {
     AccessControl::new(&auth_info).await?;
 
-    timesheet::export_as_excel(auth_info.user_id(), &year, &month)
+    timesheet::export(auth_info.user_id(), &year, &month, format)
         .await
         .map_err(|e| AppError::InternalServerError(e.to_string()))
 }
 
+pub async fn export_company_timesheet(
+    auth_info: impl AuthInfo,
+    company_id: DocumentId,
+    params: web_app_request::GetCompanyTimesheetExport,
+) -> Result<Vec<u8>, AppError> {
+    AccessControl::new(&auth_info)
+        .await?
+        .has_permission(&company_id, Permission::ViewTeamTimesheets)
+        .await?;
+
+    let mut user_ids = department_members(&company_id, params.department_id.as_ref()).await?;
+    if let Some(user_id) = params.user_id {
+        user_ids = Some(
+            user_ids
+                .unwrap_or(vec![user_id])
+                .into_iter()
+                .filter(|id| id == &user_id)
+                .collect(),
+        );
+    }
+    timesheet::export_company_as_excel(
+        &company_id,
+        params.from,
+        params.to,
+        params.project_id.map(|project_id| vec![project_id]),
+        user_ids,
+    )
+    .await
+    .map_err(|e| match e {
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    })
+}
+
 pub async fn get_eligible_companies_for_corporate_group(
     auth_info: impl AuthInfo,
 ) -> Result<Vec<web_app_response::CorporateGroupCompanyInfo>, AppError> {
@@ -958,6 +2474,8 @@ pub async fn get_user_corporate_groups(
                     .map(|elem| elem.to_hex())
                     .collect(),
                 company_names,
+                owner_id: group.owner().to_hex(),
+                admin_ids: group.admin_ids().iter().map(|elem| elem.to_hex()).collect(),
             })
         } else {
             return Err(AppError::InternalServerError(
@@ -997,11 +2515,137 @@ pub async fn edit_corporate_group(
         payload.company_ids,
     )
     .await
-    .map_err(|e| match e {
+    .map_err(corporate_group_error)
+}
+
+/// Maps the errors of the corporate group service
+fn corporate_group_error(e: ServiceAppError) -> AppError {
+    match e {
         ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
         ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+        ServiceAppError::AccessControlError(message) => AppError::AccessControlError(message),
         _ => AppError::InternalServerError(e.to_string()),
-    })
+    }
+}
+
+pub async fn set_corporate_group_admins(
+    auth_info: impl AuthInfo,
+    corporate_group_id: DocumentId,
+    payload: web_app_request::SetCorporateGroupAdmins,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info).await?;
+
+    corporate_group::set_corporate_group_admins(
+        auth_info.user_id(),
+        &corporate_group_id,
+        payload.user_ids,
+    )
+    .await
+    .map_err(corporate_group_error)
+}
+
+pub async fn get_joinable_corporate_groups(
+    auth_info: impl AuthInfo,
+) -> Result<Vec<web_app_response::CorporateGroupSummary>, AppError> {
+    AccessControl::new(&auth_info).await?;
+
+    Ok(
+        corporate_group::get_joinable_corporate_groups(auth_info.user_id())
+            .await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?
+            .into_iter()
+            .map(web_app_response::CorporateGroupSummary::from)
+            .collect(),
+    )
+}
+
+pub async fn create_corporate_group_join_request(
+    auth_info: impl AuthInfo,
+    corporate_group_id: DocumentId,
+    payload: web_app_request::CreateCorporateGroupJoinRequest,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info).await?;
+
+    corporate_group::create_join_request(
+        auth_info.user_id(),
+        &corporate_group_id,
+        &payload.company_id,
+    )
+    .await
+    .map_err(corporate_group_error)
+}
+
+pub async fn get_corporate_group_join_requests(
+    auth_info: impl AuthInfo,
+    corporate_group_id: DocumentId,
+) -> Result<Vec<web_app_response::CorporateGroupJoinRequest>, AppError> {
+    AccessControl::new(&auth_info).await?;
+
+    let join_requests =
+        corporate_group::get_pending_join_requests(auth_info.user_id(), &corporate_group_id)
+            .await
+            .map_err(corporate_group_error)?;
+    let company_ids: Vec<DocumentId> = join_requests
+        .iter()
+        .map(|join_request| *join_request.company_id())
+        .collect();
+    let company_names = company::get_company_names(&company_ids)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    let mut result = vec![];
+    for join_request in join_requests.into_iter() {
+        let Some(request_id) = join_request.get_id() else {
+            return Err(AppError::InternalServerError(
+                "Expected object id from join request read from database".into(),
+            ));
+        };
+        result.push(web_app_response::CorporateGroupJoinRequest {
+            id: request_id.to_hex(),
+            company_id: join_request.company_id().to_hex(),
+            company_name: company_names
+                .get(join_request.company_id())
+                .cloned()
+                .unwrap_or_default(),
+            requesting_user_id: join_request.requesting_user_id().to_hex(),
+            created_at: *join_request.created_at(),
+        });
+    }
+    Ok(result)
+}
+
+pub async fn answer_corporate_group_join_request(
+    auth_info: impl AuthInfo,
+    corporate_group_id: DocumentId,
+    request_id: DocumentId,
+    payload: web_app_request::AnswerCorporateGroupJoinRequest,
+) -> Result<(), AppError> {
+    AccessControl::new(&auth_info).await?;
+
+    corporate_group::answer_join_request(
+        auth_info.user_id(),
+        &corporate_group_id,
+        &request_id,
+        payload.accepted,
+    )
+    .await
+    .map_err(corporate_group_error)
+}
+
+pub async fn get_corporate_group_audit_trail(
+    auth_info: impl AuthInfo,
+    corporate_group_id: DocumentId,
+) -> Result<Vec<web_app_response::CorporateGroupAuditEntry>, AppError> {
+    AccessControl::new(&auth_info).await?;
+
+    Ok(
+        corporate_group::get_audit_trail(auth_info.user_id(), &corporate_group_id)
+            .await
+            .map_err(corporate_group_error)?
+            .into_iter()
+            .map(web_app_response::CorporateGroupAuditEntry::from)
+            .collect(),
+    )
 }
 
 pub async fn delete_corporate_group(
@@ -1018,3 +2662,150 @@ pub async fn delete_corporate_group(
             _ => AppError::InternalServerError(e.to_string()),
         })
 }
+
+/// Returns the corporate group if the user is its owner or at least admin of one of its companies
+async fn get_viewable_corporate_group(
+    user_id: &DocumentId,
+    corporate_group_id: &DocumentId,
+) -> Result<db_entities::CorporateGroup, AppError> {
+    let map_error = |e: ServiceAppError| match e {
+        ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+        _ => AppError::InternalServerError(e.to_string()),
+    };
+    let group = corporate_group::get_corporate_group(corporate_group_id)
+        .await
+        .map_err(map_error)?;
+    if corporate_group::is_group_owner_or_admin(user_id, &group)
+        .await
+        .map_err(map_error)?
+    {
+        Ok(group)
+    } else {
+        Err(AppError::AccessControlError(
+            "Only the group owner and the company admins can view the group report".into(),
+        ))
+    }
+}
+
+pub async fn get_corporate_group_report(
+    auth_info: impl AuthInfo,
+    corporate_group_id: DocumentId,
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+) -> Result<web_app_response::GroupReport, AppError> {
+    AccessControl::new(&auth_info).await?;
+    let group = get_viewable_corporate_group(auth_info.user_id(), &corporate_group_id).await?;
+
+    group_report::get_group_report(&group, from_date, to_date)
+        .await
+        .map(web_app_response::GroupReport::from)
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+pub async fn export_corporate_group_report(
+    auth_info: impl AuthInfo,
+    corporate_group_id: DocumentId,
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+) -> Result<Vec<u8>, AppError> {
+    AccessControl::new(&auth_info).await?;
+    let group = get_viewable_corporate_group(auth_info.user_id(), &corporate_group_id).await?;
+
+    group_report::export_group_report_as_excel(&group, from_date, to_date)
+        .await
+        .map_err(|e| match e {
+            ServiceAppError::InvalidRequest(message) => AppError::InvalidRequest(message),
+            _ => AppError::InternalServerError(e.to_string()),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use mongodb::bson::{doc, oid::ObjectId};
+
+    use crate::{
+        auth::JWTAuthClaim,
+        dtos::web_app_request,
+        enums::CompanyRole,
+        model::db_entities,
+        service::db::{get_database_service, DatabaseDocument},
+    };
+
+    use super::{nominate_company_owner, remove_company_user};
+
+    #[tokio::test]
+    async fn remove_nominated_user_test() {
+        let mut company = db_entities::Company::new("My Company".into(), true);
+        let company_id = ObjectId::from_str(&company.save(None).await.unwrap()).unwrap();
+        let mut user_ids = vec![];
+        for (username, role) in [
+            ("johnsmith", CompanyRole::Owner),
+            ("janesmith", CompanyRole::User),
+            ("jimsmith", CompanyRole::User),
+        ] {
+            let mut user = db_entities::User::new(
+                format!("{username}@mail.com"),
+                username.into(),
+                "fdsg39av2".into(),
+                "John".into(),
+                "Smith".into(),
+                None,
+                false,
+                true,
+            );
+            let user_id = ObjectId::from_str(&user.save(None).await.unwrap()).unwrap();
+            let mut assignment = db_entities::UserCompanyAssignment::new(
+                user_id,
+                company_id,
+                role,
+                "Employee".into(),
+                vec![],
+            );
+            assignment.save(None).await.unwrap();
+            user_ids.push(user_id);
+        }
+        let owner = JWTAuthClaim {
+            exp: 2000000000,
+            user_id: user_ids[0],
+            username: "johnsmith".into(),
+        };
+
+        let result = nominate_company_owner(
+            owner.clone(),
+            company_id,
+            web_app_request::NominateCompanyOwner {
+                user_id: user_ids[1],
+            },
+        )
+        .await;
+        assert!(result.is_ok());
+
+        let result = remove_company_user(owner.clone(), user_ids[1], company_id).await;
+        assert!(result.is_ok());
+        let transfer = db_entities::CompanyOwnershipTransfer::find_one(
+            doc! {"company_id": company_id, "nominee_id": user_ids[1]},
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(*transfer.answer(), Some(false));
+
+        let result = nominate_company_owner(
+            owner,
+            company_id,
+            web_app_request::NominateCompanyOwner {
+                user_id: user_ids[2],
+            },
+        )
+        .await;
+        assert!(result.is_ok());
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+}
@@ -7,7 +7,7 @@ use axum::{
 use employees_manager::{
     middleware::{add_cors_middleware, add_logging_middleware},
     router::{ADMIN_ROUTER, SDK_ROUTER, WEB_APP_ROUTER},
-    service::{db::get_database_service, environment::ENVIRONMENT},
+    service::{company, db::get_database_service, environment::ENVIRONMENT},
 };
 use tracing_subscriber::fmt::writer::MakeWriterExt;
 
@@ -27,6 +27,16 @@ async fn main() {
     // initialize database service
     get_database_service().await;
 
+    // bring the invitations saved by previous versions to the current schema
+    match company::migrate_legacy_invites().await {
+        Ok(0) => {}
+        Ok(total) => tracing::info!("Migrated {total} legacy invites"),
+        Err(e) => tracing::error!("Failed to migrate legacy invites. Got {e}"),
+    }
+
+    // mark expired invitations in background
+    tokio::spawn(company::run_invite_expiry_sweep());
+
     // build our application two routes, one for the sdk and the other for web application
     let mut app = Router::new()
         // `GET /` goes to `root`
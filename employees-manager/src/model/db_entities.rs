@@ -1,11 +1,15 @@
 use crate::{
-    enums::{CompanyRole, EmployeeRequest, NotificationType, WorkingDayType},
+    enums::{
+        CalendarDaySource, CompanyRole, CorporateGroupAction, EmployeeRequest, InviteStatus,
+        InvoiceStatus, LeaveAccrualType, NotificationType, Permission, ProjectRole, WorkingDayType,
+    },
     error::DatabaseError,
     service::db::DatabaseDocument,
     DocumentId,
 };
 use bson::{self, doc, Bson};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Utc, Weekday};
+use chrono_tz::Tz;
 use mongodb::bson::oid::ObjectId;
 use paste::paste;
 use serde::{Deserialize, Serialize};
@@ -158,6 +162,39 @@ database_document!(
     user_ids: Vec<DocumentId>
 );
 
+database_document!(
+    #[doc = "Role defined by the Company that bundles some permissions,"]
+    #[doc = "its users have them in addition to the ones of their CompanyRole"]
+    CustomCompanyRole,
+    "custom_company_role",
+    company_id: DocumentId,
+    name: String,
+    permissions: Vec<Permission>,
+    user_ids: Vec<DocumentId>
+);
+
+database_document!(
+    #[doc = "Reporting line of a Company employee, the manager is another employee"]
+    #[doc = "of the same Company. Users without a reporting line are at the top of the org chart"]
+    ReportingLine,
+    "reporting_line",
+    company_id: DocumentId,
+    user_id: DocumentId,
+    manager_id: DocumentId
+);
+
+database_document!(
+    #[doc = "Department or team inside a Company, an employee can be member of many of them"]
+    Department,
+    "department",
+    company_id: DocumentId,
+    name: String,
+    lead_ids: Vec<DocumentId>,
+    user_ids: Vec<DocumentId>,
+    #[doc = "Projects the new members are allocated to when they join the department"]
+    default_project_ids: Vec<DocumentId>
+);
+
 database_document!(
     #[doc = "Struct representing a company that has some employees"]
     Company,
@@ -172,7 +209,14 @@ database_document!(
     "company_employee_request",
     user_id: DocumentId,
     company_id: DocumentId,
-    request: EmployeeRequest
+    request: EmployeeRequest,
+    #[doc = "The calendar date of the request stored at midnight UTC,"]
+    #[doc = "None for the requests saved before the requests had a date"]
+    #[serde(
+        default,
+        with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional"
+    )]
+    date: Option<DateTime<Utc>>
 );
 
 database_document!(
@@ -196,7 +240,147 @@ database_document!(
     company_role: CompanyRole,
     job_title: String,
     project_ids: Vec<DocumentId>,
-    answer: Option<bool>
+    #[doc = "Invites saved before the lifecycle states have the answer field instead,"]
+    #[doc = "they are brought to the current schema by `company::migrate_legacy_invites`"]
+    #[serde(default)]
+    status: InviteStatus,
+    #[serde(default, with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    created_at: DateTime<Utc>,
+    #[doc = "After this date the invite cannot be answered and it is marked as Expired"]
+    #[serde(default, with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    expiration_date: DateTime<Utc>,
+    #[doc = "When the invite has been accepted, declined, cancelled or marked as expired"]
+    #[serde(
+        default,
+        with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional"
+    )]
+    closed_at: Option<DateTime<Utc>>
+);
+
+database_document!(
+    #[doc = "Settings of the company used to validate the timesheet of its employees"]
+    CompanySettings,
+    "company_settings",
+    company_id: DocumentId,
+    #[doc = "Standard number of working hours in a day"]
+    daily_hours: u32,
+    working_weekdays: Vec<Weekday>,
+    #[doc = "IANA timezone name, e.g. Europe/Rome"]
+    timezone: String,
+    #[doc = "Maximum number of hours that can be logged for the company in a single day"]
+    max_daily_hours: u32,
+    allowed_working_types: Vec<WorkingDayType>
+);
+
+impl CompanySettings {
+    /// Settings applied to companies that have not configured them yet
+    pub fn default_for_company(company_id: DocumentId) -> Self {
+        Self::new(
+            company_id,
+            8,
+            vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            "UTC".into(),
+            12,
+            vec![
+                WorkingDayType::Office,
+                WorkingDayType::Remote,
+                WorkingDayType::DayOff,
+                WorkingDayType::Holiday,
+                WorkingDayType::CompanyClosure,
+                WorkingDayType::Sick,
+            ],
+        )
+    }
+
+    /// Returns the timezone of the company, UTC if the stored name is not valid
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+}
+
+database_document!(
+    #[doc = "Configuration of the holiday and permission hours of the user in the company"]
+    #[doc = ""]
+    #[doc = "The balance is computed from the timesheet starting from the start date"]
+    LeaveBalance,
+    "leave_balance",
+    user_id: DocumentId,
+    company_id: DocumentId,
+    yearly_allowance_hours: u32,
+    accrual_type: LeaveAccrualType,
+    #[doc = "Maximum number of hours carried over to the next year"]
+    carry_over_cap_hours: u32,
+    #[doc = "Carried over hours not used by the end of this month are lost, None if they never expire"]
+    carry_over_expiry_month: Option<u32>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    start_date: DateTime<Utc>,
+    #[doc = "Hours available at the start date"]
+    opening_balance_hours: f64
+);
+
+database_document!(
+    #[doc = "Public holiday or closure day in the calendar of the company"]
+    CompanyCalendarDay,
+    "company_calendar_day",
+    company_id: DocumentId,
+    #[doc = "The calendar date stored at midnight UTC"]
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    date: DateTime<Utc>,
+    #[doc = "Either Holiday or CompanyClosure"]
+    day_type: WorkingDayType,
+    name: String,
+    source: CalendarDaySource
+);
+
+database_document!(
+    #[doc = "Request of the company owner to hand the company over to another member"]
+    #[doc = ""]
+    #[doc = "The nominee accepts or declines it through an AppNotification. Transfers forced"]
+    #[doc = "by a platform admin are stored as already accepted with the id of the admin"]
+    CompanyOwnershipTransfer,
+    "company_ownership_transfer",
+    company_id: DocumentId,
+    owner_id: DocumentId,
+    nominee_id: DocumentId,
+    answer: Option<bool>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    created_at: DateTime<Utc>,
+    forced_by: Option<DocumentId>
+);
+
+database_document!(
+    #[doc = "Invite sent by email to a person that is not registered in the app yet"]
+    #[doc = ""]
+    #[doc = "The person receives a signed link and, once registered through it, is added"]
+    #[doc = "to the company with the given role, job title and projects"]
+    InviteAddCompanyByEmail,
+    "invite_add_company_by_email",
+    inviting_user_id: DocumentId,
+    email: String,
+    company_id: DocumentId,
+    company_role: CompanyRole,
+    job_title: String,
+    project_ids: Vec<DocumentId>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    expiration_date: DateTime<Utc>,
+    #[doc = "The user created with the invite, it is None while the invite is pending"]
+    registered_user_id: Option<DocumentId>,
+    #[serde(default)]
+    status: InviteStatus,
+    #[serde(default, with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    created_at: DateTime<Utc>,
+    #[doc = "When the person has registered or the invite has been marked as expired"]
+    #[serde(
+        default,
+        with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional"
+    )]
+    closed_at: Option<DateTime<Utc>>
 );
 
 database_document!(
@@ -206,9 +390,90 @@ database_document!(
     name: String,
     code: String,
     company_id: DocumentId,
+    active: bool,
+    #[doc = "Client billed for the hours logged on the project"]
+    client_id: Option<DocumentId>
+);
+
+database_document!(
+    #[doc = "Customer of the Company, it is billed for the hours logged on its projects"]
+    Client,
+    "client",
+    company_id: DocumentId,
+    name: String,
+    #[doc = "ISO 4217 code of the currency of the rates billed to the client"]
+    currency: String,
     active: bool
 );
 
+database_document!(
+    #[doc = "Invoice of the hours billed to a client of the company in a period"]
+    #[doc = ""]
+    #[doc = "The lines are computed from the timesheet when the draft is created and they"]
+    #[doc = "do not change afterwards, only the status does"]
+    Invoice,
+    "invoice",
+    company_id: DocumentId,
+    client_id: DocumentId,
+    #[doc = "Progressive number of the invoice in the company, never reused"]
+    number: u32,
+    status: InvoiceStatus,
+    #[doc = "The calendar date stored at midnight UTC"]
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    from_date: DateTime<Utc>,
+    #[doc = "The calendar date stored at midnight UTC, it is included in the period"]
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    to_date: DateTime<Utc>,
+    currency: String,
+    lines: Vec<InvoiceLine>,
+    total_amount: f64,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    created_at: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional")]
+    issued_at: Option<DateTime<Utc>>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime_optional")]
+    voided_at: Option<DateTime<Utc>>
+);
+
+embedded_document!(
+    #[doc = "Hours of an activity of a project billed at the same hourly rate"]
+    #[doc = "It is a document inside the Invoice."]
+    InvoiceLine,
+    project_id: DocumentId,
+    project_name: String,
+    activity_id: DocumentId,
+    activity_name: String,
+    hours: u32,
+    hourly_rate: f64,
+    amount: f64
+);
+
+database_document!(
+    #[doc = "Last invoice number used by the company"]
+    InvoiceSequence,
+    "invoice_sequence",
+    company_id: DocumentId,
+    last_number: u32
+);
+
+database_document!(
+    #[doc = "Hourly rate billed for the hours logged in the timesheet"]
+    #[doc = ""]
+    #[doc = "A rate targets exactly one of project, activity or job title and it is valid from"]
+    #[doc = "its effective date until the next rate with the same target takes effect."]
+    #[doc = "The amount is in the currency of the client of the project."]
+    BillableRate,
+    "billable_rate",
+    company_id: DocumentId,
+    project_id: Option<DocumentId>,
+    activity_id: Option<DocumentId>,
+    job_title: Option<String>,
+    hourly_rate: f64,
+    #[doc = "The calendar date stored at midnight UTC"]
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    effective_from: DateTime<Utc>
+);
+
 database_document!(
     #[doc = "Define a type of activity that can be done in the Project."]
     #[doc  = "It is defined at Company level and can be associated to any Project."]
@@ -217,7 +482,51 @@ database_document!(
     "project_activity",
     name: String,
     description: String,
-    company_id: DocumentId
+    company_id: DocumentId,
+    #[doc = "When true the hours logged on the activity are billed to the client of the project"]
+    #[serde(default)]
+    billable: bool
+);
+
+database_document!(
+    #[doc = "Hour budget of the Project, the hours logged in the timesheet between"]
+    #[doc = "start and end date are counted against it"]
+    ProjectBudget,
+    "project_budget",
+    project_id: DocumentId,
+    company_id: DocumentId,
+    total_hours: u32,
+    #[doc = "Optional split of the total hours on some activities of the project"]
+    activity_budgets: Vec<ActivityHourBudget>,
+    #[doc = "The calendar date stored at midnight UTC"]
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    start_date: DateTime<Utc>,
+    #[doc = "The calendar date stored at midnight UTC, it is included in the budget"]
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    end_date: DateTime<Utc>,
+    #[doc = "Percentages of the total hours that notify the company admins once reached"]
+    notification_thresholds: Vec<u32>,
+    #[doc = "Thresholds already notified, a threshold is notified again only after"]
+    #[doc = "the logged hours went back below it"]
+    notified_thresholds: Vec<u32>
+);
+
+embedded_document!(
+    #[doc = "Hours of a project budget reserved to an activity"]
+    #[doc = "It is a document inside the ProjectBudget."]
+    ActivityHourBudget,
+    activity_id: DocumentId,
+    hours: u32
+);
+
+database_document!(
+    #[doc = "Role of a Company employee on a single Project of the Company"]
+    ProjectRoleAssignment,
+    "project_role_assignment",
+    company_id: DocumentId,
+    project_id: DocumentId,
+    user_id: DocumentId,
+    role: ProjectRole
 );
 
 database_document!(
@@ -251,6 +560,18 @@ impl From<TimesheetActivityHours> for Bson {
     }
 }
 
+impl From<TimesheetActivityHours> for internal::TimesheetActivityHours {
+    fn from(value: TimesheetActivityHours) -> Self {
+        Self {
+            company_id: value.company_id,
+            project_id: value.project_id,
+            activity_id: value.activity_id,
+            notes: value.notes,
+            hours: value.hours,
+        }
+    }
+}
+
 impl From<internal::TimesheetActivityHours> for TimesheetActivityHours {
     fn from(value: internal::TimesheetActivityHours) -> Self {
         Self {
@@ -274,16 +595,63 @@ database_document!(
     date: DateTime<Utc>,
     permit_hours: u32,
     working_type: WorkingDayType,
+    activities: Vec<TimesheetActivityHours>,
+    #[doc = "When true the day can be logged outside of the working weekdays of the company"]
+    #[serde(default)]
+    overtime: bool
+);
+
+database_document!(
+    #[doc = "Template of a timesheet day defined by the User to fill many days at once."]
+    #[doc = "The activities are the lines written in each day the template is applied to."]
+    TimesheetTemplate,
+    "timesheet_template",
+    user_id: DocumentId,
+    name: String,
+    working_type: WorkingDayType,
     activities: Vec<TimesheetActivityHours>
 );
 
 database_document!(
     #[doc = "Corporate Group groups together a set of Companies letting managers to have a global view."]
-    #[doc = "Admins of each company are automatically admins of the group."]
+    #[doc = "Admins of each company can view the group, while the owner and the group admins manage"]
+    #[doc = "its companies and answer the requests to join it."]
     CorporateGroup,
     "corporate_group",
     name: String,
     company_ids: Vec<DocumentId>,
     #[doc = "The user that created the corporate group"]
-    owner: DocumentId
+    owner: DocumentId,
+    #[doc = "Users that manage the group together with the owner"]
+    #[serde(default)]
+    admin_ids: Vec<DocumentId>
+);
+
+database_document!(
+    #[doc = "Request of a company admin to add the company to a corporate group."]
+    #[doc = "The answer is None while the owner or an admin of the group has not approved or refused it"]
+    CorporateGroupJoinRequest,
+    "corporate_group_join_request",
+    group_id: DocumentId,
+    company_id: DocumentId,
+    requesting_user_id: DocumentId,
+    answer: Option<bool>,
+    answered_by: Option<DocumentId>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    created_at: DateTime<Utc>
+);
+
+database_document!(
+    #[doc = "Entry of the audit trail of the membership changes of a corporate group."]
+    #[doc = "The company or the target user are set depending on the action"]
+    CorporateGroupAuditEntry,
+    "corporate_group_audit_entry",
+    group_id: DocumentId,
+    #[doc = "The user that made the change"]
+    user_id: DocumentId,
+    action: CorporateGroupAction,
+    company_id: Option<DocumentId>,
+    target_user_id: Option<DocumentId>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    created_at: DateTime<Utc>
 );
@@ -1,4 +1,11 @@
-use crate::{dtos::web_app_request, enums::CompanyRole, DocumentId};
+use chrono::{DateTime, Days, Months, NaiveDate, Utc, Weekday};
+
+use crate::{
+    dtos::web_app_request,
+    enums::{CompanyRole, EmployeeRequest, InviteStatus, Permission, WorkingDayType},
+    error::{ServiceAppError, ValidationViolation},
+    DocumentId,
+};
 
 /// Internal data type returned by the user service to the facade for the admin panel overview
 #[derive(Default)]
@@ -37,6 +44,30 @@ pub struct UserInCompanyInfo {
     pub management_team: bool,
 }
 
+/// Internal data type returned by the company role service with a built-in or custom role
+pub struct CompanyRoleDefinition {
+    /// the id of the custom role, None for the built-in roles
+    pub id: Option<DocumentId>,
+    pub name: String,
+    pub permissions: Vec<Permission>,
+    pub user_ids: Vec<DocumentId>,
+}
+
+/// Internal data type with an employee of the org chart and the employees reporting to it
+pub struct OrgChartNode {
+    pub user: UserInCompanyInfo,
+    pub reports: Vec<OrgChartNode>,
+}
+
+/// Internal data type with a day of leave of a team member, either recorded in the
+/// timesheet or requested and not refused
+pub struct TeamLeaveDay {
+    pub user_id: DocumentId,
+    pub date: NaiveDate,
+    pub working_type: Option<WorkingDayType>,
+    pub request: Option<EmployeeRequest>,
+}
+
 pub struct InvitedUserInCompanyInfo {
     pub notification_id: String,
     pub user_id: String,
@@ -46,6 +77,86 @@ pub struct InvitedUserInCompanyInfo {
     pub company_id: String,
 }
 
+/// Internal data type returned by the company service with an entry of the invitation history
+pub struct InvitationHistoryEntry {
+    pub invite_id: DocumentId,
+    /// None for the invites by email not used to register yet
+    pub invited_user_id: Option<DocumentId>,
+    pub invited_username: String,
+    /// the email of the invites sent by email
+    pub invited_email: Option<String>,
+    pub inviting_user_id: DocumentId,
+    pub inviting_username: String,
+    pub role: CompanyRole,
+    pub job_title: String,
+    pub status: InviteStatus,
+    pub created_at: DateTime<Utc>,
+    pub expiration_date: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// Internal data type returned by the leave service with the balance movements of a month
+pub struct LeaveBalanceMonth {
+    pub year: i32,
+    pub month: u32,
+    /// hours carried over from the previous year, set only in January
+    pub carried_over: f64,
+    pub accrued: f64,
+    pub consumed: f64,
+    /// carried over hours lost because not used before the expiry month
+    pub expired: f64,
+    /// balance at the end of the month
+    pub balance: f64,
+}
+
+/// Internal data type returned by the project budget service with the hours logged on a project
+pub struct ProjectBudgetStatus {
+    pub logged_hours: u32,
+    /// negative when the budget is exceeded
+    pub remaining_hours: i64,
+    pub activities: Vec<ProjectActivityBudgetStatus>,
+    pub burn_down: Vec<ProjectBurnDownWeek>,
+    /// day in which the budget has been used up or is expected to be at the current pace,
+    /// None when no hours have been logged yet
+    pub forecast_completion_date: Option<NaiveDate>,
+}
+
+/// Internal data type with the hours logged on an activity of a project with a budget
+pub struct ProjectActivityBudgetStatus {
+    pub activity_id: DocumentId,
+    pub activity_name: String,
+    /// None when the budget has no hours reserved to the activity
+    pub budget_hours: Option<u32>,
+    pub logged_hours: u32,
+}
+
+/// Internal data type with a week of the burn-down of a project budget
+pub struct ProjectBurnDownWeek {
+    /// Monday of the week
+    pub week_start: NaiveDate,
+    pub logged_hours: u32,
+    /// hours left at the end of the week
+    pub remaining_hours: i64,
+}
+
+/// Internal data type with the billable hours of a user on an activity of a project
+/// billed at the same hourly rate
+pub struct BillableLine {
+    pub client_id: DocumentId,
+    pub client_name: String,
+    pub currency: String,
+    pub project_id: DocumentId,
+    pub project_name: String,
+    pub activity_id: DocumentId,
+    pub activity_name: String,
+    pub user_id: DocumentId,
+    pub username: String,
+    /// None when no rate applies to the hours, they are not billed
+    pub hourly_rate: Option<f64>,
+    pub hours: u32,
+    pub amount: f64,
+}
+
 /// Internal data type that contains working hours for a single project activity
 pub struct TimesheetActivityHours {
     pub company_id: DocumentId,
@@ -66,3 +177,214 @@ impl From<web_app_request::TimesheetActivityHours> for TimesheetActivityHours {
         }
     }
 }
+
+/// Internal data type with the outcome of a timesheet import
+pub struct TimesheetImportReport {
+    /// when true the file has only been validated
+    pub dry_run: bool,
+    /// true when the days have been saved, that happens only when there are no errors
+    pub committed: bool,
+    pub days: usize,
+    pub activities: usize,
+    pub errors: Vec<TimesheetImportRowError>,
+}
+
+/// Error found in a row of an imported timesheet file
+pub struct TimesheetImportRowError {
+    /// row number in the file, the header is the first row
+    pub row: usize,
+    pub violation: ValidationViolation,
+}
+
+/// Internal data type with a timesheet day whose activities violate the project
+/// allocations of the user or the activity assignments of the projects
+pub struct TimesheetConsistencyIssue {
+    pub day_id: DocumentId,
+    pub user_id: DocumentId,
+    pub username: String,
+    pub date: DateTime<Utc>,
+    pub violations: Vec<ValidationViolation>,
+}
+
+/// Internal data type with the outcome of the creation of many timesheet days
+#[derive(Default)]
+pub struct TimesheetBulkReport {
+    pub created: Vec<NaiveDate>,
+    pub skipped: Vec<TimesheetSkippedDay>,
+}
+
+/// Day not created by a bulk operation with the reason
+pub struct TimesheetSkippedDay {
+    pub date: NaiveDate,
+    pub reason: String,
+}
+
+/// Period of the timesheet queried by the clients
+#[derive(Debug, PartialEq)]
+pub enum TimesheetPeriod {
+    Month {
+        year: i32,
+        month: u32,
+    },
+    /// week of the ISO 8601 week-numbering year, from Monday to Sunday
+    IsoWeek {
+        year: i32,
+        week: u32,
+    },
+    /// days between the two dates, both included
+    Range {
+        from: NaiveDate,
+        to: NaiveDate,
+    },
+}
+
+impl TimesheetPeriod {
+    /// Returns the first and the last day of the period, or None when the period is not valid
+    pub fn date_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        match *self {
+            TimesheetPeriod::Month { year, month } => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                Some((first, first.checked_add_months(Months::new(1))?.pred_opt()?))
+            }
+            TimesheetPeriod::IsoWeek { year, week } => {
+                let monday = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)?;
+                Some((monday, monday.checked_add_days(Days::new(6))?))
+            }
+            TimesheetPeriod::Range { from, to } => (from <= to).then_some((from, to)),
+        }
+    }
+}
+
+impl TryFrom<web_app_request::GetUserTimesheetDays> for TimesheetPeriod {
+    type Error = ServiceAppError;
+
+    fn try_from(value: web_app_request::GetUserTimesheetDays) -> Result<Self, Self::Error> {
+        match value {
+            web_app_request::GetUserTimesheetDays {
+                from: Some(from),
+                to: Some(to),
+                year: None,
+                month: None,
+                week: None,
+            } => Ok(Self::Range { from, to }),
+            web_app_request::GetUserTimesheetDays {
+                year: Some(year),
+                month: Some(month),
+                week: None,
+                from: None,
+                to: None,
+            } => Ok(Self::Month { year, month }),
+            web_app_request::GetUserTimesheetDays {
+                year: Some(year),
+                week: Some(week),
+                month: None,
+                from: None,
+                to: None,
+            } => Ok(Self::IsoWeek { year, week }),
+            _ => Err(ServiceAppError::InvalidRequest(
+                "The period must be either year and month, year and week or from and to".into(),
+            )),
+        }
+    }
+}
+
+/// Internal data type with the figures of the consolidated report of a corporate group
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct GroupStats {
+    pub headcount: u32,
+    pub logged_hours: u32,
+    pub office_days: u32,
+    pub remote_days: u32,
+    pub day_off_days: u32,
+    pub sick_days: u32,
+    /// hours of the days off, at the daily hours of the company, and of the permits
+    pub leave_hours: f64,
+}
+
+impl GroupStats {
+    /// Share of the remote days over the remote and office days, zero without any of them
+    pub fn remote_ratio(&self) -> f64 {
+        let working_days = self.office_days + self.remote_days;
+        if working_days == 0 {
+            0.0
+        } else {
+            self.remote_days as f64 / working_days as f64
+        }
+    }
+}
+
+/// Internal data type with the figures of a company of the corporate group
+pub struct GroupCompanyReport {
+    pub company_id: DocumentId,
+    pub company_name: String,
+    pub stats: GroupStats,
+}
+
+/// Internal data type with the hours logged on an activity of a project of a company of the group
+pub struct GroupHoursLine {
+    pub company_id: DocumentId,
+    pub company_name: String,
+    pub project_id: DocumentId,
+    pub project_name: String,
+    pub activity_id: DocumentId,
+    pub activity_name: String,
+    pub hours: u32,
+}
+
+/// Internal data type with the consolidated report of a corporate group
+pub struct GroupReport {
+    pub companies: Vec<GroupCompanyReport>,
+    pub hours: Vec<GroupHoursLine>,
+    /// the headcount counts once the employees of more companies of the group
+    pub totals: GroupStats,
+}
+
+/// Internal data type with the id and the name of a corporate group, shown to the
+/// users that are not members of the group
+pub struct CorporateGroupSummary {
+    pub id: DocumentId,
+    pub name: String,
+}
+
+/// Internal data type with an employee of a company of the corporate group.
+/// A user that belongs to more companies of the group has one entry for each company
+pub struct GroupDirectoryEntry {
+    pub user_id: DocumentId,
+    pub username: String,
+    /// hidden to the users that cannot view the users of their company
+    pub email: Option<String>,
+    pub name: String,
+    pub surname: String,
+    pub company_id: DocumentId,
+    pub company_name: String,
+    pub job_title: String,
+}
+
+/// Filters of the corporate group directory, the texts are matched ignoring case
+#[derive(Default)]
+pub struct GroupDirectoryFilter {
+    /// matched against username, name and surname
+    pub name: Option<String>,
+    pub job_title: Option<String>,
+    pub company_id: Option<DocumentId>,
+}
+
+impl GroupDirectoryFilter {
+    /// Returns true if the entry satisfies all the filters that are set
+    pub fn matches(&self, entry: &GroupDirectoryEntry) -> bool {
+        let contains =
+            |text: &str, search: &str| text.to_lowercase().contains(&search.to_lowercase());
+        self.name.as_ref().is_none_or(|name| {
+            contains(&entry.username, name)
+                || contains(&entry.name, name)
+                || contains(&entry.surname, name)
+                || contains(&format!("{} {}", entry.name, entry.surname), name)
+        }) && self
+            .job_title
+            .as_ref()
+            .is_none_or(|job_title| contains(&entry.job_title, job_title))
+            && self
+                .company_id
+                .is_none_or(|company_id| company_id == entry.company_id)
+    }
+}
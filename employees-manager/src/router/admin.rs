@@ -29,6 +29,10 @@ pub static ADMIN_ROUTER: Lazy<Router> = Lazy::new(|| {
         .route("/user/{id}/activate", delete(deactivate_platform_admin))
         .route("/user/{id}", delete(delete_user))
         .route("/user/{id}", get(get_user))
+        .route(
+            "/company/{id}/owner",
+            post(force_company_ownership_transfer),
+        )
 });
 
 /// Returns overview of all users and companies in application
@@ -72,6 +76,15 @@ async fn delete_user(jwt_claim: JWTAuthClaim, Path(id): Path<DocumentId>) -> Res
     facade::delete_user(jwt_claim, id).await
 }
 
+/// Transfer the ownership of the company without the agreement of the new owner
+async fn force_company_ownership_transfer(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::NominateCompanyOwner>,
+) -> Result<(), AppError> {
+    facade::force_company_ownership_transfer(jwt_claim, id, payload).await
+}
+
 async fn set_platform_admin(
     jwt_claim: JWTAuthClaim,
     Path(id): Path<DocumentId>,
@@ -5,10 +5,12 @@ use crate::{
         web_app_response::{self, AppNotification, CompanyInfo, UserInCompanyInfo},
         AppJson, ResponseWithHeader,
     },
+    enums::{Permission, WorkingDayType},
     DocumentId,
 };
 
 use axum::{
+    body::Bytes,
     extract::{Path, Query},
     http::header,
     response::IntoResponse,
@@ -24,22 +26,71 @@ pub static WEB_APP_ROUTER: Lazy<Router> = Lazy::new(|| {
     Router::new()
         .route("/auth/login", post(authorize))
         .route("/auth/user", get(get_auth_user_data))
+        .route("/auth/invite/{token}", get(get_email_invite))
+        .route("/auth/register", post(register_with_invite))
         .route("/notification", get(get_unread_notifications))
         .route(
             "/notification/invite-add-company/{id}",
             patch(answer_to_invite_add_company),
         )
+        .route(
+            "/notification/ownership-transfer/{id}",
+            patch(answer_to_ownership_transfer),
+        )
         .route("/notification/{id}/read", patch(set_notification_as_read))
         .route("/company", get(get_companies_of_user))
         .route("/company/{id}/user", get(get_users_in_company))
         .route("/company", post(create_company))
         .route("/company/{id}/role", patch(change_user_company_role))
+        .route(
+            "/company/{id}/ownership-transfer",
+            post(nominate_company_owner),
+        )
         .route("/company/{id}/job-title", patch(change_user_job_title))
         .route("/company/{id}/manager", patch(change_user_company_manager))
+        .route("/company/{id}/management-team", get(get_management_team))
+        .route("/company/{id}/management-team", patch(set_management_team))
+        .route("/company/{id}/permission", get(get_company_permissions))
+        .route("/company/{id}/org-chart", get(get_org_chart))
+        .route("/company/{id}/department", get(get_departments))
+        .route("/company/{id}/department", post(create_department))
+        .route(
+            "/company/{id}/department/{department_id}",
+            patch(edit_department),
+        )
+        .route(
+            "/company/{id}/department/{department_id}",
+            delete(delete_department),
+        )
+        .route(
+            "/company/{id}/department/{department_id}/members",
+            patch(set_department_members),
+        )
+        .route(
+            "/company/{id}/user/{user_id}/manager",
+            patch(set_user_manager),
+        )
+        .route(
+            "/company/{id}/user/{user_id}/reports",
+            get(get_user_reports),
+        )
+        .route(
+            "/company/{id}/user/{user_id}/team-leave",
+            get(get_team_leave_calendar),
+        )
+        .route("/company/{id}/role", get(get_company_roles))
+        .route("/company/{id}/role", post(create_custom_role))
+        .route("/company/{id}/role/{role_id}", patch(edit_custom_role))
+        .route("/company/{id}/role/{role_id}", delete(delete_custom_role))
+        .route(
+            "/company/{id}/role/{role_id}/users",
+            patch(set_custom_role_users),
+        )
         .route(
             "/company/{id}/user-to-invite",
             get(get_users_to_invite_in_company),
         )
+        .route("/company/{id}/group-directory", get(search_group_directory))
         .route(
             "/company/{id}/pending-user",
             get(get_pending_invited_users_in_company),
@@ -49,7 +100,57 @@ pub static WEB_APP_ROUTER: Lazy<Router> = Lazy::new(|| {
             delete(cancel_invite_user_to_company),
         )
         .route("/company/{id}/invite-user", post(invite_user_to_company))
+        .route(
+            "/company/{id}/invite-email",
+            post(invite_user_to_company_by_email),
+        )
+        .route(
+            "/company/{id}/invite/{invite_id}/resend",
+            post(resend_invite_to_company),
+        )
+        .route(
+            "/company/{id}/invite-history",
+            get(get_company_invitation_history),
+        )
         .route("/company/{id}/user/{user_id}", delete(remove_company_user))
+        .route(
+            "/company/{id}/leave-balance/{user_id}",
+            get(get_leave_balance_history),
+        )
+        .route(
+            "/company/{id}/leave-balance/{user_id}",
+            patch(set_leave_balance),
+        )
+        .route("/company/{id}/leave-request", post(create_leave_request))
+        .route("/company/{id}/leave-request", get(get_leave_requests))
+        .route(
+            "/company/{id}/leave-request/{request_id}",
+            patch(answer_leave_request),
+        )
+        .route(
+            "/company/{id}/user/{user_id}/timesheet-day",
+            get(get_team_timesheet_days),
+        )
+        .route("/calendar/national-rule-set", get(get_national_rule_sets))
+        .route("/company/{id}/calendar", get(get_company_calendar_days))
+        .route(
+            "/company/{id}/calendar/national",
+            post(import_national_holidays),
+        )
+        .route(
+            "/company/{id}/calendar/ics",
+            post(import_company_calendar_ics),
+        )
+        .route(
+            "/company/{id}/calendar/closure",
+            post(add_company_closure_day),
+        )
+        .route(
+            "/company/{id}/calendar/{calendar_day_id}",
+            delete(delete_company_calendar_day),
+        )
+        .route("/company/{id}/settings", get(get_company_settings))
+        .route("/company/{id}/settings", patch(edit_company_settings))
         .route("/company/{id}/project", get(get_company_projects))
         .route(
             "/company/{id}/project-allocation/{project_id}",
@@ -76,6 +177,52 @@ pub static WEB_APP_ROUTER: Lazy<Router> = Lazy::new(|| {
             "/company/{id}/project/{project_id}",
             delete(delete_company_project),
         )
+        .route(
+            "/company/{id}/project/{project_id}/budget",
+            patch(set_project_budget),
+        )
+        .route(
+            "/company/{id}/project/{project_id}/budget",
+            delete(delete_project_budget),
+        )
+        .route(
+            "/company/{id}/project/{project_id}/status",
+            get(get_project_budget_status),
+        )
+        .route(
+            "/company/{id}/project/{project_id}/role",
+            get(get_project_roles),
+        )
+        .route(
+            "/company/{id}/project/{project_id}/role/{user_id}",
+            patch(set_project_role),
+        )
+        .route(
+            "/company/{id}/project/{project_id}/role/{user_id}",
+            delete(remove_project_role),
+        )
+        .route("/company/{id}/client", get(get_clients))
+        .route("/company/{id}/client", post(create_client))
+        .route("/company/{id}/client/{client_id}", patch(edit_client))
+        .route("/company/{id}/client/{client_id}", delete(delete_client))
+        .route("/company/{id}/billable-rate", get(get_billable_rates))
+        .route("/company/{id}/billable-rate", post(create_billable_rate))
+        .route(
+            "/company/{id}/billable-rate/{rate_id}",
+            delete(delete_billable_rate),
+        )
+        .route("/company/{id}/billable-amounts", get(get_billable_amounts))
+        .route("/company/{id}/invoice", get(get_invoices))
+        .route("/company/{id}/invoice", post(create_invoice_draft))
+        .route("/company/{id}/invoice/{invoice_id}", get(get_invoice))
+        .route(
+            "/company/{id}/invoice/{invoice_id}/status",
+            patch(set_invoice_status),
+        )
+        .route(
+            "/company/{id}/invoice/{invoice_id}/export",
+            get(export_invoice),
+        )
         .route("/company/{id}/activity", post(create_project_activity))
         .route("/company/{id}/activity", get(get_project_activities))
         .route(
@@ -108,6 +255,24 @@ pub static WEB_APP_ROUTER: Lazy<Router> = Lazy::new(|| {
         )
         .route("/user/{id}/timesheet-day", post(create_timesheet_day))
         .route("/user/{id}/timesheet-day", get(get_timesheet_days))
+        .route("/user/{id}/timesheet-import", post(import_timesheet))
+        .route("/user/{id}/timesheet-day/copy", post(copy_timesheet_days))
+        .route(
+            "/user/{id}/timesheet-template",
+            get(get_timesheet_templates),
+        )
+        .route(
+            "/user/{id}/timesheet-template",
+            post(create_timesheet_template),
+        )
+        .route(
+            "/user/{id}/timesheet-template/{template_id}",
+            delete(delete_timesheet_template),
+        )
+        .route(
+            "/user/{id}/timesheet-template/{template_id}/apply",
+            post(apply_timesheet_template),
+        )
         .route(
             "/corporate-group/eligible-company",
             get(get_eligible_companies_for_corporate_group),
@@ -115,8 +280,48 @@ pub static WEB_APP_ROUTER: Lazy<Router> = Lazy::new(|| {
         .route("/corporate-group", get(get_user_corporate_groups))
         .route("/corporate-group", post(create_corporate_group))
         .route("/corporate-group/{id}", delete(delete_corporate_group))
+        .route(
+            "/corporate-group/joinable",
+            get(get_joinable_corporate_groups),
+        )
         .route("/corporate-group/{id}", patch(edit_corporate_group))
+        .route(
+            "/corporate-group/{id}/admins",
+            patch(set_corporate_group_admins),
+        )
+        .route(
+            "/corporate-group/{id}/join-request",
+            get(get_corporate_group_join_requests),
+        )
+        .route(
+            "/corporate-group/{id}/join-request",
+            post(create_corporate_group_join_request),
+        )
+        .route(
+            "/corporate-group/{id}/join-request/{request_id}",
+            patch(answer_corporate_group_join_request),
+        )
+        .route(
+            "/corporate-group/{id}/audit",
+            get(get_corporate_group_audit_trail),
+        )
+        .route(
+            "/corporate-group/{id}/report",
+            get(get_corporate_group_report),
+        )
+        .route(
+            "/corporate-group/{id}/report/export",
+            get(export_corporate_group_report),
+        )
         .route("/user/timesheet-export", get(export_personal_timesheet))
+        .route(
+            "/company/{id}/timesheet-export",
+            get(export_company_timesheet),
+        )
+        .route(
+            "/company/{id}/timesheet-consistency",
+            get(get_timesheet_consistency_report),
+        )
 });
 
 /// Authorize a user with username and password providing jwt token
@@ -161,6 +366,18 @@ async fn answer_to_invite_add_company(
         .map(AppJson)
 }
 
+/// Answer of the nominee to the ownership transfer of a company
+/// PATCH /notification/ownership-transfer/{id}
+async fn answer_to_ownership_transfer(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::OwnershipTransferAnswer>,
+) -> Result<AppJson<()>, AppError> {
+    facade::answer_to_ownership_transfer(jwt_claim, id, payload)
+        .await
+        .map(AppJson)
+}
+
 async fn get_companies_of_user(
     jwt_claim: JWTAuthClaim,
 ) -> Result<AppJson<Vec<CompanyInfo>>, AppError> {
@@ -184,6 +401,16 @@ async fn change_user_company_role(
     facade::change_user_company_role(jwt_claim, id, payload).await
 }
 
+/// The owner nominates another member of the Company as the new owner
+/// POST /company/{id}/ownership-transfer
+async fn nominate_company_owner(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::NominateCompanyOwner>,
+) -> Result<(), AppError> {
+    facade::nominate_company_owner(jwt_claim, id, payload).await
+}
+
 async fn change_user_job_title(
     jwt_claim: JWTAuthClaim,
     Path(id): Path<DocumentId>,
@@ -192,6 +419,168 @@ async fn change_user_job_title(
     facade::change_user_company_job_title(jwt_claim, id, payload).await
 }
 
+/// GET /company/{id}/department
+async fn get_departments(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<web_app_response::DepartmentInfo>>, AppError> {
+    facade::get_departments(jwt_claim, id).await.map(AppJson)
+}
+
+/// POST /company/{id}/department
+async fn create_department(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::CreateDepartment>,
+) -> Result<(), AppError> {
+    facade::create_department(jwt_claim, id, payload).await
+}
+
+/// PATCH /company/{id}/department/{department_id}
+async fn edit_department(
+    jwt_claim: JWTAuthClaim,
+    Path((id, department_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::EditDepartment>,
+) -> Result<(), AppError> {
+    facade::edit_department(jwt_claim, id, department_id, payload).await
+}
+
+/// DELETE /company/{id}/department/{department_id}
+async fn delete_department(
+    jwt_claim: JWTAuthClaim,
+    Path((id, department_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<(), AppError> {
+    facade::delete_department(jwt_claim, id, department_id).await
+}
+
+/// Replace the members of the department
+/// PATCH /company/{id}/department/{department_id}/members
+async fn set_department_members(
+    jwt_claim: JWTAuthClaim,
+    Path((id, department_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::SetDepartmentMembers>,
+) -> Result<(), AppError> {
+    facade::set_department_members(jwt_claim, id, department_id, payload).await
+}
+
+/// Returns the org chart of the company as a list of trees of employees
+/// GET /company/{id}/org-chart
+async fn get_org_chart(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<web_app_response::OrgChartNode>>, AppError> {
+    facade::get_org_chart(jwt_claim, id).await.map(AppJson)
+}
+
+/// Set or remove the manager of the employee
+/// PATCH /company/{id}/user/{user_id}/manager
+async fn set_user_manager(
+    jwt_claim: JWTAuthClaim,
+    Path((id, user_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::SetUserManager>,
+) -> Result<(), AppError> {
+    facade::set_user_manager(jwt_claim, id, user_id, payload).await
+}
+
+/// GET /company/{id}/user/{user_id}/reports?indirect=true
+async fn get_user_reports(
+    jwt_claim: JWTAuthClaim,
+    Path((id, user_id)): Path<(DocumentId, DocumentId)>,
+    Query(query): Query<web_app_request::GetUserReports>,
+) -> Result<AppJson<Vec<String>>, AppError> {
+    facade::get_user_reports(jwt_claim, id, user_id, query)
+        .await
+        .map(AppJson)
+}
+
+/// Returns the days of leave of the employees reporting to the user
+/// GET /company/{id}/user/{user_id}/team-leave?from=2025-01-01&to=2025-01-31
+async fn get_team_leave_calendar(
+    jwt_claim: JWTAuthClaim,
+    Path((id, user_id)): Path<(DocumentId, DocumentId)>,
+    Query(query): Query<web_app_request::GetTeamLeaveCalendar>,
+) -> Result<AppJson<Vec<web_app_response::TeamLeaveDay>>, AppError> {
+    facade::get_team_leave_calendar(jwt_claim, id, user_id, query)
+        .await
+        .map(AppJson)
+}
+
+/// Returns the permissions of the current user in the company
+/// GET /company/{id}/permission
+async fn get_company_permissions(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<Permission>>, AppError> {
+    facade::get_company_permissions(jwt_claim, id)
+        .await
+        .map(AppJson)
+}
+
+/// Returns the built-in and custom roles of the company with their permissions
+/// GET /company/{id}/role
+async fn get_company_roles(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<web_app_response::CompanyRoleDefinition>>, AppError> {
+    facade::get_company_roles(jwt_claim, id).await.map(AppJson)
+}
+
+/// POST /company/{id}/role
+async fn create_custom_role(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::CreateCustomRole>,
+) -> Result<(), AppError> {
+    facade::create_custom_role(jwt_claim, id, payload).await
+}
+
+/// PATCH /company/{id}/role/{role_id}
+async fn edit_custom_role(
+    jwt_claim: JWTAuthClaim,
+    Path((id, role_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::EditCustomRole>,
+) -> Result<(), AppError> {
+    facade::edit_custom_role(jwt_claim, id, role_id, payload).await
+}
+
+/// DELETE /company/{id}/role/{role_id}
+async fn delete_custom_role(
+    jwt_claim: JWTAuthClaim,
+    Path((id, role_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<(), AppError> {
+    facade::delete_custom_role(jwt_claim, id, role_id).await
+}
+
+/// Replace the users the custom role is assigned to
+/// PATCH /company/{id}/role/{role_id}/users
+async fn set_custom_role_users(
+    jwt_claim: JWTAuthClaim,
+    Path((id, role_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::SetCustomRoleUsers>,
+) -> Result<(), AppError> {
+    facade::set_custom_role_users(jwt_claim, id, role_id, payload).await
+}
+
+/// GET /company/{id}/management-team
+async fn get_management_team(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<String>>, AppError> {
+    facade::get_management_team(jwt_claim, id)
+        .await
+        .map(AppJson)
+}
+
+/// Replace the members of the management team
+/// PATCH /company/{id}/management-team
+async fn set_management_team(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::SetManagementTeam>,
+) -> Result<(), AppError> {
+    facade::set_management_team(jwt_claim, id, payload).await
+}
+
 async fn change_user_company_manager(
     jwt_claim: JWTAuthClaim,
     Path(id): Path<DocumentId>,
@@ -218,6 +607,207 @@ async fn invite_user_to_company(
     facade::invite_user_to_company(jwt_claim, id, payload).await
 }
 
+/// Returns the balance of holiday and permission hours month by month with the end of year projection
+/// Submit a leave request of the current user
+/// POST /company/{id}/leave-request
+async fn create_leave_request(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::CreateLeaveRequest>,
+) -> Result<(), AppError> {
+    facade::create_leave_request(jwt_claim, id, payload).await
+}
+
+/// GET /company/{id}/leave-request?onlyAwaiting=true
+async fn get_leave_requests(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(query): Query<web_app_request::GetLeaveRequests>,
+) -> Result<AppJson<Vec<web_app_response::LeaveRequest>>, AppError> {
+    facade::get_leave_requests(jwt_claim, id, query)
+        .await
+        .map(AppJson)
+}
+
+/// Accept or refuse the leave request
+/// PATCH /company/{id}/leave-request/{request_id}
+async fn answer_leave_request(
+    jwt_claim: JWTAuthClaim,
+    Path((id, request_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::AnswerLeaveRequest>,
+) -> Result<(), AppError> {
+    facade::answer_leave_request(jwt_claim, id, request_id, payload).await
+}
+
+/// Returns the timesheet days of an employee of the company
+/// GET /company/{id}/user/{user_id}/timesheet-day?year=2025&month=1
+async fn get_team_timesheet_days(
+    jwt_claim: JWTAuthClaim,
+    Path((id, user_id)): Path<(DocumentId, DocumentId)>,
+    Query(query): Query<web_app_request::GetUserTimesheetDays>,
+) -> Result<AppJson<Vec<web_app_response::TimesheetDay>>, AppError> {
+    facade::get_team_timesheet_days(jwt_claim, id, user_id, query)
+        .await
+        .map(AppJson)
+}
+
+/// GET /company/{id}/leave-balance/{user_id}?year=2025
+async fn get_leave_balance_history(
+    jwt_claim: JWTAuthClaim,
+    Path((id, user_id)): Path<(DocumentId, DocumentId)>,
+    Query(params): Query<web_app_request::GetLeaveBalanceHistory>,
+) -> Result<AppJson<web_app_response::LeaveBalanceHistory>, AppError> {
+    facade::get_leave_balance_history(jwt_claim, id, user_id, params.year)
+        .await
+        .map(AppJson)
+}
+
+/// Configure allowance, accrual and carry-over of the leave balance of the user
+/// PATCH /company/{id}/leave-balance/{user_id}
+async fn set_leave_balance(
+    jwt_claim: JWTAuthClaim,
+    Path((id, user_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::SetLeaveBalance>,
+) -> Result<(), AppError> {
+    facade::set_leave_balance(jwt_claim, id, user_id, payload).await
+}
+
+/// Returns the countries with a bundled national holiday rule set
+/// GET /calendar/national-rule-set
+async fn get_national_rule_sets(
+    jwt_claim: JWTAuthClaim,
+) -> Result<AppJson<Vec<web_app_response::NationalRuleSet>>, AppError> {
+    facade::get_national_rule_sets(jwt_claim).await.map(AppJson)
+}
+
+/// Returns holidays and closure days of the Company between two dates
+/// GET /company/{id}/calendar?from=2025-01-01&to=2025-12-31
+async fn get_company_calendar_days(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(params): Query<web_app_request::GetCompanyCalendarDays>,
+) -> Result<AppJson<Vec<web_app_response::CompanyCalendarDay>>, AppError> {
+    facade::get_company_calendar_days(jwt_claim, id, params.from, params.to)
+        .await
+        .map(AppJson)
+}
+
+/// Add the national holidays of a year to the Company calendar, returns the number of new days
+/// POST /company/{id}/calendar/national
+async fn import_national_holidays(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::ImportNationalHolidays>,
+) -> Result<AppJson<usize>, AppError> {
+    facade::import_national_holidays(jwt_claim, id, payload)
+        .await
+        .map(AppJson)
+}
+
+/// Import the events of the ICS file sent as body, returns the number of new days
+/// POST /company/{id}/calendar/ics?dayType=CompanyClosure
+async fn import_company_calendar_ics(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(params): Query<web_app_request::ImportCompanyCalendarIcs>,
+    content: String,
+) -> Result<AppJson<usize>, AppError> {
+    facade::import_company_calendar_ics(
+        jwt_claim,
+        id,
+        params.day_type.unwrap_or(WorkingDayType::Holiday),
+        content,
+    )
+    .await
+    .map(AppJson)
+}
+
+/// Declare a closure day of the Company
+/// POST /company/{id}/calendar/closure
+async fn add_company_closure_day(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::AddCompanyClosureDay>,
+) -> Result<(), AppError> {
+    facade::add_company_closure_day(jwt_claim, id, payload).await
+}
+
+/// Remove a day from the Company calendar
+/// DELETE /company/{id}/calendar/{calendar_day_id}
+async fn delete_company_calendar_day(
+    jwt_claim: JWTAuthClaim,
+    Path((id, calendar_day_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<(), AppError> {
+    facade::delete_company_calendar_day(jwt_claim, id, calendar_day_id).await
+}
+
+/// Returns the settings of the Company used to validate the timesheet
+/// GET /company/{id}/settings
+async fn get_company_settings(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<web_app_response::CompanySettings>, AppError> {
+    facade::get_company_settings(jwt_claim, id)
+        .await
+        .map(AppJson)
+}
+
+/// Edit working hours, working weekdays, timezone and allowed working types of the Company
+/// PATCH /company/{id}/settings
+async fn edit_company_settings(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::EditCompanySettings>,
+) -> Result<(), AppError> {
+    facade::edit_company_settings(jwt_claim, id, payload).await
+}
+
+/// Send again a pending or expired invite
+/// POST /company/{id}/invite/{invite_id}/resend
+async fn resend_invite_to_company(
+    jwt_claim: JWTAuthClaim,
+    Path((id, invite_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<(), AppError> {
+    facade::resend_invite_to_company(jwt_claim, id, invite_id).await
+}
+
+/// Returns accepted, declined, cancelled, expired and pending invites of the Company
+/// GET /company/{id}/invite-history
+async fn get_company_invitation_history(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<web_app_response::InvitationHistoryEntry>>, AppError> {
+    facade::get_company_invitation_history(jwt_claim, id)
+        .await
+        .map(AppJson)
+}
+
+/// Invite to the Company a person that may not be registered yet
+/// POST /company/{id}/invite-email
+async fn invite_user_to_company_by_email(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::InviteUserToCompanyByEmail>,
+) -> Result<(), AppError> {
+    facade::invite_user_to_company_by_email(jwt_claim, id, payload).await
+}
+
+/// Returns the invitation received by email to show it before the registration
+/// GET /auth/invite/{token}
+async fn get_email_invite(
+    Path(token): Path<String>,
+) -> Result<AppJson<web_app_response::EmailInviteInfo>, AppError> {
+    facade::get_email_invite(token).await.map(AppJson)
+}
+
+/// Register a new user with the invitation received by email
+/// POST /auth/register
+async fn register_with_invite(
+    Json(payload): Json<web_app_request::RegisterWithInvite>,
+) -> Result<AppJson<String>, AppError> {
+    facade::register_with_invite(payload).await.map(AppJson)
+}
+
 /// Remove user from the Company
 async fn remove_company_user(
     jwt_claim: JWTAuthClaim,
@@ -227,6 +817,9 @@ async fn remove_company_user(
     Ok(AppJson(()))
 }
 
+/// Returns the users that can be invited in the Company. Unless the user is platform admin,
+/// they are restricted to the employees of the corporate group of the Company
+/// GET /company/{id}/user-to-invite
 async fn get_users_to_invite_in_company(
     jwt_claim: JWTAuthClaim,
     Path(id): Path<DocumentId>,
@@ -236,6 +829,19 @@ async fn get_users_to_invite_in_company(
         .map(AppJson)
 }
 
+/// Returns the employees of the companies of the corporate group of the Company, or only of the
+/// Company when it does not belong to a group, filtered by name, job title and company
+/// GET /company/{id}/group-directory?name=...&jobTitle=...&companyId=...
+async fn search_group_directory(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(params): Query<web_app_request::SearchGroupDirectory>,
+) -> Result<AppJson<Vec<web_app_response::GroupDirectoryEntry>>, AppError> {
+    facade::search_group_directory(jwt_claim, id, params)
+        .await
+        .map(AppJson)
+}
+
 async fn get_pending_invited_users_in_company(
     jwt_claim: JWTAuthClaim,
     Path(id): Path<DocumentId>,
@@ -310,6 +916,202 @@ async fn delete_company_project(
         .map(AppJson)
 }
 
+/// Create or replace the hour budget of the project
+/// PATCH /company/{id}/project/{project_id}/budget
+async fn set_project_budget(
+    jwt_claim: JWTAuthClaim,
+    Path((id, project_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::SetProjectBudget>,
+) -> Result<(), AppError> {
+    facade::set_project_budget(jwt_claim, id, project_id, payload).await
+}
+
+/// DELETE /company/{id}/project/{project_id}/budget
+async fn delete_project_budget(
+    jwt_claim: JWTAuthClaim,
+    Path((id, project_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<(), AppError> {
+    facade::delete_project_budget(jwt_claim, id, project_id).await
+}
+
+/// Returns the hours logged on the project against its budget, the weekly burn-down and the forecast completion date
+/// GET /company/{id}/project/{project_id}/status
+async fn get_project_budget_status(
+    jwt_claim: JWTAuthClaim,
+    Path((id, project_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<AppJson<web_app_response::ProjectBudgetStatus>, AppError> {
+    facade::get_project_budget_status(jwt_claim, id, project_id)
+        .await
+        .map(AppJson)
+}
+
+/// Returns the users with a role explicitly assigned on the project
+/// GET /company/{id}/project/{project_id}/role
+async fn get_project_roles(
+    jwt_claim: JWTAuthClaim,
+    Path((id, project_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<AppJson<Vec<web_app_response::ProjectRoleInfo>>, AppError> {
+    facade::get_project_roles(jwt_claim, id, project_id)
+        .await
+        .map(AppJson)
+}
+
+/// PATCH /company/{id}/project/{project_id}/role/{user_id}
+async fn set_project_role(
+    jwt_claim: JWTAuthClaim,
+    Path((id, project_id, user_id)): Path<(DocumentId, DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::SetProjectRole>,
+) -> Result<(), AppError> {
+    facade::set_project_role(jwt_claim, id, project_id, user_id, payload).await
+}
+
+/// DELETE /company/{id}/project/{project_id}/role/{user_id}
+async fn remove_project_role(
+    jwt_claim: JWTAuthClaim,
+    Path((id, project_id, user_id)): Path<(DocumentId, DocumentId, DocumentId)>,
+) -> Result<(), AppError> {
+    facade::remove_project_role(jwt_claim, id, project_id, user_id).await
+}
+
+/// GET /company/{id}/client
+async fn get_clients(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<web_app_response::ClientInfo>>, AppError> {
+    facade::get_clients(jwt_claim, id).await.map(AppJson)
+}
+
+/// Create a client of the company, returns its id
+/// POST /company/{id}/client
+async fn create_client(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::CreateClient>,
+) -> Result<AppJson<String>, AppError> {
+    facade::create_client(jwt_claim, id, payload)
+        .await
+        .map(AppJson)
+}
+
+/// PATCH /company/{id}/client/{client_id}
+async fn edit_client(
+    jwt_claim: JWTAuthClaim,
+    Path((id, client_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::EditClient>,
+) -> Result<(), AppError> {
+    facade::edit_client(jwt_claim, id, client_id, payload).await
+}
+
+/// DELETE /company/{id}/client/{client_id}
+async fn delete_client(
+    jwt_claim: JWTAuthClaim,
+    Path((id, client_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<(), AppError> {
+    facade::delete_client(jwt_claim, id, client_id).await
+}
+
+/// GET /company/{id}/billable-rate
+async fn get_billable_rates(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<web_app_response::BillableRate>>, AppError> {
+    facade::get_billable_rates(jwt_claim, id).await.map(AppJson)
+}
+
+/// Create an hourly rate for a project, an activity or a job title, returns its id
+/// POST /company/{id}/billable-rate
+async fn create_billable_rate(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::CreateBillableRate>,
+) -> Result<AppJson<String>, AppError> {
+    facade::create_billable_rate(jwt_claim, id, payload)
+        .await
+        .map(AppJson)
+}
+
+/// DELETE /company/{id}/billable-rate/{rate_id}
+async fn delete_billable_rate(
+    jwt_claim: JWTAuthClaim,
+    Path((id, rate_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<(), AppError> {
+    facade::delete_billable_rate(jwt_claim, id, rate_id).await
+}
+
+/// Returns the hours and amounts billable to the clients of the company in the period
+/// GET /company/{id}/billable-amounts?from=2025-01-01&to=2025-01-31&clientId=...
+async fn get_billable_amounts(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(params): Query<web_app_request::GetBillableAmounts>,
+) -> Result<AppJson<web_app_response::BillableAmounts>, AppError> {
+    facade::get_billable_amounts(jwt_claim, id, params)
+        .await
+        .map(AppJson)
+}
+
+/// GET /company/{id}/invoice?clientId=...&status=Draft
+async fn get_invoices(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(params): Query<web_app_request::GetInvoices>,
+) -> Result<AppJson<Vec<web_app_response::Invoice>>, AppError> {
+    facade::get_invoices(jwt_claim, id, params)
+        .await
+        .map(AppJson)
+}
+
+/// Create the draft invoice of the billable hours of a client in a period, returns its id
+/// POST /company/{id}/invoice
+async fn create_invoice_draft(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::CreateInvoiceDraft>,
+) -> Result<AppJson<String>, AppError> {
+    facade::create_invoice_draft(jwt_claim, id, payload)
+        .await
+        .map(AppJson)
+}
+
+/// GET /company/{id}/invoice/{invoice_id}
+async fn get_invoice(
+    jwt_claim: JWTAuthClaim,
+    Path((id, invoice_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<AppJson<web_app_response::Invoice>, AppError> {
+    facade::get_invoice(jwt_claim, id, invoice_id)
+        .await
+        .map(AppJson)
+}
+
+/// Issue or void the invoice
+/// PATCH /company/{id}/invoice/{invoice_id}/status
+async fn set_invoice_status(
+    jwt_claim: JWTAuthClaim,
+    Path((id, invoice_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::SetInvoiceStatus>,
+) -> Result<(), AppError> {
+    facade::set_invoice_status(jwt_claim, id, invoice_id, payload).await
+}
+
+/// Export the invoice as excel file
+/// GET /company/{id}/invoice/{invoice_id}/export
+async fn export_invoice(
+    jwt_claim: JWTAuthClaim,
+    Path((id, invoice_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<impl IntoResponse, AppError> {
+    facade::export_invoice(jwt_claim, id, invoice_id)
+        .await
+        .map(|content| {
+            ResponseWithHeader::new(content).with_header(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_str(
+                    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                )
+                .unwrap(),
+            )
+        })
+}
+
 async fn edit_company_project_allocations_by_project(
     jwt_claim: JWTAuthClaim,
     Path((id, project_id)): Path<(DocumentId, DocumentId)>,
@@ -426,12 +1228,16 @@ async fn create_timesheet_day(
         .map(AppJson)
 }
 
+/// Returns the timesheet days of the user in a month, an ISO week or a date range
+/// GET /user/{id}/timesheet-day?year=2025&month=1
+/// GET /user/{id}/timesheet-day?year=2025&week=1
+/// GET /user/{id}/timesheet-day?from=2024-12-30&to=2025-01-05
 async fn get_timesheet_days(
     jwt_claim: JWTAuthClaim,
     Path(id): Path<DocumentId>,
-    query: Query<web_app_request::GetUserTimesheetDays>,
+    Query(query): Query<web_app_request::GetUserTimesheetDays>,
 ) -> Result<AppJson<Vec<web_app_response::TimesheetDay>>, AppError> {
-    facade::get_timesheet_days(jwt_claim, id, query.year, query.month)
+    facade::get_timesheet_days(jwt_claim, id, query)
         .await
         .map(AppJson)
 }
@@ -489,11 +1295,204 @@ async fn edit_corporate_group(
         .map(AppJson)
 }
 
+/// Replace the admins of the corporate group, only the owner can choose them
+/// PATCH /corporate-group/{id}/admins
+async fn set_corporate_group_admins(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::SetCorporateGroupAdmins>,
+) -> Result<AppJson<()>, AppError> {
+    facade::set_corporate_group_admins(jwt_claim, id, payload)
+        .await
+        .map(AppJson)
+}
+
+/// Returns the corporate groups the user can ask to join with one of its companies
+/// GET /corporate-group/joinable
+async fn get_joinable_corporate_groups(
+    jwt_claim: JWTAuthClaim,
+) -> Result<AppJson<Vec<web_app_response::CorporateGroupSummary>>, AppError> {
+    facade::get_joinable_corporate_groups(jwt_claim)
+        .await
+        .map(AppJson)
+}
+
+/// Ask to add a company, of which the user is at least admin, to the corporate group
+/// POST /corporate-group/{id}/join-request
+async fn create_corporate_group_join_request(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::CreateCorporateGroupJoinRequest>,
+) -> Result<AppJson<()>, AppError> {
+    facade::create_corporate_group_join_request(jwt_claim, id, payload)
+        .await
+        .map(AppJson)
+}
+
+/// Returns the requests to join the corporate group awaiting an answer
+/// GET /corporate-group/{id}/join-request
+async fn get_corporate_group_join_requests(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<web_app_response::CorporateGroupJoinRequest>>, AppError> {
+    facade::get_corporate_group_join_requests(jwt_claim, id)
+        .await
+        .map(AppJson)
+}
+
+/// Approve or refuse the request to join the corporate group
+/// PATCH /corporate-group/{id}/join-request/{request_id}
+async fn answer_corporate_group_join_request(
+    jwt_claim: JWTAuthClaim,
+    Path((id, request_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::AnswerCorporateGroupJoinRequest>,
+) -> Result<AppJson<()>, AppError> {
+    facade::answer_corporate_group_join_request(jwt_claim, id, request_id, payload)
+        .await
+        .map(AppJson)
+}
+
+/// Returns the audit trail of the membership changes of the corporate group
+/// GET /corporate-group/{id}/audit
+async fn get_corporate_group_audit_trail(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<web_app_response::CorporateGroupAuditEntry>>, AppError> {
+    facade::get_corporate_group_audit_trail(jwt_claim, id)
+        .await
+        .map(AppJson)
+}
+
+/// Returns headcount, hours by company, project and activity, remote and office days
+/// and leave usage of all the companies of the corporate group
+/// GET /corporate-group/{id}/report?from=2025-01-01&to=2025-12-31
+async fn get_corporate_group_report(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(params): Query<web_app_request::GetGroupReport>,
+) -> Result<AppJson<web_app_response::GroupReport>, AppError> {
+    facade::get_corporate_group_report(jwt_claim, id, params.from, params.to)
+        .await
+        .map(AppJson)
+}
+
+/// Export the corporate group report as excel file, with a summary sheet and a sheet with the hours
+/// GET /corporate-group/{id}/report/export?from=2025-01-01&to=2025-12-31
+async fn export_corporate_group_report(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(params): Query<web_app_request::GetGroupReport>,
+) -> Result<impl IntoResponse, AppError> {
+    facade::export_corporate_group_report(jwt_claim, id, params.from, params.to)
+        .await
+        .map(|content| {
+            ResponseWithHeader::new(content).with_header(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_str(
+                    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                )
+                .unwrap(),
+            )
+        })
+}
+
+/// Copy the previous day or week of the user into the given day or week
+/// POST /user/{id}/timesheet-day/copy
+async fn copy_timesheet_days(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::CopyTimesheetDays>,
+) -> Result<AppJson<web_app_response::TimesheetBulkReport>, AppError> {
+    facade::copy_timesheet_days(jwt_claim, id, payload)
+        .await
+        .map(AppJson)
+}
+
+/// Returns the timesheet templates of the user
+/// GET /user/{id}/timesheet-template
+async fn get_timesheet_templates(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+) -> Result<AppJson<Vec<web_app_response::TimesheetTemplate>>, AppError> {
+    facade::get_timesheet_templates(jwt_claim, id)
+        .await
+        .map(AppJson)
+}
+
+/// Create a timesheet template of the user
+/// POST /user/{id}/timesheet-template
+async fn create_timesheet_template(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Json(payload): Json<web_app_request::CreateTimesheetTemplate>,
+) -> Result<(), AppError> {
+    facade::create_timesheet_template(jwt_claim, id, payload).await
+}
+
+/// Delete a timesheet template of the user
+/// DELETE /user/{id}/timesheet-template/{template_id}
+async fn delete_timesheet_template(
+    jwt_claim: JWTAuthClaim,
+    Path((id, template_id)): Path<(DocumentId, DocumentId)>,
+) -> Result<(), AppError> {
+    facade::delete_timesheet_template(jwt_claim, id, template_id).await
+}
+
+/// Fill the days of a date range with the activities of the template
+/// POST /user/{id}/timesheet-template/{template_id}/apply
+async fn apply_timesheet_template(
+    jwt_claim: JWTAuthClaim,
+    Path((id, template_id)): Path<(DocumentId, DocumentId)>,
+    Json(payload): Json<web_app_request::ApplyTimesheetTemplate>,
+) -> Result<AppJson<web_app_response::TimesheetBulkReport>, AppError> {
+    facade::apply_timesheet_template(jwt_claim, id, template_id, payload)
+        .await
+        .map(AppJson)
+}
+
+/// Import the timesheet days from the csv, xlsx or ods file sent as body, in the export layout
+/// POST /user/{id}/timesheet-import?format=csv&dryRun=true
+async fn import_timesheet(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(params): Query<web_app_request::ImportTimesheet>,
+    content: Bytes,
+) -> Result<AppJson<web_app_response::TimesheetImportReport>, AppError> {
+    facade::import_timesheet(
+        jwt_claim,
+        id,
+        params.format,
+        params.dry_run,
+        content.to_vec(),
+    )
+    .await
+    .map(AppJson)
+}
+
+/// Export the timesheet of the month of the logged user as csv, xlsx or ods
+/// GET /user/timesheet-export?year=2025&month=1&format=csv
 async fn export_personal_timesheet(
     jwt_claim: JWTAuthClaim,
     query: Query<web_app_request::GetUserTimesheetExport>,
 ) -> Result<impl IntoResponse, AppError> {
-    facade::export_personal_timesheet(jwt_claim, query.year, query.month)
+    facade::export_personal_timesheet(jwt_claim, query.year, query.month, query.format)
+        .await
+        .map(|content| {
+            ResponseWithHeader::new(content).with_header(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static(query.format.content_type()),
+            )
+        })
+}
+
+/// Export the timesheets of the Company employees, with one sheet per employee and a summary sheet
+/// GET /company/{id}/timesheet-export?from=2025-01-01&to=2025-01-31&projectId=...&userId=...&departmentId=...
+async fn export_company_timesheet(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(params): Query<web_app_request::GetCompanyTimesheetExport>,
+) -> Result<impl IntoResponse, AppError> {
+    facade::export_company_timesheet(jwt_claim, id, params)
         .await
         .map(|content| {
             ResponseWithHeader::new(content).with_header(
@@ -505,3 +1504,22 @@ async fn export_personal_timesheet(
             )
         })
 }
+
+/// Returns the timesheet days of the Company employees with activities on projects they are
+/// not allocated to or on activities not assigned to the project
+/// GET /company/{id}/timesheet-consistency?from=2025-01-01&to=2025-12-31&departmentId=...
+async fn get_timesheet_consistency_report(
+    jwt_claim: JWTAuthClaim,
+    Path(id): Path<DocumentId>,
+    Query(params): Query<web_app_request::GetTimesheetConsistencyReport>,
+) -> Result<AppJson<Vec<web_app_response::TimesheetConsistencyIssue>>, AppError> {
+    facade::get_timesheet_consistency_report(
+        jwt_claim,
+        id,
+        params.from,
+        params.to,
+        params.department_id,
+    )
+    .await
+    .map(AppJson)
+}
@@ -11,10 +11,21 @@
 //!
 
 pub mod access_control;
+pub mod billing;
+pub mod calendar;
 pub mod company;
+pub mod company_role;
 pub mod corporate_group;
 pub mod db;
+pub mod department;
 pub mod environment;
+pub mod group_report;
+pub mod invoice;
+pub mod leave;
+pub mod mailer;
 pub mod notification;
+pub mod org_chart;
+pub mod password_policy;
+pub mod project_budget;
 pub mod timesheet;
 pub mod user;
@@ -1,12 +1,17 @@
 use crate::{
     auth::AuthInfo,
-    enums::CompanyRole,
+    enums::{CompanyRole, Permission, ProjectRole},
     error::{AppError, ServiceAppError},
     service::user::get_user,
     DocumentId,
 };
 
-use super::company::get_user_company_role;
+use super::{
+    company::{get_user_company_role, get_user_project_role},
+    company_role::get_user_permissions,
+    department::is_lead_of,
+    org_chart::is_manager_of,
+};
 
 /// Access control struct that validate and verify the
 /// role of the user
@@ -66,12 +71,121 @@ impl<'a, T: AuthInfo> AccessControl<'a, T> {
             ))
         }
     }
+
+    /// Verify that the user has a higher role than the other user of the company.
+    /// The Owner is never outranked
+    pub async fn outranks_user(
+        self,
+        company_id: &DocumentId,
+        user_id: &DocumentId,
+    ) -> Result<Self, AppError> {
+        let own_assignment = get_user_company_role(self.auth_info.user_id(), company_id)
+            .await
+            .map_err(|_| {
+                AppError::AccessControlError("You are not allowed to do this operation".into())
+            })?;
+        let user_assignment =
+            get_user_company_role(user_id, company_id)
+                .await
+                .map_err(|e| match e {
+                    ServiceAppError::EntityDoesNotExist(message) => AppError::DoesNotExist(message),
+                    _ => AppError::InternalServerError(e.to_string()),
+                })?;
+        if *own_assignment.role() > *user_assignment.role() {
+            Ok(self)
+        } else {
+            Err(AppError::AccessControlError(
+                "You are not allowed to do this operation on a user with your role or a higher one"
+                    .into(),
+            ))
+        }
+    }
+
+    /// Verify that the user has the role indicated by the parameter on the
+    /// project of the company. Users that can manage the projects of the company
+    /// have every project role.
+    pub async fn has_project_role_or_higher(
+        self,
+        company_id: &DocumentId,
+        project_id: &DocumentId,
+        role: ProjectRole,
+    ) -> Result<Self, AppError> {
+        let user_id = self.auth_info.user_id();
+        let permissions = get_user_permissions(user_id, company_id)
+            .await
+            .map_err(|e| match e {
+                ServiceAppError::EntityDoesNotExist(_) => {
+                    AppError::AccessControlError("You are not allowed to do this operation".into())
+                }
+                _ => AppError::InternalServerError(e.to_string()),
+            })?;
+        if permissions.contains(&Permission::ManageProjects) {
+            return Ok(self);
+        }
+        let project_role = get_user_project_role(user_id, company_id, project_id)
+            .await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        if project_role.is_some_and(|project_role| project_role >= role) {
+            Ok(self)
+        } else {
+            Err(AppError::AccessControlError(
+                "You are not allowed to do this operation".into(),
+            ))
+        }
+    }
+
+    /// Verify that the user has the permission in the company from its built-in role,
+    /// its custom roles or the management team
+    pub async fn has_permission(
+        self,
+        company_id: &DocumentId,
+        permission: Permission,
+    ) -> Result<Self, AppError> {
+        let permissions = get_user_permissions(self.auth_info.user_id(), company_id)
+            .await
+            .map_err(|e| match e {
+                ServiceAppError::EntityDoesNotExist(_) => {
+                    AppError::AccessControlError("You are not allowed to do this operation".into())
+                }
+                _ => AppError::InternalServerError(e.to_string()),
+            })?;
+        if permissions.contains(&permission) {
+            Ok(self)
+        } else {
+            Err(AppError::AccessControlError(
+                "You are not allowed to do this operation".into(),
+            ))
+        }
+    }
+
+    /// Verify that the user manages, directly or indirectly, the employee of the
+    /// company, leads one of its departments or that it has the permission in the company
+    pub async fn manages_user_or_has_permission(
+        self,
+        company_id: &DocumentId,
+        user_id: &DocumentId,
+        permission: Permission,
+    ) -> Result<Self, AppError> {
+        let manager_id = self.auth_info.user_id();
+        let manages_user = is_manager_of(company_id, manager_id, user_id)
+            .await
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?
+            || is_lead_of(company_id, manager_id, user_id)
+                .await
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+        if manages_user {
+            Ok(self)
+        } else {
+            self.has_permission(company_id, permission).await
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
         auth::APIKeyAuthClaim,
+        enums::{CompanyRole, Permission, ProjectRole, MANAGEMENT_TEAM_PERMISSIONS},
         model::db_entities,
         service::{
             access_control::AccessControl,
@@ -136,4 +250,224 @@ mod tests {
         let drop_result = get_database_service().await.db.drop().await;
         assert!(drop_result.is_ok());
     }
+
+    #[tokio::test]
+    async fn outranks_user_test() {
+        let mut company = db_entities::Company::new("Company".into(), true);
+        company.save(None).await.unwrap();
+        let company_id = *company.get_id().unwrap();
+
+        let mut user_ids = vec![];
+        for (username, role) in [
+            ("owner", CompanyRole::Owner),
+            ("admin", CompanyRole::Admin),
+            ("user", CompanyRole::User),
+        ] {
+            let mut user = db_entities::User::new(
+                format!("{username}@mail.com"),
+                username.into(),
+                "fdsg39av2".into(),
+                "John".into(),
+                "Smith".into(),
+                Some(format!("api_key_{username}")),
+                false,
+                true,
+            );
+            user.save(None).await.unwrap();
+            let user_id = *user.get_id().unwrap();
+            let mut company_assignment = db_entities::UserCompanyAssignment::new(
+                user_id,
+                company_id,
+                role,
+                "Employee".into(),
+                vec![],
+            );
+            company_assignment.save(None).await.unwrap();
+            user_ids.push(user_id);
+        }
+
+        for (auth_index, user_index, expected) in [
+            (0, 1, true),
+            (0, 2, true),
+            (1, 0, false),
+            (1, 1, false),
+            (1, 2, true),
+            (2, 1, false),
+        ] {
+            let auth_info = APIKeyAuthClaim {
+                key: "api_key".into(),
+                user_id: user_ids[auth_index],
+            };
+            let access_control = AccessControl {
+                auth_info: &auth_info,
+            }
+            .outranks_user(&company_id, &user_ids[user_index])
+            .await;
+            assert_eq!(access_control.is_ok(), expected);
+        }
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn has_project_role_or_higher_test() {
+        let mut user = db_entities::User::new(
+            "jane.doe@mail.com".into(),
+            "janedoe".into(),
+            "fdsg39av2".into(),
+            "Jane".into(),
+            "Doe".into(),
+            Some("api_key_project".into()),
+            false,
+            true,
+        );
+        user.save(None).await.unwrap();
+        let user_id = *user.get_id().unwrap();
+
+        let mut company = db_entities::Company::new("Company".into(), true);
+        company.save(None).await.unwrap();
+        let company_id = *company.get_id().unwrap();
+
+        let mut project = db_entities::CompanyProject::new(
+            "Project".into(),
+            "PRJ".into(),
+            company_id,
+            true,
+            None,
+        );
+        project.save(None).await.unwrap();
+        let project_id = *project.get_id().unwrap();
+
+        let mut company_assignment = db_entities::UserCompanyAssignment::new(
+            user_id,
+            company_id,
+            CompanyRole::User,
+            "Developer".into(),
+            vec![],
+        );
+        company_assignment.save(None).await.unwrap();
+
+        let auth_info = APIKeyAuthClaim {
+            key: "api_key_project".into(),
+            user_id,
+        };
+        let check = |role: ProjectRole| {
+            let access_control = AccessControl {
+                auth_info: &auth_info,
+            };
+            async move {
+                access_control
+                    .has_project_role_or_higher(&company_id, &project_id, role)
+                    .await
+                    .is_ok()
+            }
+        };
+
+        // not allocated to the project
+        assert!(!check(ProjectRole::Member).await);
+
+        // allocated to the project
+        company_assignment.set_project_ids(vec![project_id]);
+        company_assignment.save(None).await.unwrap();
+        assert!(check(ProjectRole::Member).await);
+        assert!(!check(ProjectRole::Manager).await);
+
+        // project manager
+        let mut project_role = db_entities::ProjectRoleAssignment::new(
+            company_id,
+            project_id,
+            user_id,
+            ProjectRole::Manager,
+        );
+        project_role.save(None).await.unwrap();
+        assert!(check(ProjectRole::Manager).await);
+        project_role.delete(None).await.unwrap();
+
+        // company admin
+        company_assignment.set_role(CompanyRole::Admin);
+        company_assignment.save(None).await.unwrap();
+        assert!(check(ProjectRole::Manager).await);
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn has_permission_test() {
+        let mut user = db_entities::User::new(
+            "mark.brown@mail.com".into(),
+            "markbrown".into(),
+            "fdsg39av2".into(),
+            "Mark".into(),
+            "Brown".into(),
+            Some("api_key_permission".into()),
+            false,
+            true,
+        );
+        user.save(None).await.unwrap();
+        let user_id = *user.get_id().unwrap();
+
+        let mut company = db_entities::Company::new("Company".into(), true);
+        company.save(None).await.unwrap();
+        let company_id = *company.get_id().unwrap();
+
+        let mut company_assignment = db_entities::UserCompanyAssignment::new(
+            user_id,
+            company_id,
+            CompanyRole::User,
+            "HR".into(),
+            vec![],
+        );
+        company_assignment.save(None).await.unwrap();
+        let mut management_team = db_entities::CompanyManagementTeam::new(company_id, vec![]);
+        management_team.save(None).await.unwrap();
+
+        let auth_info = APIKeyAuthClaim {
+            key: "api_key_permission".into(),
+            user_id,
+        };
+        let check = |permission: Permission| {
+            let access_control = AccessControl {
+                auth_info: &auth_info,
+            };
+            async move {
+                access_control
+                    .has_permission(&company_id, permission)
+                    .await
+                    .is_ok()
+            }
+        };
+
+        assert!(!check(Permission::ViewTeamTimesheets).await);
+
+        // custom role
+        let mut custom_role = db_entities::CustomCompanyRole::new(
+            company_id,
+            "HR".into(),
+            vec![Permission::ViewTeamTimesheets],
+            vec![user_id],
+        );
+        custom_role.save(None).await.unwrap();
+        assert!(check(Permission::ViewTeamTimesheets).await);
+        assert!(!check(Permission::ManageProjects).await);
+
+        // management team
+        management_team.set_user_ids(vec![user_id]);
+        management_team.save(None).await.unwrap();
+        for permission in MANAGEMENT_TEAM_PERMISSIONS {
+            assert!(check(permission).await);
+        }
+        assert!(!check(Permission::ManageProjects).await);
+
+        // built-in admin role
+        company_assignment.set_role(CompanyRole::Admin);
+        company_assignment.save(None).await.unwrap();
+        for permission in Permission::ALL {
+            assert!(check(permission).await);
+        }
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
 }
@@ -0,0 +1,519 @@
+//! Billing service that computes the amounts billed to the clients of a company
+//! from the hours logged in the timesheet.
+//!
+//! Only the hours of billable activities on projects linked to a client are billed.
+//! The hourly rate of a line is the most specific rate in effect on the day: a rate of
+//! the activity first, then a rate of the current job title of the user in the company,
+//! then a rate of the project.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use chrono::NaiveDate;
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ServiceAppError, ValidationViolation},
+    model::{db_entities, internal::BillableLine},
+    DocumentId,
+};
+
+use super::{company, db::DatabaseDocument, timesheet};
+
+/// Check that the currency is an ISO 4217 code, e.g. EUR
+fn check_currency(currency: &str) -> Result<(), ServiceAppError> {
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(ServiceAppError::ValidationError(vec![
+            ValidationViolation::new(
+                "currency",
+                "format",
+                format!("Currency {currency} is not a valid ISO 4217 code"),
+            ),
+        ]));
+    }
+    Ok(())
+}
+
+pub async fn create_client(
+    company_id: DocumentId,
+    name: String,
+    currency: String,
+) -> Result<String, ServiceAppError> {
+    check_currency(&currency)?;
+    if db_entities::Client::count_documents(doc! {"company_id": company_id, "name": &name}).await?
+        > 0
+    {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "Client name must be unique got name: {name}"
+        )));
+    }
+    db_entities::Client::new(company_id, name, currency, true)
+        .save(None)
+        .await
+}
+
+pub async fn get_clients(
+    company_id: &DocumentId,
+) -> Result<Vec<db_entities::Client>, ServiceAppError> {
+    db_entities::Client::find_many(doc! {"company_id": company_id}).await
+}
+
+pub async fn edit_client(
+    company_id: DocumentId,
+    client_id: DocumentId,
+    name: String,
+    currency: String,
+    active: bool,
+) -> Result<(), ServiceAppError> {
+    check_currency(&currency)?;
+    let mut client =
+        db_entities::Client::find_one(doc! {"_id": client_id, "company_id": company_id})
+            .await?
+            .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+                "Client with id {client_id} does not exist"
+            )))?;
+    if db_entities::Client::count_documents(
+        doc! {"company_id": company_id, "name": &name, "_id": {"$ne": client_id}},
+    )
+    .await?
+        > 0
+    {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "Client name must be unique got name: {name}"
+        )));
+    }
+    client.set_name(name);
+    client.set_currency(currency);
+    client.set_active(active);
+    client.save(None).await?;
+    Ok(())
+}
+
+/// Deletes the client, it can be deleted only if no project is linked to it
+pub async fn delete_client(
+    company_id: DocumentId,
+    client_id: DocumentId,
+) -> Result<(), ServiceAppError> {
+    let client = db_entities::Client::find_one(doc! {"_id": client_id, "company_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Client with id {client_id} does not exist"
+        )))?;
+    if db_entities::CompanyProject::count_documents(doc! {"client_id": client_id}).await? > 0 {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "Client with id {client_id} has projects and cannot be deleted"
+        )));
+    }
+    client.delete(None).await
+}
+
+/// Create an hourly rate of the company.
+///
+/// The rate must target exactly one of project, activity or job title and there
+/// cannot be two rates with the same target and effective date.
+pub async fn create_rate(
+    company_id: DocumentId,
+    project_id: Option<DocumentId>,
+    activity_id: Option<DocumentId>,
+    job_title: Option<String>,
+    hourly_rate: f64,
+    effective_from: NaiveDate,
+) -> Result<String, ServiceAppError> {
+    let job_title = job_title
+        .map(|job_title| job_title.trim().to_string())
+        .filter(|job_title| !job_title.is_empty());
+    let effective_from = effective_from
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is always a valid time")
+        .and_utc();
+    let mut rate = db_entities::BillableRate::new(
+        company_id,
+        project_id,
+        activity_id,
+        job_title,
+        hourly_rate,
+        effective_from,
+    );
+
+    let mut violations = check_rate(&rate, &get_rates(&company_id).await?);
+    if let Some(project_id) = project_id {
+        if db_entities::CompanyProject::count_documents(
+            doc! {"_id": project_id, "company_id": company_id},
+        )
+        .await?
+            == 0
+        {
+            violations.push(ValidationViolation::new(
+                "projectId",
+                "notFound",
+                format!("Project {project_id} does not exist"),
+            ));
+        }
+    }
+    if let Some(activity_id) = activity_id {
+        if db_entities::ProjectActivity::count_documents(
+            doc! {"_id": activity_id, "company_id": company_id},
+        )
+        .await?
+            == 0
+        {
+            violations.push(ValidationViolation::new(
+                "activityId",
+                "notFound",
+                format!("Activity {activity_id} does not exist"),
+            ));
+        }
+    }
+    if !violations.is_empty() {
+        return Err(ServiceAppError::ValidationError(violations));
+    }
+    rate.save(None).await
+}
+
+/// Returns the rules violated by the rate with respect to the existing rates
+fn check_rate(
+    rate: &db_entities::BillableRate,
+    existing_rates: &[db_entities::BillableRate],
+) -> Vec<ValidationViolation> {
+    let mut violations = vec![];
+    let targets = [
+        rate.project_id().is_some(),
+        rate.activity_id().is_some(),
+        rate.job_title().is_some(),
+    ];
+    if targets.into_iter().filter(|&target| target).count() != 1 {
+        violations.push(ValidationViolation::new(
+            "projectId",
+            "target",
+            "A rate must target exactly one of project, activity or job title".into(),
+        ));
+    }
+    if !rate.hourly_rate().is_finite() || *rate.hourly_rate() < 0.0 {
+        violations.push(ValidationViolation::new(
+            "hourlyRate",
+            "range",
+            "The hourly rate must be a positive number".into(),
+        ));
+    }
+    if existing_rates.iter().any(|existing| {
+        existing.project_id() == rate.project_id()
+            && existing.activity_id() == rate.activity_id()
+            && existing.job_title() == rate.job_title()
+            && existing.effective_from() == rate.effective_from()
+    }) {
+        violations.push(ValidationViolation::new(
+            "effectiveFrom",
+            "duplicate",
+            "A rate with the same target is already effective from this date".into(),
+        ));
+    }
+    violations
+}
+
+/// Returns the rates of the company sorted by effective date
+pub async fn get_rates(
+    company_id: &DocumentId,
+) -> Result<Vec<db_entities::BillableRate>, ServiceAppError> {
+    let mut rates = db_entities::BillableRate::find_many(doc! {"company_id": company_id}).await?;
+    rates.sort_by_key(|rate| *rate.effective_from());
+    Ok(rates)
+}
+
+pub async fn delete_rate(
+    company_id: DocumentId,
+    rate_id: DocumentId,
+) -> Result<(), ServiceAppError> {
+    db_entities::BillableRate::find_one(doc! {"_id": rate_id, "company_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Rate with id {rate_id} does not exist"
+        )))?
+        .delete(None)
+        .await
+}
+
+/// Returns the rate that applies to an activity line logged at the date
+fn find_rate<'a>(
+    rates: &'a [db_entities::BillableRate],
+    project_id: &DocumentId,
+    activity_id: &DocumentId,
+    job_title: Option<&str>,
+    date: NaiveDate,
+) -> Option<&'a db_entities::BillableRate> {
+    let latest = |target: &dyn Fn(&db_entities::BillableRate) -> bool| {
+        rates
+            .iter()
+            .filter(|rate| target(rate) && rate.effective_from().date_naive() <= date)
+            .max_by_key(|rate| *rate.effective_from())
+    };
+    latest(&|rate| rate.activity_id().as_ref() == Some(activity_id))
+        .or_else(|| {
+            job_title.and_then(|job_title| {
+                latest(&|rate| rate.job_title().as_deref() == Some(job_title))
+            })
+        })
+        .or_else(|| latest(&|rate| rate.project_id().as_ref() == Some(project_id)))
+}
+
+/// Returns the hours billable to the clients of the company between `from` and `to`,
+/// both included in the timezone of the company, optionally restricted to a client.
+///
+/// There is a line for each client, project, activity, user and rate, sorted by names.
+/// Hours without a rate are returned with no rate and a zero amount.
+pub async fn get_billable_amounts(
+    company_id: &DocumentId,
+    from: NaiveDate,
+    to: NaiveDate,
+    client_id: Option<DocumentId>,
+) -> Result<Vec<BillableLine>, ServiceAppError> {
+    let (from_date, to_date) = timesheet::company_date_range(company_id, from, to).await?;
+    let tz = company::get_company_settings(company_id).await?.tz();
+
+    let clients = get_clients(company_id)
+        .await?
+        .into_iter()
+        .filter(|client| client_id.is_none_or(|client_id| client.get_id() == Some(&client_id)))
+        .filter_map(|client| Some((*client.get_id()?, client)))
+        .collect::<HashMap<DocumentId, db_entities::Client>>();
+    let projects = db_entities::CompanyProject::find_many(doc! {"company_id": company_id})
+        .await?
+        .into_iter()
+        .filter(|project| {
+            project
+                .client_id()
+                .is_some_and(|client_id| clients.contains_key(&client_id))
+        })
+        .filter_map(|project| Some((*project.get_id()?, project)))
+        .collect::<HashMap<DocumentId, db_entities::CompanyProject>>();
+    let activities =
+        db_entities::ProjectActivity::find_many(doc! {"company_id": company_id, "billable": true})
+            .await?
+            .into_iter()
+            .filter_map(|activity| Some((*activity.get_id()?, activity)))
+            .collect::<HashMap<DocumentId, db_entities::ProjectActivity>>();
+    let project_ids = projects.keys().collect::<Vec<&DocumentId>>();
+    let days = db_entities::TimesheetDay::find_many(doc! {
+        "activities.project_id": {"$in": project_ids},
+        "date": {"$gte": from_date, "$lt": to_date},
+    })
+    .await?;
+
+    let mut user_ids = days
+        .iter()
+        .map(|day| *day.user_id())
+        .collect::<Vec<DocumentId>>();
+    user_ids.sort();
+    user_ids.dedup();
+    let job_titles = db_entities::UserCompanyAssignment::find_many(
+        doc! {"company_id": company_id, "user_id": {"$in": &user_ids}},
+    )
+    .await?
+    .into_iter()
+    .map(|assignment| (*assignment.user_id(), assignment.job_title().clone()))
+    .collect::<HashMap<DocumentId, String>>();
+    #[derive(Serialize, Deserialize, Debug)]
+    struct UserQueryResult {
+        _id: DocumentId,
+        username: String,
+    }
+    let usernames = db_entities::User::find_many_projection::<UserQueryResult>(
+        doc! {"_id": {"$in": &user_ids}},
+        doc! {"username": 1},
+    )
+    .await?
+    .into_iter()
+    .map(|user| (user._id, user.username))
+    .collect::<HashMap<DocumentId, String>>();
+    let rates = get_rates(company_id).await?;
+
+    // hours grouped by project, activity, user and rate
+    let mut hours: HashMap<(DocumentId, DocumentId, DocumentId, Option<DocumentId>), u32> =
+        HashMap::new();
+    for day in days.iter() {
+        let date = day.date().with_timezone(&tz).date_naive();
+        for activity in day.activities() {
+            if !projects.contains_key(activity.project_id())
+                || !activities.contains_key(activity.activity_id())
+            {
+                continue;
+            }
+            let rate_id = find_rate(
+                &rates,
+                activity.project_id(),
+                activity.activity_id(),
+                job_titles
+                    .get(day.user_id())
+                    .map(|job_title| job_title.as_str()),
+                date,
+            )
+            .and_then(|rate| rate.get_id().copied());
+            *hours
+                .entry((
+                    *activity.project_id(),
+                    *activity.activity_id(),
+                    *day.user_id(),
+                    rate_id,
+                ))
+                .or_default() += *activity.hours();
+        }
+    }
+
+    let mut lines = vec![];
+    for ((project_id, activity_id, user_id, rate_id), hours) in hours {
+        let project = &projects[&project_id];
+        let Some(client) = project
+            .client_id()
+            .and_then(|client_id| clients.get(&client_id))
+        else {
+            continue;
+        };
+        let hourly_rate = rate_id.and_then(|rate_id| {
+            rates
+                .iter()
+                .find(|rate| rate.get_id() == Some(&rate_id))
+                .map(|rate| *rate.hourly_rate())
+        });
+        lines.push(BillableLine {
+            client_id: *client.get_id().expect("client id is the key of the map"),
+            client_name: client.name().clone(),
+            currency: client.currency().clone(),
+            project_id,
+            project_name: project.name().clone(),
+            activity_id,
+            activity_name: activities[&activity_id].name().clone(),
+            user_id,
+            username: usernames.get(&user_id).cloned().unwrap_or_default(),
+            hourly_rate,
+            hours,
+            amount: hourly_rate
+                .map(|hourly_rate| (hours as f64 * hourly_rate * 100.0).round() / 100.0)
+                .unwrap_or_default(),
+        });
+    }
+    lines.sort_by(|a, b| {
+        (
+            &a.client_name,
+            &a.project_name,
+            &a.activity_name,
+            &a.username,
+        )
+            .cmp(&(
+                &b.client_name,
+                &b.project_name,
+                &b.activity_name,
+                &b.username,
+            ))
+            .then(
+                a.hourly_rate
+                    .partial_cmp(&b.hourly_rate)
+                    .unwrap_or(Ordering::Equal),
+            )
+    });
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use crate::{model::db_entities, service::db::DatabaseDocument, DocumentId};
+
+    use super::{check_rate, find_rate};
+
+    fn rate(
+        project_id: Option<DocumentId>,
+        activity_id: Option<DocumentId>,
+        job_title: Option<&str>,
+        hourly_rate: f64,
+        effective_from: (u32, u32),
+    ) -> db_entities::BillableRate {
+        let mut rate = db_entities::BillableRate::new(
+            DocumentId::new(),
+            project_id,
+            activity_id,
+            job_title.map(|job_title| job_title.to_string()),
+            hourly_rate,
+            Utc.with_ymd_and_hms(2025, effective_from.0, effective_from.1, 0, 0, 0)
+                .unwrap(),
+        );
+        rate.set_id(&DocumentId::new().to_hex()).unwrap();
+        rate
+    }
+
+    #[test]
+    fn find_rate_test() {
+        let project_id = DocumentId::new();
+        let activity_id = DocumentId::new();
+        let rates = vec![
+            rate(Some(project_id), None, None, 50.0, (1, 1)),
+            rate(Some(project_id), None, None, 55.0, (3, 1)),
+            rate(None, None, Some("Developer"), 60.0, (2, 1)),
+            rate(None, Some(activity_id), None, 80.0, (4, 1)),
+        ];
+        let hourly_rate = |activity_id: &DocumentId, job_title: Option<&str>, month: u32| {
+            find_rate(
+                &rates,
+                &project_id,
+                activity_id,
+                job_title,
+                NaiveDate::from_ymd_opt(2025, month, 15).unwrap(),
+            )
+            .map(|rate| *rate.hourly_rate())
+        };
+
+        // project rates change with their effective date
+        assert_eq!(hourly_rate(&activity_id, None, 1), Some(50.0));
+        assert_eq!(hourly_rate(&activity_id, None, 3), Some(55.0));
+        // job title rates win over project rates once effective
+        assert_eq!(hourly_rate(&activity_id, Some("Developer"), 1), Some(50.0));
+        assert_eq!(hourly_rate(&activity_id, Some("Developer"), 2), Some(60.0));
+        assert_eq!(hourly_rate(&activity_id, Some("Designer"), 2), Some(50.0));
+        // activity rates win over all the others
+        assert_eq!(hourly_rate(&activity_id, Some("Developer"), 4), Some(80.0));
+        assert_eq!(hourly_rate(&DocumentId::new(), None, 4), Some(55.0));
+
+        assert_eq!(
+            find_rate(
+                &rates,
+                &DocumentId::new(),
+                &DocumentId::new(),
+                None,
+                NaiveDate::from_ymd_opt(2025, 6, 1).unwrap()
+            )
+            .map(|rate| *rate.hourly_rate()),
+            None
+        );
+    }
+
+    #[test]
+    fn check_rate_test() {
+        let project_id = DocumentId::new();
+        let existing = vec![rate(Some(project_id), None, None, 50.0, (1, 1))];
+
+        let rules = |rate: &db_entities::BillableRate| {
+            check_rate(rate, &existing)
+                .into_iter()
+                .map(|violation| violation.rule)
+                .collect::<Vec<String>>()
+        };
+        assert!(rules(&rate(Some(project_id), None, None, 55.0, (2, 1))).is_empty());
+        assert_eq!(
+            rules(&rate(Some(project_id), None, None, 55.0, (1, 1))),
+            vec!["duplicate"]
+        );
+        assert_eq!(
+            rules(&rate(None, None, None, -1.0, (1, 1))),
+            vec!["target", "range"]
+        );
+        assert_eq!(
+            rules(&rate(
+                Some(project_id),
+                None,
+                Some("Developer"),
+                f64::NAN,
+                (2, 1)
+            )),
+            vec!["target", "range"]
+        );
+    }
+}
@@ -0,0 +1,547 @@
+//! Calendar service with public holidays and closure days of the companies.
+//!
+//! Calendar days are stored as concrete dates and they can be created from the bundled
+//! national rule sets, from ICS files or declared one by one by company admins.
+//! Each date is stored at midnight UTC and it is converted to the timezone of the
+//! company when it is compared with timesheet days.
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    enums::{CalendarDaySource, WorkingDayType},
+    error::ServiceAppError,
+    model::{db_entities, internal::TimesheetPeriod},
+    DocumentId,
+};
+
+use super::{
+    company,
+    db::{get_database_service, DatabaseDocument},
+};
+
+/// Maximum number of days generated by a single ICS event
+const MAX_ICS_EVENT_DAYS: i64 = 366;
+
+/// Returns the instant the date starts in the timezone.
+///
+/// It is the local midnight, or the first valid local time when the daylight saving
+/// time change skips the midnight.
+pub fn local_midnight(tz: &Tz, date: NaiveDate) -> Result<DateTime<Utc>, ServiceAppError> {
+    let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is always valid");
+    (0..24 * 60)
+        .find_map(|minutes| {
+            tz.from_local_datetime(&(midnight + Duration::minutes(minutes)))
+                .earliest()
+        })
+        .map(|date| date.with_timezone(&Utc))
+        .ok_or(ServiceAppError::InvalidRequest(format!(
+            "Invalid date {date}"
+        )))
+}
+
+/// Rule that defines a public holiday
+enum HolidayRule {
+    /// the holiday falls on the same month and day every year
+    Fixed(u32, u32, &'static str),
+    /// the holiday falls a number of days after (or before) Easter Sunday
+    EasterRelative(i64, &'static str),
+}
+
+/// National holiday rule sets bundled with the application.
+/// Each set is identified by the ISO 3166-1 alpha-2 country code
+const NATIONAL_RULE_SETS: [(&str, &str, &[HolidayRule]); 4] = [
+    (
+        "IT",
+        "Italy",
+        &[
+            HolidayRule::Fixed(1, 1, "New Year's Day"),
+            HolidayRule::Fixed(1, 6, "Epiphany"),
+            HolidayRule::EasterRelative(0, "Easter Sunday"),
+            HolidayRule::EasterRelative(1, "Easter Monday"),
+            HolidayRule::Fixed(4, 25, "Liberation Day"),
+            HolidayRule::Fixed(5, 1, "Labour Day"),
+            HolidayRule::Fixed(6, 2, "Republic Day"),
+            HolidayRule::Fixed(8, 15, "Assumption Day"),
+            HolidayRule::Fixed(11, 1, "All Saints' Day"),
+            HolidayRule::Fixed(12, 8, "Immaculate Conception"),
+            HolidayRule::Fixed(12, 25, "Christmas Day"),
+            HolidayRule::Fixed(12, 26, "St. Stephen's Day"),
+        ],
+    ),
+    (
+        "FR",
+        "France",
+        &[
+            HolidayRule::Fixed(1, 1, "New Year's Day"),
+            HolidayRule::EasterRelative(1, "Easter Monday"),
+            HolidayRule::Fixed(5, 1, "Labour Day"),
+            HolidayRule::Fixed(5, 8, "Victory in Europe Day"),
+            HolidayRule::EasterRelative(39, "Ascension Day"),
+            HolidayRule::EasterRelative(50, "Whit Monday"),
+            HolidayRule::Fixed(7, 14, "Bastille Day"),
+            HolidayRule::Fixed(8, 15, "Assumption Day"),
+            HolidayRule::Fixed(11, 1, "All Saints' Day"),
+            HolidayRule::Fixed(11, 11, "Armistice Day"),
+            HolidayRule::Fixed(12, 25, "Christmas Day"),
+        ],
+    ),
+    (
+        "DE",
+        "Germany",
+        &[
+            HolidayRule::Fixed(1, 1, "New Year's Day"),
+            HolidayRule::EasterRelative(-2, "Good Friday"),
+            HolidayRule::EasterRelative(1, "Easter Monday"),
+            HolidayRule::Fixed(5, 1, "Labour Day"),
+            HolidayRule::EasterRelative(39, "Ascension Day"),
+            HolidayRule::EasterRelative(50, "Whit Monday"),
+            HolidayRule::Fixed(10, 3, "German Unity Day"),
+            HolidayRule::Fixed(12, 25, "Christmas Day"),
+            HolidayRule::Fixed(12, 26, "St. Stephen's Day"),
+        ],
+    ),
+    (
+        "ES",
+        "Spain",
+        &[
+            HolidayRule::Fixed(1, 1, "New Year's Day"),
+            HolidayRule::Fixed(1, 6, "Epiphany"),
+            HolidayRule::EasterRelative(-2, "Good Friday"),
+            HolidayRule::Fixed(5, 1, "Labour Day"),
+            HolidayRule::Fixed(8, 15, "Assumption Day"),
+            HolidayRule::Fixed(10, 12, "National Day"),
+            HolidayRule::Fixed(11, 1, "All Saints' Day"),
+            HolidayRule::Fixed(12, 6, "Constitution Day"),
+            HolidayRule::Fixed(12, 8, "Immaculate Conception"),
+            HolidayRule::Fixed(12, 25, "Christmas Day"),
+        ],
+    ),
+];
+
+/// Returns code and name of the countries with a bundled national rule set
+pub fn get_national_rule_sets() -> Vec<(&'static str, &'static str)> {
+    NATIONAL_RULE_SETS
+        .iter()
+        .map(|(code, name, _)| (*code, *name))
+        .collect()
+}
+
+/// Returns the date of Easter Sunday in the Gregorian calendar
+fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+/// Returns the national holidays of the country for the year, sorted by date
+fn national_holidays(
+    country_code: &str,
+    year: i32,
+) -> Result<Vec<(NaiveDate, String)>, ServiceAppError> {
+    let (_, _, rules) = NATIONAL_RULE_SETS
+        .iter()
+        .find(|(code, _, _)| code.eq_ignore_ascii_case(country_code))
+        .ok_or(ServiceAppError::InvalidRequest(format!(
+            "There is no national rule set for country {country_code}"
+        )))?;
+    let easter = easter_sunday(year).ok_or(ServiceAppError::InvalidRequest(format!(
+        "Invalid year {year}"
+    )))?;
+
+    let mut holidays = rules
+        .iter()
+        .filter_map(|rule| match rule {
+            HolidayRule::Fixed(month, day, name) => {
+                NaiveDate::from_ymd_opt(year, *month, *day).map(|date| (date, name.to_string()))
+            }
+            HolidayRule::EasterRelative(offset, name) => {
+                Some((easter + Duration::days(*offset), name.to_string()))
+            }
+        })
+        .collect::<Vec<(NaiveDate, String)>>();
+    holidays.sort_by_key(|(date, _)| *date);
+    Ok(holidays)
+}
+
+/// Parse the date of a DTSTART or DTEND value, both date and date-time values are accepted
+fn parse_ics_date(value: &str) -> Option<NaiveDate> {
+    value
+        .get(..8)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+}
+
+/// Returns the days of the events inside an ICS file.
+///
+/// Multi-day events generate one entry for each day, the end date is exclusive as
+/// defined by RFC 5545. Recurrence rules are not expanded.
+fn parse_ics(content: &str) -> Result<Vec<(NaiveDate, String)>, ServiceAppError> {
+    // unfold lines: a line starting with a space or a tab continues the previous one
+    let mut lines: Vec<String> = vec![];
+    for line in content.lines() {
+        let line = line.trim_end_matches('\r');
+        if let Some(continuation) = line.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(continuation);
+                continue;
+            }
+        }
+        lines.push(line.to_string());
+    }
+
+    let mut days = vec![];
+    let mut event: Option<(Option<NaiveDate>, Option<NaiveDate>, String)> = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let property = name
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        match (property.as_str(), event.as_mut()) {
+            ("BEGIN", _) if value.eq_ignore_ascii_case("VEVENT") => {
+                event = Some((None, None, String::new()))
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some((start, end, summary)) = event.take() {
+                    let start = start.ok_or(ServiceAppError::InvalidRequest(format!(
+                        "Event {summary} does not have a valid DTSTART"
+                    )))?;
+                    let end = end
+                        .filter(|end| *end > start)
+                        .unwrap_or(start + Duration::days(1));
+                    if (end - start).num_days() > MAX_ICS_EVENT_DAYS {
+                        return Err(ServiceAppError::InvalidRequest(format!(
+                            "Event {summary} lasts more than {MAX_ICS_EVENT_DAYS} days"
+                        )));
+                    }
+                    let mut day = start;
+                    while day < end {
+                        days.push((day, summary.clone()));
+                        day += Duration::days(1);
+                    }
+                }
+            }
+            ("DTSTART", Some((start, _, _))) => *start = parse_ics_date(value),
+            ("DTEND", Some((_, end, _))) => *end = parse_ics_date(value),
+            ("SUMMARY", Some((_, _, summary))) => {
+                *summary = value
+                    .replace("\\n", " ")
+                    .replace("\\N", " ")
+                    .replace("\\,", ",")
+                    .replace("\\;", ";")
+                    .replace("\\\\", "\\")
+                    .trim()
+                    .to_string()
+            }
+            _ => {}
+        }
+    }
+    if days.is_empty() {
+        Err(ServiceAppError::InvalidRequest(
+            "The ICS file does not contain any event".into(),
+        ))
+    } else {
+        Ok(days)
+    }
+}
+
+/// Returns the instant used to store the calendar date in the database
+fn to_stored_date(date: &NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0)
+        .expect("midnight is always a valid time")
+        .and_utc()
+}
+
+/// Save the calendar days of the company.
+///
+/// Days already present in the calendar are left untouched.
+/// It returns the number of created days.
+async fn save_calendar_days(
+    company_id: DocumentId,
+    days: Vec<(NaiveDate, String)>,
+    day_type: WorkingDayType,
+    source: CalendarDaySource,
+) -> Result<usize, ServiceAppError> {
+    if db_entities::Company::count_documents(doc! {"_id": company_id}).await? == 0 {
+        return Err(ServiceAppError::EntityDoesNotExist(format!(
+            "Company with id {company_id} does not exist"
+        )));
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct QueryResult {
+        #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+        date: DateTime<Utc>,
+    }
+    let mut existing_dates = db_entities::CompanyCalendarDay::find_many_projection::<QueryResult>(
+        doc! {
+            "company_id": company_id,
+            "date": {"$in": days.iter().map(|(date, _)| to_stored_date(date)).collect::<Vec<DateTime<Utc>>>()},
+        },
+        doc! {"date": 1},
+    )
+    .await?
+    .into_iter()
+    .map(|day| day.date.date_naive())
+    .collect::<Vec<NaiveDate>>();
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+
+    let mut created = 0;
+    for (date, name) in days {
+        if existing_dates.contains(&date) {
+            continue;
+        }
+        let mut calendar_day = db_entities::CompanyCalendarDay::new(
+            company_id,
+            to_stored_date(&date),
+            day_type,
+            name,
+            source,
+        );
+        calendar_day.save(Some(&mut transaction)).await?;
+        existing_dates.push(date);
+        created += 1;
+    }
+
+    transaction.commit_transaction().await?;
+    Ok(created)
+}
+
+/// Add to the calendar of the company the national holidays of the country for the year
+pub async fn import_national_holidays(
+    company_id: DocumentId,
+    country_code: &str,
+    year: i32,
+) -> Result<usize, ServiceAppError> {
+    let holidays = national_holidays(country_code, year)?;
+    save_calendar_days(
+        company_id,
+        holidays,
+        WorkingDayType::Holiday,
+        CalendarDaySource::NationalRuleSet,
+    )
+    .await
+}
+
+/// Add to the calendar of the company the days of the events inside the ICS file
+pub async fn import_ics(
+    company_id: DocumentId,
+    content: &str,
+    day_type: WorkingDayType,
+) -> Result<usize, ServiceAppError> {
+    if !matches!(
+        day_type,
+        WorkingDayType::Holiday | WorkingDayType::CompanyClosure
+    ) {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "Calendar days can be Holiday or CompanyClosure, got {day_type}"
+        )));
+    }
+    let days = parse_ics(content)?;
+    save_calendar_days(company_id, days, day_type, CalendarDaySource::IcsImport).await
+}
+
+/// Declare a closure day for the company
+pub async fn add_closure_day(
+    company_id: DocumentId,
+    date: NaiveDate,
+    name: String,
+) -> Result<(), ServiceAppError> {
+    let created = save_calendar_days(
+        company_id,
+        vec![(date, name)],
+        WorkingDayType::CompanyClosure,
+        CalendarDaySource::Manual,
+    )
+    .await?;
+    if created == 0 {
+        Err(ServiceAppError::InvalidRequest(format!(
+            "Day {date} is already in the calendar of the company"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+pub async fn delete_calendar_day(
+    company_id: DocumentId,
+    calendar_day_id: DocumentId,
+) -> Result<(), ServiceAppError> {
+    db_entities::CompanyCalendarDay::find_one(
+        doc! {"_id": calendar_day_id, "company_id": company_id},
+    )
+    .await?
+    .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+        "Calendar day with id {calendar_day_id} does not exist"
+    )))?
+    .delete(None)
+    .await
+}
+
+/// Returns the calendar days of the company between the two dates, both included
+pub async fn get_calendar_days(
+    company_id: &DocumentId,
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+) -> Result<Vec<db_entities::CompanyCalendarDay>, ServiceAppError> {
+    let mut days = db_entities::CompanyCalendarDay::find_many(doc! {
+        "company_id": company_id,
+        "date": {"$gte": to_stored_date(&from_date), "$lte": to_stored_date(&to_date)},
+    })
+    .await?;
+    days.sort_by_key(|day| *day.date());
+    Ok(days)
+}
+
+/// Returns the calendar day of the company for the given date, if any
+pub async fn get_calendar_day(
+    company_id: &DocumentId,
+    date: NaiveDate,
+) -> Result<Option<db_entities::CompanyCalendarDay>, ServiceAppError> {
+    db_entities::CompanyCalendarDay::find_one(
+        doc! {"company_id": company_id, "date": to_stored_date(&date)},
+    )
+    .await
+}
+
+/// Returns unsaved timesheet days for the calendar days of the companies of the user
+/// in the period, excluding the dates that already have a timesheet day.
+/// When `company_id` is given only the calendar of that company is used.
+///
+/// The date of each day is the midnight in the timezone of the company, like the
+/// dates sent by the clients
+pub async fn get_prefilled_days(
+    user_id: &DocumentId,
+    company_id: Option<&DocumentId>,
+    period: &TimesheetPeriod,
+    existing_days: &[db_entities::TimesheetDay],
+) -> Result<Vec<db_entities::TimesheetDay>, ServiceAppError> {
+    let (from_date, to_date) =
+        period
+            .date_range()
+            .ok_or(ServiceAppError::InvalidRequest(format!(
+                "Invalid period {period:?}"
+            )))?;
+
+    let mut prefilled: Vec<db_entities::TimesheetDay> = vec![];
+    for company in company::get_user_companies(user_id).await? {
+        let user_company_id = *company
+            .get_id()
+            .expect("id should exist from document retrieved from db");
+        if company_id.is_some_and(|company_id| *company_id != user_company_id) {
+            continue;
+        }
+        let company_id = user_company_id;
+        let tz = company::get_company_settings(&company_id).await?.tz();
+        for calendar_day in get_calendar_days(&company_id, from_date, to_date).await? {
+            let date = calendar_day.date().date_naive();
+            let already_present = existing_days
+                .iter()
+                .chain(prefilled.iter())
+                .any(|day| day.date().with_timezone(&tz).date_naive() == date);
+            if already_present {
+                continue;
+            }
+            prefilled.push(db_entities::TimesheetDay::new(
+                *user_id,
+                local_midnight(&tz, date)?,
+                0,
+                *calendar_day.day_type(),
+                vec![],
+                false,
+            ));
+        }
+    }
+    prefilled.sort_by_key(|day| *day.date());
+    Ok(prefilled)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use super::{easter_sunday, local_midnight, national_holidays, parse_ics};
+
+    #[test]
+    fn easter_sunday_test() {
+        assert_eq!(easter_sunday(2024), NaiveDate::from_ymd_opt(2024, 3, 31));
+        assert_eq!(easter_sunday(2025), NaiveDate::from_ymd_opt(2025, 4, 20));
+        assert_eq!(easter_sunday(2038), NaiveDate::from_ymd_opt(2038, 4, 25));
+    }
+
+    #[test]
+    fn local_midnight_test() {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 30).unwrap();
+        assert_eq!(
+            local_midnight(&chrono_tz::Europe::Rome, date).unwrap(),
+            Utc.with_ymd_and_hms(2025, 3, 29, 23, 0, 0).unwrap()
+        );
+        // the daylight saving time starts at midnight, so the day starts at 01:00
+        let date = NaiveDate::from_ymd_opt(2024, 9, 8).unwrap();
+        assert_eq!(
+            local_midnight(&chrono_tz::America::Santiago, date).unwrap(),
+            Utc.with_ymd_and_hms(2024, 9, 8, 4, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn national_holidays_test() {
+        let holidays = national_holidays("de", 2025).unwrap();
+        assert_eq!(holidays.len(), 9);
+        assert_eq!(
+            holidays[1],
+            (
+                NaiveDate::from_ymd_opt(2025, 4, 18).unwrap(),
+                "Good Friday".to_string()
+            )
+        );
+        assert!(holidays.contains(&(
+            NaiveDate::from_ymd_opt(2025, 6, 9).unwrap(),
+            "Whit Monday".to_string()
+        )));
+        assert!(national_holidays("XX", 2025).is_err());
+    }
+
+    #[test]
+    fn parse_ics_test() {
+        let content = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+            BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20251224\r\nDTEND;VALUE=DATE:20251227\r\n\
+            SUMMARY:Christmas\\, closure\r\nEND:VEVENT\r\n\
+            BEGIN:VEVENT\r\nDTSTART:20250815T000000Z\r\nSUMMARY:Assump\r\n tion\r\nEND:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let days = parse_ics(content).unwrap();
+        assert_eq!(days.len(), 4);
+        assert_eq!(days[0].0, NaiveDate::from_ymd_opt(2025, 12, 24).unwrap());
+        assert_eq!(days[0].1, "Christmas, closure");
+        assert_eq!(days[2].0, NaiveDate::from_ymd_opt(2025, 12, 26).unwrap());
+        assert_eq!(
+            days[3],
+            (
+                NaiveDate::from_ymd_opt(2025, 8, 15).unwrap(),
+                "Assumption".to_string()
+            )
+        );
+
+        assert!(parse_ics("BEGIN:VCALENDAR\r\nEND:VCALENDAR").is_err());
+    }
+}
@@ -1,16 +1,28 @@
 use std::{collections::HashMap, str::FromStr};
 
+use chrono::{Duration, Utc, Weekday};
+use chrono_tz::Tz;
 use mongodb::bson::{doc, oid::ObjectId, Bson};
 use serde::{Deserialize, Serialize};
-use tracing::debug;
-
-use super::db::{get_database_service, DatabaseDocument};
+use tracing::{debug, error, info};
+
+use super::{
+    corporate_group,
+    db::{get_database_service, DatabaseDocument, DatabaseTransaction},
+    environment::ENVIRONMENT,
+    mailer::{get_mailer, Email},
+    user,
+};
 use crate::{
-    enums::{CompanyRole, NotificationType},
-    error::ServiceAppError,
+    auth::InviteTokenClaim,
+    enums::{CompanyRole, InviteStatus, NotificationType, ProjectRole, WorkingDayType},
+    error::{AuthError, ServiceAppError, ValidationViolation},
     model::{
         db_entities,
-        internal::{AdminPanelOverviewCompanyInfo, InvitedUserInCompanyInfo, UserInCompanyInfo},
+        internal::{
+            AdminPanelOverviewCompanyInfo, InvitationHistoryEntry, InvitedUserInCompanyInfo,
+            UserInCompanyInfo,
+        },
     },
     DocumentId,
 };
@@ -177,6 +189,7 @@ pub async fn add_user_to_company(
     role: CompanyRole,
     job_title: String,
     project_ids: Vec<DocumentId>,
+    transaction: Option<&mut DatabaseTransaction>,
 ) -> Result<(), ServiceAppError> {
     let query = doc! { "user_id": user_id, "company_id": company_id};
     let query_result = db_entities::UserCompanyAssignment::find_one(query).await?;
@@ -190,16 +203,92 @@ pub async fn add_user_to_company(
             job_title,
             project_ids,
         );
-        new_assignment.save(None).await?;
+        new_assignment.save(transaction).await?;
         Ok(())
     }
 }
 
-/// Remove the user from the company
+/// Returns the ids of the users in the management team of the company
+pub async fn get_management_team(
+    company_id: &DocumentId,
+) -> Result<Vec<DocumentId>, ServiceAppError> {
+    db_entities::CompanyManagementTeam::find_one(doc! { "company_id": company_id})
+        .await?
+        .map(|management_team| management_team.user_ids().clone())
+        .ok_or(ServiceAppError::InternalServerError(format!(
+            "Missing management team for company {}",
+            company_id
+        )))
+}
+
+/// Replace the members of the management team of the company.
+/// All the users must belong to the company.
+pub async fn set_management_team(
+    company_id: &DocumentId,
+    mut user_ids: Vec<DocumentId>,
+) -> Result<(), ServiceAppError> {
+    user_ids.sort();
+    user_ids.dedup();
+    let n_users = db_entities::UserCompanyAssignment::count_documents(doc! {
+        "company_id": company_id,
+        "user_id": {"$in": user_ids.iter().map(|id| Bson::ObjectId(*id)).collect::<Vec<Bson>>()},
+    })
+    .await?;
+    if n_users != user_ids.len() as u64 {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "All the members of the management team must belong to the company {company_id}"
+        )));
+    }
+
+    let mut management_team =
+        db_entities::CompanyManagementTeam::find_one(doc! { "company_id": company_id})
+            .await?
+            .ok_or(ServiceAppError::InternalServerError(format!(
+                "Missing management team for company {}",
+                company_id
+            )))?;
+    management_team.set_user_ids(user_ids);
+    management_team.save(None).await?;
+    Ok(())
+}
+
+/// Returns true if the user is in the management team of the company
+pub async fn is_in_management_team(
+    user_id: &DocumentId,
+    company_id: &DocumentId,
+) -> Result<bool, ServiceAppError> {
+    Ok(db_entities::CompanyManagementTeam::count_documents(
+        doc! { "company_id": company_id, "user_ids": user_id},
+    )
+    .await?
+        > 0)
+}
+
+/// Remove the user from the company.
+///
+/// Pending ownership transfers nominating the user or nominated by the user are declined
 pub async fn remove_user_from_company(
     user_id: &DocumentId,
     company_id: &DocumentId,
 ) -> Result<(), ServiceAppError> {
+    #[derive(Serialize, Deserialize, Debug)]
+    struct TransferQueryResult {
+        _id: DocumentId,
+    }
+    let pending_transfer_ids =
+        db_entities::CompanyOwnershipTransfer::find_many_projection::<TransferQueryResult>(
+            doc! {
+                "company_id": company_id,
+                "answer": null,
+                "$or": [{"owner_id": user_id}, {"nominee_id": user_id}],
+            },
+            doc! {"_id": 1},
+        )
+        .await?
+        .into_iter()
+        .map(|transfer| transfer._id)
+        .collect::<Vec<DocumentId>>();
+
     let db_service = get_database_service().await;
     let mut transaction = db_service.new_transaction().await?;
     transaction.start_transaction().await?;
@@ -212,6 +301,41 @@ pub async fn remove_user_from_company(
         transaction.abort_transaction().await?;
         return Err(ServiceAppError::InvalidRequest(format!("Failed to remove user {user_id} from company {company_id} because he does not belong to it.")));
     }
+    if !pending_transfer_ids.is_empty() {
+        db_entities::CompanyOwnershipTransfer::update_many(
+            doc! {"_id": {"$in": &pending_transfer_ids}},
+            doc! {"$set": {"answer": false}},
+            Some(&mut transaction),
+        )
+        .await?;
+        db_entities::AppNotification::delete_many(
+            doc! {"entity_id": {"$in": &pending_transfer_ids}},
+            Some(&mut transaction),
+        )
+        .await?;
+    }
+    db_entities::ProjectRoleAssignment::delete_many(
+        doc! { "user_id": user_id, "company_id": company_id},
+        Some(&mut transaction),
+    )
+    .await?;
+    db_entities::CustomCompanyRole::update_many(
+        doc! { "company_id": company_id, "user_ids": user_id},
+        doc! { "$pull": {"user_ids": user_id}},
+        Some(&mut transaction),
+    )
+    .await?;
+    db_entities::Department::update_many(
+        doc! { "company_id": company_id},
+        doc! { "$pull": {"user_ids": user_id, "lead_ids": user_id}},
+        Some(&mut transaction),
+    )
+    .await?;
+    db_entities::ReportingLine::delete_many(
+        doc! { "company_id": company_id, "$or": [{"user_id": user_id}, {"manager_id": user_id}]},
+        Some(&mut transaction),
+    )
+    .await?;
 
     // if the user is in the management team, we remove him
     if let Some(management_team) =
@@ -241,6 +365,12 @@ pub async fn update_user_in_company(
     let query = doc! { "user_id": user_id, "company_id": company_id};
     let query_result = db_entities::UserCompanyAssignment::find_one(query).await?;
     if let Some(assignment) = query_result {
+        if *assignment.role() == CompanyRole::Owner && role.is_some_and(|r| r != CompanyRole::Owner)
+        {
+            return Err(ServiceAppError::InvalidRequest(
+                "The role of the owner can be changed only through an ownership transfer".into(),
+            ));
+        }
         let mut update = doc! {};
         if let Some(role_obj) = role {
             update.insert("role", role_obj.to_string());
@@ -265,6 +395,9 @@ pub async fn change_user_company_manager(
     company_id: &DocumentId,
     manager: bool,
 ) -> Result<(), ServiceAppError> {
+    if manager {
+        get_user_company_role(user_id, company_id).await?;
+    }
     let query_result =
         db_entities::CompanyManagementTeam::find_one(doc! { "company_id": company_id}).await?;
 
@@ -368,29 +501,13 @@ pub async fn invite_user(
     Create InviteAddCompany document and AppNotification document
     */
 
-    // the role can be Admin only if the requesting user is an admin
-    if role == CompanyRole::Admin {
-        #[derive(Serialize, Deserialize, Debug)]
-        struct QueryResult {
-            role: CompanyRole,
-        }
-
-        let inviting_user_role = db_entities::UserCompanyAssignment::find_one_projection::<
-            QueryResult,
-        >(doc! {"user_id": inviting_user_id}, doc! {"role": 1})
-        .await?
-        .expect("user must be in company")
-        .role;
-
-        if inviting_user_role == CompanyRole::Admin {
-            return Err(ServiceAppError::AccessControlError(format!("You don't have Admin role in Company {company_id}, hence, you cannot assign Admin role to other users")));
-        }
-    }
+    verify_assignable_role(&inviting_user_id, &company_id, role).await?;
 
     let db_service = get_database_service().await;
     let mut transaction = db_service.new_transaction().await?;
     transaction.start_transaction().await?;
 
+    let now = Utc::now();
     let mut invite = db_entities::InviteAddCompany::new(
         inviting_user_id,
         invited_user_id,
@@ -398,6 +515,9 @@ pub async fn invite_user(
         role,
         job_title,
         project_ids,
+        InviteStatus::Pending,
+        now,
+        now + Duration::hours(ENVIRONMENT.invitation.invite_validity_hours),
         None,
     );
     invite.save(Some(&mut transaction)).await?;
@@ -425,12 +545,276 @@ pub async fn invite_user(
     }
 }
 
+/// Verifies that the inviting user can assign the role to the invited person.
+///
+/// The Admin role can be assigned only by the Owner and the Owner role cannot be
+/// assigned through an invitation.
+async fn verify_assignable_role(
+    inviting_user_id: &DocumentId,
+    company_id: &DocumentId,
+    role: CompanyRole,
+) -> Result<(), ServiceAppError> {
+    match role {
+        CompanyRole::Owner => Err(ServiceAppError::InvalidRequest(
+            "The Owner role cannot be assigned with an invitation".into(),
+        )),
+        CompanyRole::Admin => {
+            let inviting_user_role = *get_user_company_role(inviting_user_id, company_id)
+                .await?
+                .role();
+            if inviting_user_role != CompanyRole::Owner {
+                Err(ServiceAppError::AccessControlError(format!("You don't have Admin role in Company {company_id}, hence, you cannot assign Admin role to other users")))
+            } else {
+                Ok(())
+            }
+        }
+        CompanyRole::User => Ok(()),
+    }
+}
+
+/// Invite to the company a person by email.
+///
+/// If the email belongs to a registered user, the standard invitation is created.
+/// Otherwise, an `InviteAddCompanyByEmail` document is created and the person receives
+/// an email with a signed link that expires after the hours defined in the environment.
+/// Through the link the person can register and is added to the company.
+pub async fn invite_user_by_email(
+    inviting_user_id: DocumentId,
+    company_id: DocumentId,
+    email: String,
+    role: CompanyRole,
+    job_title: String,
+    project_ids: Vec<DocumentId>,
+) -> Result<(), ServiceAppError> {
+    let email = email.trim().to_lowercase();
+    if !email.contains('@') {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "Email {email} is not valid"
+        )));
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct UserQueryResult {
+        _id: DocumentId,
+    }
+    // emails are stored as typed by the user, hence the match ignores case
+    let registered_user = db_entities::User::find_one_projection::<UserQueryResult>(
+        doc! {"email": {"$regex": format!("^{}$", regex::escape(&email)), "$options": "i"}},
+        doc! {"_id": 1},
+    )
+    .await?;
+    if let Some(registered_user) = registered_user {
+        return invite_user(
+            inviting_user_id,
+            company_id,
+            registered_user._id,
+            role,
+            job_title,
+            project_ids,
+        )
+        .await;
+    }
+
+    verify_assignable_role(&inviting_user_id, &company_id, role).await?;
+
+    let company = db_entities::Company::find_one(doc! {"_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Company with id {company_id} does not exist"
+        )))?;
+
+    let now = Utc::now();
+    if db_entities::InviteAddCompanyByEmail::count_documents(doc! {
+        "company_id": company_id,
+        "email": &email,
+        "registered_user_id": null,
+        "expiration_date": {"$gt": now},
+    })
+    .await?
+        > 0
+    {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "There is already a pending invitation for {email}"
+        )));
+    }
+
+    let expiration_date = now + Duration::hours(ENVIRONMENT.invitation.email_invite_validity_hours);
+    let mut invite = db_entities::InviteAddCompanyByEmail::new(
+        inviting_user_id,
+        email.clone(),
+        company_id,
+        role,
+        job_title,
+        project_ids,
+        expiration_date,
+        None,
+        InviteStatus::Pending,
+        now,
+        None,
+    );
+    invite.save(None).await?;
+
+    if let Err(e) = send_email_invite(&invite, company.name()).await {
+        // the invite is useless if the person cannot receive it
+        invite.delete(None).await?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Send the email with the signed registration link of the invite
+async fn send_email_invite(
+    invite: &db_entities::InviteAddCompanyByEmail,
+    company_name: &str,
+) -> Result<(), ServiceAppError> {
+    let token = InviteTokenClaim::new(
+        invite.expiration_date().timestamp() as usize,
+        *invite
+            .get_id()
+            .expect("expecting id after saving the document"),
+        invite.email().clone(),
+    )
+    .build_token()
+    .map_err(ServiceAppError::AuthorizationError)?;
+
+    let mail = Email {
+        to: invite.email().clone(),
+        subject: format!("You have been invited to join {}", company_name),
+        body: format!(
+            "You have been invited to join the company {} as {}.\r\n\r\nRegister using the following link before {}:\r\n{}/register?token={}",
+            company_name,
+            invite.job_title(),
+            invite.expiration_date().format("%Y-%m-%d %H:%M UTC"),
+            ENVIRONMENT.mailer.app_base_url,
+            token
+        ),
+    };
+    get_mailer().send(&mail).await
+}
+
+/// Returns the pending invitation by email identified by the token
+/// together with the name of the company.
+///
+/// It returns `ServiceAppError::AuthorizationError` if the token is not valid and
+/// `ServiceAppError::InvalidRequest` if the invite has already been used or is expired.
+pub async fn get_email_invite_from_token(
+    token: &str,
+) -> Result<(db_entities::InviteAddCompanyByEmail, String), ServiceAppError> {
+    let claim = InviteTokenClaim::from_token(token).map_err(ServiceAppError::AuthorizationError)?;
+    let invite = db_entities::InviteAddCompanyByEmail::find_one(doc! {"_id": claim.invite_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Invite with id {} does not exist",
+            claim.invite_id
+        )))?;
+    if *invite.email() != claim.email {
+        Err(ServiceAppError::AuthorizationError(AuthError::InvalidToken))
+    } else if invite.registered_user_id().is_some() {
+        Err(ServiceAppError::InvalidRequest(
+            "The invitation has already been used".into(),
+        ))
+    } else if *invite.status() != InviteStatus::Pending || *invite.expiration_date() <= Utc::now() {
+        Err(ServiceAppError::InvalidRequest(
+            "The invitation is expired".into(),
+        ))
+    } else {
+        let company_name = db_entities::Company::find_one(doc! {"_id": invite.company_id()})
+            .await?
+            .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+                "Company with id {} does not exist",
+                invite.company_id()
+            )))?
+            .name()
+            .clone();
+        Ok((invite, company_name))
+    }
+}
+
+/// Register a new user through the invitation received by email.
+///
+/// The user is created with the email of the invite and then added to the company
+/// with the invited role, job title and projects. The inviting user is notified.
+/// Everything happens in one transaction that marks the invite as used only if it is
+/// still pending, so the same token cannot register two users.
+/// It returns the id of the new user.
+pub async fn register_invited_user(
+    token: &str,
+    username: String,
+    password: String,
+    name: String,
+    surname: String,
+) -> Result<String, ServiceAppError> {
+    let (invite, company_name) = get_email_invite_from_token(token).await?;
+
+    let mut new_user = user::new_user(
+        username.clone(),
+        password,
+        invite.email().clone(),
+        name,
+        surname,
+    )
+    .await?;
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+
+    let user_id = new_user.save(Some(&mut transaction)).await?;
+    let user_object_id = ObjectId::from_str(&user_id).map_err(|_| {
+        ServiceAppError::InternalServerError("Unexpected failed conversion of ObjectId".into())
+    })?;
+
+    if db_entities::InviteAddCompanyByEmail::update_one_matched(
+        doc! {"_id": invite.get_id(), "registered_user_id": null},
+        doc! {"$set": {
+            "registered_user_id": user_object_id,
+            "status": InviteStatus::Accepted,
+            "closed_at": Utc::now(),
+        }},
+        Some(&mut transaction),
+    )
+    .await?
+        == 0
+    {
+        transaction.abort_transaction().await?;
+        return Err(ServiceAppError::InvalidRequest(
+            "The invitation has already been used".into(),
+        ));
+    }
+
+    let mut assignment = db_entities::UserCompanyAssignment::new(
+        user_object_id,
+        *invite.company_id(),
+        *invite.company_role(),
+        invite.job_title().clone(),
+        invite.project_ids().clone(),
+    );
+    assignment.save(Some(&mut transaction)).await?;
+
+    let mut notification = db_entities::AppNotification::new(
+        *invite.inviting_user_id(),
+        NotificationType::InviteAddCompanyAnswer,
+        format!(
+            "User {} has registered and joined company {}",
+            username.trim(),
+            company_name
+        ),
+        false,
+        invite.get_id().copied(),
+    );
+    notification.save(Some(&mut transaction)).await?;
+
+    transaction.commit_transaction().await?;
+    Ok(user_id)
+}
+
 pub async fn get_pending_invited_users(
     company_id: &DocumentId,
 ) -> Result<Vec<InvitedUserInCompanyInfo>, ServiceAppError> {
-    let pending_invitations =
-        db_entities::InviteAddCompany::find_many(doc! {"company_id": company_id, "answer": null})
-            .await?;
+    let pending_invitations = db_entities::InviteAddCompany::find_many(
+        doc! {"company_id": company_id, "status": InviteStatus::Pending},
+    )
+    .await?;
 
     #[derive(Serialize, Deserialize, Debug)]
     struct NotificationQueryResult {
@@ -487,21 +871,761 @@ pub async fn get_pending_invited_users(
         }
     }
 
-    Ok(to_return)
+    Ok(to_return)
+}
+
+/// Returns the settings of the company or the default ones if they have not been configured
+pub async fn get_company_settings(
+    company_id: &DocumentId,
+) -> Result<db_entities::CompanySettings, ServiceAppError> {
+    Ok(
+        db_entities::CompanySettings::find_one(doc! {"company_id": company_id})
+            .await?
+            .unwrap_or_else(|| db_entities::CompanySettings::default_for_company(*company_id)),
+    )
+}
+
+/// Create or replace the settings of the company.
+///
+/// Every invalid value is reported through `ServiceAppError::ValidationError`
+pub async fn update_company_settings(
+    company_id: DocumentId,
+    daily_hours: u32,
+    working_weekdays: Vec<Weekday>,
+    timezone: String,
+    max_daily_hours: u32,
+    allowed_working_types: Vec<WorkingDayType>,
+) -> Result<(), ServiceAppError> {
+    let mut violations = vec![];
+    if daily_hours == 0 || daily_hours > 24 {
+        violations.push(ValidationViolation::new(
+            "dailyHours",
+            "range",
+            "Daily hours must be between 1 and 24".into(),
+        ));
+    }
+    if max_daily_hours < daily_hours || max_daily_hours > 24 {
+        violations.push(ValidationViolation::new(
+            "maxDailyHours",
+            "range",
+            "Maximum daily hours must be between the daily hours and 24".into(),
+        ));
+    }
+    if working_weekdays.is_empty() {
+        violations.push(ValidationViolation::new(
+            "workingWeekdays",
+            "required",
+            "At least one working weekday is required".into(),
+        ));
+    }
+    if timezone.parse::<Tz>().is_err() {
+        violations.push(ValidationViolation::new(
+            "timezone",
+            "timezone",
+            format!("Timezone {timezone} is not valid"),
+        ));
+    }
+    if allowed_working_types.is_empty() {
+        violations.push(ValidationViolation::new(
+            "allowedWorkingTypes",
+            "required",
+            "At least one working type is required".into(),
+        ));
+    }
+    if !violations.is_empty() {
+        return Err(ServiceAppError::ValidationError(violations));
+    }
+    if db_entities::Company::count_documents(doc! {"_id": company_id}).await? == 0 {
+        return Err(ServiceAppError::EntityDoesNotExist(format!(
+            "Company with id {company_id} does not exist"
+        )));
+    }
+
+    let mut settings = get_company_settings(&company_id).await?;
+    settings.set_daily_hours(daily_hours);
+    settings.set_working_weekdays(working_weekdays);
+    settings.set_timezone(timezone);
+    settings.set_max_daily_hours(max_daily_hours);
+    settings.set_allowed_working_types(allowed_working_types);
+    settings.save(None).await?;
+    Ok(())
+}
+
+/// The owner of the company nominates another member as the new owner.
+///
+/// The nominee receives an AppNotification and the roles are swapped only when
+/// the nominee accepts, see `notification::answer_to_ownership_transfer`
+pub async fn nominate_new_owner(
+    owner_id: DocumentId,
+    company_id: DocumentId,
+    nominee_id: DocumentId,
+) -> Result<(), ServiceAppError> {
+    if owner_id == nominee_id {
+        return Err(ServiceAppError::InvalidRequest(
+            "You are already the owner of the company".into(),
+        ));
+    }
+    if db_entities::UserCompanyAssignment::count_documents(
+        doc! {"user_id": nominee_id, "company_id": company_id},
+    )
+    .await?
+        == 0
+    {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "User {nominee_id} does not belong to company {company_id}"
+        )));
+    }
+    if db_entities::CompanyOwnershipTransfer::count_documents(
+        doc! {"company_id": company_id, "answer": null},
+    )
+    .await?
+        > 0
+    {
+        return Err(ServiceAppError::InvalidRequest(
+            "There is already a pending ownership transfer for the company".into(),
+        ));
+    }
+    let company = db_entities::Company::find_one(doc! {"_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Company with id {company_id} does not exist"
+        )))?;
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+
+    let mut transfer = db_entities::CompanyOwnershipTransfer::new(
+        company_id,
+        owner_id,
+        nominee_id,
+        None,
+        Utc::now(),
+        None,
+    );
+    transfer.save(Some(&mut transaction)).await?;
+
+    let mut notification = db_entities::AppNotification::new(
+        nominee_id,
+        NotificationType::OwnershipTransfer,
+        format!(
+            "You have been nominated as the new owner of Company {}",
+            company.name()
+        ),
+        false,
+        transfer.get_id().copied(),
+    );
+    notification.save(Some(&mut transaction)).await?;
+
+    transaction.commit_transaction().await?;
+    Ok(())
+}
+
+/// Swap the roles inside the transaction: every current owner becomes Admin
+/// and the new owner gets the Owner role.
+///
+/// The transaction is aborted if the new owner does not belong to the company anymore
+pub async fn swap_company_owner(
+    company_id: &DocumentId,
+    new_owner_id: &DocumentId,
+    transaction: &mut DatabaseTransaction,
+) -> Result<(), ServiceAppError> {
+    if db_entities::UserCompanyAssignment::update_one_matched(
+        doc! {"company_id": company_id, "user_id": new_owner_id},
+        doc! {"$set": {"role": CompanyRole::Owner}},
+        Some(transaction),
+    )
+    .await?
+        != 1
+    {
+        transaction.abort_transaction().await?;
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "User {new_owner_id} does not belong to company {company_id}"
+        )));
+    }
+    db_entities::UserCompanyAssignment::update_many(
+        doc! {"company_id": company_id, "role": CompanyRole::Owner, "user_id": {"$ne": new_owner_id}},
+        doc! {"$set": {"role": CompanyRole::Admin}},
+        Some(transaction),
+    )
+    .await
+}
+
+/// Transfer the ownership of the company without the agreement of the nominee.
+///
+/// It is reserved to platform admins, e.g. when the owner has left without handing
+/// the company over. Pending transfers are declined and former owners become Admin.
+pub async fn force_ownership_transfer(
+    platform_admin_id: DocumentId,
+    company_id: DocumentId,
+    new_owner_id: DocumentId,
+) -> Result<(), ServiceAppError> {
+    let company = db_entities::Company::find_one(doc! {"_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Company with id {company_id} does not exist"
+        )))?;
+    if db_entities::UserCompanyAssignment::count_documents(
+        doc! {"user_id": new_owner_id, "company_id": company_id},
+    )
+    .await?
+        == 0
+    {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "User {new_owner_id} does not belong to company {company_id}"
+        )));
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct OwnerQueryResult {
+        user_id: DocumentId,
+    }
+    let previous_owner_ids =
+        db_entities::UserCompanyAssignment::find_many_projection::<OwnerQueryResult>(
+            doc! {"company_id": company_id, "role": CompanyRole::Owner},
+            doc! {"user_id": 1},
+        )
+        .await?
+        .into_iter()
+        .map(|assignment| assignment.user_id)
+        .filter(|user_id| *user_id != new_owner_id)
+        .collect::<Vec<DocumentId>>();
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct TransferQueryResult {
+        _id: DocumentId,
+    }
+    let pending_transfer_ids =
+        db_entities::CompanyOwnershipTransfer::find_many_projection::<TransferQueryResult>(
+            doc! {"company_id": company_id, "answer": null},
+            doc! {"_id": 1},
+        )
+        .await?
+        .into_iter()
+        .map(|transfer| transfer._id)
+        .collect::<Vec<DocumentId>>();
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+
+    db_entities::CompanyOwnershipTransfer::update_many(
+        doc! {"_id": {"$in": &pending_transfer_ids}},
+        doc! {"$set": {"answer": false}},
+        Some(&mut transaction),
+    )
+    .await?;
+    db_entities::AppNotification::delete_many(
+        doc! {"entity_id": {"$in": &pending_transfer_ids}},
+        Some(&mut transaction),
+    )
+    .await?;
+
+    swap_company_owner(&company_id, &new_owner_id, &mut transaction).await?;
+
+    let mut transfer = db_entities::CompanyOwnershipTransfer::new(
+        company_id,
+        previous_owner_ids.first().copied().unwrap_or(new_owner_id),
+        new_owner_id,
+        Some(true),
+        Utc::now(),
+        Some(platform_admin_id),
+    );
+    transfer.save(Some(&mut transaction)).await?;
+
+    for user_id in previous_owner_ids.iter().chain([&new_owner_id]) {
+        let mut notification = db_entities::AppNotification::new(
+            *user_id,
+            NotificationType::OwnershipTransferAnswer,
+            format!(
+                "The ownership of Company {} has been transferred by a platform admin",
+                company.name()
+            ),
+            false,
+            transfer.get_id().copied(),
+        );
+        notification.save(Some(&mut transaction)).await?;
+    }
+
+    transaction.commit_transaction().await?;
+    Ok(())
+}
+
+/// Mark as Expired every pending invite whose expiration date is passed.
+///
+/// The notification of the invited user is removed and the inviting user is notified.
+/// It returns the number of expired invites.
+pub async fn expire_invites() -> Result<usize, ServiceAppError> {
+    let now = Utc::now();
+    let expired_invites = db_entities::InviteAddCompany::find_many(doc! {
+        "status": InviteStatus::Pending,
+        "expiration_date": {"$lte": now},
+    })
+    .await?;
+    let expired_email_invites = db_entities::InviteAddCompanyByEmail::find_many(doc! {
+        "status": InviteStatus::Pending,
+        "registered_user_id": null,
+        "expiration_date": {"$lte": now},
+    })
+    .await?;
+    if expired_invites.is_empty() && expired_email_invites.is_empty() {
+        return Ok(0);
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct UserQueryResult {
+        _id: DocumentId,
+        username: String,
+    }
+    let usernames = db_entities::User::find_many_projection::<UserQueryResult>(
+        doc! {"_id": {"$in": expired_invites.iter().map(|invite| invite.invited_user_id()).collect::<Vec<&DocumentId>>()}},
+        doc! {"_id": 1, "username": 1},
+    )
+    .await?
+    .into_iter()
+    .map(|user| (user._id, user.username))
+    .collect::<HashMap<DocumentId, String>>();
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct CompanyQueryResult {
+        _id: DocumentId,
+        name: String,
+    }
+    let company_names = db_entities::Company::find_many_projection::<CompanyQueryResult>(
+        doc! {"_id": {"$in": expired_invites.iter().map(|invite| invite.company_id()).chain(expired_email_invites.iter().map(|invite| invite.company_id())).collect::<Vec<&DocumentId>>()}},
+        doc! {"_id": 1, "name": 1},
+    )
+    .await?
+    .into_iter()
+    .map(|company| (company._id, company.name))
+    .collect::<HashMap<DocumentId, String>>();
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+
+    for invite in expired_invites.iter() {
+        db_entities::InviteAddCompany::update_one(
+            doc! {"_id": invite.get_id()},
+            doc! {"$set": {"status": InviteStatus::Expired, "closed_at": now}},
+            Some(&mut transaction),
+        )
+        .await?;
+        db_entities::AppNotification::delete_many(
+            doc! {"user_id": invite.invited_user_id(), "entity_id": invite.get_id()},
+            Some(&mut transaction),
+        )
+        .await?;
+
+        let mut notification = db_entities::AppNotification::new(
+            *invite.inviting_user_id(),
+            NotificationType::ExpiredInvite,
+            format!(
+                "The invite of user {} to join company {} has expired",
+                usernames
+                    .get(invite.invited_user_id())
+                    .map(String::as_str)
+                    .unwrap_or_default(),
+                company_names
+                    .get(invite.company_id())
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            ),
+            false,
+            invite.get_id().copied(),
+        );
+        notification.save(Some(&mut transaction)).await?;
+    }
+    for invite in expired_email_invites.iter() {
+        db_entities::InviteAddCompanyByEmail::update_one(
+            doc! {"_id": invite.get_id()},
+            doc! {"$set": {"status": InviteStatus::Expired, "closed_at": now}},
+            Some(&mut transaction),
+        )
+        .await?;
+
+        let mut notification = db_entities::AppNotification::new(
+            *invite.inviting_user_id(),
+            NotificationType::ExpiredInvite,
+            format!(
+                "The invite of {} to join company {} has expired",
+                invite.email(),
+                company_names
+                    .get(invite.company_id())
+                    .map(String::as_str)
+                    .unwrap_or_default()
+            ),
+            false,
+            invite.get_id().copied(),
+        );
+        notification.save(Some(&mut transaction)).await?;
+    }
+
+    transaction.commit_transaction().await?;
+    Ok(expired_invites.len() + expired_email_invites.len())
+}
+
+/// Bring the invites saved before the lifecycle states to the current schema.
+///
+/// Invites with the legacy `answer` become Accepted or Declined when answered, otherwise
+/// Pending with a new expiration date. Invites by email get their status from the registered
+/// user and the expiration date. The creation date is taken from the document id.
+/// It returns the number of migrated invites.
+pub async fn migrate_legacy_invites() -> Result<usize, ServiceAppError> {
+    let now = Utc::now();
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct LegacyInvite {
+        _id: DocumentId,
+        answer: Option<bool>,
+    }
+    let legacy_invites = db_entities::InviteAddCompany::find_many_projection::<LegacyInvite>(
+        doc! {"status": {"$exists": false}},
+        doc! {"_id": 1, "answer": 1},
+    )
+    .await?;
+    for invite in legacy_invites.iter() {
+        let created_at = invite._id.timestamp().to_chrono();
+        let update = match invite.answer {
+            Some(accepted) => doc! {
+                "status": if accepted { InviteStatus::Accepted } else { InviteStatus::Declined },
+                "created_at": created_at,
+                "expiration_date": created_at,
+                "closed_at": created_at,
+            },
+            None => doc! {
+                "status": InviteStatus::Pending,
+                "created_at": created_at,
+                "expiration_date": now + Duration::hours(ENVIRONMENT.invitation.invite_validity_hours),
+                "closed_at": null,
+            },
+        };
+        db_entities::InviteAddCompany::update_one(
+            doc! {"_id": invite._id},
+            doc! {"$set": update, "$unset": {"answer": ""}},
+            None,
+        )
+        .await?;
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct LegacyEmailInvite {
+        _id: DocumentId,
+        registered_user_id: Option<DocumentId>,
+        #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+        expiration_date: chrono::DateTime<Utc>,
+    }
+    let legacy_email_invites =
+        db_entities::InviteAddCompanyByEmail::find_many_projection::<LegacyEmailInvite>(
+            doc! {"status": {"$exists": false}},
+            doc! {"_id": 1, "registered_user_id": 1, "expiration_date": 1},
+        )
+        .await?;
+    for invite in legacy_email_invites.iter() {
+        let (status, closed_at) = if invite.registered_user_id.is_some() {
+            (
+                InviteStatus::Accepted,
+                Some(invite.expiration_date.min(now)),
+            )
+        } else if invite.expiration_date <= now {
+            (InviteStatus::Expired, Some(invite.expiration_date))
+        } else {
+            (InviteStatus::Pending, None)
+        };
+        db_entities::InviteAddCompanyByEmail::update_one(
+            doc! {"_id": invite._id},
+            doc! {"$set": {
+                "status": status,
+                "created_at": invite._id.timestamp().to_chrono(),
+                "closed_at": closed_at,
+            }},
+            None,
+        )
+        .await?;
+    }
+
+    Ok(legacy_invites.len() + legacy_email_invites.len())
+}
+
+/// Background task that periodically marks expired invites.
+///
+/// The interval between two runs is defined by the environment service
+pub async fn run_invite_expiry_sweep() {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        ENVIRONMENT.invitation.expiry_sweep_interval_seconds,
+    ));
+    loop {
+        interval.tick().await;
+        match expire_invites().await {
+            Ok(0) => {}
+            Ok(total) => info!("Marked {total} invites as expired"),
+            Err(e) => error!("Failed to mark expired invites. Got {e}"),
+        }
+    }
+}
+
+/// Send again an invite to the invited user.
+///
+/// A pending invite gets a new expiration date and its notification is shown again,
+/// while an expired invite is replaced by a new invite with the same role, job title
+/// and projects. Accepted, declined and cancelled invites cannot be resent.
+pub async fn resend_invite(
+    resending_user_id: DocumentId,
+    company_id: DocumentId,
+    invite_id: DocumentId,
+) -> Result<(), ServiceAppError> {
+    let Some(invite) =
+        db_entities::InviteAddCompany::find_one(doc! {"_id": invite_id, "company_id": company_id})
+            .await?
+    else {
+        return resend_email_invite(resending_user_id, company_id, invite_id).await;
+    };
+
+    verify_assignable_role(&resending_user_id, &company_id, *invite.company_role()).await?;
+
+    match invite.status() {
+        InviteStatus::Pending => {
+            let db_service = get_database_service().await;
+            let mut transaction = db_service.new_transaction().await?;
+            transaction.start_transaction().await?;
+
+            db_entities::InviteAddCompany::update_one(
+                doc! {"_id": invite_id},
+                doc! {"$set": {"expiration_date": Utc::now() + Duration::hours(ENVIRONMENT.invitation.invite_validity_hours)}},
+                Some(&mut transaction),
+            )
+            .await?;
+            db_entities::AppNotification::update_many(
+                doc! {"user_id": invite.invited_user_id(), "entity_id": invite_id},
+                doc! {"$set": {"read": false}},
+                Some(&mut transaction),
+            )
+            .await?;
+
+            transaction.commit_transaction().await?;
+            Ok(())
+        }
+        InviteStatus::Expired => {
+            if db_entities::UserCompanyAssignment::count_documents(
+                doc! {"user_id": invite.invited_user_id(), "company_id": company_id},
+            )
+            .await?
+                > 0
+            {
+                return Err(ServiceAppError::InvalidRequest(format!(
+                    "User {} is already in the company",
+                    invite.invited_user_id()
+                )));
+            }
+            if db_entities::InviteAddCompany::count_documents(doc! {
+                "company_id": company_id,
+                "invited_user_id": invite.invited_user_id(),
+                "status": InviteStatus::Pending,
+            })
+            .await?
+                > 0
+            {
+                return Err(ServiceAppError::InvalidRequest(format!(
+                    "There is already a pending invite for user {}",
+                    invite.invited_user_id()
+                )));
+            }
+            invite_user(
+                resending_user_id,
+                company_id,
+                *invite.invited_user_id(),
+                *invite.company_role(),
+                invite.job_title().clone(),
+                invite.project_ids().clone(),
+            )
+            .await
+        }
+        status => Err(ServiceAppError::InvalidRequest(format!(
+            "Invite with status {status:?} cannot be resent"
+        ))),
+    }
+}
+
+/// Send again an invite by email.
+///
+/// A pending invite gets a new expiration date and the email is sent again with a new link,
+/// while an expired invite is replaced by a new invite by email.
+async fn resend_email_invite(
+    resending_user_id: DocumentId,
+    company_id: DocumentId,
+    invite_id: DocumentId,
+) -> Result<(), ServiceAppError> {
+    let mut invite = db_entities::InviteAddCompanyByEmail::find_one(
+        doc! {"_id": invite_id, "company_id": company_id},
+    )
+    .await?
+    .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+        "Invite with id {invite_id} does not exist"
+    )))?;
+
+    verify_assignable_role(&resending_user_id, &company_id, *invite.company_role()).await?;
+
+    let now = Utc::now();
+    let status = if *invite.status() == InviteStatus::Pending && *invite.expiration_date() <= now {
+        InviteStatus::Expired
+    } else {
+        *invite.status()
+    };
+    match status {
+        InviteStatus::Pending => {
+            let company = db_entities::Company::find_one(doc! {"_id": company_id})
+                .await?
+                .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+                    "Company with id {company_id} does not exist"
+                )))?;
+            invite.set_expiration_date(
+                now + Duration::hours(ENVIRONMENT.invitation.email_invite_validity_hours),
+            );
+            invite.save(None).await?;
+            send_email_invite(&invite, company.name()).await
+        }
+        InviteStatus::Expired => {
+            if *invite.status() == InviteStatus::Pending {
+                db_entities::InviteAddCompanyByEmail::update_one(
+                    doc! {"_id": invite_id},
+                    doc! {"$set": {"status": InviteStatus::Expired, "closed_at": invite.expiration_date()}},
+                    None,
+                )
+                .await?;
+            }
+            invite_user_by_email(
+                resending_user_id,
+                company_id,
+                invite.email().clone(),
+                *invite.company_role(),
+                invite.job_title().clone(),
+                invite.project_ids().clone(),
+            )
+            .await
+        }
+        status => Err(ServiceAppError::InvalidRequest(format!(
+            "Invite with status {status:?} cannot be resent"
+        ))),
+    }
+}
+
+/// Returns every invite of the company, the most recent first.
+///
+/// Pending invites whose expiration date is passed are reported as Expired
+/// even if the sweep has not processed them yet.
+pub async fn get_invitation_history(
+    company_id: &DocumentId,
+) -> Result<Vec<InvitationHistoryEntry>, ServiceAppError> {
+    let invites = db_entities::InviteAddCompany::find_many(doc! {"company_id": company_id}).await?;
+    let email_invites =
+        db_entities::InviteAddCompanyByEmail::find_many(doc! {"company_id": company_id}).await?;
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct UserQueryResult {
+        _id: DocumentId,
+        username: String,
+    }
+    let user_ids = invites
+        .iter()
+        .flat_map(|invite| [invite.invited_user_id(), invite.inviting_user_id()])
+        .chain(email_invites.iter().flat_map(|invite| {
+            std::iter::once(invite.inviting_user_id()).chain(invite.registered_user_id())
+        }))
+        .collect::<Vec<&DocumentId>>();
+    let usernames = db_entities::User::find_many_projection::<UserQueryResult>(
+        doc! {"_id": {"$in": user_ids}},
+        doc! {"_id": 1, "username": 1},
+    )
+    .await?
+    .into_iter()
+    .map(|user| (user._id, user.username))
+    .collect::<HashMap<DocumentId, String>>();
+
+    let now = Utc::now();
+    // pending invites past the expiration date are reported as expired before the sweep
+    let status_and_closed_at =
+        |status: InviteStatus,
+         expiration_date: &chrono::DateTime<Utc>,
+         closed_at: &Option<chrono::DateTime<Utc>>| {
+            if status == InviteStatus::Pending && *expiration_date <= now {
+                (InviteStatus::Expired, Some(*expiration_date))
+            } else {
+                (status, *closed_at)
+            }
+        };
+    let username = |user_id: &DocumentId| usernames.get(user_id).cloned().unwrap_or_default();
+
+    let mut history: Vec<InvitationHistoryEntry> = invites
+        .into_iter()
+        .map(|invite| {
+            let (status, closed_at) = status_and_closed_at(
+                *invite.status(),
+                invite.expiration_date(),
+                invite.closed_at(),
+            );
+            InvitationHistoryEntry {
+                invite_id: *invite
+                    .get_id()
+                    .expect("id should exist from document retrieved from db"),
+                invited_user_id: Some(*invite.invited_user_id()),
+                invited_username: username(invite.invited_user_id()),
+                invited_email: None,
+                inviting_user_id: *invite.inviting_user_id(),
+                inviting_username: username(invite.inviting_user_id()),
+                role: *invite.company_role(),
+                job_title: invite.job_title().clone(),
+                status,
+                created_at: *invite.created_at(),
+                expiration_date: *invite.expiration_date(),
+                closed_at,
+            }
+        })
+        .collect();
+    history.extend(email_invites.into_iter().map(|invite| {
+        let (status, closed_at) = status_and_closed_at(
+            *invite.status(),
+            invite.expiration_date(),
+            invite.closed_at(),
+        );
+        InvitationHistoryEntry {
+            invite_id: *invite
+                .get_id()
+                .expect("id should exist from document retrieved from db"),
+            invited_user_id: *invite.registered_user_id(),
+            invited_username: invite
+                .registered_user_id()
+                .as_ref()
+                .map(username)
+                .unwrap_or_default(),
+            invited_email: Some(invite.email().clone()),
+            inviting_user_id: *invite.inviting_user_id(),
+            inviting_username: username(invite.inviting_user_id()),
+            role: *invite.company_role(),
+            job_title: invite.job_title().clone(),
+            status,
+            created_at: *invite.created_at(),
+            expiration_date: *invite.expiration_date(),
+            closed_at,
+        }
+    }));
+    history.sort_by_key(|entry| std::cmp::Reverse(entry.created_at));
+    Ok(history)
 }
 
+/// Returns the users that can be invited in the company, i.e., the ones that are not already
+/// in it and have no pending invitation. When `group_members_only` is true the users are
+/// restricted to the employees of the other companies of the corporate group of the company.
 pub async fn get_users_to_invite_in_company(
     company_id: DocumentId,
+    group_members_only: bool,
 ) -> Result<Vec<(DocumentId, String)>, ServiceAppError> {
-    // Users can be invited to a company if they are not already in it and if there is no pending invitation
-
     #[derive(Serialize, Deserialize, Debug)]
     struct InvitedUsersQueryResult {
         invited_user_id: DocumentId,
     }
     let mut users_to_exclude: Vec<DocumentId> =
         db_entities::InviteAddCompany::find_many_projection::<InvitedUsersQueryResult>(
-            doc! {"company_id": company_id, "answer": null},
+            doc! {"company_id": company_id, "status": InviteStatus::Pending},
             doc! {"invited_user_id": 1},
         )
         .await?
@@ -532,10 +1656,16 @@ pub async fn get_users_to_invite_in_company(
     }
 
     users_to_exclude.append(&mut users_in_company);
+    let mut query = doc! {"_id": {"$not": {"$in": users_to_exclude}}};
+    if group_members_only {
+        let company_ids = corporate_group::get_directory_company_ids(&company_id).await?;
+        let group_members = corporate_group::get_member_ids(&company_ids).await?;
+        query = doc! {"$and": [query, {"_id": {"$in": group_members}}]};
+    }
 
     let to_return: Vec<(DocumentId, String)> =
         db_entities::User::find_many_projection::<UserQueryResult>(
-            doc! {"_id": {"$not": {"$in": users_to_exclude}}},
+            query,
             doc! {
                 "_id": 1,
                 "username": 1,
@@ -587,7 +1717,9 @@ pub async fn create_project(
     company_id: DocumentId,
     name: String,
     code: String,
+    client_id: Option<DocumentId>,
 ) -> Result<String, ServiceAppError> {
+    check_client(&company_id, client_id.as_ref()).await?;
     let company_projects =
         db_entities::CompanyProject::find_many(doc! {"company_id": company_id}).await?;
 
@@ -601,7 +1733,7 @@ pub async fn create_project(
         }
     }
 
-    let mut new_project = db_entities::CompanyProject::new(name, code, company_id, true);
+    let mut new_project = db_entities::CompanyProject::new(name, code, company_id, true, client_id);
 
     new_project.save(None).await
 }
@@ -612,8 +1744,10 @@ pub async fn edit_project(
     name: String,
     code: String,
     active: bool,
+    client_id: Option<DocumentId>,
 ) -> Result<String, ServiceAppError> {
     // TODO: instead of loading all the projects documents, load a project with id and then use update_one()
+    check_client(&company_id, client_id.as_ref()).await?;
 
     let company_project_query =
         db_entities::CompanyProject::find_one(doc! {"_id": project_id, "company_id": company_id})
@@ -638,6 +1772,7 @@ pub async fn edit_project(
         company_project.set_name(name);
         company_project.set_code(code);
         company_project.set_active(active);
+        company_project.set_client_id(client_id);
         company_project.save(None).await
     } else {
         Err(ServiceAppError::EntityDoesNotExist(format!(
@@ -647,6 +1782,24 @@ pub async fn edit_project(
     }
 }
 
+/// Check that the client, if any, belongs to the company
+async fn check_client(
+    company_id: &DocumentId,
+    client_id: Option<&DocumentId>,
+) -> Result<(), ServiceAppError> {
+    if let Some(client_id) = client_id {
+        if db_entities::Client::count_documents(doc! {"_id": client_id, "company_id": company_id})
+            .await?
+            == 0
+        {
+            return Err(ServiceAppError::EntityDoesNotExist(format!(
+                "Client with id {client_id} does not exist"
+            )));
+        }
+    }
+    Ok(())
+}
+
 pub async fn delete_project(
     company_id: DocumentId,
     project_id: DocumentId,
@@ -664,7 +1817,22 @@ pub async fn delete_project(
         .await?;
 
         if n_allocations == 0 {
-            company_project.delete(None).await
+            let db_service = get_database_service().await;
+            let mut transaction = db_service.new_transaction().await?;
+            transaction.start_transaction().await?;
+            company_project.delete(Some(&mut transaction)).await?;
+            db_entities::Department::update_many(
+                doc! {"company_id": company_id, "default_project_ids": project_id},
+                doc! {"$pull": {"default_project_ids": project_id}},
+                Some(&mut transaction),
+            )
+            .await?;
+            db_entities::ProjectRoleAssignment::delete_many(
+                doc! {"project_id": project_id},
+                Some(&mut transaction),
+            )
+            .await?;
+            transaction.commit_transaction().await
         } else {
             Err(ServiceAppError::InvalidRequest(format!(
                 "Project with id {} is used in your company and cannot  be deleted",
@@ -765,6 +1933,105 @@ pub async fn edit_company_project_allocations_for_user(
     }
 }
 
+/// Returns the roles explicitly assigned on the project
+pub async fn get_project_roles(
+    company_id: &DocumentId,
+    project_id: &DocumentId,
+) -> Result<Vec<db_entities::ProjectRoleAssignment>, ServiceAppError> {
+    db_entities::ProjectRoleAssignment::find_many(
+        doc! {"company_id": company_id, "project_id": project_id},
+    )
+    .await
+}
+
+/// Assign the role on the project to the user, replacing the previous one.
+/// The user must belong to the company of the project.
+pub async fn set_project_role(
+    company_id: DocumentId,
+    project_id: DocumentId,
+    user_id: DocumentId,
+    role: ProjectRole,
+) -> Result<(), ServiceAppError> {
+    if db_entities::CompanyProject::count_documents(
+        doc! {"_id": project_id, "company_id": company_id},
+    )
+    .await?
+        == 0
+    {
+        return Err(ServiceAppError::EntityDoesNotExist(format!(
+            "Project with id {project_id} does not exist for company {company_id}"
+        )));
+    }
+    if db_entities::UserCompanyAssignment::count_documents(
+        doc! {"user_id": user_id, "company_id": company_id},
+    )
+    .await?
+        == 0
+    {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "User with id {user_id} is not in the company with id {company_id}"
+        )));
+    }
+
+    let mut assignment = if let Some(mut assignment) = db_entities::ProjectRoleAssignment::find_one(
+        doc! {"project_id": project_id, "user_id": user_id},
+    )
+    .await?
+    {
+        assignment.set_role(role);
+        assignment
+    } else {
+        db_entities::ProjectRoleAssignment::new(company_id, project_id, user_id, role)
+    };
+    assignment.save(None).await?;
+    Ok(())
+}
+
+/// Remove the role explicitly assigned on the project to the user
+pub async fn remove_project_role(
+    company_id: DocumentId,
+    project_id: DocumentId,
+    user_id: DocumentId,
+) -> Result<(), ServiceAppError> {
+    let assignment = db_entities::ProjectRoleAssignment::find_one(
+        doc! {"company_id": company_id, "project_id": project_id, "user_id": user_id},
+    )
+    .await?;
+    if let Some(assignment) = assignment {
+        assignment.delete(None).await
+    } else {
+        Err(ServiceAppError::EntityDoesNotExist(format!(
+            "User with id {user_id} has no role on project {project_id}"
+        )))
+    }
+}
+
+/// Returns the role of the user on the project of the company.
+///
+/// The role explicitly assigned takes precedence, otherwise the users
+/// allocated to the project are Members of it.
+pub async fn get_user_project_role(
+    user_id: &DocumentId,
+    company_id: &DocumentId,
+    project_id: &DocumentId,
+) -> Result<Option<ProjectRole>, ServiceAppError> {
+    if let Some(assignment) = db_entities::ProjectRoleAssignment::find_one(
+        doc! {"company_id": company_id, "project_id": project_id, "user_id": user_id},
+    )
+    .await?
+    {
+        return Ok(Some(*assignment.role()));
+    }
+    let allocated = db_entities::UserCompanyAssignment::count_documents(doc! {
+        "user_id": user_id,
+        "company_id": company_id,
+        "project_ids": project_id,
+    })
+    .await?
+        > 0;
+    Ok(allocated.then_some(ProjectRole::Member))
+}
+
 pub async fn get_company_project_activities(
     company_id: DocumentId,
 ) -> Result<Vec<db_entities::ProjectActivity>, ServiceAppError> {
@@ -814,6 +2081,7 @@ pub async fn create_company_project_activity(
     company_id: DocumentId,
     name: String,
     description: String,
+    billable: bool,
 ) -> Result<(), ServiceAppError> {
     // First check if the name does not exist yet for this company
     #[derive(Serialize, Deserialize, Debug)]
@@ -834,7 +2102,7 @@ pub async fn create_company_project_activity(
             "Activity with name {name} already exist for company with id {company_id}"
         )))
     } else {
-        db_entities::ProjectActivity::new(name, description, company_id)
+        db_entities::ProjectActivity::new(name, description, company_id, billable)
             .save(None)
             .await?;
         Ok(())
@@ -846,6 +2114,7 @@ pub async fn edit_company_project_activity(
     activity_id: DocumentId,
     name: String,
     description: String,
+    billable: bool,
 ) -> Result<(), ServiceAppError> {
     // First check if the name does not already exist for this company
     if db_entities::ProjectActivity::find_one(doc! {"name": &name, "_id": {"$ne": activity_id}})
@@ -864,6 +2133,7 @@ pub async fn edit_company_project_activity(
     {
         activity.set_name(name);
         activity.set_description(description);
+        activity.set_billable(billable);
         activity.save(None).await?;
         Ok(())
     } else {
@@ -1009,14 +2279,18 @@ mod tests {
     use mongodb::bson::{doc, oid::ObjectId};
 
     use crate::{
-        enums::CompanyRole,
+        auth::InviteTokenClaim,
+        enums::{CompanyRole, InviteStatus, NotificationType},
         model::db_entities,
         service::{
             company::{
-                add_user_to_company, create_company, get_user_companies, get_user_company,
-                remove_user_from_company, update_user_in_company,
+                add_user_to_company, create_company, expire_invites, force_ownership_transfer,
+                get_invitation_history, get_user_companies, get_user_company, invite_user,
+                invite_user_by_email, nominate_new_owner, register_invited_user,
+                remove_user_from_company, resend_invite, update_user_in_company,
             },
             db::{get_database_service, DatabaseDocument},
+            notification,
         },
     };
 
@@ -1132,6 +2406,7 @@ mod tests {
             CompanyRole::User,
             "CTO".into(),
             vec![],
+            None,
         )
         .await;
         assert!(result.is_ok());
@@ -1148,6 +2423,281 @@ mod tests {
         assert!(drop_result.is_ok());
     }
 
+    #[tokio::test]
+    async fn invite_user_by_email_test() {
+        let mut company = db_entities::Company::new("My Company".into(), true);
+        let company_id = ObjectId::from_str(&company.save(None).await.unwrap()).unwrap();
+        let mut owner = db_entities::User::new(
+            "john.smith@mail.com".into(),
+            "johnsmith".into(),
+            "fdsg39av2".into(),
+            "John".into(),
+            "Smith".into(),
+            Some("api_key".into()),
+            false,
+            true,
+        );
+        let owner_id = ObjectId::from_str(&owner.save(None).await.unwrap()).unwrap();
+        let mut owner_assignment = db_entities::UserCompanyAssignment::new(
+            owner_id,
+            company_id,
+            CompanyRole::Owner,
+            "CEO".into(),
+            vec![],
+        );
+        owner_assignment.save(None).await.unwrap();
+
+        let result = invite_user_by_email(
+            owner_id,
+            company_id,
+            "Jane.Doe@mail.com".into(),
+            CompanyRole::User,
+            "Developer".into(),
+            vec![],
+        )
+        .await;
+        assert!(result.is_ok());
+
+        // a second pending invite for the same email is rejected
+        let result = invite_user_by_email(
+            owner_id,
+            company_id,
+            "jane.doe@mail.com".into(),
+            CompanyRole::User,
+            "Developer".into(),
+            vec![],
+        )
+        .await;
+        assert!(result.is_err());
+
+        let invite = db_entities::InviteAddCompanyByEmail::find_one(doc! {})
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(invite.email(), "jane.doe@mail.com");
+        let token = InviteTokenClaim::new(
+            invite.expiration_date().timestamp() as usize,
+            *invite.get_id().unwrap(),
+            invite.email().clone(),
+        )
+        .build_token()
+        .unwrap();
+
+        let user_id = register_invited_user(
+            &token,
+            "janedoe".into(),
+            "Horse-Battery-7".into(),
+            "Jane".into(),
+            "Doe".into(),
+        )
+        .await
+        .unwrap();
+        let user_id = ObjectId::from_str(&user_id).unwrap();
+
+        let assignment = db_entities::UserCompanyAssignment::find_one(doc! {"user_id": user_id})
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*assignment.company_id(), company_id);
+        assert_eq!(*assignment.role(), CompanyRole::User);
+
+        // the invite cannot be used twice
+        let result = register_invited_user(
+            &token,
+            "janedoe2".into(),
+            "Horse-Battery-7".into(),
+            "Jane".into(),
+            "Doe".into(),
+        )
+        .await;
+        assert!(result.is_err());
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn invite_expiry_and_history_test() {
+        let mut company = db_entities::Company::new("My Company".into(), true);
+        let company_id = ObjectId::from_str(&company.save(None).await.unwrap()).unwrap();
+        let mut owner = db_entities::User::new(
+            "john.smith@mail.com".into(),
+            "johnsmith".into(),
+            "fdsg39av2".into(),
+            "John".into(),
+            "Smith".into(),
+            Some("api_key".into()),
+            false,
+            true,
+        );
+        let owner_id = ObjectId::from_str(&owner.save(None).await.unwrap()).unwrap();
+        let mut owner_assignment = db_entities::UserCompanyAssignment::new(
+            owner_id,
+            company_id,
+            CompanyRole::Owner,
+            "CEO".into(),
+            vec![],
+        );
+        owner_assignment.save(None).await.unwrap();
+        let mut invited_user = db_entities::User::new(
+            "jane.doe@mail.com".into(),
+            "janedoe".into(),
+            "fdsg39av2".into(),
+            "Jane".into(),
+            "Doe".into(),
+            None,
+            false,
+            true,
+        );
+        let invited_user_id = ObjectId::from_str(&invited_user.save(None).await.unwrap()).unwrap();
+
+        invite_user(
+            owner_id,
+            company_id,
+            invited_user_id,
+            CompanyRole::User,
+            "Developer".into(),
+            vec![],
+        )
+        .await
+        .unwrap();
+        // nothing to expire yet
+        assert_eq!(expire_invites().await.unwrap(), 0);
+
+        let invite = db_entities::InviteAddCompany::find_one(doc! {})
+            .await
+            .unwrap()
+            .unwrap();
+        db_entities::InviteAddCompany::update_one(
+            doc! {"_id": invite.get_id()},
+            doc! {"$set": {"expiration_date": Utc::now() - chrono::Duration::hours(1)}},
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(expire_invites().await.unwrap(), 1);
+
+        let invite = db_entities::InviteAddCompany::find_one(doc! {"_id": invite.get_id()})
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*invite.status(), InviteStatus::Expired);
+        assert!(invite.closed_at().is_some());
+        let notifications = db_entities::AppNotification::find_many(doc! {})
+            .await
+            .unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(*notifications[0].user_id(), owner_id);
+        assert_eq!(
+            *notifications[0].notification_type(),
+            NotificationType::ExpiredInvite
+        );
+
+        resend_invite(owner_id, company_id, *invite.get_id().unwrap())
+            .await
+            .unwrap();
+        let history = get_invitation_history(&company_id).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].status, InviteStatus::Pending);
+        assert_eq!(history[1].status, InviteStatus::Expired);
+        assert_eq!(history[1].invited_username, "janedoe");
+        assert_eq!(history[1].inviting_username, "johnsmith");
+
+        // an invite can be resent only while it is pending or expired
+        db_entities::InviteAddCompany::update_one(
+            doc! {"_id": invite.get_id()},
+            doc! {"$set": {"status": InviteStatus::Cancelled}},
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(
+            resend_invite(owner_id, company_id, *invite.get_id().unwrap())
+                .await
+                .is_err()
+        );
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn ownership_transfer_test() {
+        let mut company = db_entities::Company::new("My Company".into(), true);
+        let company_id = ObjectId::from_str(&company.save(None).await.unwrap()).unwrap();
+        let mut user_ids = vec![];
+        for (username, role) in [
+            ("johnsmith", CompanyRole::Owner),
+            ("janedoe", CompanyRole::User),
+            ("markwhite", CompanyRole::Admin),
+        ] {
+            let mut user = db_entities::User::new(
+                format!("{username}@mail.com"),
+                username.into(),
+                "fdsg39av2".into(),
+                "Name".into(),
+                "Surname".into(),
+                None,
+                false,
+                true,
+            );
+            let user_id = ObjectId::from_str(&user.save(None).await.unwrap()).unwrap();
+            add_user_to_company(user_id, company_id, role, "Job".into(), vec![], None)
+                .await
+                .unwrap();
+            user_ids.push(user_id);
+        }
+        let (owner_id, nominee_id, admin_id) = (user_ids[0], user_ids[1], user_ids[2]);
+
+        nominate_new_owner(owner_id, company_id, nominee_id)
+            .await
+            .unwrap();
+        // only one pending transfer at a time
+        assert!(nominate_new_owner(owner_id, company_id, admin_id)
+            .await
+            .is_err());
+
+        let notification = db_entities::AppNotification::find_one(
+            doc! {"user_id": nominee_id, "notification_type": "OwnershipTransfer"},
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        notification::answer_to_ownership_transfer(notification, true)
+            .await
+            .unwrap();
+
+        let role_of = |user_id: ObjectId| async move {
+            *db_entities::UserCompanyAssignment::find_one(
+                doc! {"user_id": user_id, "company_id": company_id},
+            )
+            .await
+            .unwrap()
+            .unwrap()
+            .role()
+        };
+        assert_eq!(role_of(nominee_id).await, CompanyRole::Owner);
+        assert_eq!(role_of(owner_id).await, CompanyRole::Admin);
+
+        // a platform admin gives the company to the admin without asking
+        force_ownership_transfer(owner_id, company_id, admin_id)
+            .await
+            .unwrap();
+        assert_eq!(role_of(admin_id).await, CompanyRole::Owner);
+        assert_eq!(role_of(nominee_id).await, CompanyRole::Admin);
+        assert_eq!(
+            db_entities::UserCompanyAssignment::count_documents(
+                doc! {"company_id": company_id, "role": CompanyRole::Owner}
+            )
+            .await
+            .unwrap(),
+            1
+        );
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+
     #[tokio::test]
     async fn remove_user_from_company_test() {
         let mut company = db_entities::Company::new("My Company".into(), true);
@@ -1238,12 +2788,14 @@ mod tests {
             "my_activity".into(),
             "description".into(),
             company_id.clone(),
+            true,
         );
         activity.save(None).await.unwrap();
         let mut second_activity = db_entities::ProjectActivity::new(
             "my_activity_2".into(),
             "description".into(),
             company_id.clone(),
+            true,
         );
         second_activity.save(None).await.unwrap();
 
@@ -1259,6 +2811,7 @@ mod tests {
                 "description".into(),
                 1,
             )],
+            false,
         );
         timesheet_day.save(None).await.unwrap();
 
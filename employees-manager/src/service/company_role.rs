@@ -0,0 +1,260 @@
+//! Company role service that resolves the permissions of the employees.
+//!
+//! Every employee has a built-in role, User, Admin or Owner, with a fixed set of
+//! permissions. Companies can define custom roles that bundle some permissions and
+//! assign them to their employees, who have them in addition to the ones of their
+//! built-in role. The members of the management team have the management team permissions.
+
+use mongodb::bson::{doc, Bson};
+
+use crate::{
+    enums::{CompanyRole, Permission, MANAGEMENT_TEAM_PERMISSIONS},
+    error::{ServiceAppError, ValidationViolation},
+    model::{db_entities, internal::CompanyRoleDefinition},
+    DocumentId,
+};
+
+use super::{company, db::DatabaseDocument};
+
+const BUILTIN_ROLES: [CompanyRole; 3] = [CompanyRole::User, CompanyRole::Admin, CompanyRole::Owner];
+
+/// Returns the permissions of the built-in role
+pub fn builtin_role_permissions(role: CompanyRole) -> Vec<Permission> {
+    match role {
+        CompanyRole::User => vec![],
+        CompanyRole::Admin | CompanyRole::Owner => Permission::ALL.to_vec(),
+    }
+}
+
+/// Returns the permissions of the user in the company from the built-in role,
+/// the custom roles and the management team
+pub async fn get_user_permissions(
+    user_id: &DocumentId,
+    company_id: &DocumentId,
+) -> Result<Vec<Permission>, ServiceAppError> {
+    let assignment = company::get_user_company_role(user_id, company_id).await?;
+    let mut permissions = builtin_role_permissions(*assignment.role());
+
+    for custom_role in db_entities::CustomCompanyRole::find_many(
+        doc! {"company_id": company_id, "user_ids": user_id},
+    )
+    .await?
+    {
+        permissions.extend(custom_role.permissions());
+    }
+    if company::is_in_management_team(user_id, company_id).await? {
+        permissions.extend(MANAGEMENT_TEAM_PERMISSIONS);
+    }
+
+    Ok(Permission::ALL
+        .into_iter()
+        .filter(|permission| permissions.contains(permission))
+        .collect())
+}
+
+/// Returns the built-in roles followed by the custom roles of the company
+pub async fn get_roles(
+    company_id: &DocumentId,
+) -> Result<Vec<CompanyRoleDefinition>, ServiceAppError> {
+    let mut roles = vec![];
+    for role in BUILTIN_ROLES {
+        let user_ids = db_entities::UserCompanyAssignment::find_many(
+            doc! {"company_id": company_id, "role": role},
+        )
+        .await?
+        .iter()
+        .map(|assignment| *assignment.user_id())
+        .collect();
+        roles.push(CompanyRoleDefinition {
+            id: None,
+            name: role.to_string(),
+            permissions: builtin_role_permissions(role),
+            user_ids,
+        });
+    }
+    for custom_role in
+        db_entities::CustomCompanyRole::find_many(doc! {"company_id": company_id}).await?
+    {
+        roles.push(CompanyRoleDefinition {
+            id: custom_role.get_id().cloned(),
+            name: custom_role.name().clone(),
+            permissions: custom_role.permissions().clone(),
+            user_ids: custom_role.user_ids().clone(),
+        });
+    }
+    Ok(roles)
+}
+
+/// Check that the custom role has a name and some permissions
+fn check_custom_role(name: &str, permissions: &[Permission]) -> Result<(), ServiceAppError> {
+    let mut violations = vec![];
+    if name.trim().is_empty() {
+        violations.push(ValidationViolation::new(
+            "name",
+            "required",
+            "The name of the role is required".into(),
+        ));
+    } else if BUILTIN_ROLES
+        .iter()
+        .any(|role| role.to_string().eq_ignore_ascii_case(name.trim()))
+    {
+        violations.push(ValidationViolation::new(
+            "name",
+            "unique",
+            format!("{} is the name of a built-in role", name.trim()),
+        ));
+    }
+    if permissions.is_empty() {
+        violations.push(ValidationViolation::new(
+            "permissions",
+            "required",
+            "The role must have at least one permission".into(),
+        ));
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ServiceAppError::ValidationError(violations))
+    }
+}
+
+/// Check that no other custom role of the company has the name
+async fn check_unique_name(
+    company_id: &DocumentId,
+    role_id: Option<&DocumentId>,
+    name: &str,
+) -> Result<(), ServiceAppError> {
+    let mut query = doc! {"company_id": company_id, "name": name};
+    if let Some(role_id) = role_id {
+        query.insert("_id", doc! {"$ne": role_id});
+    }
+    if db_entities::CustomCompanyRole::count_documents(query).await? > 0 {
+        Err(ServiceAppError::InvalidRequest(format!(
+            "A role named {name} already exists"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+pub async fn create_custom_role(
+    company_id: DocumentId,
+    name: String,
+    permissions: Vec<Permission>,
+) -> Result<String, ServiceAppError> {
+    check_custom_role(&name, &permissions)?;
+    let name = name.trim().to_string();
+    check_unique_name(&company_id, None, &name).await?;
+
+    let mut role = db_entities::CustomCompanyRole::new(company_id, name, permissions, vec![]);
+    role.save(None).await
+}
+
+async fn get_custom_role(
+    company_id: &DocumentId,
+    role_id: &DocumentId,
+) -> Result<db_entities::CustomCompanyRole, ServiceAppError> {
+    db_entities::CustomCompanyRole::find_one(doc! {"_id": role_id, "company_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Role with id {role_id} does not exist"
+        )))
+}
+
+pub async fn edit_custom_role(
+    company_id: DocumentId,
+    role_id: DocumentId,
+    name: String,
+    permissions: Vec<Permission>,
+) -> Result<(), ServiceAppError> {
+    check_custom_role(&name, &permissions)?;
+    let name = name.trim().to_string();
+    let mut role = get_custom_role(&company_id, &role_id).await?;
+    check_unique_name(&company_id, Some(&role_id), &name).await?;
+
+    role.set_name(name);
+    role.set_permissions(permissions);
+    role.save(None).await?;
+    Ok(())
+}
+
+pub async fn delete_custom_role(
+    company_id: DocumentId,
+    role_id: DocumentId,
+) -> Result<(), ServiceAppError> {
+    get_custom_role(&company_id, &role_id)
+        .await?
+        .delete(None)
+        .await
+}
+
+/// Replace the users the custom role is assigned to.
+/// All the users must belong to the company.
+pub async fn set_custom_role_users(
+    company_id: DocumentId,
+    role_id: DocumentId,
+    mut user_ids: Vec<DocumentId>,
+) -> Result<(), ServiceAppError> {
+    user_ids.sort();
+    user_ids.dedup();
+    let mut role = get_custom_role(&company_id, &role_id).await?;
+    let n_users = db_entities::UserCompanyAssignment::count_documents(doc! {
+        "company_id": company_id,
+        "user_id": {"$in": user_ids.iter().map(|id| Bson::ObjectId(*id)).collect::<Vec<Bson>>()},
+    })
+    .await?;
+    if n_users != user_ids.len() as u64 {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "All the users of the role must belong to the company {company_id}"
+        )));
+    }
+
+    role.set_user_ids(user_ids);
+    role.save(None).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        enums::{CompanyRole, Permission},
+        error::ServiceAppError,
+    };
+
+    use super::{builtin_role_permissions, check_custom_role};
+
+    #[test]
+    fn builtin_role_permissions_test() {
+        assert!(builtin_role_permissions(CompanyRole::User).is_empty());
+        for role in [CompanyRole::Admin, CompanyRole::Owner] {
+            let permissions = builtin_role_permissions(role);
+            assert!(Permission::ALL
+                .iter()
+                .all(|permission| permissions.contains(permission)));
+        }
+    }
+
+    #[test]
+    fn check_custom_role_test() {
+        assert!(check_custom_role("HR", &[Permission::ViewTeamTimesheets]).is_ok());
+
+        let rules = |result: Result<(), ServiceAppError>| match result {
+            Err(ServiceAppError::ValidationError(violations)) => violations
+                .into_iter()
+                .map(|violation| (violation.field, violation.rule))
+                .collect::<Vec<(String, String)>>(),
+            _ => vec![],
+        };
+        assert_eq!(
+            rules(check_custom_role(" ", &[])),
+            vec![
+                ("name".to_string(), "required".to_string()),
+                ("permissions".to_string(), "required".to_string()),
+            ]
+        );
+        assert_eq!(
+            rules(check_custom_role("admin", &[Permission::ViewUsers])),
+            vec![("name".to_string(), "unique".to_string())]
+        );
+    }
+}
@@ -1,9 +1,23 @@
+use std::collections::HashMap;
+
 use bson::doc;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
-use crate::{enums::CompanyRole, error::ServiceAppError, model::db_entities, DocumentId};
+use crate::{
+    enums::{CompanyRole, CorporateGroupAction, NotificationType},
+    error::ServiceAppError,
+    model::{
+        db_entities,
+        internal::{CorporateGroupSummary, GroupDirectoryEntry, GroupDirectoryFilter},
+    },
+    DocumentId,
+};
 
-use super::db::DatabaseDocument;
+use super::{
+    company,
+    db::{get_database_service, DatabaseDocument, DatabaseTransaction},
+};
 
 /// Returns the list of companies the user can use to create a new Corporate Group
 ///
@@ -105,38 +119,79 @@ pub async fn create_corporate_group(
     ).await? != company_ids.len() as u64 {
         Err(ServiceAppError::InvalidRequest("User must have at least admin role to add a company in the corporate group.".to_string()))
     } else {
-        let mut new_doc = db_entities::CorporateGroup::new(name, company_ids, *user_id);
-        new_doc.save(None).await?;
-        Ok(())
+        let db_service = get_database_service().await;
+        let mut transaction = db_service.new_transaction().await?;
+        transaction.start_transaction().await?;
+
+        let mut new_doc =
+            db_entities::CorporateGroup::new(name, company_ids.clone(), *user_id, vec![]);
+        new_doc.save(Some(&mut transaction)).await?;
+        let group_id = *new_doc.get_id().ok_or(ServiceAppError::InternalServerError(
+            "Expected object id of the saved corporate group".into(),
+        ))?;
+        record_action(
+            &group_id,
+            user_id,
+            CorporateGroupAction::Created,
+            None,
+            None,
+            &mut transaction,
+        )
+        .await?;
+        for company_id in company_ids.iter() {
+            record_action(
+                &group_id,
+                user_id,
+                CorporateGroupAction::CompanyAdded,
+                Some(*company_id),
+                None,
+                &mut transaction,
+            )
+            .await?;
+        }
+
+        transaction.commit_transaction().await
     }
 }
 
-/// Deletes corporate group if the user has permissions to do it
+/// Deletes corporate group with its join requests, the audit trail is kept
+/// with the deletion as last entry.
+/// Only the owner of the group can delete it.
 pub async fn delete_corporate_group(
     user_id: &DocumentId,
     corporate_group_id: &DocumentId,
 ) -> Result<(), ServiceAppError> {
-    let corporate_group_list = get_corporate_groups_for_user(user_id)
-        .await?
-        .into_iter()
-        .filter(|doc| {
-            doc.get_id()
-                .is_some_and(|doc_id| doc_id == corporate_group_id)
-        })
-        .collect::<Vec<db_entities::CorporateGroup>>();
-
-    if let Some(corporate_group) = corporate_group_list.first() {
-        corporate_group.delete(None).await
-    } else {
-        Err(ServiceAppError::EntityDoesNotExist(format!(
-            "Corporate group with id {corporate_group_id} does not exist."
-        )))
+    let corporate_group = get_corporate_group(corporate_group_id).await?;
+    if corporate_group.owner() != user_id {
+        return Err(ServiceAppError::AccessControlError(
+            "Only the owner can delete the corporate group".into(),
+        ));
     }
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+    corporate_group.delete(Some(&mut transaction)).await?;
+    db_entities::CorporateGroupJoinRequest::delete_many(
+        doc! {"group_id": corporate_group_id},
+        Some(&mut transaction),
+    )
+    .await?;
+    record_action(
+        corporate_group_id,
+        user_id,
+        CorporateGroupAction::Deleted,
+        None,
+        None,
+        &mut transaction,
+    )
+    .await?;
+    transaction.commit_transaction().await
 }
 
 /// Returns the corporate groups visible by the user.
-/// A user can view a corporate group if it is at least admin of a Company
-/// that is in the group.
+/// A user can view a corporate group if it is the owner or an admin of the group
+/// or if it is at least admin of a Company that is in the group.
 /// A user can see more than one group because it can belong to more companies that are in different groups
 pub async fn get_corporate_groups_for_user(
     user_id: &DocumentId,
@@ -155,7 +210,60 @@ pub async fn get_corporate_groups_for_user(
     .map(|elem| elem.company_id)
     .collect::<Vec<DocumentId>>();
 
-    db_entities::CorporateGroup::find_many(doc! {"company_ids": {"$in": user_companies}}).await
+    db_entities::CorporateGroup::find_many(doc! {"$or": [
+        {"company_ids": {"$in": user_companies}},
+        {"owner": user_id},
+        {"admin_ids": user_id},
+    ]})
+    .await
+}
+
+/// Returns the corporate group or ServiceAppError::EntityDoesNotExist
+pub async fn get_corporate_group(
+    group_id: &DocumentId,
+) -> Result<db_entities::CorporateGroup, ServiceAppError> {
+    db_entities::CorporateGroup::find_one(doc! {"_id": group_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Corporate group with id {group_id} does not exist."
+        )))
+}
+
+/// Returns true if the user manages the group, i.e., it is the owner or one of the group admins
+pub fn is_group_manager(user_id: &DocumentId, group: &db_entities::CorporateGroup) -> bool {
+    group.owner() == user_id || group.admin_ids().contains(user_id)
+}
+
+/// Returns ServiceAppError::AccessControlError if the user does not manage the group
+fn check_group_manager(
+    user_id: &DocumentId,
+    group: &db_entities::CorporateGroup,
+) -> Result<(), ServiceAppError> {
+    if is_group_manager(user_id, group) {
+        Ok(())
+    } else {
+        Err(ServiceAppError::AccessControlError(
+            "Only the owner and the admins can manage the corporate group".into(),
+        ))
+    }
+}
+
+/// Returns true if the user manages the group or if it is at least admin of
+/// one of its companies
+pub async fn is_group_owner_or_admin(
+    user_id: &DocumentId,
+    group: &db_entities::CorporateGroup,
+) -> Result<bool, ServiceAppError> {
+    if is_group_manager(user_id, group) {
+        return Ok(true);
+    }
+    Ok(db_entities::UserCompanyAssignment::count_documents(doc! {
+        "user_id": user_id,
+        "company_id": {"$in": group.company_ids()},
+        "role": {"$in": [CompanyRole::Owner, CompanyRole::Admin]},
+    })
+    .await?
+        > 0)
 }
 
 /// Returns the corporate group that contains the company.
@@ -166,18 +274,107 @@ pub async fn get_corporate_group_for_company(
     db_entities::CorporateGroup::find_one(doc! {"company_ids": company_id}).await
 }
 
-/// Edit corporate group by changing the name or the company list
+/// Returns the companies whose employees share the directory with the company:
+/// the companies of its corporate group or only the company if it does not belong to any group
+pub async fn get_directory_company_ids(
+    company_id: &DocumentId,
+) -> Result<Vec<DocumentId>, ServiceAppError> {
+    Ok(get_corporate_group_for_company(company_id)
+        .await?
+        .map(|group| group.company_ids().clone())
+        .unwrap_or_else(|| vec![*company_id]))
+}
+
+/// Returns the users that belong to at least one of the companies
+pub async fn get_member_ids(
+    company_ids: &Vec<DocumentId>,
+) -> Result<Vec<DocumentId>, ServiceAppError> {
+    #[derive(Serialize, Deserialize, Debug)]
+    struct UserQueryResult {
+        user_id: DocumentId,
+    }
+    let mut member_ids: Vec<DocumentId> =
+        db_entities::UserCompanyAssignment::find_many_projection::<UserQueryResult>(
+            doc! {"company_id": {"$in": company_ids}},
+            doc! {"user_id": 1},
+        )
+        .await?
+        .into_iter()
+        .map(|elem| elem.user_id)
+        .collect();
+    member_ids.sort();
+    member_ids.dedup();
+    Ok(member_ids)
+}
+
+/// Returns the employees of the companies of the corporate group of the company
+/// that satisfy the filter, sorted by surname and name
+pub async fn search_group_directory(
+    company_id: &DocumentId,
+    filter: GroupDirectoryFilter,
+) -> Result<Vec<GroupDirectoryEntry>, ServiceAppError> {
+    let company_ids = get_directory_company_ids(company_id).await?;
+    let company_names = company::get_company_names(&company_ids).await?;
+    let assignments =
+        db_entities::UserCompanyAssignment::find_many(doc! {"company_id": {"$in": &company_ids}})
+            .await?;
+    let user_ids: Vec<DocumentId> = assignments
+        .iter()
+        .map(|assignment| *assignment.user_id())
+        .collect();
+    let users: HashMap<DocumentId, db_entities::User> =
+        db_entities::User::find_many(doc! {"_id": {"$in": user_ids}, "active": true})
+            .await?
+            .into_iter()
+            .filter_map(|user| user.get_id().copied().map(|user_id| (user_id, user)))
+            .collect();
+
+    let mut entries: Vec<GroupDirectoryEntry> = assignments
+        .into_iter()
+        .filter_map(|assignment| {
+            users
+                .get(assignment.user_id())
+                .map(|user| GroupDirectoryEntry {
+                    user_id: *assignment.user_id(),
+                    username: user.username().clone(),
+                    email: Some(user.email().clone()),
+                    name: user.name().clone(),
+                    surname: user.surname().clone(),
+                    company_id: *assignment.company_id(),
+                    company_name: company_names
+                        .get(assignment.company_id())
+                        .cloned()
+                        .unwrap_or_default(),
+                    job_title: assignment.job_title().clone(),
+                })
+        })
+        .filter(|entry| filter.matches(entry))
+        .collect();
+    entries.sort_by(|a, b| {
+        (&a.surname, &a.name, &a.company_name).cmp(&(&b.surname, &b.name, &b.company_name))
+    });
+    Ok(entries)
+}
+
+/// Edit corporate group by changing the name or the company list.
+/// Only the owner and the group admins can edit the group; the companies added
+/// are recorded in the audit trail together with the removed ones.
 ///
 /// It returns ServiceAppError::InvalidRequest:
 ///     - if a corporate group with the same name already exists
 ///     - if a Company already belongs to another group
 ///     - if company vector is empty
+///     - if an added company has not this user as owner or admin
 pub async fn edit_corporate_group(
     user_id: &DocumentId,
     group_id: &DocumentId,
     name: String,
     company_ids: Vec<DocumentId>,
 ) -> Result<(), ServiceAppError> {
+    let group = get_corporate_group(group_id).await?;
+    check_group_manager(user_id, &group)?;
+    let (added_company_ids, removed_company_ids) = diff_ids(group.company_ids(), &company_ids);
+
     if company_ids.is_empty() {
         Err(ServiceAppError::InvalidRequest(
             "You cannot have a Corporate Group without companies.".to_string()
@@ -200,17 +397,356 @@ pub async fn edit_corporate_group(
         Err(ServiceAppError::InvalidRequest(
             "Companies cannot belong to more than one Corporate Group".to_string()))
     } else if db_entities::UserCompanyAssignment::count_documents(
-        doc! { "user_id": user_id, "company_id": {"$in": &company_ids}, "role": {"$in": [CompanyRole::Owner, CompanyRole::Admin]}},
-    ).await? != company_ids.len() as u64 {
+        doc! { "user_id": user_id, "company_id": {"$in": &added_company_ids}, "role": {"$in": [CompanyRole::Owner, CompanyRole::Admin]}},
+    ).await? != added_company_ids.len() as u64 {
         Err(ServiceAppError::InvalidRequest("User must have at least admin role to add a company in the corporate group.".to_string()))
     } else {
+        let db_service = get_database_service().await;
+        let mut transaction = db_service.new_transaction().await?;
+        transaction.start_transaction().await?;
         db_entities::CorporateGroup::update_one(
             doc! {"_id": group_id},
             doc! {"$set": {"name": name, "company_ids": company_ids}},
-            None,
+            Some(&mut transaction),
+        )
+        .await?;
+        for (action, ids) in [
+            (CorporateGroupAction::CompanyAdded, added_company_ids),
+            (CorporateGroupAction::CompanyRemoved, removed_company_ids),
+        ] {
+            for company_id in ids {
+                record_action(
+                    group_id,
+                    user_id,
+                    action,
+                    Some(company_id),
+                    None,
+                    &mut transaction,
+                )
+                .await?;
+            }
+        }
+        transaction.commit_transaction().await
+    }
+}
+
+/// Replace the admins of the group. Only the owner can choose the admins, that must
+/// belong to one of the companies of the group.
+pub async fn set_corporate_group_admins(
+    user_id: &DocumentId,
+    group_id: &DocumentId,
+    admin_ids: Vec<DocumentId>,
+) -> Result<(), ServiceAppError> {
+    let group = get_corporate_group(group_id).await?;
+    if group.owner() != user_id {
+        return Err(ServiceAppError::AccessControlError(
+            "Only the owner can choose the admins of the corporate group".into(),
+        ));
+    }
+    let mut admin_ids = admin_ids;
+    admin_ids.sort();
+    admin_ids.dedup();
+    if admin_ids.contains(group.owner()) {
+        return Err(ServiceAppError::InvalidRequest(
+            "The owner cannot be an admin of the corporate group".into(),
+        ));
+    }
+    #[derive(Serialize, Deserialize, Debug)]
+    struct UserQueryResult {
+        user_id: DocumentId,
+    }
+    let mut members: Vec<DocumentId> =
+        db_entities::UserCompanyAssignment::find_many_projection::<UserQueryResult>(
+            doc! {"user_id": {"$in": &admin_ids}, "company_id": {"$in": group.company_ids()}},
+            doc! {"user_id": 1},
         )
+        .await?
+        .into_iter()
+        .map(|elem| elem.user_id)
+        .collect();
+    members.sort();
+    members.dedup();
+    if members.len() != admin_ids.len() {
+        return Err(ServiceAppError::InvalidRequest(
+            "The admins must belong to a company of the corporate group".into(),
+        ));
+    }
+
+    let (added_admin_ids, removed_admin_ids) = diff_ids(group.admin_ids(), &admin_ids);
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+    db_entities::CorporateGroup::update_one(
+        doc! {"_id": group_id},
+        doc! {"$set": {"admin_ids": &admin_ids}},
+        Some(&mut transaction),
+    )
+    .await?;
+    for (action, ids) in [
+        (CorporateGroupAction::AdminAdded, added_admin_ids),
+        (CorporateGroupAction::AdminRemoved, removed_admin_ids),
+    ] {
+        for target_user_id in ids {
+            record_action(
+                group_id,
+                user_id,
+                action,
+                None,
+                Some(target_user_id),
+                &mut transaction,
+            )
+            .await?;
+        }
+    }
+    transaction.commit_transaction().await
+}
+
+/// Returns id and name of the groups the user can ask to join, i.e., all the groups
+/// when the user has at least an eligible company, none otherwise
+pub async fn get_joinable_corporate_groups(
+    user_id: &DocumentId,
+) -> Result<Vec<CorporateGroupSummary>, ServiceAppError> {
+    if get_eligible_companies_for_corporate_group(user_id)
+        .await?
+        .is_empty()
+    {
+        return Ok(vec![]);
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct QueryResult {
+        _id: DocumentId,
+        name: String,
+    }
+    let mut groups = db_entities::CorporateGroup::find_many_projection::<QueryResult>(
+        doc! {},
+        doc! {"_id": 1, "name": 1},
+    )
+    .await?
+    .into_iter()
+    .map(|group| CorporateGroupSummary {
+        id: group._id,
+        name: group.name,
+    })
+    .collect::<Vec<CorporateGroupSummary>>();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(groups)
+}
+
+/// Creates the request to add the company to the group and notifies the group managers.
+///
+/// It returns ServiceAppError::InvalidRequest:
+///     - if the company is not eligible for the user, i.e., the user is not at least
+///       admin of the company or the company already belongs to a group
+///     - if there is already a pending request for the company
+pub async fn create_join_request(
+    user_id: &DocumentId,
+    group_id: &DocumentId,
+    company_id: &DocumentId,
+) -> Result<(), ServiceAppError> {
+    let group = get_corporate_group(group_id).await?;
+    let company = get_eligible_companies_for_corporate_group(user_id)
+        .await?
+        .into_iter()
+        .find(|company| company.get_id() == Some(company_id))
+        .ok_or(ServiceAppError::InvalidRequest(format!(
+            "Company with id {company_id} cannot join a corporate group"
+        )))?;
+    if db_entities::CorporateGroupJoinRequest::count_documents(
+        doc! {"company_id": company_id, "answer": null},
+    )
+    .await?
+        > 0
+    {
+        return Err(ServiceAppError::InvalidRequest(
+            "There is already a pending request to join a corporate group for the company".into(),
+        ));
+    }
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+    let mut join_request = db_entities::CorporateGroupJoinRequest::new(
+        *group_id,
+        *company_id,
+        *user_id,
+        None,
+        None,
+        Utc::now(),
+    );
+    join_request.save(Some(&mut transaction)).await?;
+    record_action(
+        group_id,
+        user_id,
+        CorporateGroupAction::JoinRequested,
+        Some(*company_id),
+        None,
+        &mut transaction,
+    )
+    .await?;
+    for manager_id in std::iter::once(group.owner()).chain(group.admin_ids().iter()) {
+        let mut notification = db_entities::AppNotification::new(
+            *manager_id,
+            NotificationType::CorporateGroupJoinRequest,
+            format!(
+                "Company {} asks to join the corporate group {}",
+                company.name(),
+                group.name()
+            ),
+            false,
+            join_request.get_id().copied(),
+        );
+        notification.save(Some(&mut transaction)).await?;
+    }
+    transaction.commit_transaction().await
+}
+
+/// Returns the requests to join the group that are awaiting an answer.
+/// Only the owner and the group admins can see them.
+pub async fn get_pending_join_requests(
+    user_id: &DocumentId,
+    group_id: &DocumentId,
+) -> Result<Vec<db_entities::CorporateGroupJoinRequest>, ServiceAppError> {
+    let group = get_corporate_group(group_id).await?;
+    check_group_manager(user_id, &group)?;
+    db_entities::CorporateGroupJoinRequest::find_many(doc! {"group_id": group_id, "answer": null})
         .await
+}
+
+/// Approve or refuse the request to join the group and notify the requesting user.
+/// When approved the company is added to the group, if it has not joined another group meanwhile.
+///
+/// The answer is stored only if the request is still awaiting one, so concurrent answers
+/// cannot both succeed.
+pub async fn answer_join_request(
+    user_id: &DocumentId,
+    group_id: &DocumentId,
+    request_id: &DocumentId,
+    approved: bool,
+) -> Result<(), ServiceAppError> {
+    let group = get_corporate_group(group_id).await?;
+    check_group_manager(user_id, &group)?;
+    let join_request = db_entities::CorporateGroupJoinRequest::find_one(
+        doc! {"_id": request_id, "group_id": group_id},
+    )
+    .await?
+    .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+        "Join request with id {request_id} does not exist"
+    )))?;
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+    if db_entities::CorporateGroupJoinRequest::update_one_matched(
+        doc! {"_id": request_id, "answer": null},
+        doc! {"$set": {"answer": approved, "answered_by": user_id}},
+        Some(&mut transaction),
+    )
+    .await?
+        == 0
+    {
+        transaction.abort_transaction().await?;
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "Join request with id {request_id} has already been answered"
+        )));
+    }
+    if approved {
+        if transaction
+            .count_documents::<db_entities::CorporateGroup>(doc! {
+                "_id": {"$ne": group_id},
+                "company_ids": join_request.company_id(),
+            })
+            .await?
+            > 0
+        {
+            transaction.abort_transaction().await?;
+            return Err(ServiceAppError::InvalidRequest(
+                "Companies cannot belong to more than one Corporate Group".into(),
+            ));
+        }
+        db_entities::CorporateGroup::update_one(
+            doc! {"_id": group_id},
+            doc! {"$addToSet": {"company_ids": join_request.company_id()}},
+            Some(&mut transaction),
+        )
+        .await?;
     }
+    record_action(
+        group_id,
+        user_id,
+        if approved {
+            CorporateGroupAction::JoinApproved
+        } else {
+            CorporateGroupAction::JoinRefused
+        },
+        Some(*join_request.company_id()),
+        None,
+        &mut transaction,
+    )
+    .await?;
+    let mut notification = db_entities::AppNotification::new(
+        *join_request.requesting_user_id(),
+        NotificationType::CorporateGroupJoinRequestAnswer,
+        format!(
+            "Your request to join the corporate group {} has been {}",
+            group.name(),
+            if approved { "approved" } else { "refused" }
+        ),
+        false,
+        join_request.get_id().copied(),
+    );
+    notification.save(Some(&mut transaction)).await?;
+    transaction.commit_transaction().await
+}
+
+/// Returns the audit trail of the membership changes of the group, from the most recent.
+/// Only the owner and the group admins can see it.
+pub async fn get_audit_trail(
+    user_id: &DocumentId,
+    group_id: &DocumentId,
+) -> Result<Vec<db_entities::CorporateGroupAuditEntry>, ServiceAppError> {
+    let group = get_corporate_group(group_id).await?;
+    check_group_manager(user_id, &group)?;
+    let mut entries =
+        db_entities::CorporateGroupAuditEntry::find_many(doc! {"group_id": group_id}).await?;
+    entries.sort_by(|a, b| b.created_at().cmp(a.created_at()));
+    Ok(entries)
+}
+
+/// Save an entry of the audit trail of the group inside the transaction
+async fn record_action(
+    group_id: &DocumentId,
+    user_id: &DocumentId,
+    action: CorporateGroupAction,
+    company_id: Option<DocumentId>,
+    target_user_id: Option<DocumentId>,
+    transaction: &mut DatabaseTransaction,
+) -> Result<(), ServiceAppError> {
+    let mut entry = db_entities::CorporateGroupAuditEntry::new(
+        *group_id,
+        *user_id,
+        action,
+        company_id,
+        target_user_id,
+        Utc::now(),
+    );
+    entry.save(Some(transaction)).await?;
+    Ok(())
+}
+
+/// Returns the ids that are only in `new_ids` and the ones that are only in `old_ids`
+fn diff_ids(old_ids: &[DocumentId], new_ids: &[DocumentId]) -> (Vec<DocumentId>, Vec<DocumentId>) {
+    let added = new_ids
+        .iter()
+        .filter(|id| !old_ids.contains(id))
+        .copied()
+        .collect();
+    let removed = old_ids
+        .iter()
+        .filter(|id| !new_ids.contains(id))
+        .copied()
+        .collect();
+    (added, removed)
 }
 
 #[cfg(test)]
@@ -218,20 +754,162 @@ mod tests {
     use bson::oid::ObjectId;
 
     use crate::{
-        enums::CompanyRole,
-        model::db_entities,
+        enums::{CompanyRole, CorporateGroupAction},
+        model::{
+            db_entities,
+            internal::{GroupDirectoryEntry, GroupDirectoryFilter},
+        },
         service::{
-            corporate_group::{create_corporate_group, get_eligible_companies_for_corporate_group},
+            corporate_group::{
+                answer_join_request, create_corporate_group, create_join_request,
+                delete_corporate_group, diff_ids, get_audit_trail,
+                get_eligible_companies_for_corporate_group, get_pending_join_requests,
+            },
             db::{get_database_service, DatabaseDocument},
         },
     };
 
+    /// Save a group with a company of the owner and a company of another user,
+    /// returning the group, the other user and the other company
+    async fn group_with_candidate_company() -> (db_entities::CorporateGroup, ObjectId, ObjectId) {
+        let mut companies: Vec<ObjectId> = vec![];
+        for i in 0..2 {
+            let mut company = db_entities::Company::new(format!("company {i}"), true);
+            company.save(None).await.unwrap();
+            company.reload().await.unwrap();
+            companies.push(*company.get_id().unwrap());
+        }
+        let owner = ObjectId::new();
+        let requesting_user = ObjectId::new();
+        let mut assignment = db_entities::UserCompanyAssignment::new(
+            requesting_user,
+            companies[1],
+            CompanyRole::Admin,
+            "job_title".into(),
+            vec![],
+        );
+        assignment.save(None).await.unwrap();
+
+        let mut group =
+            db_entities::CorporateGroup::new("Group".into(), vec![companies[0]], owner, vec![]);
+        group.save(None).await.unwrap();
+        group.reload().await.unwrap();
+        (group, requesting_user, companies[1])
+    }
+
+    #[tokio::test]
+    async fn test_approve_join_request() {
+        let (group, requesting_user, company_id) = group_with_candidate_company().await;
+        let group_id = *group.get_id().unwrap();
+        let owner = *group.owner();
+
+        let result = create_join_request(&requesting_user, &group_id, &company_id).await;
+        assert!(result.is_ok());
+        let result = create_join_request(&requesting_user, &group_id, &company_id).await;
+        assert!(
+            result.is_err(),
+            "expecting an error because there is already a pending request"
+        );
+
+        assert!(get_pending_join_requests(&requesting_user, &group_id)
+            .await
+            .is_err());
+        let pending_requests = get_pending_join_requests(&owner, &group_id).await.unwrap();
+        assert_eq!(pending_requests.len(), 1);
+        let request_id = *pending_requests[0].get_id().unwrap();
+
+        let result = answer_join_request(&requesting_user, &group_id, &request_id, true).await;
+        assert!(
+            result.is_err(),
+            "expecting an error because only the group managers can answer"
+        );
+        let result = answer_join_request(&owner, &group_id, &request_id, true).await;
+        assert!(result.is_ok());
+        let result = answer_join_request(&owner, &group_id, &request_id, true).await;
+        assert!(
+            result.is_err(),
+            "expecting an error because the request has already been answered"
+        );
+
+        let group = db_entities::CorporateGroup::find_one(bson::doc! {"_id": group_id})
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            group
+                .company_ids()
+                .iter()
+                .filter(|id| **id == company_id)
+                .count(),
+            1
+        );
+        assert!(get_pending_join_requests(&owner, &group_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let actions = get_audit_trail(&owner, &group_id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|entry| *entry.action())
+            .collect::<Vec<CorporateGroupAction>>();
+        assert!(actions.contains(&CorporateGroupAction::JoinRequested));
+        assert!(actions.contains(&CorporateGroupAction::JoinApproved));
+
+        // the audit trail outlives the group
+        assert!(delete_corporate_group(&owner, &group_id).await.is_ok());
+        let entries =
+            db_entities::CorporateGroupAuditEntry::find_many(bson::doc! {"group_id": group_id})
+                .await
+                .unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(entries
+            .iter()
+            .any(|entry| *entry.action() == CorporateGroupAction::Deleted));
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_refuse_join_request() {
+        let (group, requesting_user, company_id) = group_with_candidate_company().await;
+        let group_id = *group.get_id().unwrap();
+        let owner = *group.owner();
+
+        create_join_request(&requesting_user, &group_id, &company_id)
+            .await
+            .unwrap();
+        let request_id = *get_pending_join_requests(&owner, &group_id).await.unwrap()[0]
+            .get_id()
+            .unwrap();
+        let result = answer_join_request(&owner, &group_id, &request_id, false).await;
+        assert!(result.is_ok());
+
+        let group = db_entities::CorporateGroup::find_one(bson::doc! {"_id": group_id})
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!group.company_ids().contains(&company_id));
+        // the company can ask again once the request has been refused
+        let result = create_join_request(&requesting_user, &group_id, &company_id).await;
+        assert!(result.is_ok());
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+
     #[tokio::test]
     async fn test_create_corporate_group() {
         let companies: Vec<ObjectId> = (0..5).map(|_| ObjectId::new()).collect();
         let user = ObjectId::new();
-        let mut first_group =
-            db_entities::CorporateGroup::new("First group".into(), companies[0..3].to_vec(), user);
+        let mut first_group = db_entities::CorporateGroup::new(
+            "First group".into(),
+            companies[0..3].to_vec(),
+            user,
+            vec![],
+        );
 
         for (index, company_id) in companies.iter().enumerate() {
             let mut assignment = db_entities::UserCompanyAssignment::new(
@@ -285,8 +963,12 @@ mod tests {
         }
 
         let user = ObjectId::new();
-        let mut first_group =
-            db_entities::CorporateGroup::new("First group".into(), companies[0..3].to_vec(), user);
+        let mut first_group = db_entities::CorporateGroup::new(
+            "First group".into(),
+            companies[0..3].to_vec(),
+            user,
+            vec![],
+        );
         first_group.save(None).await.unwrap();
 
         for (index, company_id) in companies.iter().enumerate() {
@@ -317,4 +999,54 @@ mod tests {
         let drop_result = get_database_service().await.db.drop().await;
         assert!(drop_result.is_ok());
     }
+
+    #[test]
+    fn diff_ids_test() {
+        let ids: Vec<ObjectId> = (0..4).map(|_| ObjectId::new()).collect();
+        let (added, removed) = diff_ids(&ids[0..3], &[ids[1], ids[3], ids[2]]);
+        assert_eq!(added, vec![ids[3]]);
+        assert_eq!(removed, vec![ids[0]]);
+
+        let (added, removed) = diff_ids(&ids, &ids);
+        assert!(added.is_empty());
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn group_directory_filter_test() {
+        let company_id = ObjectId::new();
+        let entry = GroupDirectoryEntry {
+            user_id: ObjectId::new(),
+            username: "mrossi".into(),
+            email: Some("mario.rossi@example.com".into()),
+            name: "Mario".into(),
+            surname: "Rossi".into(),
+            company_id,
+            company_name: "First company".into(),
+            job_title: "Software Engineer".into(),
+        };
+
+        assert!(GroupDirectoryFilter::default().matches(&entry));
+        assert!(GroupDirectoryFilter {
+            name: Some("mario ROSSI".into()),
+            job_title: Some("engineer".into()),
+            company_id: Some(company_id),
+        }
+        .matches(&entry));
+        assert!(GroupDirectoryFilter {
+            name: Some("mros".into()),
+            ..Default::default()
+        }
+        .matches(&entry));
+        assert!(!GroupDirectoryFilter {
+            job_title: Some("manager".into()),
+            ..Default::default()
+        }
+        .matches(&entry));
+        assert!(!GroupDirectoryFilter {
+            company_id: Some(ObjectId::new()),
+            ..Default::default()
+        }
+        .matches(&entry));
+    }
 }
@@ -3,7 +3,7 @@ use std::{borrow::Borrow, str::FromStr, sync::Arc};
 use futures::TryStreamExt;
 use mongodb::{
     bson::{doc, Document},
-    options::{ClientOptions, FindOneOptions, FindOptions},
+    options::{ClientOptions, FindOneOptions, FindOptions, IndexOptions, ReturnDocument},
     Client, ClientSession, Database, IndexModel,
 };
 
@@ -243,6 +243,75 @@ impl DatabaseTransaction {
         }
     }
 
+    /// Update the first document matching the query and returns the number of matched documents,
+    /// so that conditional updates can detect that the condition no longer holds
+    pub async fn update_one_matched<T>(
+        &mut self,
+        query: Document,
+        update: Document,
+    ) -> Result<u64, ServiceAppError>
+    where
+        T: DatabaseDocument + Send + Sync + Serialize,
+    {
+        if self.transaction_closed {
+            Err(DatabaseError::TransactionClosed.into())
+        } else if self.transaction_started {
+            let db = self
+                .session
+                .client()
+                .database(get_database_service().await.db.name());
+            let collection = db.collection::<T>(T::collection_name());
+            if let Ok(result) = collection
+                .update_one(query, update)
+                .session(&mut self.session)
+                .await
+            {
+                Ok(result.matched_count)
+            } else {
+                self.abort_transaction().await?;
+                Err(DatabaseError::TransactionError.into())
+            }
+        } else {
+            Err(DatabaseError::TransactionNotStarted.into())
+        }
+    }
+
+    /// Atomically update the first document matching the query, inserting it when `upsert`
+    /// is true and no document matches, and returns the document after the update
+    pub async fn find_one_and_update<T>(
+        &mut self,
+        query: Document,
+        update: Document,
+        upsert: bool,
+    ) -> Result<Option<T>, ServiceAppError>
+    where
+        T: DatabaseDocument + Send + Sync + Serialize,
+    {
+        if self.transaction_closed {
+            Err(DatabaseError::TransactionClosed.into())
+        } else if self.transaction_started {
+            let db = self
+                .session
+                .client()
+                .database(get_database_service().await.db.name());
+            let collection = db.collection::<T>(T::collection_name());
+            if let Ok(result) = collection
+                .find_one_and_update(query, update)
+                .upsert(upsert)
+                .return_document(ReturnDocument::After)
+                .session(&mut self.session)
+                .await
+            {
+                Ok(result)
+            } else {
+                self.abort_transaction().await?;
+                Err(DatabaseError::TransactionError.into())
+            }
+        } else {
+            Err(DatabaseError::TransactionNotStarted.into())
+        }
+    }
+
     pub async fn update_many<T>(
         &mut self,
         query: Document,
@@ -362,6 +431,34 @@ impl DatabaseTransaction {
             Err(DatabaseError::TransactionNotStarted.into())
         }
     }
+
+    /// Count the documents matching the query as seen by the transaction
+    pub async fn count_documents<T>(&mut self, query: Document) -> Result<u64, ServiceAppError>
+    where
+        T: DatabaseDocument + Send + Sync + Serialize,
+    {
+        if self.transaction_closed {
+            Err(DatabaseError::TransactionClosed.into())
+        } else if self.transaction_started {
+            let db = self
+                .session
+                .client()
+                .database(get_database_service().await.db.name());
+            let collection = db.collection::<T>(T::collection_name());
+            if let Ok(result) = collection
+                .count_documents(query)
+                .session(&mut self.session)
+                .await
+            {
+                Ok(result)
+            } else {
+                self.abort_transaction().await?;
+                Err(DatabaseError::TransactionError.into())
+            }
+        } else {
+            Err(DatabaseError::TransactionNotStarted.into())
+        }
+    }
 }
 
 /// Trait that defines the behavior for each collection in database.
@@ -388,6 +485,23 @@ pub trait DatabaseDocument: Sized + Send + Sync + Serialize + DeserializeOwned {
         }
     }
 
+    /// Create an index that rejects two documents with the same keys
+    fn set_unique_indexes(
+        keys: Document,
+    ) -> impl std::future::Future<Output = Result<(), ServiceAppError>> {
+        async {
+            let index = IndexModel::builder()
+                .keys(keys)
+                .options(IndexOptions::builder().unique(true).build())
+                .build();
+
+            let db_service = get_database_service().await;
+            let collection = db_service.db.collection::<Self>(Self::collection_name());
+            collection.create_index(index).await?;
+            Ok(())
+        }
+    }
+
     /// Reload the document from the database
     fn reload(&mut self) -> impl std::future::Future<Output = Result<(), ServiceAppError>> {
         async {
@@ -589,6 +703,50 @@ pub trait DatabaseDocument: Sized + Send + Sync + Serialize + DeserializeOwned {
         }
     }
 
+    /// Update the first document matching the query and returns the number of matched documents
+    fn update_one_matched(
+        query: Document,
+        update: Document,
+        transaction: Option<&mut DatabaseTransaction>,
+    ) -> impl std::future::Future<Output = Result<u64, ServiceAppError>> + Send {
+        async {
+            if let Some(transaction) = transaction {
+                transaction.update_one_matched::<Self>(query, update).await
+            } else {
+                let db_service = get_database_service().await;
+                let collection = db_service.db.collection::<Self>(Self::collection_name());
+                let result = collection.update_one(query, update).await?;
+                Ok(result.matched_count)
+            }
+        }
+    }
+
+    /// Atomically update the first document matching the query, inserting it when `upsert`
+    /// is true and no document matches, and returns the document after the update
+    fn find_one_and_update(
+        query: Document,
+        update: Document,
+        upsert: bool,
+        transaction: Option<&mut DatabaseTransaction>,
+    ) -> impl std::future::Future<Output = Result<Option<Self>, ServiceAppError>> + Send {
+        async move {
+            if let Some(transaction) = transaction {
+                transaction
+                    .find_one_and_update::<Self>(query, update, upsert)
+                    .await
+            } else {
+                let db_service = get_database_service().await;
+                let collection = db_service.db.collection::<Self>(Self::collection_name());
+                let result = collection
+                    .find_one_and_update(query, update)
+                    .upsert(upsert)
+                    .return_document(ReturnDocument::After)
+                    .await?;
+                Ok(result)
+            }
+        }
+    }
+
     fn update_many(
         query: Document,
         update: Document,
@@ -0,0 +1,264 @@
+//! Department service that groups the employees of a company in departments or teams.
+//!
+//! A department has its leads and members, an employee can be member of many
+//! departments. The members that join a department are allocated to its default projects.
+
+use mongodb::bson::{doc, Bson};
+
+use crate::{
+    error::{ServiceAppError, ValidationViolation},
+    model::db_entities,
+    DocumentId,
+};
+
+use super::db::{get_database_service, DatabaseDocument};
+
+pub async fn get_departments(
+    company_id: &DocumentId,
+) -> Result<Vec<db_entities::Department>, ServiceAppError> {
+    let mut departments =
+        db_entities::Department::find_many(doc! {"company_id": company_id}).await?;
+    departments.sort_by(|a, b| a.name().cmp(b.name()));
+    Ok(departments)
+}
+
+pub async fn get_department(
+    company_id: &DocumentId,
+    department_id: &DocumentId,
+) -> Result<db_entities::Department, ServiceAppError> {
+    db_entities::Department::find_one(doc! {"_id": department_id, "company_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Department with id {department_id} does not exist"
+        )))
+}
+
+/// Returns the ids of the members of the department
+pub async fn get_department_user_ids(
+    company_id: &DocumentId,
+    department_id: &DocumentId,
+) -> Result<Vec<DocumentId>, ServiceAppError> {
+    Ok(get_department(company_id, department_id)
+        .await?
+        .user_ids()
+        .clone())
+}
+
+/// Returns true if the user is member of a department led by the lead
+pub async fn is_lead_of(
+    company_id: &DocumentId,
+    lead_id: &DocumentId,
+    user_id: &DocumentId,
+) -> Result<bool, ServiceAppError> {
+    Ok(db_entities::Department::count_documents(doc! {
+        "company_id": company_id,
+        "lead_ids": lead_id,
+        "user_ids": user_id,
+    })
+    .await?
+        > 0)
+}
+
+fn to_bson(ids: &[DocumentId]) -> Vec<Bson> {
+    ids.iter().map(|id| Bson::ObjectId(*id)).collect()
+}
+
+/// Check that the name is unique in the company and that leads and default projects
+/// belong to the company
+async fn check_department(
+    company_id: &DocumentId,
+    department_id: Option<&DocumentId>,
+    name: &str,
+    lead_ids: &[DocumentId],
+    default_project_ids: &[DocumentId],
+) -> Result<(), ServiceAppError> {
+    let mut violations = vec![];
+    if name.trim().is_empty() {
+        violations.push(ValidationViolation::new(
+            "name",
+            "required",
+            "The name of the department is required".into(),
+        ));
+    } else {
+        let mut query = doc! {"company_id": company_id, "name": name.trim()};
+        if let Some(department_id) = department_id {
+            query.insert("_id", doc! {"$ne": department_id});
+        }
+        if db_entities::Department::count_documents(query).await? > 0 {
+            violations.push(ValidationViolation::new(
+                "name",
+                "unique",
+                format!("A department named {} already exists", name.trim()),
+            ));
+        }
+    }
+    let n_leads = db_entities::UserCompanyAssignment::count_documents(doc! {
+        "company_id": company_id,
+        "user_id": {"$in": to_bson(lead_ids)},
+    })
+    .await?;
+    if n_leads != lead_ids.len() as u64 {
+        violations.push(ValidationViolation::new(
+            "leadIds",
+            "company",
+            "All the leads must belong to the company".into(),
+        ));
+    }
+    let n_projects = db_entities::CompanyProject::count_documents(doc! {
+        "company_id": company_id,
+        "_id": {"$in": to_bson(default_project_ids)},
+    })
+    .await?;
+    if n_projects != default_project_ids.len() as u64 {
+        violations.push(ValidationViolation::new(
+            "defaultProjectIds",
+            "company",
+            "All the default projects must belong to the company".into(),
+        ));
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ServiceAppError::ValidationError(violations))
+    }
+}
+
+fn dedup(mut ids: Vec<DocumentId>) -> Vec<DocumentId> {
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+pub async fn create_department(
+    company_id: DocumentId,
+    name: String,
+    lead_ids: Vec<DocumentId>,
+    default_project_ids: Vec<DocumentId>,
+) -> Result<String, ServiceAppError> {
+    let (lead_ids, default_project_ids) = (dedup(lead_ids), dedup(default_project_ids));
+    check_department(&company_id, None, &name, &lead_ids, &default_project_ids).await?;
+
+    let mut department = db_entities::Department::new(
+        company_id,
+        name.trim().to_string(),
+        lead_ids,
+        vec![],
+        default_project_ids,
+    );
+    department.save(None).await
+}
+
+/// Edit name, leads and default projects of the department.
+/// The default projects are used only for the members that join the department afterwards.
+pub async fn edit_department(
+    company_id: DocumentId,
+    department_id: DocumentId,
+    name: String,
+    lead_ids: Vec<DocumentId>,
+    default_project_ids: Vec<DocumentId>,
+) -> Result<(), ServiceAppError> {
+    let (lead_ids, default_project_ids) = (dedup(lead_ids), dedup(default_project_ids));
+    let mut department = get_department(&company_id, &department_id).await?;
+    check_department(
+        &company_id,
+        Some(&department_id),
+        &name,
+        &lead_ids,
+        &default_project_ids,
+    )
+    .await?;
+
+    department.set_name(name.trim().to_string());
+    department.set_lead_ids(lead_ids);
+    department.set_default_project_ids(default_project_ids);
+    department.save(None).await?;
+    Ok(())
+}
+
+pub async fn delete_department(
+    company_id: DocumentId,
+    department_id: DocumentId,
+) -> Result<(), ServiceAppError> {
+    get_department(&company_id, &department_id)
+        .await?
+        .delete(None)
+        .await
+}
+
+/// Returns the default projects the member is not allocated to yet
+fn missing_allocations(
+    default_project_ids: &[DocumentId],
+    project_ids: &[DocumentId],
+) -> Vec<DocumentId> {
+    default_project_ids
+        .iter()
+        .filter(|project_id| !project_ids.contains(project_id))
+        .copied()
+        .collect()
+}
+
+/// Replace the members of the department. The new members are allocated
+/// to the default projects of the department.
+pub async fn set_department_members(
+    company_id: DocumentId,
+    department_id: DocumentId,
+    user_ids: Vec<DocumentId>,
+) -> Result<(), ServiceAppError> {
+    let user_ids = dedup(user_ids);
+    let mut department = get_department(&company_id, &department_id).await?;
+    let mut assignments = db_entities::UserCompanyAssignment::find_many(doc! {
+        "company_id": company_id,
+        "user_id": {"$in": to_bson(&user_ids)},
+    })
+    .await?;
+    if assignments.len() != user_ids.len() {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "All the members of the department must belong to the company {company_id}"
+        )));
+    }
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+    for assignment in assignments.iter_mut() {
+        if department.user_ids().contains(assignment.user_id()) {
+            continue;
+        }
+        let missing_projects =
+            missing_allocations(department.default_project_ids(), assignment.project_ids());
+        if missing_projects.is_empty() {
+            continue;
+        }
+        assignment.project_ids_mut().extend(missing_projects);
+        if let Err(e) = assignment.save(Some(&mut transaction)).await {
+            transaction.abort_transaction().await?;
+            return Err(e);
+        }
+    }
+    department.set_user_ids(user_ids);
+    if let Err(e) = department.save(Some(&mut transaction)).await {
+        transaction.abort_transaction().await?;
+        return Err(e);
+    }
+    transaction.commit_transaction().await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DocumentId;
+
+    use super::{dedup, missing_allocations};
+
+    #[test]
+    fn missing_allocations_test() {
+        let (first, second, third) = (DocumentId::new(), DocumentId::new(), DocumentId::new());
+
+        assert_eq!(
+            missing_allocations(&[first, second], &[second, third]),
+            vec![first]
+        );
+        assert!(missing_allocations(&[first], &[first]).is_empty());
+        assert!(missing_allocations(&[], &[first]).is_empty());
+        assert_eq!(dedup(vec![second, first, second]).len(), 2);
+    }
+}
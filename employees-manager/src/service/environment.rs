@@ -16,6 +16,9 @@ pub struct EnvironmentVariables {
     pub logging: LoggingVariables,
     pub authentication: AuthenticationVariables,
     pub database: DatabaseVariables,
+    pub password_policy: PasswordPolicyVariables,
+    pub mailer: MailerVariables,
+    pub invitation: InvitationVariables,
 }
 
 impl EnvironmentVariables {
@@ -39,6 +42,9 @@ impl EnvironmentVariables {
             logging: Self::build_logging(&local, &deploy_environment),
             authentication: Self::build_authentication(&local, &deploy_environment),
             database: Self::build_database(&local, &deploy_environment),
+            password_policy: Self::build_password_policy(&local, &deploy_environment),
+            mailer: Self::build_mailer(&local, &deploy_environment),
+            invitation: Self::build_invitation(&local, &deploy_environment),
         }
     }
 
@@ -97,6 +103,111 @@ impl EnvironmentVariables {
             db_name,
         }
     }
+
+    /// Build password policy variables
+    ///
+    /// Every rule can be tuned with the corresponding `PASSWORD_*` environment variable,
+    /// when it is missing or not valid the default value is used.
+    fn build_password_policy(local: &bool, _deploy_environment: &str) -> PasswordPolicyVariables {
+        let default = PasswordPolicyVariables::default();
+        if *local {
+            default
+        } else {
+            let read_bool = |name: &str, default: bool| {
+                std::env::var(name)
+                    .map(|value| value.to_lowercase() == "true")
+                    .unwrap_or(default)
+            };
+            PasswordPolicyVariables {
+                min_length: std::env::var("PASSWORD_MIN_LENGTH")
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(default.min_length),
+                require_lowercase: read_bool(
+                    "PASSWORD_REQUIRE_LOWERCASE",
+                    default.require_lowercase,
+                ),
+                require_uppercase: read_bool(
+                    "PASSWORD_REQUIRE_UPPERCASE",
+                    default.require_uppercase,
+                ),
+                require_digit: read_bool("PASSWORD_REQUIRE_DIGIT", default.require_digit),
+                require_symbol: read_bool("PASSWORD_REQUIRE_SYMBOL", default.require_symbol),
+                reject_user_similarity: read_bool(
+                    "PASSWORD_REJECT_USER_SIMILARITY",
+                    default.reject_user_similarity,
+                ),
+                reject_common_passwords: read_bool(
+                    "PASSWORD_REJECT_COMMON",
+                    default.reject_common_passwords,
+                ),
+            }
+        }
+    }
+
+    /// Build mailer variables
+    ///
+    /// Environment variable `MAILER_KIND` selects the mailer implementation, at the moment
+    /// only `file` is available and it writes every email in the directory `MAILER_DIRECTORY`.
+    /// `APP_BASE_URL` is the url of the web application used to build links inside emails.
+    fn build_mailer(local: &bool, _deploy_environment: &str) -> MailerVariables {
+        if *local {
+            MailerVariables {
+                kind: MailerKind::File,
+                sender: "no-reply@employees-manager.local".into(),
+                directory: std::env::temp_dir()
+                    .join("employees-manager-mails")
+                    .to_string_lossy()
+                    .into(),
+                app_base_url: "http://localhost:4200".into(),
+            }
+        } else {
+            let kind = match std::env::var("MAILER_KIND")
+                .unwrap_or("file".into())
+                .to_lowercase()
+                .as_str()
+            {
+                "file" => MailerKind::File,
+                other => panic!("MAILER_KIND {other} is not supported"),
+            };
+            MailerVariables {
+                kind,
+                sender: std::env::var("MAILER_SENDER").expect("MAILER_SENDER must be set"),
+                directory: std::env::var("MAILER_DIRECTORY").unwrap_or(".mails".into()),
+                app_base_url: std::env::var("APP_BASE_URL").expect("APP_BASE_URL must be set"),
+            }
+        }
+    }
+
+    /// Build invitation variables
+    ///
+    /// `INVITE_VALIDITY_HOURS` defines for how long a registered user can answer an invitation,
+    /// `INVITE_EMAIL_VALIDITY_HOURS` defines for how long the link sent by email to invite
+    /// a person not registered yet is valid and `INVITE_EXPIRY_SWEEP_INTERVAL_SECONDS` defines
+    /// how often expired invitations are looked for
+    fn build_invitation(local: &bool, _deploy_environment: &str) -> InvitationVariables {
+        let default = InvitationVariables::default();
+        if *local {
+            default
+        } else {
+            InvitationVariables {
+                invite_validity_hours: std::env::var("INVITE_VALIDITY_HOURS")
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(default.invite_validity_hours),
+                email_invite_validity_hours: std::env::var("INVITE_EMAIL_VALIDITY_HOURS")
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(default.email_invite_validity_hours),
+                expiry_sweep_interval_seconds: std::env::var(
+                    "INVITE_EXPIRY_SWEEP_INTERVAL_SECONDS",
+                )
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default.expiry_sweep_interval_seconds),
+            }
+        }
+    }
 }
 
 /// Struct containing logging variables like logging level
@@ -120,3 +231,70 @@ pub struct DatabaseVariables {
     pub connection_string: String,
     pub db_name: String,
 }
+
+/// Struct containing the rules a password must satisfy when a user is created
+#[derive(Clone)]
+pub struct PasswordPolicyVariables {
+    /// minimum number of characters
+    pub min_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// if true, the password cannot contain the username or the email and vice versa
+    pub reject_user_similarity: bool,
+    /// if true, the password cannot be one of the bundled common passwords
+    pub reject_common_passwords: bool,
+}
+
+impl Default for PasswordPolicyVariables {
+    fn default() -> Self {
+        Self {
+            min_length: 10,
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: false,
+            reject_user_similarity: true,
+            reject_common_passwords: true,
+        }
+    }
+}
+
+/// Available implementations of the mailer service
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MailerKind {
+    /// Emails are written as files in a local directory
+    File,
+}
+
+/// Struct containing variables used to send emails
+pub struct MailerVariables {
+    pub kind: MailerKind,
+    /// address used as sender of the emails
+    pub sender: String,
+    /// directory where the file mailer writes the emails
+    pub directory: String,
+    /// url of the web application used to build links
+    pub app_base_url: String,
+}
+
+/// Struct containing variables for invitations to companies
+pub struct InvitationVariables {
+    /// number of hours a registered user can answer an invitation before it expires
+    pub invite_validity_hours: i64,
+    /// number of hours an invitation sent by email can be used to register
+    pub email_invite_validity_hours: i64,
+    /// number of seconds between two runs of the sweep that marks expired invitations
+    pub expiry_sweep_interval_seconds: u64,
+}
+
+impl Default for InvitationVariables {
+    fn default() -> Self {
+        Self {
+            invite_validity_hours: 168,
+            email_invite_validity_hours: 72,
+            expiry_sweep_interval_seconds: 3600,
+        }
+    }
+}
@@ -0,0 +1,312 @@
+//! Group report service that consolidates the figures of the companies of a corporate group.
+//!
+//! For each company the report has the headcount, the hours logged on its projects, the
+//! office and remote days and the leave usage of its employees. Dates are interpreted in
+//! the timezone of each company and a day of an employee of more companies of the group
+//! is counted in each of them, while the hours are counted only in the company they are logged for.
+//! The totals of the group count each day of an employee once.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+use mongodb::bson::doc;
+use rust_xlsxwriter::{workbook::Workbook, Format};
+
+use crate::{
+    enums::WorkingDayType,
+    error::ServiceAppError,
+    model::{
+        db_entities,
+        internal::{GroupCompanyReport, GroupHoursLine, GroupReport, GroupStats},
+    },
+    DocumentId,
+};
+
+use super::{company, db::DatabaseDocument, timesheet};
+
+/// Add the timesheet day to the figures, counting only the hours of the given companies
+fn add_day(
+    stats: &mut GroupStats,
+    company_ids: &[DocumentId],
+    daily_hours: u32,
+    day: &db_entities::TimesheetDay,
+) {
+    stats.logged_hours += day
+        .activities()
+        .iter()
+        .filter(|activity| company_ids.contains(activity.company_id()))
+        .map(|activity| activity.hours())
+        .sum::<u32>();
+    match day.working_type() {
+        WorkingDayType::Office => stats.office_days += 1,
+        WorkingDayType::Remote => stats.remote_days += 1,
+        WorkingDayType::DayOff => {
+            stats.day_off_days += 1;
+            stats.leave_hours += daily_hours as f64;
+        }
+        WorkingDayType::Sick => stats.sick_days += 1,
+        WorkingDayType::Holiday | WorkingDayType::CompanyClosure => {}
+    }
+    stats.leave_hours += *day.permit_hours() as f64;
+}
+
+/// Compute the figures of the company from the timesheet days of its employees
+fn company_stats(
+    company_id: &DocumentId,
+    daily_hours: u32,
+    headcount: u32,
+    days: &[db_entities::TimesheetDay],
+) -> GroupStats {
+    let mut stats = GroupStats {
+        headcount,
+        ..Default::default()
+    };
+    for day in days {
+        add_day(&mut stats, &[*company_id], daily_hours, day);
+    }
+    stats
+}
+
+/// Compute the figures of the group from the timesheet days of the employees of its companies,
+/// each with the daily hours of the company it was first read for.
+///
+/// Days are counted once even when the employee works for more companies of the group,
+/// the headcount is given apart
+fn total_stats(
+    company_ids: &[DocumentId],
+    headcount: u32,
+    days: &[(u32, db_entities::TimesheetDay)],
+) -> GroupStats {
+    let mut totals = GroupStats {
+        headcount,
+        ..Default::default()
+    };
+    let mut counted_days = HashSet::new();
+    for (daily_hours, day) in days {
+        if counted_days.insert((*day.user_id(), *day.date())) {
+            add_day(&mut totals, company_ids, *daily_hours, day);
+        }
+    }
+    totals
+}
+
+/// Returns the consolidated report of the companies of the group between `from` and `to`, both included
+pub async fn get_group_report(
+    group: &db_entities::CorporateGroup,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<GroupReport, ServiceAppError> {
+    let company_names = company::get_company_names(group.company_ids()).await?;
+    let mut companies = vec![];
+    let mut hours: HashMap<(DocumentId, DocumentId, DocumentId), u32> = HashMap::new();
+    let mut all_days = vec![];
+    let mut all_employees = HashSet::new();
+
+    for company_id in group.company_ids() {
+        let (from_date, to_date) = timesheet::company_date_range(company_id, from, to).await?;
+        let settings = company::get_company_settings(company_id).await?;
+        let employees: Vec<DocumentId> =
+            db_entities::UserCompanyAssignment::find_many(doc! {"company_id": company_id})
+                .await?
+                .iter()
+                .map(|assignment| *assignment.user_id())
+                .collect();
+        let days = db_entities::TimesheetDay::find_many(doc! {
+            "user_id": {"$in": &employees},
+            "date": {"$gte": from_date, "$lt": to_date},
+        })
+        .await?;
+
+        for activity in days
+            .iter()
+            .flat_map(|day| day.activities())
+            .filter(|activity| activity.company_id() == company_id)
+        {
+            *hours
+                .entry((*company_id, *activity.project_id(), *activity.activity_id()))
+                .or_default() += activity.hours();
+        }
+        companies.push(GroupCompanyReport {
+            company_id: *company_id,
+            company_name: company_names.get(company_id).cloned().unwrap_or_default(),
+            stats: company_stats(
+                company_id,
+                *settings.daily_hours(),
+                employees.len() as u32,
+                &days,
+            ),
+        });
+        all_employees.extend(employees);
+        all_days.extend(days.into_iter().map(|day| (*settings.daily_hours(), day)));
+    }
+
+    let names = timesheet::TimesheetNames::load(all_days.iter().map(|(_, day)| day)).await?;
+    let mut hours_lines = vec![];
+    for ((company_id, project_id, activity_id), hours) in hours {
+        hours_lines.push(GroupHoursLine {
+            company_id,
+            company_name: company_names.get(&company_id).cloned().unwrap_or_default(),
+            project_id,
+            project_name: names.project_name(&project_id)?.clone(),
+            activity_id,
+            activity_name: names.activity_name(&activity_id)?.clone(),
+            hours,
+        });
+    }
+    hours_lines.sort_by(|a, b| {
+        (&a.company_name, &a.project_name, &a.activity_name).cmp(&(
+            &b.company_name,
+            &b.project_name,
+            &b.activity_name,
+        ))
+    });
+    companies.sort_by(|a, b| a.company_name.cmp(&b.company_name));
+
+    let totals = total_stats(group.company_ids(), all_employees.len() as u32, &all_days);
+    Ok(GroupReport {
+        companies,
+        hours: hours_lines,
+        totals,
+    })
+}
+
+/// Columns of the summary sheet of the group report
+const SUMMARY_COLUMNS: [&str; 9] = [
+    "Company",
+    "Headcount",
+    "Logged hours",
+    "Office days",
+    "Remote days",
+    "Remote ratio",
+    "Days off",
+    "Sick days",
+    "Leave hours",
+];
+
+/// Export the consolidated report of the group with a summary sheet and a sheet with the hours
+pub async fn export_group_report_as_excel(
+    group: &db_entities::CorporateGroup,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<u8>, ServiceAppError> {
+    let report = get_group_report(group, from, to).await?;
+
+    let bold_format = Format::new().set_bold();
+    let ratio_format = Format::new().set_num_format("0.0%");
+    let mut workbook = Workbook::new();
+
+    let summary = workbook.add_worksheet().set_name("Summary")?;
+    for (column_index, &column) in SUMMARY_COLUMNS.iter().enumerate() {
+        summary.write_with_format(0, column_index as u16, column, &bold_format)?;
+    }
+    let rows = report
+        .companies
+        .iter()
+        .map(|company| (company.company_name.as_str(), &company.stats))
+        .chain([("Total", &report.totals)]);
+    for (row_index, (name, stats)) in rows.enumerate() {
+        let row = row_index as u32 + 1;
+        summary.write(row, 0, name)?;
+        summary.write(row, 1, stats.headcount)?;
+        summary.write(row, 2, stats.logged_hours)?;
+        summary.write(row, 3, stats.office_days)?;
+        summary.write(row, 4, stats.remote_days)?;
+        summary.write_with_format(row, 5, stats.remote_ratio(), &ratio_format)?;
+        summary.write(row, 6, stats.day_off_days)?;
+        summary.write(row, 7, stats.sick_days)?;
+        summary.write(row, 8, stats.leave_hours)?;
+    }
+
+    let hours_sheet = workbook.add_worksheet().set_name("Hours")?;
+    for (column_index, &column) in ["Company", "Project", "Activity", "Hours"]
+        .iter()
+        .enumerate()
+    {
+        hours_sheet.write_with_format(0, column_index as u16, column, &bold_format)?;
+    }
+    for (row_index, line) in report.hours.iter().enumerate() {
+        let row = row_index as u32 + 1;
+        hours_sheet.write(row, 0, &line.company_name)?;
+        hours_sheet.write(row, 1, &line.project_name)?;
+        hours_sheet.write(row, 2, &line.activity_name)?;
+        hours_sheet.write(row, 3, line.hours)?;
+    }
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{enums::WorkingDayType, model::db_entities, DocumentId};
+
+    use super::{company_stats, total_stats};
+
+    fn day(
+        company_id: DocumentId,
+        working_type: WorkingDayType,
+        permit_hours: u32,
+        hours: u32,
+    ) -> db_entities::TimesheetDay {
+        db_entities::TimesheetDay::new(
+            DocumentId::new(),
+            Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap(),
+            permit_hours,
+            working_type,
+            if hours > 0 {
+                vec![db_entities::TimesheetActivityHours::new(
+                    company_id,
+                    DocumentId::new(),
+                    DocumentId::new(),
+                    String::new(),
+                    hours,
+                )]
+            } else {
+                vec![]
+            },
+            false,
+        )
+    }
+
+    #[test]
+    fn company_stats_test() {
+        let company_id = DocumentId::new();
+        let days = vec![
+            day(company_id, WorkingDayType::Office, 0, 8),
+            day(company_id, WorkingDayType::Remote, 2, 6),
+            day(company_id, WorkingDayType::Remote, 0, 8),
+            // hours of another company are not counted
+            day(DocumentId::new(), WorkingDayType::Office, 0, 8),
+            day(company_id, WorkingDayType::DayOff, 0, 0),
+            day(company_id, WorkingDayType::Sick, 0, 0),
+        ];
+
+        let stats = company_stats(&company_id, 8, 3, &days);
+        assert_eq!(stats.headcount, 3);
+        assert_eq!(stats.logged_hours, 22);
+        assert_eq!(stats.office_days, 2);
+        assert_eq!(stats.remote_days, 2);
+        assert_eq!(stats.remote_ratio(), 0.5);
+        assert_eq!(stats.day_off_days, 1);
+        assert_eq!(stats.sick_days, 1);
+        assert_eq!(stats.leave_hours, 10.0);
+
+        // the days of an employee of both companies are read once per company
+        let other_company_id = DocumentId::new();
+        let shared_day = day(company_id, WorkingDayType::Remote, 0, 8);
+        let all_days = days
+            .into_iter()
+            .chain([shared_day.clone()])
+            .map(|day| (8, day))
+            .chain([(6, shared_day)])
+            .collect::<Vec<(u32, db_entities::TimesheetDay)>>();
+        let totals = total_stats(&[company_id, other_company_id], 5, &all_days);
+        assert_eq!(totals.headcount, 5);
+        assert_eq!(totals.logged_hours, 30);
+        assert_eq!(totals.office_days, 2);
+        assert_eq!(totals.remote_days, 3);
+        assert_eq!(totals.remote_ratio(), 0.6);
+        assert_eq!(totals.leave_hours, 10.0);
+    }
+}
@@ -0,0 +1,408 @@
+//! Invoice service that turns the billable hours of a client into invoices.
+//!
+//! A draft collects the hours billable to the client in a period, as computed by the
+//! billing service, with a line for each project, activity and hourly rate. The lines are
+//! a snapshot: later changes to the timesheet or to the rates do not affect the invoice.
+//! Numbers are progressive in the company, they are assigned when the draft is created
+//! and never reused, so a wrong invoice is voided instead of deleted.
+
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, Utc};
+use mongodb::bson::doc;
+use rust_xlsxwriter::{workbook::Workbook, Format};
+
+use crate::{
+    enums::InvoiceStatus,
+    error::{ServiceAppError, ValidationViolation},
+    model::{db_entities, internal::BillableLine},
+    DocumentId,
+};
+
+use super::{
+    billing,
+    db::{get_database_service, DatabaseDocument, DatabaseTransaction},
+};
+
+/// Create the draft invoice of the hours billable to the client between `from` and `to`,
+/// both included, and returns its id.
+///
+/// The period cannot overlap the one of another invoice of the client that is not void
+/// and all the billable hours must have a rate.
+pub async fn create_draft(
+    company_id: DocumentId,
+    client_id: DocumentId,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<String, ServiceAppError> {
+    let client = db_entities::Client::find_one(doc! {"_id": client_id, "company_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Client with id {client_id} does not exist"
+        )))?;
+
+    let from_date = from
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is always a valid time")
+        .and_utc();
+    let to_date = to
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is always a valid time")
+        .and_utc();
+    let billable_lines =
+        billing::get_billable_amounts(&company_id, from, to, Some(client_id)).await?;
+    let lines = group_lines(&billable_lines)?;
+    if lines.is_empty() {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "There are no billable hours for the client between {from} and {to}"
+        )));
+    }
+    let total_amount =
+        (lines.iter().map(|line| line.amount()).sum::<f64>() * 100.0).round() / 100.0;
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+    // the sequence is incremented first, so concurrent drafts of the company conflict
+    // and the overlap check below sees the invoices committed before this one
+    let number = next_invoice_number(&company_id, &mut transaction).await?;
+    if transaction
+        .count_documents::<db_entities::Invoice>(doc! {
+            "company_id": company_id,
+            "client_id": client_id,
+            "status": {"$ne": InvoiceStatus::Void},
+            "from_date": {"$lte": to_date},
+            "to_date": {"$gte": from_date},
+        })
+        .await?
+        > 0
+    {
+        transaction.abort_transaction().await?;
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "The client already has an invoice between {from} and {to}"
+        )));
+    }
+    let mut invoice = db_entities::Invoice::new(
+        company_id,
+        client_id,
+        number,
+        InvoiceStatus::Draft,
+        from_date,
+        to_date,
+        client.currency().clone(),
+        lines,
+        total_amount,
+        Utc::now(),
+        None,
+        None,
+    );
+    let invoice_id = match invoice.save(Some(&mut transaction)).await {
+        Ok(invoice_id) => invoice_id,
+        Err(e) => {
+            transaction.abort_transaction().await?;
+            return Err(e);
+        }
+    };
+    transaction.commit_transaction().await?;
+    Ok(invoice_id)
+}
+
+/// Group the billable lines by project, activity and hourly rate, sorted by names and rate.
+///
+/// Returns a validation error with the project activities that have hours without a rate.
+fn group_lines(
+    billable_lines: &[BillableLine],
+) -> Result<Vec<db_entities::InvoiceLine>, ServiceAppError> {
+    let mut violations = vec![];
+    let mut lines: HashMap<(DocumentId, DocumentId, u64), db_entities::InvoiceLine> =
+        HashMap::new();
+    for billable_line in billable_lines {
+        let Some(hourly_rate) = billable_line.hourly_rate else {
+            violations.push(ValidationViolation::new(
+                "rate",
+                "missing",
+                format!(
+                    "No rate applies to {} hours of {} on activity {} of project {}",
+                    billable_line.hours,
+                    billable_line.username,
+                    billable_line.activity_name,
+                    billable_line.project_name
+                ),
+            ));
+            continue;
+        };
+        let line = lines
+            .entry((
+                billable_line.project_id,
+                billable_line.activity_id,
+                hourly_rate.to_bits(),
+            ))
+            .or_insert_with(|| {
+                db_entities::InvoiceLine::new(
+                    billable_line.project_id,
+                    billable_line.project_name.clone(),
+                    billable_line.activity_id,
+                    billable_line.activity_name.clone(),
+                    0,
+                    hourly_rate,
+                    0.0,
+                )
+            });
+        line.set_hours(line.hours() + billable_line.hours);
+        line.set_amount(((line.amount() + billable_line.amount) * 100.0).round() / 100.0);
+    }
+    if !violations.is_empty() {
+        return Err(ServiceAppError::ValidationError(violations));
+    }
+    let mut lines = lines
+        .into_values()
+        .collect::<Vec<db_entities::InvoiceLine>>();
+    lines.sort_by(|a, b| {
+        (a.project_name(), a.activity_name())
+            .cmp(&(b.project_name(), b.activity_name()))
+            .then(a.hourly_rate().total_cmp(b.hourly_rate()))
+    });
+    Ok(lines)
+}
+
+/// Increment the invoice sequence of the company in the transaction and returns the new number.
+///
+/// The sequence is created with the first invoice of the company
+async fn next_invoice_number(
+    company_id: &DocumentId,
+    transaction: &mut DatabaseTransaction,
+) -> Result<u32, ServiceAppError> {
+    let sequence = db_entities::InvoiceSequence::find_one_and_update(
+        doc! {"company_id": company_id},
+        doc! {"$inc": {"last_number": 1}},
+        true,
+        Some(transaction),
+    )
+    .await?
+    .ok_or(ServiceAppError::InternalServerError(format!(
+        "Invoice sequence of company {company_id} was not created"
+    )))?;
+    Ok(*sequence.last_number())
+}
+
+/// Returns the invoices of the company sorted by number, optionally of a client or in a status
+pub async fn get_invoices(
+    company_id: &DocumentId,
+    client_id: Option<DocumentId>,
+    status: Option<InvoiceStatus>,
+) -> Result<Vec<db_entities::Invoice>, ServiceAppError> {
+    let mut query = doc! {"company_id": company_id};
+    if let Some(client_id) = client_id {
+        query.insert("client_id", client_id);
+    }
+    if let Some(status) = status {
+        query.insert("status", status);
+    }
+    let mut invoices = db_entities::Invoice::find_many(query).await?;
+    invoices.sort_by_key(|invoice| *invoice.number());
+    Ok(invoices)
+}
+
+pub async fn get_invoice(
+    company_id: &DocumentId,
+    invoice_id: &DocumentId,
+) -> Result<db_entities::Invoice, ServiceAppError> {
+    db_entities::Invoice::find_one(doc! {"_id": invoice_id, "company_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Invoice with id {invoice_id} does not exist"
+        )))
+}
+
+/// Returns true when an invoice can move from the status `from` to the status `to`
+fn is_valid_transition(from: InvoiceStatus, to: InvoiceStatus) -> bool {
+    matches!(
+        (from, to),
+        (InvoiceStatus::Draft, InvoiceStatus::Issued)
+            | (InvoiceStatus::Draft, InvoiceStatus::Void)
+            | (InvoiceStatus::Issued, InvoiceStatus::Void)
+    )
+}
+
+/// Issue or void the invoice. Drafts can be issued or voided, issued invoices can only be voided
+pub async fn set_invoice_status(
+    company_id: &DocumentId,
+    invoice_id: &DocumentId,
+    status: InvoiceStatus,
+) -> Result<(), ServiceAppError> {
+    let mut invoice = get_invoice(company_id, invoice_id).await?;
+    if !is_valid_transition(*invoice.status(), status) {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "An invoice in status {} cannot become {status}",
+            invoice.status()
+        )));
+    }
+    match status {
+        InvoiceStatus::Issued => invoice.set_issued_at(Some(Utc::now())),
+        InvoiceStatus::Void => invoice.set_voided_at(Some(Utc::now())),
+        InvoiceStatus::Draft => {}
+    }
+    invoice.set_status(status);
+    invoice.save(None).await?;
+    Ok(())
+}
+
+/// Export the invoice as excel file.
+///
+/// The sheet starts with number, status, client and period of the invoice followed by
+/// the lines with the columns:
+/// - project
+/// - activity
+/// - hours
+/// - hourly rate
+/// - amount
+///
+/// and a last row with the totals.
+pub async fn export_as_excel(
+    company_id: &DocumentId,
+    invoice_id: &DocumentId,
+) -> Result<Vec<u8>, ServiceAppError> {
+    let invoice = get_invoice(company_id, invoice_id).await?;
+    let client_name = db_entities::Client::find_one(doc! {"_id": invoice.client_id()})
+        .await?
+        .map(|client| client.name().clone())
+        .unwrap_or_default();
+
+    let bold_format = Format::new().set_bold();
+    let date_format = Format::new().set_num_format("yyyy-mm-dd");
+    let amount_format = Format::new().set_num_format("#,##0.00");
+    let mut workbook = Workbook::new();
+    let worksheet = workbook
+        .add_worksheet()
+        .set_name(format!("Invoice {}", invoice.number()))?;
+
+    worksheet.write_with_format(0, 0, "Invoice", &bold_format)?;
+    worksheet.write(0, 1, *invoice.number())?;
+    worksheet.write_with_format(1, 0, "Status", &bold_format)?;
+    worksheet.write(1, 1, invoice.status().to_string())?;
+    worksheet.write_with_format(2, 0, "Client", &bold_format)?;
+    worksheet.write(2, 1, &client_name)?;
+    worksheet.write_with_format(3, 0, "From", &bold_format)?;
+    worksheet.write_with_format(3, 1, &invoice.from_date().date_naive(), &date_format)?;
+    worksheet.write_with_format(4, 0, "To", &bold_format)?;
+    worksheet.write_with_format(4, 1, &invoice.to_date().date_naive(), &date_format)?;
+    worksheet.write_with_format(5, 0, "Currency", &bold_format)?;
+    worksheet.write(5, 1, invoice.currency())?;
+
+    let header_row = 7;
+    for (column_index, &column) in ["Project", "Activity", "Hours", "Hourly rate", "Amount"]
+        .iter()
+        .enumerate()
+    {
+        worksheet.write_with_format(header_row, column_index as u16, column, &bold_format)?;
+    }
+    let mut current_row = header_row + 1;
+    for line in invoice.lines() {
+        worksheet.write(current_row, 0, line.project_name())?;
+        worksheet.write(current_row, 1, line.activity_name())?;
+        worksheet.write(current_row, 2, *line.hours())?;
+        worksheet.write_with_format(current_row, 3, *line.hourly_rate(), &amount_format)?;
+        worksheet.write_with_format(current_row, 4, *line.amount(), &amount_format)?;
+        current_row += 1;
+    }
+    worksheet.write_with_format(current_row, 0, "Total", &bold_format)?;
+    worksheet.write_with_format(
+        current_row,
+        2,
+        invoice.lines().iter().map(|line| line.hours()).sum::<u32>(),
+        &bold_format,
+    )?;
+    worksheet.write_with_format(
+        current_row,
+        4,
+        *invoice.total_amount(),
+        &amount_format.set_bold(),
+    )?;
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        enums::InvoiceStatus, error::ServiceAppError, model::internal::BillableLine, DocumentId,
+    };
+
+    use super::{group_lines, is_valid_transition};
+
+    fn billable_line(
+        project_id: DocumentId,
+        activity_id: DocumentId,
+        username: &str,
+        hourly_rate: Option<f64>,
+        hours: u32,
+    ) -> BillableLine {
+        BillableLine {
+            client_id: DocumentId::new(),
+            client_name: "Client".into(),
+            currency: "EUR".into(),
+            project_id,
+            project_name: "Project".into(),
+            activity_id,
+            activity_name: "Development".into(),
+            user_id: DocumentId::new(),
+            username: username.into(),
+            hourly_rate,
+            hours,
+            amount: hourly_rate.unwrap_or_default() * hours as f64,
+        }
+    }
+
+    #[test]
+    fn group_lines_test() {
+        let project_id = DocumentId::new();
+        let activity_id = DocumentId::new();
+        let lines = group_lines(&[
+            billable_line(project_id, activity_id, "john", Some(50.0), 10),
+            billable_line(project_id, activity_id, "jane", Some(60.5), 4),
+            billable_line(project_id, activity_id, "mark", Some(50.0), 6),
+        ])
+        .unwrap();
+        // hours of different users at the same rate are merged
+        assert_eq!(lines.len(), 2);
+        assert_eq!(*lines[0].hourly_rate(), 50.0);
+        assert_eq!(*lines[0].hours(), 16);
+        assert_eq!(*lines[0].amount(), 800.0);
+        assert_eq!(*lines[1].hours(), 4);
+        assert_eq!(*lines[1].amount(), 242.0);
+
+        let result = group_lines(&[
+            billable_line(project_id, activity_id, "john", Some(50.0), 10),
+            billable_line(project_id, activity_id, "jane", None, 4),
+        ]);
+        let Err(ServiceAppError::ValidationError(violations)) = result else {
+            panic!("hours without a rate must be rejected");
+        };
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule, "missing");
+    }
+
+    #[test]
+    fn status_transition_test() {
+        assert!(is_valid_transition(
+            InvoiceStatus::Draft,
+            InvoiceStatus::Issued
+        ));
+        assert!(is_valid_transition(
+            InvoiceStatus::Issued,
+            InvoiceStatus::Void
+        ));
+        assert!(!is_valid_transition(
+            InvoiceStatus::Issued,
+            InvoiceStatus::Draft
+        ));
+        assert!(!is_valid_transition(
+            InvoiceStatus::Void,
+            InvoiceStatus::Issued
+        ));
+        assert!(!is_valid_transition(
+            InvoiceStatus::Draft,
+            InvoiceStatus::Draft
+        ));
+    }
+}
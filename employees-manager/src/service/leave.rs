@@ -0,0 +1,504 @@
+//! Leave service that computes holiday and permission balances of the employees.
+//!
+//! The configuration of each balance is stored per user and company, while the balance
+//! itself is always computed from the timesheet: days of type `DayOff` with an accepted
+//! holiday request consume the standard daily hours of the company and `permit_hours`
+//! are consumed as they are.
+//! Hours carried over from the previous year are consumed first and the ones left
+//! at the end of the expiry month are lost. Only the days with activities of the company
+//! are charged to its balance, days without activities are charged when the user belongs
+//! only to the company or when there is a leave request of the company for that date.
+//!
+//! Employees can also submit leave requests for a day, which are accepted or refused
+//! by the management team of the company.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use chrono_tz::Tz;
+use mongodb::bson::doc;
+
+use crate::{
+    enums::{
+        EmployeeRequest, EmployeeRequestOutcome, LeaveAccrualType, NotificationType, WorkingDayType,
+    },
+    error::{ServiceAppError, ValidationViolation},
+    model::{db_entities, internal::LeaveBalanceMonth},
+    DocumentId,
+};
+
+use super::{
+    calendar, company,
+    db::{get_database_service, DatabaseDocument},
+};
+
+/// Create or replace the leave balance configuration of the user in the company
+#[allow(clippy::too_many_arguments)]
+pub async fn set_leave_balance(
+    user_id: DocumentId,
+    company_id: DocumentId,
+    yearly_allowance_hours: u32,
+    accrual_type: LeaveAccrualType,
+    carry_over_cap_hours: u32,
+    carry_over_expiry_month: Option<u32>,
+    start_date: NaiveDate,
+    opening_balance_hours: f64,
+) -> Result<(), ServiceAppError> {
+    if carry_over_expiry_month.is_some_and(|month| !(1..=12).contains(&month)) {
+        return Err(ServiceAppError::ValidationError(vec![
+            ValidationViolation::new(
+                "carryOverExpiryMonth",
+                "range",
+                "The expiry month of the carried over hours must be between 1 and 12".into(),
+            ),
+        ]));
+    }
+    if db_entities::UserCompanyAssignment::count_documents(
+        doc! {"user_id": user_id, "company_id": company_id},
+    )
+    .await?
+        == 0
+    {
+        return Err(ServiceAppError::EntityDoesNotExist(format!(
+            "User {user_id} does not belong to company {company_id}"
+        )));
+    }
+
+    let start_date = start_date
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is always a valid time")
+        .and_utc();
+    match db_entities::LeaveBalance::find_one(doc! {"user_id": user_id, "company_id": company_id})
+        .await?
+    {
+        Some(mut balance) => {
+            balance.set_yearly_allowance_hours(yearly_allowance_hours);
+            balance.set_accrual_type(accrual_type);
+            balance.set_carry_over_cap_hours(carry_over_cap_hours);
+            balance.set_carry_over_expiry_month(carry_over_expiry_month);
+            balance.set_start_date(start_date);
+            balance.set_opening_balance_hours(opening_balance_hours);
+            balance.save(None).await?;
+        }
+        None => {
+            db_entities::LeaveBalance::new(
+                user_id,
+                company_id,
+                yearly_allowance_hours,
+                accrual_type,
+                carry_over_cap_hours,
+                carry_over_expiry_month,
+                start_date,
+                opening_balance_hours,
+            )
+            .save(None)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn get_leave_balance(
+    user_id: &DocumentId,
+    company_id: &DocumentId,
+) -> Result<db_entities::LeaveBalance, ServiceAppError> {
+    db_entities::LeaveBalance::find_one(doc! {"user_id": user_id, "company_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Leave balance of user {user_id} in company {company_id} does not exist"
+        )))
+}
+
+/// Returns the month by month history of the balance for the year and the
+/// projected balance at the end of the year.
+///
+/// The history stops at the current month, while the projection includes the accruals
+/// of the remaining months and the leaves already recorded in the timesheet.
+pub async fn get_leave_balance_history(
+    user_id: &DocumentId,
+    company_id: &DocumentId,
+    year: i32,
+) -> Result<(Vec<LeaveBalanceMonth>, f64), ServiceAppError> {
+    let balance = get_leave_balance(user_id, company_id).await?;
+    let settings = company::get_company_settings(company_id).await?;
+    let tz = settings.tz();
+
+    let to_date = year
+        .checked_add(1)
+        .and_then(|next_year| NaiveDate::from_ymd_opt(next_year, 1, 1))
+        .and_then(|date| calendar::local_midnight(&tz, date).ok())
+        .ok_or(ServiceAppError::InvalidRequest(format!(
+            "Invalid year {year}"
+        )))?;
+    let days = db_entities::TimesheetDay::find_many(doc! {
+        "user_id": user_id,
+        "date": {"$gte": balance.start_date(), "$lt": to_date},
+        "$or": [{"activities.company_id": company_id}, {"activities": {"$size": 0}}],
+    })
+    .await?;
+
+    // days without activities cannot be told apart when the user works for other companies
+    let only_company = db_entities::UserCompanyAssignment::count_documents(
+        doc! {"user_id": user_id, "company_id": {"$ne": company_id}},
+    )
+    .await?
+        == 0;
+    let requests = db_entities::CompanyEmployeeRequest::find_many(doc! {
+        "user_id": user_id,
+        "company_id": company_id,
+    })
+    .await?;
+
+    let consumed_hours = compute_consumed_hours(
+        &days,
+        &requests,
+        tz,
+        *settings.daily_hours() as f64,
+        only_company,
+    );
+    let months = compute_balance(&balance, &consumed_hours, year);
+    let projected_end_of_year = months.last().map(|month| month.balance).unwrap_or_default();
+    let now = Utc::now().with_timezone(&tz);
+    let history = months
+        .into_iter()
+        .filter(|month| {
+            month.year == year && (month.year, month.month) <= (now.year(), now.month())
+        })
+        .collect();
+    Ok((history, projected_end_of_year))
+}
+
+/// Compute the hours consumed month by month by the timesheet days of the company.
+///
+/// `DayOff` days consume the daily hours only when a holiday request for that date
+/// has been accepted.
+fn compute_consumed_hours(
+    days: &[db_entities::TimesheetDay],
+    requests: &[db_entities::CompanyEmployeeRequest],
+    tz: Tz,
+    daily_hours: f64,
+    only_company: bool,
+) -> HashMap<(i32, u32), f64> {
+    let request_dates = requests
+        .iter()
+        .filter(|request| {
+            !matches!(request.request(), EmployeeRequest::Remote(_))
+                && request.request().outcome() != EmployeeRequestOutcome::Refused
+        })
+        .filter_map(|request| request.date().map(|date| date.date_naive()))
+        .collect::<Vec<NaiveDate>>();
+    let holiday_dates = requests
+        .iter()
+        .filter(|request| {
+            *request.request() == EmployeeRequest::Holiday(EmployeeRequestOutcome::Accepted)
+        })
+        .filter_map(|request| request.date().map(|date| date.date_naive()))
+        .collect::<Vec<NaiveDate>>();
+
+    let mut consumed_hours: HashMap<(i32, u32), f64> = HashMap::new();
+    for day in days {
+        let local_date = day.date().with_timezone(&tz);
+        if day.activities().is_empty()
+            && !only_company
+            && !request_dates.contains(&local_date.date_naive())
+        {
+            continue;
+        }
+        let mut hours = *day.permit_hours() as f64;
+        if *day.working_type() == WorkingDayType::DayOff
+            && holiday_dates.contains(&local_date.date_naive())
+        {
+            hours += daily_hours;
+        }
+        *consumed_hours
+            .entry((local_date.year(), local_date.month()))
+            .or_default() += hours;
+    }
+    consumed_hours
+}
+
+/// Compute the balance month by month from the start date to the end of the year
+fn compute_balance(
+    balance: &db_entities::LeaveBalance,
+    consumed_hours: &HashMap<(i32, u32), f64>,
+    to_year: i32,
+) -> Vec<LeaveBalanceMonth> {
+    let start_date: &DateTime<Utc> = balance.start_date();
+    let allowance = *balance.yearly_allowance_hours() as f64;
+
+    let mut months = vec![];
+    let mut current = *balance.opening_balance_hours();
+    let mut carried_remaining = 0.0;
+    let (mut year, mut month) = (start_date.year(), start_date.month());
+    while year <= to_year {
+        let mut carried_over = 0.0;
+        if month == 1 && (year, month) != (start_date.year(), start_date.month()) {
+            // year end: only a capped amount of the positive balance is kept
+            carried_over = current.min(*balance.carry_over_cap_hours() as f64);
+            current = carried_over;
+            carried_remaining = carried_over.max(0.0);
+        }
+
+        let accrued = match balance.accrual_type() {
+            LeaveAccrualType::Monthly => allowance / 12.0,
+            LeaveAccrualType::YearlyUpfront
+                if year == start_date.year() && month == start_date.month() =>
+            {
+                allowance * (13 - month) as f64 / 12.0
+            }
+            LeaveAccrualType::YearlyUpfront if month == 1 => allowance,
+            LeaveAccrualType::YearlyUpfront => 0.0,
+        };
+        current += accrued;
+
+        let consumed = consumed_hours
+            .get(&(year, month))
+            .copied()
+            .unwrap_or_default();
+        carried_remaining -= consumed.min(carried_remaining);
+        current -= consumed;
+
+        let mut expired = 0.0;
+        if *balance.carry_over_expiry_month() == Some(month) && carried_remaining > 0.0 {
+            expired = carried_remaining;
+            current -= expired;
+            carried_remaining = 0.0;
+        }
+
+        months.push(LeaveBalanceMonth {
+            year,
+            month,
+            carried_over,
+            accrued,
+            consumed,
+            expired,
+            balance: current,
+        });
+
+        (year, month) = if month == 12 {
+            let Some(next_year) = year.checked_add(1) else {
+                break;
+            };
+            (next_year, 1)
+        } else {
+            (year, month + 1)
+        };
+    }
+    months
+}
+
+/// Submit a leave request of the user for the day, it awaits the answer of the management team
+pub async fn create_leave_request(
+    user_id: DocumentId,
+    company_id: DocumentId,
+    request: EmployeeRequest,
+    date: NaiveDate,
+) -> Result<String, ServiceAppError> {
+    if company::get_user_company_role(&user_id, &company_id)
+        .await
+        .is_err()
+    {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "User with id {user_id} is not in the company with id {company_id}"
+        )));
+    }
+    let date = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let n_requests = db_entities::CompanyEmployeeRequest::count_documents(doc! {
+        "user_id": user_id,
+        "company_id": company_id,
+        "date": date,
+    })
+    .await?;
+    if n_requests > 0 {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "A leave request for {} already exists",
+            date.date_naive()
+        )));
+    }
+
+    let mut leave_request = db_entities::CompanyEmployeeRequest::new(
+        user_id,
+        company_id,
+        request.with_outcome(EmployeeRequestOutcome::Awaiting),
+        Some(date),
+    );
+    leave_request.save(None).await
+}
+
+/// Returns the leave requests of the company sorted by date, optionally only the ones
+/// awaiting an answer
+pub async fn get_leave_requests(
+    company_id: &DocumentId,
+    only_awaiting: bool,
+) -> Result<Vec<db_entities::CompanyEmployeeRequest>, ServiceAppError> {
+    let mut requests =
+        db_entities::CompanyEmployeeRequest::find_many(doc! {"company_id": company_id}).await?;
+    if only_awaiting {
+        requests.retain(|request| request.request().outcome() == EmployeeRequestOutcome::Awaiting);
+    }
+    requests.sort_by_key(|request| *request.date());
+    Ok(requests)
+}
+
+/// Accept or refuse the leave request and notify the user of the answer.
+/// Only the requests awaiting an answer can be answered and never by the user who submitted them.
+pub async fn answer_leave_request(
+    approver_id: &DocumentId,
+    company_id: &DocumentId,
+    request_id: &DocumentId,
+    accepted: bool,
+) -> Result<(), ServiceAppError> {
+    let mut leave_request = db_entities::CompanyEmployeeRequest::find_one(
+        doc! {"_id": request_id, "company_id": company_id},
+    )
+    .await?
+    .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+        "Leave request with id {request_id} does not exist"
+    )))?;
+    if leave_request.user_id() == approver_id {
+        return Err(ServiceAppError::InvalidRequest(
+            "You cannot answer your own leave request".into(),
+        ));
+    }
+    if leave_request.request().outcome() != EmployeeRequestOutcome::Awaiting {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "Leave request with id {request_id} has already been answered"
+        )));
+    }
+    let outcome = if accepted {
+        EmployeeRequestOutcome::Accepted
+    } else {
+        EmployeeRequestOutcome::Refused
+    };
+    leave_request.set_request(leave_request.request().with_outcome(outcome));
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+    leave_request.save(Some(&mut transaction)).await?;
+    let mut notification = db_entities::AppNotification::new(
+        *leave_request.user_id(),
+        NotificationType::LeaveRequestAnswer,
+        match leave_request.date() {
+            Some(date) => format!(
+                "Your leave request for {} has been {}",
+                date.date_naive(),
+                if accepted { "accepted" } else { "refused" }
+            ),
+            None => format!(
+                "Your leave request has been {}",
+                if accepted { "accepted" } else { "refused" }
+            ),
+        },
+        false,
+        leave_request.get_id().cloned(),
+    );
+    notification.save(Some(&mut transaction)).await?;
+    transaction.commit_transaction().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    use crate::{
+        enums::{EmployeeRequest, EmployeeRequestOutcome, LeaveAccrualType, WorkingDayType},
+        model::db_entities,
+        DocumentId,
+    };
+
+    use super::{compute_balance, compute_consumed_hours};
+
+    #[test]
+    fn monthly_accrual_with_carry_over_test() {
+        let balance = db_entities::LeaveBalance::new(
+            DocumentId::new(),
+            DocumentId::new(),
+            120,
+            LeaveAccrualType::Monthly,
+            16,
+            Some(3),
+            Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap(),
+            4.0,
+        );
+        let consumed = HashMap::from([((2024, 8), 40.0), ((2025, 2), 8.0)]);
+        let months = compute_balance(&balance, &consumed, 2025);
+        assert_eq!(months.len(), 18);
+
+        // 4 + 6 months of 10 hours - 40 consumed
+        let december = &months[5];
+        assert_eq!((december.year, december.month), (2024, 12));
+        assert_eq!(december.balance, 24.0);
+
+        // only 16 hours are carried over
+        assert_eq!(months[6].carried_over, 16.0);
+        assert_eq!(months[6].balance, 26.0);
+        // 8 of the 16 carried hours are consumed in February, the other 8 expire in March
+        assert_eq!(months[8].expired, 8.0);
+        assert_eq!(months[8].balance, 30.0);
+        assert_eq!(months.last().unwrap().balance, 120.0);
+    }
+
+    #[test]
+    fn yearly_upfront_accrual_test() {
+        let balance = db_entities::LeaveBalance::new(
+            DocumentId::new(),
+            DocumentId::new(),
+            120,
+            LeaveAccrualType::YearlyUpfront,
+            0,
+            None,
+            Utc.with_ymd_and_hms(2024, 10, 1, 0, 0, 0).unwrap(),
+            0.0,
+        );
+        let consumed = HashMap::from([((2024, 12), 50.0)]);
+        let months = compute_balance(&balance, &consumed, 2025);
+        // prorated allowance for October, November and December
+        assert_eq!(months[0].accrued, 30.0);
+        // negative balances are carried over without cap
+        assert_eq!(months[2].balance, -20.0);
+        assert_eq!(months[3].balance, 100.0);
+    }
+
+    #[test]
+    fn consumed_hours_of_refused_holiday_test() {
+        let user_id = DocumentId::new();
+        let company_id = DocumentId::new();
+        let day_off = |day: u32, permit_hours: u32| {
+            db_entities::TimesheetDay::new(
+                user_id,
+                Utc.with_ymd_and_hms(2025, 3, day, 0, 0, 0).unwrap(),
+                permit_hours,
+                WorkingDayType::DayOff,
+                vec![],
+                false,
+            )
+        };
+        let request = |day: u32, outcome: EmployeeRequestOutcome| {
+            db_entities::CompanyEmployeeRequest::new(
+                user_id,
+                company_id,
+                EmployeeRequest::Holiday(outcome),
+                Some(Utc.with_ymd_and_hms(2025, 3, day, 0, 0, 0).unwrap()),
+            )
+        };
+        let days = vec![day_off(3, 0), day_off(4, 0), day_off(5, 2), day_off(6, 0)];
+        let requests = vec![
+            request(3, EmployeeRequestOutcome::Accepted),
+            request(4, EmployeeRequestOutcome::Refused),
+            request(5, EmployeeRequestOutcome::Refused),
+            request(6, EmployeeRequestOutcome::Awaiting),
+        ];
+
+        // only the accepted holiday and the permit hours are consumed
+        let consumed = compute_consumed_hours(&days, &requests, Tz::UTC, 8.0, true);
+        assert_eq!(consumed, HashMap::from([((2025, 3), 10.0)]));
+
+        // with other companies the days of the refused requests are not charged at all
+        let consumed = compute_consumed_hours(&days, &requests, Tz::UTC, 8.0, false);
+        assert_eq!(consumed, HashMap::from([((2025, 3), 8.0)]));
+    }
+}
@@ -0,0 +1,117 @@
+//! Mailer service used to send emails to people outside the application.
+//!
+//! The actual implementation is selected by the environment service, in this way
+//! a real provider can be plugged in without changing the callers.
+//! The `FileMailer` writes every email in a local directory and it is meant to be
+//! used during development and tests.
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use tracing::info;
+
+use crate::{
+    error::ServiceAppError,
+    service::environment::{MailerKind, ENVIRONMENT},
+};
+
+/// Mailer instance built according to the environment
+static MAILER: Lazy<Box<dyn Mailer>> = Lazy::new(|| match ENVIRONMENT.mailer.kind {
+    MailerKind::File => Box::new(FileMailer::new(
+        ENVIRONMENT.mailer.directory.clone().into(),
+        ENVIRONMENT.mailer.sender.clone(),
+    )),
+});
+
+/// Email message to send
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Trait implemented by every mailer
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), ServiceAppError>;
+}
+
+/// Returns the mailer configured for the application
+pub fn get_mailer() -> &'static dyn Mailer {
+    MAILER.as_ref()
+}
+
+/// Mailer that writes emails as `.eml` files inside a directory
+pub struct FileMailer {
+    directory: PathBuf,
+    sender: String,
+}
+
+impl FileMailer {
+    pub fn new(directory: PathBuf, sender: String) -> Self {
+        Self { directory, sender }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<(), ServiceAppError> {
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .map_err(|e| {
+                ServiceAppError::InternalServerError(format!(
+                    "Failed to create mail directory {:?}. Got {e}",
+                    self.directory
+                ))
+            })?;
+        let file_path = self.directory.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            uuid::Uuid::new_v4()
+        ));
+        let content = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\n\r\n{}\r\n",
+            self.sender,
+            email.to,
+            email.subject,
+            Utc::now().to_rfc2822(),
+            email.body
+        );
+        tokio::fs::write(&file_path, content).await.map_err(|e| {
+            ServiceAppError::InternalServerError(format!(
+                "Failed to write email to {:?}. Got {e}",
+                file_path
+            ))
+        })?;
+        info!("Email to {} written in {:?}", email.to, file_path);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Email, FileMailer, Mailer};
+
+    #[tokio::test]
+    async fn file_mailer_test() {
+        let directory = std::env::temp_dir().join(format!("mailer-test-{}", uuid::Uuid::new_v4()));
+        let mailer = FileMailer::new(directory.clone(), "sender@mail.com".into());
+        mailer
+            .send(&Email {
+                to: "john@smith.com".into(),
+                subject: "Subject".into(),
+                body: "Body of the email".into(),
+            })
+            .await
+            .unwrap();
+
+        let mut entries = std::fs::read_dir(&directory).unwrap();
+        let content = std::fs::read_to_string(entries.next().unwrap().unwrap().path()).unwrap();
+        assert!(content.contains("To: john@smith.com"));
+        assert!(content.contains("Body of the email"));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
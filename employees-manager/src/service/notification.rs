@@ -1,8 +1,9 @@
+use chrono::Utc;
 use mongodb::bson::doc;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    enums::NotificationType,
+    enums::{CompanyRole, InviteStatus, NotificationType},
     error::ServiceAppError,
     model::db_entities,
     service::{company, db::get_database_service},
@@ -43,16 +44,32 @@ pub async fn answer_to_invite_add_company(
     notification.save(Some(&mut transaction)).await?;
 
     if let Some(entity_id) = notification.entity_id() {
-        db_entities::InviteAddCompany::update_one(
-            doc! {"_id": entity_id},
-            doc! { "$set": { "answer":  answer}},
-            Some(&mut transaction),
-        )
-        .await?;
-
         let invite_add_company_doc_result =
-            db_entities::InviteAddCompany::find_one(doc! {"_id": notification.entity_id()}).await?;
+            db_entities::InviteAddCompany::find_one(doc! {"_id": entity_id}).await?;
         if let Some(invite_add_company) = invite_add_company_doc_result {
+            // the invitation is closed only if it is still pending and not expired,
+            // so it cannot be answered concurrently with its cancellation or expiry
+            if db_entities::InviteAddCompany::update_one_matched(
+                doc! {
+                    "_id": entity_id,
+                    "status": InviteStatus::Pending,
+                    "expiration_date": {"$gt": Utc::now()},
+                },
+                doc! { "$set": {
+                    "status": if answer { InviteStatus::Accepted } else { InviteStatus::Declined },
+                    "closed_at": Utc::now(),
+                }},
+                Some(&mut transaction),
+            )
+            .await?
+                == 0
+            {
+                transaction.abort_transaction().await?;
+                return Err(ServiceAppError::InvalidRequest(
+                    "The invitation is no longer pending or it is expired".into(),
+                ));
+            }
+
             if answer {
                 company::add_user_to_company(
                     *invite_add_company.invited_user_id(),
@@ -60,6 +77,7 @@ pub async fn answer_to_invite_add_company(
                     *invite_add_company.company_role(),
                     invite_add_company.job_title().clone(),
                     invite_add_company.project_ids().clone(),
+                    Some(&mut transaction),
                 )
                 .await?;
             }
@@ -125,6 +143,99 @@ pub async fn answer_to_invite_add_company(
     Ok(())
 }
 
+/// Answer of the nominee to the ownership transfer of a company.
+///
+/// If the nominee accepts, the roles of the current owner and of the nominee are
+/// swapped in the same transaction that stores the answer
+pub async fn answer_to_ownership_transfer(
+    mut notification: db_entities::AppNotification,
+    accept: bool,
+) -> Result<(), ServiceAppError> {
+    let entity_id = notification
+        .entity_id()
+        .ok_or(ServiceAppError::InternalServerError(format!(
+            "Notification with id {:?} does not not contain entity id",
+            notification.get_id()
+        )))?;
+    let transfer = db_entities::CompanyOwnershipTransfer::find_one(doc! {"_id": entity_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Ownership transfer with id {entity_id} does not exist"
+        )))?;
+    if transfer.answer().is_some() {
+        return Err(ServiceAppError::InvalidRequest(
+            "The ownership transfer has already been answered".into(),
+        ));
+    }
+    let owner_assignment = db_entities::UserCompanyAssignment::find_one(
+        doc! {"user_id": transfer.owner_id(), "company_id": transfer.company_id()},
+    )
+    .await?;
+    if owner_assignment.is_none_or(|assignment| *assignment.role() != CompanyRole::Owner) {
+        return Err(ServiceAppError::InvalidRequest(
+            "The nominating user is no longer the owner of the company".into(),
+        ));
+    }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct CompanyQueryResult {
+        name: String,
+    }
+    let company_name = db_entities::Company::find_one_projection::<CompanyQueryResult>(
+        doc! {"_id": transfer.company_id()},
+        doc! {"name": 1},
+    )
+    .await?
+    .expect("expected company in database")
+    .name;
+
+    let db_service = get_database_service().await;
+    let mut transaction = db_service.new_transaction().await?;
+    transaction.start_transaction().await?;
+
+    notification.set_read(true);
+    notification.save(Some(&mut transaction)).await?;
+
+    if db_entities::CompanyOwnershipTransfer::update_one_matched(
+        doc! {"_id": entity_id, "answer": null},
+        doc! {"$set": {"answer": accept}},
+        Some(&mut transaction),
+    )
+    .await?
+        != 1
+    {
+        transaction.abort_transaction().await?;
+        return Err(ServiceAppError::InvalidRequest(
+            "The ownership transfer has already been answered".into(),
+        ));
+    }
+    if accept {
+        company::swap_company_owner(
+            transfer.company_id(),
+            transfer.nominee_id(),
+            &mut transaction,
+        )
+        .await?;
+    }
+
+    let message = if accept {
+        format!("Your nominee has accepted to become the owner of company {company_name}")
+    } else {
+        format!("Your nominee has declined to become the owner of company {company_name}")
+    };
+    let mut answer_notification = db_entities::AppNotification::new(
+        *transfer.owner_id(),
+        NotificationType::OwnershipTransferAnswer,
+        message,
+        false,
+        transfer.get_id().copied(),
+    );
+    answer_notification.save(Some(&mut transaction)).await?;
+
+    transaction.commit_transaction().await?;
+    Ok(())
+}
+
 pub async fn cancel_invite_user_to_company(
     notification_id: DocumentId,
 ) -> Result<(), ServiceAppError> {
@@ -137,11 +248,23 @@ pub async fn cancel_invite_user_to_company(
             })
             .await?
             {
+                if *invitation.status() != InviteStatus::Pending {
+                    return Err(ServiceAppError::InvalidRequest(
+                        "Only pending invitations can be cancelled".into(),
+                    ));
+                }
+
                 let db_service = get_database_service().await;
                 let mut transaction = db_service.new_transaction().await?;
                 transaction.start_transaction().await?;
 
-                invitation.delete(Some(&mut transaction)).await?;
+                // the invitation is kept to appear in the invitation history of the company
+                db_entities::InviteAddCompany::update_one(
+                    doc! {"_id": entity_id},
+                    doc! {"$set": {"status": InviteStatus::Cancelled, "closed_at": Utc::now()}},
+                    Some(&mut transaction),
+                )
+                .await?;
                 notification.delete(Some(&mut transaction)).await?;
 
                 transaction.commit_transaction().await?;
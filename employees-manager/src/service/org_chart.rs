@@ -0,0 +1,333 @@
+//! Org chart service that reads the reporting lines of the employees of a company
+//! as a hierarchy.
+//!
+//! Every employee has at most one manager in the company. The manager changes that
+//! would make an employee report, directly or indirectly, to itself are rejected.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+use mongodb::bson::{doc, Bson};
+
+use crate::{
+    enums::{EmployeeRequest, EmployeeRequestOutcome, WorkingDayType},
+    error::ServiceAppError,
+    model::{
+        db_entities,
+        internal::{OrgChartNode, TeamLeaveDay, UserInCompanyInfo},
+    },
+    DocumentId,
+};
+
+use super::{company, db::DatabaseDocument, timesheet};
+
+/// Returns the manager of each employee of the company that has one
+async fn get_managers(
+    company_id: &DocumentId,
+) -> Result<HashMap<DocumentId, DocumentId>, ServiceAppError> {
+    Ok(
+        db_entities::ReportingLine::find_many(doc! {"company_id": company_id})
+            .await?
+            .into_iter()
+            .map(|line| (*line.user_id(), *line.manager_id()))
+            .collect(),
+    )
+}
+
+/// Returns true if the user would report to itself with the new manager, i.e. the
+/// user is the manager or one of its managers up the hierarchy
+fn creates_cycle(
+    managers: &HashMap<DocumentId, DocumentId>,
+    user_id: &DocumentId,
+    manager_id: &DocumentId,
+) -> bool {
+    let mut visited = HashSet::new();
+    let mut current = Some(manager_id);
+    while let Some(id) = current {
+        // an existing cycle would loop forever, it is reported as a cycle as well
+        if id == user_id || !visited.insert(id) {
+            return true;
+        }
+        current = managers.get(id);
+    }
+    false
+}
+
+/// Set or remove the manager of the user in the company
+pub async fn set_user_manager(
+    company_id: DocumentId,
+    user_id: DocumentId,
+    manager_id: Option<DocumentId>,
+) -> Result<(), ServiceAppError> {
+    company::get_user_company_role(&user_id, &company_id).await?;
+    let line =
+        db_entities::ReportingLine::find_one(doc! {"company_id": company_id, "user_id": user_id})
+            .await?;
+
+    let Some(manager_id) = manager_id else {
+        if let Some(line) = line {
+            line.delete(None).await?;
+        }
+        return Ok(());
+    };
+    company::get_user_company_role(&manager_id, &company_id).await?;
+    if creates_cycle(&get_managers(&company_id).await?, &user_id, &manager_id) {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "User with id {manager_id} reports to user with id {user_id} and cannot be its manager"
+        )));
+    }
+
+    let mut line = if let Some(mut line) = line {
+        line.set_manager_id(manager_id);
+        line
+    } else {
+        db_entities::ReportingLine::new(company_id, user_id, manager_id)
+    };
+    line.save(None).await?;
+    Ok(())
+}
+
+/// Returns the employees reporting to the manager, only the direct ones or
+/// all of them down the hierarchy
+fn collect_reports(
+    managers: &HashMap<DocumentId, DocumentId>,
+    manager_id: &DocumentId,
+    indirect: bool,
+) -> Vec<DocumentId> {
+    let mut reports_by_manager: HashMap<&DocumentId, Vec<DocumentId>> = HashMap::new();
+    for (user_id, manager_id) in managers {
+        reports_by_manager
+            .entry(manager_id)
+            .or_default()
+            .push(*user_id);
+    }
+
+    let mut reports = vec![];
+    let mut to_visit = vec![*manager_id];
+    while let Some(id) = to_visit.pop() {
+        for report in reports_by_manager.get(&id).into_iter().flatten() {
+            if report != manager_id && !reports.contains(report) {
+                reports.push(*report);
+                if indirect {
+                    to_visit.push(*report);
+                }
+            }
+        }
+    }
+    reports.sort();
+    reports
+}
+
+/// Returns the employees reporting to the manager in the company
+pub async fn get_reports(
+    company_id: &DocumentId,
+    manager_id: &DocumentId,
+    indirect: bool,
+) -> Result<Vec<DocumentId>, ServiceAppError> {
+    Ok(collect_reports(
+        &get_managers(company_id).await?,
+        manager_id,
+        indirect,
+    ))
+}
+
+/// Returns true if the user reports directly or indirectly to the manager
+pub async fn is_manager_of(
+    company_id: &DocumentId,
+    manager_id: &DocumentId,
+    user_id: &DocumentId,
+) -> Result<bool, ServiceAppError> {
+    Ok(get_reports(company_id, manager_id, true)
+        .await?
+        .contains(user_id))
+}
+
+/// Build the tree of the employees, the roots are the ones without a manager in the company
+fn build_org_chart(
+    users: Vec<UserInCompanyInfo>,
+    managers: &HashMap<DocumentId, DocumentId>,
+) -> Vec<OrgChartNode> {
+    let user_ids: HashSet<DocumentId> = users.iter().map(|user| user.user_id).collect();
+    let mut roots = vec![];
+    let mut reports_by_manager: HashMap<DocumentId, Vec<UserInCompanyInfo>> = HashMap::new();
+    for user in users {
+        match managers.get(&user.user_id) {
+            Some(manager_id) if user_ids.contains(manager_id) => reports_by_manager
+                .entry(*manager_id)
+                .or_default()
+                .push(user),
+            _ => roots.push(user),
+        }
+    }
+
+    fn build_node(
+        user: UserInCompanyInfo,
+        reports_by_manager: &mut HashMap<DocumentId, Vec<UserInCompanyInfo>>,
+    ) -> OrgChartNode {
+        let mut reports: Vec<OrgChartNode> = reports_by_manager
+            .remove(&user.user_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|report| build_node(report, reports_by_manager))
+            .collect();
+        reports.sort_by(|a, b| a.user.username.cmp(&b.user.username));
+        OrgChartNode { user, reports }
+    }
+
+    let mut chart: Vec<OrgChartNode> = roots
+        .into_iter()
+        .map(|root| build_node(root, &mut reports_by_manager))
+        .collect();
+    chart.sort_by(|a, b| a.user.username.cmp(&b.user.username));
+    chart
+}
+
+/// Returns the org chart of the company as a list of trees
+pub async fn get_org_chart(company_id: &DocumentId) -> Result<Vec<OrgChartNode>, ServiceAppError> {
+    let users = company::get_users_in_company(company_id).await?;
+    let managers = get_managers(company_id).await?;
+    Ok(build_org_chart(users, &managers))
+}
+
+/// Returns the days of leave of the employees reporting directly or indirectly to the manager.
+///
+/// The days of leave are the days off and sick days recorded in the timesheet and the
+/// holiday and permission requests that have not been refused.
+pub async fn get_team_leave_calendar(
+    company_id: &DocumentId,
+    manager_id: &DocumentId,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<TeamLeaveDay>, ServiceAppError> {
+    let (from_date, to_date) = timesheet::company_date_range(company_id, from, to).await?;
+    let tz = company::get_company_settings(company_id).await?.tz();
+    let reports: Vec<Bson> = get_reports(company_id, manager_id, true)
+        .await?
+        .into_iter()
+        .map(Bson::ObjectId)
+        .collect();
+
+    let mut leave_days: Vec<TeamLeaveDay> = db_entities::TimesheetDay::find_many(doc! {
+        "user_id": {"$in": reports.clone()},
+        "date": {"$gte": from_date, "$lt": to_date},
+        "working_type": {"$in": [WorkingDayType::DayOff, WorkingDayType::Sick]},
+    })
+    .await?
+    .into_iter()
+    .map(|day| TeamLeaveDay {
+        user_id: *day.user_id(),
+        date: day.date().with_timezone(&tz).date_naive(),
+        working_type: Some(*day.working_type()),
+        request: None,
+    })
+    .collect();
+
+    // requests are stored at midnight UTC of their calendar date
+    leave_days.extend(
+        db_entities::CompanyEmployeeRequest::find_many(doc! {
+            "company_id": company_id,
+            "user_id": {"$in": reports},
+            "date": {
+                "$gte": from.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+                "$lte": to.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            },
+        })
+        .await?
+        .into_iter()
+        .filter(|request| {
+            !matches!(request.request(), EmployeeRequest::Remote(_))
+                && request.request().outcome() != EmployeeRequestOutcome::Refused
+        })
+        .filter_map(|request| {
+            Some(TeamLeaveDay {
+                user_id: *request.user_id(),
+                date: request.date().as_ref()?.date_naive(),
+                working_type: None,
+                request: Some(*request.request()),
+            })
+        }),
+    );
+    leave_days.sort_by_key(|day| (day.date, day.user_id));
+    Ok(leave_days)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{enums::CompanyRole, model::internal::UserInCompanyInfo, DocumentId};
+
+    use super::{build_org_chart, collect_reports, creates_cycle};
+
+    fn user(user_id: DocumentId, username: &str) -> UserInCompanyInfo {
+        UserInCompanyInfo {
+            user_id,
+            company_id: DocumentId::new(),
+            role: CompanyRole::User,
+            username: username.into(),
+            name: username.into(),
+            surname: username.into(),
+            job_title: "Developer".into(),
+            management_team: false,
+        }
+    }
+
+    #[test]
+    fn creates_cycle_test() {
+        let (ceo, cto, dev) = (DocumentId::new(), DocumentId::new(), DocumentId::new());
+        let managers = HashMap::from([(cto, ceo), (dev, cto)]);
+
+        assert!(!creates_cycle(&managers, &dev, &ceo));
+        assert!(creates_cycle(&managers, &ceo, &dev));
+        assert!(creates_cycle(&managers, &cto, &cto));
+        assert!(creates_cycle(&managers, &ceo, &cto));
+    }
+
+    #[test]
+    fn collect_reports_test() {
+        let (ceo, cto, cfo, dev) = (
+            DocumentId::new(),
+            DocumentId::new(),
+            DocumentId::new(),
+            DocumentId::new(),
+        );
+        let managers = HashMap::from([(cto, ceo), (cfo, ceo), (dev, cto)]);
+
+        let mut direct = vec![cto, cfo];
+        direct.sort();
+        assert_eq!(collect_reports(&managers, &ceo, false), direct);
+        let mut all = vec![cto, cfo, dev];
+        all.sort();
+        assert_eq!(collect_reports(&managers, &ceo, true), all);
+        assert!(collect_reports(&managers, &dev, true).is_empty());
+    }
+
+    #[test]
+    fn build_org_chart_test() {
+        let (ceo, cto, dev, other) = (
+            DocumentId::new(),
+            DocumentId::new(),
+            DocumentId::new(),
+            DocumentId::new(),
+        );
+        // the manager of other is not in the company anymore
+        let managers = HashMap::from([(cto, ceo), (dev, cto), (other, DocumentId::new())]);
+        let chart = build_org_chart(
+            vec![
+                user(dev, "dev"),
+                user(ceo, "ceo"),
+                user(other, "other"),
+                user(cto, "cto"),
+            ],
+            &managers,
+        );
+
+        assert_eq!(chart.len(), 2);
+        assert_eq!(chart[0].user.user_id, ceo);
+        assert_eq!(chart[0].reports.len(), 1);
+        assert_eq!(chart[0].reports[0].user.user_id, cto);
+        assert_eq!(chart[0].reports[0].reports[0].user.user_id, dev);
+        assert_eq!(chart[1].user.user_id, other);
+        assert!(chart[1].reports.is_empty());
+    }
+}
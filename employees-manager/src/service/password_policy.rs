@@ -0,0 +1,178 @@
+//! Password policy service used to validate passwords before they are hashed and stored.
+//!
+//! Rules are read from the environment service and every violated rule is collected
+//! so that the client receives the complete list instead of the first failure.
+
+use std::collections::HashSet;
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    error::{ServiceAppError, ValidationViolation},
+    service::environment::{PasswordPolicyVariables, ENVIRONMENT},
+};
+
+/// Offline list of common passwords bundled with the application
+static COMMON_PASSWORDS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    include_str!("../../resources/common_passwords.txt")
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect()
+});
+
+/// Minimum length of password, username or email local part considered in the similarity check.
+/// Shorter values would reject too many valid passwords.
+const MIN_SIMILARITY_LENGTH: usize = 3;
+
+/// Validate the password against the policy defined in the environment
+pub fn validate_password(
+    password: &str,
+    username: &str,
+    email: &str,
+) -> Result<(), ServiceAppError> {
+    let violations = check_password(&ENVIRONMENT.password_policy, password, username, email);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ServiceAppError::ValidationError(violations))
+    }
+}
+
+/// Returns the list of rules of the policy that the password violates
+fn check_password(
+    policy: &PasswordPolicyVariables,
+    password: &str,
+    username: &str,
+    email: &str,
+) -> Vec<ValidationViolation> {
+    let mut violations = vec![];
+
+    if password.chars().count() < policy.min_length {
+        violations.push(ValidationViolation::new(
+            "password",
+            "minLength",
+            format!(
+                "Password must be at least {} characters long",
+                policy.min_length
+            ),
+        ));
+    }
+    if policy.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+        violations.push(ValidationViolation::new(
+            "password",
+            "lowercase",
+            "Password must contain at least one lowercase letter".into(),
+        ));
+    }
+    if policy.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+        violations.push(ValidationViolation::new(
+            "password",
+            "uppercase",
+            "Password must contain at least one uppercase letter".into(),
+        ));
+    }
+    if policy.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+        violations.push(ValidationViolation::new(
+            "password",
+            "digit",
+            "Password must contain at least one digit".into(),
+        ));
+    }
+    if policy.require_symbol && password.chars().all(|c| c.is_alphanumeric()) {
+        violations.push(ValidationViolation::new(
+            "password",
+            "symbol",
+            "Password must contain at least one symbol".into(),
+        ));
+    }
+
+    let normalized_password = password.trim().to_lowercase();
+    if policy.reject_user_similarity && normalized_password.chars().count() >= MIN_SIMILARITY_LENGTH
+    {
+        let email_local_part = email.split('@').next().unwrap_or_default();
+        for (field, value) in [("username", username), ("email", email_local_part)] {
+            let value = value.trim().to_lowercase();
+            if value.chars().count() >= MIN_SIMILARITY_LENGTH
+                && (normalized_password.contains(&value) || value.contains(&normalized_password))
+            {
+                violations.push(ValidationViolation::new(
+                    "password",
+                    "similarity",
+                    format!("Password must not be similar to the {field}"),
+                ));
+            }
+        }
+    }
+    if policy.reject_common_passwords && COMMON_PASSWORDS.contains(normalized_password.as_str()) {
+        violations.push(ValidationViolation::new(
+            "password",
+            "common",
+            "Password is too common, please choose a different one".into(),
+        ));
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::environment::PasswordPolicyVariables;
+
+    use super::check_password;
+
+    #[test]
+    fn valid_password_test() {
+        let violations = check_password(
+            &PasswordPolicyVariables::default(),
+            "Horse-Battery-7",
+            "johnsmith",
+            "john@smith.com",
+        );
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn every_violation_is_reported_test() {
+        let violations = check_password(
+            &PasswordPolicyVariables {
+                require_symbol: true,
+                ..Default::default()
+            },
+            "",
+            "johnsmith",
+            "john@smith.com",
+        );
+        let rules: Vec<&str> = violations.iter().map(|v| v.rule.as_str()).collect();
+        assert_eq!(
+            rules,
+            vec!["minLength", "lowercase", "uppercase", "digit", "symbol"]
+        );
+    }
+
+    #[test]
+    fn similarity_and_common_password_test() {
+        let violations = check_password(
+            &PasswordPolicyVariables::default(),
+            "JohnSmith2025",
+            "johnsmith",
+            "jsmith@mail.com",
+        );
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule, "similarity");
+
+        let violations = check_password(
+            &PasswordPolicyVariables {
+                min_length: 1,
+                require_uppercase: false,
+                require_digit: false,
+                ..Default::default()
+            },
+            "Password",
+            "johnsmith",
+            "john@smith.com",
+        );
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule, "common");
+    }
+}
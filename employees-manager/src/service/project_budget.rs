@@ -0,0 +1,631 @@
+//! Project budget service that tracks the hours logged in the timesheet against the
+//! hour budget of the projects.
+//!
+//! A budget has a total amount of hours, optionally split on some activities of the
+//! project, and only the hours logged between its start and end date are counted.
+//! Dates are interpreted in the timezone of the company. The company admins and the
+//! project managers are notified once for every threshold, a percentage of the total
+//! hours, that is reached.
+
+use std::collections::HashMap;
+
+use chrono::{Datelike, Days, NaiveDate, Utc};
+use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    enums::{CompanyRole, NotificationType, ProjectRole},
+    error::{ServiceAppError, ValidationViolation},
+    model::{
+        db_entities,
+        internal::{ProjectActivityBudgetStatus, ProjectBudgetStatus, ProjectBurnDownWeek},
+    },
+    DocumentId,
+};
+
+use super::{
+    calendar::local_midnight,
+    company,
+    db::{get_database_service, DatabaseDocument},
+};
+
+/// Create or replace the hour budget of the project.
+///
+/// The activities of the budget must be assigned to the project and their hours
+/// cannot exceed the total hours. Thresholds already reached are notified right away.
+#[allow(clippy::too_many_arguments)]
+pub async fn set_project_budget(
+    company_id: DocumentId,
+    project_id: DocumentId,
+    total_hours: u32,
+    activity_budgets: Vec<db_entities::ActivityHourBudget>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    mut notification_thresholds: Vec<u32>,
+) -> Result<(), ServiceAppError> {
+    if db_entities::CompanyProject::count_documents(
+        doc! {"_id": project_id, "company_id": company_id},
+    )
+    .await?
+        == 0
+    {
+        return Err(ServiceAppError::EntityDoesNotExist(format!(
+            "Project with id {project_id} does not exist"
+        )));
+    }
+    let assigned_activity_ids =
+        db_entities::ProjectActivityAssignment::find_one(doc! {"project_id": project_id})
+            .await?
+            .map(|assignment| assignment.activity_ids().clone())
+            .unwrap_or_default();
+
+    let violations = check_budget(
+        total_hours,
+        &activity_budgets,
+        &assigned_activity_ids,
+        start_date,
+        end_date,
+        &notification_thresholds,
+    );
+    if !violations.is_empty() {
+        return Err(ServiceAppError::ValidationError(violations));
+    }
+    notification_thresholds.sort();
+    notification_thresholds.dedup();
+
+    let start_date = start_date
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is always a valid time")
+        .and_utc();
+    let end_date = end_date
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is always a valid time")
+        .and_utc();
+    match db_entities::ProjectBudget::find_one(doc! {"project_id": project_id}).await? {
+        Some(mut budget) => {
+            // thresholds still configured are not notified twice
+            let notified_thresholds = budget
+                .notified_thresholds()
+                .iter()
+                .filter(|threshold| notification_thresholds.contains(threshold))
+                .copied()
+                .collect();
+            budget.set_total_hours(total_hours);
+            budget.set_activity_budgets(activity_budgets);
+            budget.set_start_date(start_date);
+            budget.set_end_date(end_date);
+            budget.set_notification_thresholds(notification_thresholds);
+            budget.set_notified_thresholds(notified_thresholds);
+            budget.save(None).await?;
+        }
+        None => {
+            db_entities::ProjectBudget::new(
+                project_id,
+                company_id,
+                total_hours,
+                activity_budgets,
+                start_date,
+                end_date,
+                notification_thresholds,
+                vec![],
+            )
+            .save(None)
+            .await?;
+        }
+    }
+    check_budget_thresholds(vec![project_id]).await
+}
+
+/// Returns the rules of the budget violated by the given values
+fn check_budget(
+    total_hours: u32,
+    activity_budgets: &[db_entities::ActivityHourBudget],
+    assigned_activity_ids: &[DocumentId],
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    notification_thresholds: &[u32],
+) -> Vec<ValidationViolation> {
+    let mut violations = vec![];
+    if total_hours == 0 {
+        violations.push(ValidationViolation::new(
+            "totalHours",
+            "range",
+            "The total hours of the budget must be greater than zero".into(),
+        ));
+    }
+    if end_date < start_date {
+        violations.push(ValidationViolation::new(
+            "endDate",
+            "range",
+            "The end date of the budget must not be before the start date".into(),
+        ));
+    }
+    if notification_thresholds.contains(&0) {
+        violations.push(ValidationViolation::new(
+            "notificationThresholds",
+            "range",
+            "The notification thresholds must be greater than zero".into(),
+        ));
+    }
+    for (index, activity_budget) in activity_budgets.iter().enumerate() {
+        if !assigned_activity_ids.contains(activity_budget.activity_id()) {
+            violations.push(ValidationViolation::new(
+                &format!("activityBudgets[{index}].activityId"),
+                "assignment",
+                format!(
+                    "Activity {} is not assigned to the project",
+                    activity_budget.activity_id()
+                ),
+            ));
+        }
+        if activity_budgets[..index]
+            .iter()
+            .any(|other| other.activity_id() == activity_budget.activity_id())
+        {
+            violations.push(ValidationViolation::new(
+                &format!("activityBudgets[{index}].activityId"),
+                "duplicate",
+                format!(
+                    "Activity {} has more than one budget",
+                    activity_budget.activity_id()
+                ),
+            ));
+        }
+    }
+    if activity_budgets
+        .iter()
+        .map(|activity_budget| *activity_budget.hours() as u64)
+        .sum::<u64>()
+        > total_hours as u64
+    {
+        violations.push(ValidationViolation::new(
+            "activityBudgets",
+            "total",
+            "The hours of the activities exceed the total hours of the budget".into(),
+        ));
+    }
+    violations
+}
+
+pub async fn get_project_budget(
+    company_id: &DocumentId,
+    project_id: &DocumentId,
+) -> Result<db_entities::ProjectBudget, ServiceAppError> {
+    db_entities::ProjectBudget::find_one(doc! {"project_id": project_id, "company_id": company_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Budget of project {project_id} does not exist"
+        )))
+}
+
+pub async fn delete_project_budget(
+    company_id: &DocumentId,
+    project_id: &DocumentId,
+) -> Result<(), ServiceAppError> {
+    get_project_budget(company_id, project_id)
+        .await?
+        .delete(None)
+        .await
+}
+
+/// Returns the hours logged on the project against its budget, with the weekly
+/// burn-down up to today and the forecast of the day the budget is used up
+pub async fn get_project_budget_status(
+    budget: &db_entities::ProjectBudget,
+) -> Result<ProjectBudgetStatus, ServiceAppError> {
+    let settings = company::get_company_settings(budget.company_id()).await?;
+    let logged_hours = get_logged_hours(budget).await?;
+
+    let mut activity_ids = logged_hours
+        .iter()
+        .map(|logged| logged.activity_id)
+        .chain(
+            budget
+                .activity_budgets()
+                .iter()
+                .map(|activity_budget| *activity_budget.activity_id()),
+        )
+        .collect::<Vec<DocumentId>>();
+    activity_ids.sort();
+    activity_ids.dedup();
+    #[derive(Serialize, Deserialize, Debug)]
+    struct QueryResult {
+        _id: DocumentId,
+        name: String,
+    }
+    let activity_names = db_entities::ProjectActivity::find_many_projection::<QueryResult>(
+        doc! {"_id": {"$in": activity_ids}},
+        doc! {"name": 1},
+    )
+    .await?
+    .into_iter()
+    .map(|activity| (activity._id, activity.name))
+    .collect::<HashMap<DocumentId, String>>();
+
+    let today = Utc::now().with_timezone(&settings.tz()).date_naive();
+    Ok(compute_status(
+        budget,
+        &logged_hours,
+        &activity_names,
+        today,
+    ))
+}
+
+/// Hours logged on an activity of the project in a day
+struct LoggedHours {
+    date: NaiveDate,
+    activity_id: DocumentId,
+    hours: u32,
+}
+
+/// Read the hours logged on the project between start and end date of the budget
+async fn get_logged_hours(
+    budget: &db_entities::ProjectBudget,
+) -> Result<Vec<LoggedHours>, ServiceAppError> {
+    let tz = company::get_company_settings(budget.company_id())
+        .await?
+        .tz();
+    let from = local_midnight(&tz, budget.start_date().date_naive())?;
+    let to = local_midnight(&tz, budget.end_date().date_naive() + Days::new(1))?;
+    Ok(db_entities::TimesheetDay::find_many(doc! {
+        "activities.project_id": budget.project_id(),
+        "date": {"$gte": from, "$lt": to},
+    })
+    .await?
+    .into_iter()
+    .flat_map(|day| {
+        let date = day.date().with_timezone(&tz).date_naive();
+        day.activities()
+            .iter()
+            .filter(|activity| activity.project_id() == budget.project_id())
+            .map(|activity| LoggedHours {
+                date,
+                activity_id: *activity.activity_id(),
+                hours: *activity.hours(),
+            })
+            .collect::<Vec<LoggedHours>>()
+    })
+    .collect())
+}
+
+/// Compute the status of the budget from the logged hours.
+///
+/// The burn-down has a week for each Monday from the week of the start date to the week
+/// of today, or of the end date if it has passed. The forecast assumes the hours keep being
+/// logged at the average daily pace since the start date.
+fn compute_status(
+    budget: &db_entities::ProjectBudget,
+    logged_hours: &[LoggedHours],
+    activity_names: &HashMap<DocumentId, String>,
+    today: NaiveDate,
+) -> ProjectBudgetStatus {
+    let total_hours = *budget.total_hours() as i64;
+    let start_date = budget.start_date().date_naive();
+    let end_date = budget.end_date().date_naive();
+    let logged_total = logged_hours.iter().map(|logged| logged.hours).sum::<u32>();
+
+    let mut activity_hours: HashMap<DocumentId, u32> = HashMap::new();
+    for logged in logged_hours {
+        *activity_hours.entry(logged.activity_id).or_default() += logged.hours;
+    }
+    for activity_budget in budget.activity_budgets() {
+        activity_hours
+            .entry(*activity_budget.activity_id())
+            .or_default();
+    }
+    let mut activities = activity_hours
+        .into_iter()
+        .map(|(activity_id, logged_hours)| ProjectActivityBudgetStatus {
+            activity_id,
+            activity_name: activity_names
+                .get(&activity_id)
+                .cloned()
+                .unwrap_or_default(),
+            budget_hours: budget
+                .activity_budgets()
+                .iter()
+                .find(|activity_budget| *activity_budget.activity_id() == activity_id)
+                .map(|activity_budget| *activity_budget.hours()),
+            logged_hours,
+        })
+        .collect::<Vec<ProjectActivityBudgetStatus>>();
+    activities.sort_by(|a, b| a.activity_name.cmp(&b.activity_name));
+
+    let last_date = today.min(end_date);
+    let mut burn_down = vec![];
+    let mut remaining_hours = total_hours;
+    let mut week_start = start_date - Days::new(start_date.weekday().num_days_from_monday() as u64);
+    while week_start <= last_date {
+        let week_end = week_start + Days::new(7);
+        let week_hours = logged_hours
+            .iter()
+            .filter(|logged| logged.date >= week_start && logged.date < week_end)
+            .map(|logged| logged.hours)
+            .sum::<u32>();
+        remaining_hours -= week_hours as i64;
+        burn_down.push(ProjectBurnDownWeek {
+            week_start,
+            logged_hours: week_hours,
+            remaining_hours,
+        });
+        week_start = week_end;
+    }
+
+    let forecast_completion_date = if logged_total as i64 >= total_hours {
+        // the day in which the logged hours reached the budget
+        let mut dates = logged_hours
+            .iter()
+            .map(|logged| (logged.date, logged.hours))
+            .collect::<Vec<(NaiveDate, u32)>>();
+        dates.sort();
+        let mut cumulative = 0;
+        dates.into_iter().find_map(|(date, hours)| {
+            cumulative += hours as i64;
+            (cumulative >= total_hours).then_some(date)
+        })
+    } else if logged_total == 0 {
+        None
+    } else {
+        let last_date = last_date.max(start_date);
+        let elapsed_days = (last_date - start_date).num_days() + 1;
+        let daily_hours = logged_total as f64 / elapsed_days as f64;
+        let missing_days = ((total_hours - logged_total as i64) as f64 / daily_hours).ceil() as u64;
+        last_date.checked_add_days(Days::new(missing_days))
+    };
+
+    ProjectBudgetStatus {
+        logged_hours: logged_total,
+        remaining_hours: total_hours - logged_total as i64,
+        activities,
+        burn_down,
+        forecast_completion_date,
+    }
+}
+
+/// Returns the thresholds reached by the logged hours, in ascending order
+fn reached_thresholds(thresholds: &[u32], total_hours: u32, logged_hours: u32) -> Vec<u32> {
+    let mut reached = thresholds
+        .iter()
+        .filter(|&&threshold| logged_hours as u64 * 100 >= threshold as u64 * total_hours as u64)
+        .copied()
+        .collect::<Vec<u32>>();
+    reached.sort();
+    reached
+}
+
+/// Notify the company admins and the project managers of the projects whose logged hours reached a new threshold
+/// of their budget.
+///
+/// It must be called after the hours logged on the projects have changed. Only the highest
+/// new threshold is notified, and the thresholds the logged hours went back below are
+/// notified again when they are reached another time.
+pub async fn check_budget_thresholds(
+    mut project_ids: Vec<DocumentId>,
+) -> Result<(), ServiceAppError> {
+    project_ids.sort();
+    project_ids.dedup();
+    if project_ids.is_empty() {
+        return Ok(());
+    }
+    let budgets =
+        db_entities::ProjectBudget::find_many(doc! {"project_id": {"$in": project_ids}}).await?;
+    for mut budget in budgets {
+        let logged_hours = get_logged_hours(&budget)
+            .await?
+            .iter()
+            .map(|logged| logged.hours)
+            .sum::<u32>();
+        let reached = reached_thresholds(
+            budget.notification_thresholds(),
+            *budget.total_hours(),
+            logged_hours,
+        );
+        if reached == *budget.notified_thresholds() {
+            continue;
+        }
+        let new_threshold = reached
+            .iter()
+            .filter(|threshold| !budget.notified_thresholds().contains(threshold))
+            .max()
+            .copied();
+
+        let db_service = get_database_service().await;
+        let mut transaction = db_service.new_transaction().await?;
+        transaction.start_transaction().await?;
+        if let Some(threshold) = new_threshold {
+            let project = db_entities::CompanyProject::find_one(doc! {"_id": budget.project_id()})
+                .await?
+                .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+                    "Project with id {} does not exist",
+                    budget.project_id()
+                )))?;
+            let message = format!(
+                "Project {} has reached {threshold}% of its budget: {logged_hours} of {} hours logged",
+                project.name(),
+                budget.total_hours()
+            );
+            let mut user_ids: Vec<DocumentId> =
+                db_entities::UserCompanyAssignment::find_many(doc! {
+                    "company_id": budget.company_id(),
+                    "role": {"$in": [CompanyRole::Admin, CompanyRole::Owner]},
+                })
+                .await?
+                .iter()
+                .map(|admin| *admin.user_id())
+                .collect();
+            user_ids.extend(
+                db_entities::ProjectRoleAssignment::find_many(doc! {
+                    "project_id": budget.project_id(),
+                    "role": ProjectRole::Manager,
+                })
+                .await?
+                .iter()
+                .map(|manager| *manager.user_id()),
+            );
+            user_ids.sort();
+            user_ids.dedup();
+            for user_id in user_ids {
+                let mut notification = db_entities::AppNotification::new(
+                    user_id,
+                    NotificationType::ProjectBudgetThreshold,
+                    message.clone(),
+                    false,
+                    Some(*budget.project_id()),
+                );
+                if let Err(e) = notification.save(Some(&mut transaction)).await {
+                    transaction.abort_transaction().await?;
+                    return Err(e);
+                }
+            }
+        }
+        budget.set_notified_thresholds(reached);
+        if let Err(e) = budget.save(Some(&mut transaction)).await {
+            transaction.abort_transaction().await?;
+            return Err(e);
+        }
+        transaction.commit_transaction().await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use crate::{model::db_entities, DocumentId};
+
+    use super::{check_budget, compute_status, reached_thresholds, LoggedHours};
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    #[test]
+    fn check_budget_test() {
+        let activity_id = DocumentId::new();
+        let activity_budgets = vec![
+            db_entities::ActivityHourBudget::new(activity_id, 60),
+            db_entities::ActivityHourBudget::new(activity_id, 60),
+            db_entities::ActivityHourBudget::new(DocumentId::new(), 10),
+        ];
+        let violations = check_budget(
+            100,
+            &activity_budgets,
+            &[activity_id],
+            date(3, 1),
+            date(2, 1),
+            &[0, 80],
+        );
+        let rules = violations
+            .iter()
+            .map(|violation| (violation.field.as_str(), violation.rule.as_str()))
+            .collect::<Vec<(&str, &str)>>();
+        assert_eq!(
+            rules,
+            vec![
+                ("endDate", "range"),
+                ("notificationThresholds", "range"),
+                ("activityBudgets[1].activityId", "duplicate"),
+                ("activityBudgets[2].activityId", "assignment"),
+                ("activityBudgets", "total"),
+            ]
+        );
+        assert!(check_budget(100, &[], &[], date(1, 1), date(1, 1), &[50]).is_empty());
+    }
+
+    #[test]
+    fn reached_thresholds_test() {
+        assert_eq!(reached_thresholds(&[100, 50, 80], 40, 32), vec![50, 80]);
+        assert_eq!(
+            reached_thresholds(&[50, 80, 100], 40, 19),
+            Vec::<u32>::new()
+        );
+        assert_eq!(reached_thresholds(&[50, 120], 40, 48), vec![50, 120]);
+    }
+
+    #[test]
+    fn compute_status_test() {
+        let development = DocumentId::new();
+        let meeting = DocumentId::new();
+        // Wednesday 1st January to Friday 31st January
+        let budget = db_entities::ProjectBudget::new(
+            DocumentId::new(),
+            DocumentId::new(),
+            100,
+            vec![db_entities::ActivityHourBudget::new(development, 80)],
+            Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2025, 1, 31, 0, 0, 0).unwrap(),
+            vec![50, 100],
+            vec![],
+        );
+        let logged_hours = vec![
+            LoggedHours {
+                date: date(1, 2),
+                activity_id: development,
+                hours: 8,
+            },
+            LoggedHours {
+                date: date(1, 6),
+                activity_id: development,
+                hours: 6,
+            },
+            LoggedHours {
+                date: date(1, 6),
+                activity_id: meeting,
+                hours: 2,
+            },
+        ];
+        let names = HashMap::from([
+            (development, "Development".to_string()),
+            (meeting, "Meeting".to_string()),
+        ]);
+
+        // 16 hours in 8 days, 2 hours per day, 84 hours left
+        let status = compute_status(&budget, &logged_hours, &names, date(1, 8));
+        assert_eq!(status.logged_hours, 16);
+        assert_eq!(status.remaining_hours, 84);
+        assert_eq!(status.forecast_completion_date, Some(date(2, 19)));
+        assert_eq!(status.activities.len(), 2);
+        assert_eq!(status.activities[0].activity_name, "Development");
+        assert_eq!(status.activities[0].budget_hours, Some(80));
+        assert_eq!(status.activities[0].logged_hours, 14);
+        assert_eq!(status.activities[1].budget_hours, None);
+
+        // weeks start on Monday 30th December
+        let weeks = status
+            .burn_down
+            .iter()
+            .map(|week| (week.week_start, week.logged_hours, week.remaining_hours))
+            .collect::<Vec<(NaiveDate, u32, i64)>>();
+        assert_eq!(
+            weeks,
+            vec![
+                (NaiveDate::from_ymd_opt(2024, 12, 30).unwrap(), 8, 92),
+                (date(1, 6), 8, 84)
+            ]
+        );
+
+        // the burn-down stops at the end date and the forecast is the day the budget was used up
+        let logged_hours = vec![
+            LoggedHours {
+                date: date(1, 10),
+                activity_id: development,
+                hours: 60,
+            },
+            LoggedHours {
+                date: date(1, 20),
+                activity_id: meeting,
+                hours: 50,
+            },
+        ];
+        let status = compute_status(&budget, &logged_hours, &names, date(6, 1));
+        assert_eq!(status.remaining_hours, -10);
+        assert_eq!(status.burn_down.len(), 5);
+        assert_eq!(status.forecast_completion_date, Some(date(1, 20)));
+
+        let status = compute_status(&budget, &[], &names, date(1, 8));
+        assert_eq!(status.forecast_completion_date, None);
+    }
+}
@@ -1,20 +1,42 @@
-use std::collections::{hash_map::Entry, HashMap};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    io::{Cursor, Write},
+    str::FromStr,
+};
 
-use chrono::{DateTime, TimeZone, Utc};
-use rust_xlsxwriter::{workbook::Workbook, Format, FormatAlign};
+use calamine::{open_workbook_auto_from_rs, Data, DataType, Reader};
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
+use chrono_tz::Tz;
+use rust_xlsxwriter::{workbook::Workbook, Format, FormatAlign, Worksheet};
+use zip::{result::ZipError, write::SimpleFileOptions, CompressionMethod, ZipWriter};
 
 use crate::{
-    enums::WorkingDayType,
-    error::ServiceAppError,
-    model::{db_entities, internal::TimesheetActivityHours},
+    enums::{TimesheetCopyPeriod, TimesheetExportFormat, WorkingDayType},
+    error::{ServiceAppError, ValidationViolation},
+    model::{
+        db_entities,
+        internal::{
+            TimesheetActivityHours, TimesheetBulkReport, TimesheetConsistencyIssue,
+            TimesheetImportReport, TimesheetImportRowError, TimesheetPeriod, TimesheetSkippedDay,
+        },
+    },
     DocumentId,
 };
 use mongodb::bson::doc;
+use serde::{Deserialize, Serialize};
 
-use super::db::DatabaseDocument;
+use super::{
+    calendar::{self, local_midnight},
+    company,
+    db::{get_database_service, DatabaseDocument, DatabaseTransaction},
+    project_budget,
+};
 
 /// Create or update a timesheet day.
 ///
+/// The activities are validated against the project allocations of the user and the
+/// activities assigned to the projects, the day against the settings of the companies
+/// of its activities, or of the companies of the user when there are no activities.
 /// If an entry in the database exists for the tuple (user_id, date) then it is entirely
 /// updated with the given parameters.
 /// Otherwise, it is created as a new document.
@@ -24,6 +46,160 @@ pub async fn create_day(
     permit_hours: u32,
     working_type: WorkingDayType,
     activities: Vec<TimesheetActivityHours>,
+    overtime: bool,
+) -> Result<(), ServiceAppError> {
+    let violations = validate_day(&user_id, &date, working_type, &activities, overtime).await?;
+    if !violations.is_empty() {
+        return Err(ServiceAppError::ValidationError(violations));
+    }
+    let project_ids = activities
+        .iter()
+        .map(|activity| activity.project_id)
+        .collect();
+    save_day(
+        user_id,
+        date,
+        permit_hours,
+        working_type,
+        activities,
+        overtime,
+        None,
+    )
+    .await?;
+    notify_budget_thresholds(project_ids).await;
+    Ok(())
+}
+
+/// Notify the budget thresholds reached with the saved days.
+///
+/// The days are already saved, so a failure is logged instead of failing the request
+async fn notify_budget_thresholds(project_ids: Vec<DocumentId>) {
+    if let Err(e) = project_budget::check_budget_thresholds(project_ids).await {
+        tracing::error!("Failed to check the budget thresholds. Got {e}");
+    }
+}
+
+/// Returns the rules violated by the day.
+///
+/// Every activity line must be on a project the user is allocated to and on an activity
+/// assigned to the project. The day must respect the settings and calendars of the companies
+/// of its activities, or of the companies of the user when there are no activities.
+async fn validate_day(
+    user_id: &DocumentId,
+    date: &DateTime<Utc>,
+    working_type: WorkingDayType,
+    activities: &[TimesheetActivityHours],
+    overtime: bool,
+) -> Result<Vec<ValidationViolation>, ServiceAppError> {
+    let mut violations = check_activities(user_id, activities).await?;
+    for company_id in get_day_company_ids(user_id, activities).await? {
+        let settings = company::get_company_settings(&company_id).await?;
+        let calendar_day = calendar::get_calendar_day(
+            &company_id,
+            date.with_timezone(&settings.tz()).date_naive(),
+        )
+        .await?;
+        let company_hours = activities
+            .iter()
+            .filter(|activity| activity.company_id == company_id)
+            .map(|activity| activity.hours)
+            .sum();
+        violations.append(&mut check_day(
+            &settings,
+            calendar_day.as_ref(),
+            date,
+            working_type,
+            company_hours,
+            overtime,
+        ));
+    }
+    Ok(violations)
+}
+
+/// Returns the rules violated by the activity lines, every line must be on a project
+/// the user is allocated to and on an activity assigned to the project
+async fn check_activities(
+    user_id: &DocumentId,
+    activities: &[TimesheetActivityHours],
+) -> Result<Vec<ValidationViolation>, ServiceAppError> {
+    if activities.is_empty() {
+        return Ok(vec![]);
+    }
+    let references = ActivityReferences::load(
+        &[*user_id],
+        &activities
+            .iter()
+            .map(|activity| activity.company_id)
+            .collect::<Vec<DocumentId>>(),
+        &activities
+            .iter()
+            .map(|activity| activity.project_id)
+            .collect::<Vec<DocumentId>>(),
+        &activities
+            .iter()
+            .map(|activity| activity.activity_id)
+            .collect::<Vec<DocumentId>>(),
+    )
+    .await?;
+    Ok(activities
+        .iter()
+        .enumerate()
+        .flat_map(|(index, activity)| {
+            references.check(
+                index,
+                user_id,
+                &activity.company_id,
+                &activity.project_id,
+                &activity.activity_id,
+            )
+        })
+        .collect())
+}
+
+/// Returns the companies a day belongs to, they are the companies of its activities
+/// or the companies of the user when there are no activities
+async fn get_day_company_ids(
+    user_id: &DocumentId,
+    activities: &[TimesheetActivityHours],
+) -> Result<Vec<DocumentId>, ServiceAppError> {
+    let mut company_ids = activities
+        .iter()
+        .map(|activity| activity.company_id)
+        .collect::<Vec<DocumentId>>();
+    if company_ids.is_empty() {
+        company_ids = get_user_company_ids(user_id).await?;
+    }
+    company_ids.sort();
+    company_ids.dedup();
+    Ok(company_ids)
+}
+
+async fn get_user_company_ids(user_id: &DocumentId) -> Result<Vec<DocumentId>, ServiceAppError> {
+    #[derive(Serialize, Deserialize, Debug)]
+    struct QueryResult {
+        company_id: DocumentId,
+    }
+    Ok(
+        db_entities::UserCompanyAssignment::find_many_projection::<QueryResult>(
+            doc! {"user_id": user_id},
+            doc! {"company_id": 1},
+        )
+        .await?
+        .into_iter()
+        .map(|assignment| assignment.company_id)
+        .collect(),
+    )
+}
+
+/// Insert the day, or replace the existing one of the user at the same date
+async fn save_day(
+    user_id: DocumentId,
+    date: DateTime<Utc>,
+    permit_hours: u32,
+    working_type: WorkingDayType,
+    activities: Vec<TimesheetActivityHours>,
+    overtime: bool,
+    transaction: Option<&mut DatabaseTransaction>,
 ) -> Result<(), ServiceAppError> {
     let count =
         db_entities::TimesheetDay::count_documents(doc! {"user_id": user_id, "date": date}).await?;
@@ -38,8 +214,9 @@ pub async fn create_day(
                     .into_iter()
                     .map(|e| e.into())
                     .collect::<Vec<db_entities::TimesheetActivityHours>>(),
+                overtime,
             );
-            new_document.save(None).await?;
+            new_document.save(transaction).await?;
             Ok(())
         }
         1 => {
@@ -51,10 +228,11 @@ pub async fn create_day(
                         "working_type": working_type,
                         "activities": activities.into_iter()
                         .map(|e| e.into())
-                        .collect::<Vec<db_entities::TimesheetActivityHours>>()
+                        .collect::<Vec<db_entities::TimesheetActivityHours>>(),
+                        "overtime": overtime,
                     }
                 },
-                None,
+                transaction,
             )
             .await?;
             Ok(())
@@ -66,36 +244,687 @@ pub async fn create_day(
     }
 }
 
-/// Returns the timesheet days for the user and the month passed as parameters
+/// Returns the rules of the company settings violated by the day.
+///
+/// `company_hours` is the sum of the activity hours of the day for the company.
+/// Activities on non-working weekdays are accepted only when overtime is flagged, the same
+/// holds for days in the company calendar which otherwise must have the calendar working type.
+fn check_day(
+    settings: &db_entities::CompanySettings,
+    calendar_day: Option<&db_entities::CompanyCalendarDay>,
+    date: &DateTime<Utc>,
+    working_type: WorkingDayType,
+    company_hours: u32,
+    overtime: bool,
+) -> Vec<ValidationViolation> {
+    let mut violations = vec![];
+    if !settings.allowed_working_types().contains(&working_type) {
+        violations.push(ValidationViolation::new(
+            "workingType",
+            "allowedWorkingTypes",
+            format!(
+                "Working type {working_type} is not allowed by company {}",
+                settings.company_id()
+            ),
+        ));
+    }
+    if company_hours > *settings.max_daily_hours() {
+        violations.push(ValidationViolation::new(
+            "activities",
+            "maxDailyHours",
+            format!(
+                "Activities of company {} exceed the maximum of {} hours per day",
+                settings.company_id(),
+                settings.max_daily_hours()
+            ),
+        ));
+    }
+    if let Some(calendar_day) = calendar_day {
+        if !overtime && working_type != *calendar_day.day_type() {
+            violations.push(ValidationViolation::new(
+                "workingType",
+                "calendar",
+                format!(
+                    "The day is {} ({}) for company {}, flag the day as overtime to log a different working type",
+                    calendar_day.name(),
+                    calendar_day.day_type(),
+                    settings.company_id()
+                ),
+            ));
+        }
+    }
+    let weekday = date.with_timezone(&settings.tz()).weekday();
+    if company_hours > 0 && !overtime && !settings.working_weekdays().contains(&weekday) {
+        violations.push(ValidationViolation::new(
+            "date",
+            "workingWeekdays",
+            format!(
+                "{weekday} is not a working day for company {}, flag the day as overtime to log activities",
+                settings.company_id()
+            ),
+        ));
+    }
+    violations
+}
+
+/// Documents referenced by the activity lines of timesheet days, used to check
+/// that users log hours only on their projects and on the activities of the projects
+#[derive(Default)]
+struct ActivityReferences {
+    /// assignments of the users indexed by user and company
+    assignments: HashMap<(DocumentId, DocumentId), db_entities::UserCompanyAssignment>,
+    projects: HashMap<DocumentId, db_entities::CompanyProject>,
+    activities: HashMap<DocumentId, db_entities::ProjectActivity>,
+    /// activities assigned to each project
+    project_activity_ids: HashMap<DocumentId, Vec<DocumentId>>,
+}
+
+impl ActivityReferences {
+    async fn load(
+        user_ids: &[DocumentId],
+        company_ids: &[DocumentId],
+        project_ids: &[DocumentId],
+        activity_ids: &[DocumentId],
+    ) -> Result<Self, ServiceAppError> {
+        Ok(Self {
+            assignments: db_entities::UserCompanyAssignment::find_many(doc! {
+                "user_id": {"$in": user_ids},
+                "company_id": {"$in": company_ids},
+            })
+            .await?
+            .into_iter()
+            .map(|assignment| {
+                (
+                    (*assignment.user_id(), *assignment.company_id()),
+                    assignment,
+                )
+            })
+            .collect(),
+            projects: db_entities::CompanyProject::find_many(doc! {"_id": {"$in": project_ids}})
+                .await?
+                .into_iter()
+                .filter_map(|project| Some((*project.get_id()?, project)))
+                .collect(),
+            activities: db_entities::ProjectActivity::find_many(
+                doc! {"_id": {"$in": activity_ids}},
+            )
+            .await?
+            .into_iter()
+            .filter_map(|activity| Some((*activity.get_id()?, activity)))
+            .collect(),
+            project_activity_ids: db_entities::ProjectActivityAssignment::find_many(
+                doc! {"project_id": {"$in": project_ids}},
+            )
+            .await?
+            .into_iter()
+            .map(|assignment| (*assignment.project_id(), assignment.activity_ids().clone()))
+            .collect(),
+        })
+    }
+
+    /// Returns the rules violated by the activity line at `index` of a day of the user
+    fn check(
+        &self,
+        index: usize,
+        user_id: &DocumentId,
+        company_id: &DocumentId,
+        project_id: &DocumentId,
+        activity_id: &DocumentId,
+    ) -> Vec<ValidationViolation> {
+        let field = |name: &str| format!("activities[{index}].{name}");
+        let mut violations = vec![];
+
+        let assignment = self.assignments.get(&(*user_id, *company_id));
+        if assignment.is_none() {
+            violations.push(ValidationViolation::new(
+                &field("companyId"),
+                "companyAssignment",
+                format!("The user does not belong to company {company_id}"),
+            ));
+        }
+        let project = self
+            .projects
+            .get(project_id)
+            .filter(|project| project.company_id() == company_id);
+        match project {
+            None => violations.push(ValidationViolation::new(
+                &field("projectId"),
+                "notFound",
+                format!("Project {project_id} does not exist in company {company_id}"),
+            )),
+            Some(project)
+                if assignment
+                    .is_some_and(|assignment| !assignment.project_ids().contains(project_id)) =>
+            {
+                violations.push(ValidationViolation::new(
+                    &field("projectId"),
+                    "allocation",
+                    format!("The user is not allocated to project {}", project.name()),
+                ))
+            }
+            Some(_) => {}
+        }
+        match self
+            .activities
+            .get(activity_id)
+            .filter(|activity| activity.company_id() == company_id)
+        {
+            None => violations.push(ValidationViolation::new(
+                &field("activityId"),
+                "notFound",
+                format!("Activity {activity_id} does not exist in company {company_id}"),
+            )),
+            Some(activity)
+                if project.is_some()
+                    && !self
+                        .project_activity_ids
+                        .get(project_id)
+                        .is_some_and(|activity_ids| activity_ids.contains(activity_id)) =>
+            {
+                violations.push(ValidationViolation::new(
+                    &field("activityId"),
+                    "assignment",
+                    format!(
+                        "Activity {} is not assigned to project {}",
+                        activity.name(),
+                        project
+                            .map(|project| project.name().as_str())
+                            .unwrap_or_default()
+                    ),
+                ))
+            }
+            Some(_) => {}
+        }
+        violations
+    }
+}
+
+/// Returns the timesheet days with activities of the company that violate the project
+/// allocations of the users or the activity assignments of the projects.
+///
+/// Days are taken between `from` and `to`, both included, in the timezone of the company,
+/// optionally only for some users. Only the activity lines of the company are checked.
+pub async fn get_consistency_report(
+    company_id: &DocumentId,
+    from: NaiveDate,
+    to: NaiveDate,
+    user_ids: Option<Vec<DocumentId>>,
+) -> Result<Vec<TimesheetConsistencyIssue>, ServiceAppError> {
+    let (from_date, to_date) = company_date_range(company_id, from, to).await?;
+    let mut query = doc! {
+        "activities.company_id": company_id,
+        "date": {"$gte": from_date, "$lt": to_date},
+    };
+    if let Some(user_ids) = user_ids {
+        query.insert("user_id", doc! {"$in": user_ids});
+    }
+    let days = db_entities::TimesheetDay::find_many(query).await?;
+
+    let mut user_ids = vec![];
+    let mut project_ids = vec![];
+    let mut activity_ids = vec![];
+    for day in days.iter() {
+        user_ids.push(*day.user_id());
+        for activity in day.activities() {
+            project_ids.push(*activity.project_id());
+            activity_ids.push(*activity.activity_id());
+        }
+    }
+    for ids in [&mut user_ids, &mut project_ids, &mut activity_ids] {
+        ids.sort();
+        ids.dedup();
+    }
+    let references =
+        ActivityReferences::load(&user_ids, &[*company_id], &project_ids, &activity_ids).await?;
+    let usernames = db_entities::User::find_many(doc! {"_id": {"$in": &user_ids}})
+        .await?
+        .into_iter()
+        .filter_map(|user| Some((*user.get_id()?, user.username().clone())))
+        .collect::<HashMap<DocumentId, String>>();
+
+    let mut issues = vec![];
+    for day in days {
+        let violations = day
+            .activities()
+            .iter()
+            .enumerate()
+            .filter(|(_, activity)| activity.company_id() == company_id)
+            .flat_map(|(index, activity)| {
+                references.check(
+                    index,
+                    day.user_id(),
+                    activity.company_id(),
+                    activity.project_id(),
+                    activity.activity_id(),
+                )
+            })
+            .collect::<Vec<ValidationViolation>>();
+        if violations.is_empty() {
+            continue;
+        }
+        if let Some(day_id) = day.get_id() {
+            issues.push(TimesheetConsistencyIssue {
+                day_id: *day_id,
+                user_id: *day.user_id(),
+                username: usernames.get(day.user_id()).cloned().unwrap_or_default(),
+                date: *day.date(),
+                violations,
+            });
+        }
+    }
+    issues.sort_by(|a, b| (&a.username, a.date).cmp(&(&b.username, b.date)));
+    Ok(issues)
+}
+
+/// Returns the instants between the local midnight of `from` and the local midnight of the
+/// day after `to` in the timezone of the company
+pub async fn company_date_range(
+    company_id: &DocumentId,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), ServiceAppError> {
+    if to < from {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "Invalid date range. Got from: {from} and to: {to}"
+        )));
+    }
+    let tz = company::get_company_settings(company_id).await?.tz();
+    Ok((
+        local_midnight(&tz, from)?,
+        local_midnight(&tz, to + Days::new(1))?,
+    ))
+}
+
+/// Maximum number of days returned by a single query
+const MAX_QUERY_DAYS: i64 = 366;
+
+/// Returns the timesheet days of the user in the period, sorted by date.
+///
+/// The period starts and ends at local midnight in the timezone of the first company
+/// of the user, so that days logged at local midnight fall in their own month or week.
 pub async fn get_days(
     user_id: &DocumentId,
-    year: &i32,
-    month: &u32,
+    period: &TimesheetPeriod,
 ) -> Result<Vec<db_entities::TimesheetDay>, ServiceAppError> {
-    let from_date = Utc.with_ymd_and_hms(*year, *month, 1, 0, 0, 0).earliest();
-    let to_date = Utc
-        .with_ymd_and_hms(*year, *month + 1, 1, 0, 0, 0)
-        .earliest()
-        .or_else(|| {
-            Utc.with_ymd_and_hms(*year + 1, *month, 0, 0, 0, 0)
-                .earliest()
-        });
-    if from_date.is_none() || to_date.is_none() {
-        Err(ServiceAppError::InvalidRequest(format!(
-            "Invalid year and month. Got year: {year} and month {month}"
-        )))
-    } else {
-        let from_date = from_date.unwrap();
-        let to_date = to_date.unwrap();
-        db_entities::TimesheetDay::find_many(doc! {
-            "user_id": user_id,
-            "date": {"$lt": to_date, "$gte": from_date},
+    let (from, to) = period
+        .date_range()
+        .filter(|(from, to)| (*to - *from).num_days() < MAX_QUERY_DAYS)
+        .ok_or(ServiceAppError::InvalidRequest(format!(
+            "Invalid period {period:?}, at most {MAX_QUERY_DAYS} days can be queried at once"
+        )))?;
+    let tz = get_day_timezone(user_id, &[]).await?;
+    let mut days = db_entities::TimesheetDay::find_many(doc! {
+        "user_id": user_id,
+        "date": {
+            "$gte": local_midnight(&tz, from)?,
+            "$lt": local_midnight(&tz, to + Days::new(1))?,
+        },
+    })
+    .await?;
+    days.sort_by_key(|day| *day.date());
+    Ok(days)
+}
+
+/// Columns of the timesheet exports
+const EXPORT_COLUMNS: [&str; 8] = [
+    "Date",
+    "Work Type",
+    "Permission hours",
+    "Company",
+    "Project",
+    "Activity",
+    "Hours",
+    "Notes",
+];
+
+/// Names of the companies, projects and activities referenced by timesheet days.
+///
+/// The names are loaded at once with one `$in` query per collection, so the number of reads
+/// does not depend on the number of days or activities to resolve.
+#[derive(Debug, Default)]
+pub struct TimesheetNames {
+    companies: HashMap<DocumentId, String>,
+    projects: HashMap<DocumentId, String>,
+    activities: HashMap<DocumentId, String>,
+}
+
+impl TimesheetNames {
+    /// Load the names of all the entities referenced by the activities of the days
+    pub async fn load<'a>(
+        timesheet_days: impl IntoIterator<Item = &'a db_entities::TimesheetDay>,
+    ) -> Result<Self, ServiceAppError> {
+        let mut company_ids = vec![];
+        let mut project_ids = vec![];
+        let mut activity_ids = vec![];
+        for activity in timesheet_days
+            .into_iter()
+            .flat_map(|day| day.activities().iter())
+        {
+            company_ids.push(*activity.company_id());
+            project_ids.push(*activity.project_id());
+            activity_ids.push(*activity.activity_id());
+        }
+
+        Ok(Self {
+            companies: load_names::<db_entities::Company>(company_ids).await?,
+            projects: load_names::<db_entities::CompanyProject>(project_ids).await?,
+            activities: load_names::<db_entities::ProjectActivity>(activity_ids).await?,
         })
-        .await
     }
+
+    pub fn company_name(&self, id: &DocumentId) -> Result<&String, ServiceAppError> {
+        self.companies
+            .get(id)
+            .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+                "Company with id {id} does not exist."
+            )))
+    }
+
+    pub fn project_name(&self, id: &DocumentId) -> Result<&String, ServiceAppError> {
+        self.projects
+            .get(id)
+            .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+                "Project with id {id} does not exist."
+            )))
+    }
+
+    pub fn activity_name(&self, id: &DocumentId) -> Result<&String, ServiceAppError> {
+        self.activities
+            .get(id)
+            .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+                "Activity with id {id} does not exist."
+            )))
+    }
+}
+
+/// Read the names of the documents with the given ids, reading only `_id` and `name`
+async fn load_names<D: DatabaseDocument>(
+    mut ids: Vec<DocumentId>,
+) -> Result<HashMap<DocumentId, String>, ServiceAppError> {
+    #[derive(Serialize, Deserialize, Debug)]
+    struct QueryResult {
+        #[serde(rename = "_id")]
+        id: DocumentId,
+        name: String,
+    }
+    ids.sort();
+    ids.dedup();
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    Ok(
+        D::find_many_projection::<QueryResult>(doc! {"_id": {"$in": ids}}, doc! {"name": 1})
+            .await?
+            .into_iter()
+            .map(|document| (document.id, document.name))
+            .collect(),
+    )
+}
+
+/// Activity of a timesheet day ready to be exported
+struct ExportActivity {
+    company: String,
+    project: String,
+    activity: String,
+    hours: u32,
+    notes: String,
+}
+
+/// Timesheet day ready to be exported, it is the column model shared by all the export formats
+struct ExportDay {
+    date: NaiveDate,
+    working_type: WorkingDayType,
+    permit_hours: u32,
+    activities: Vec<ExportActivity>,
+}
+
+/// Resolve the names of the activities of the timesheet days to export.
+///
+/// Only the activities accepted by `activity_filter` are kept and the dates are
+/// converted to the timezone `tz`.
+fn to_export_days(
+    timesheet_days: &[db_entities::TimesheetDay],
+    activity_filter: impl Fn(&db_entities::TimesheetActivityHours) -> bool,
+    names: &TimesheetNames,
+    tz: &Tz,
+) -> Result<Vec<ExportDay>, ServiceAppError> {
+    let mut export_days = vec![];
+    for timesheet_day in timesheet_days.iter() {
+        let mut activities = vec![];
+        for activity_doc in timesheet_day
+            .activities()
+            .iter()
+            .filter(|activity| activity_filter(activity))
+        {
+            activities.push(ExportActivity {
+                company: names.company_name(activity_doc.company_id())?.clone(),
+                project: names.project_name(activity_doc.project_id())?.clone(),
+                activity: names.activity_name(activity_doc.activity_id())?.clone(),
+                hours: *activity_doc.hours(),
+                notes: activity_doc.notes().clone(),
+            });
+        }
+        export_days.push(ExportDay {
+            date: timesheet_day.date().with_timezone(tz).date_naive(),
+            working_type: *timesheet_day.working_type(),
+            permit_hours: *timesheet_day.permit_hours(),
+            activities,
+        });
+    }
+    Ok(export_days)
+}
+
+/// Write the timesheet days in the worksheet, one row per activity.
+///
+/// Date, work type and permission hours are merged over the rows of the same day,
+/// while a day without activities takes a single row.
+fn write_timesheet_worksheet(
+    worksheet: &mut Worksheet,
+    export_days: &[ExportDay],
+) -> Result<(), ServiceAppError> {
+    let bold_format = Format::new().set_bold();
+    let date_format = Format::new().set_num_format("yyyy-mm-dd");
+    let merge_format = Format::new().set_align(FormatAlign::Center);
+
+    // create columns
+    for (column_index, &column) in EXPORT_COLUMNS.iter().enumerate() {
+        worksheet.write_with_format(0, column_index as u16, column, &bold_format)?;
+    }
+
+    let mut current_row = 1;
+    for export_day in export_days.iter() {
+        let initial_activities_row = current_row;
+        for activity in export_day.activities.iter() {
+            worksheet.write(current_row, 3, &activity.company)?;
+            worksheet.write(current_row, 4, &activity.project)?;
+            worksheet.write(current_row, 5, &activity.activity)?;
+            worksheet.write(current_row, 6, activity.hours)?;
+            worksheet.write(current_row, 7, &activity.notes)?;
+
+            current_row += 1;
+        }
+        if current_row == initial_activities_row {
+            current_row += 1;
+        }
+        let last_activities_row = current_row - 1;
+
+        // To write date we need to first merge cells with an empty string and then write with the right format
+        if initial_activities_row != last_activities_row {
+            worksheet.merge_range(
+                initial_activities_row,
+                0,
+                last_activities_row,
+                0,
+                "",
+                &merge_format,
+            )?;
+        }
+        worksheet.write_with_format(initial_activities_row, 0, &export_day.date, &date_format)?;
+        // We can write working type directly as string
+        if initial_activities_row != last_activities_row {
+            worksheet.merge_range(
+                initial_activities_row,
+                1,
+                last_activities_row,
+                1,
+                &export_day.working_type.to_string(),
+                &merge_format,
+            )?;
+        } else {
+            worksheet.write(
+                initial_activities_row,
+                1,
+                export_day.working_type.to_string(),
+            )?;
+        }
+        // To write hours we need to first merge cells with an empty string and then write with the right format
+        if initial_activities_row != last_activities_row {
+            worksheet.merge_range(
+                initial_activities_row,
+                2,
+                last_activities_row,
+                2,
+                "",
+                &merge_format,
+            )?;
+        }
+        worksheet.write(initial_activities_row, 2, export_day.permit_hours)?;
+    }
+    Ok(())
+}
+
+/// Write the timesheet days as csv, one record per activity.
+///
+/// Date and work type are repeated on every record of the day, while the permission
+/// hours are written only on the first one so that summing the column gives the total.
+fn write_timesheet_csv(export_days: &[ExportDay]) -> Result<Vec<u8>, ServiceAppError> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(EXPORT_COLUMNS)?;
+    for export_day in export_days.iter() {
+        let date = export_day.date.format("%Y-%m-%d").to_string();
+        let working_type = export_day.working_type.to_string();
+        let permit_hours = export_day.permit_hours.to_string();
+        if export_day.activities.is_empty() {
+            writer.write_record([
+                date.as_str(),
+                working_type.as_str(),
+                permit_hours.as_str(),
+                "",
+                "",
+                "",
+                "",
+                "",
+            ])?;
+        }
+        for (index, activity) in export_day.activities.iter().enumerate() {
+            writer.write_record([
+                date.as_str(),
+                working_type.as_str(),
+                if index == 0 {
+                    permit_hours.as_str()
+                } else {
+                    ""
+                },
+                activity.company.as_str(),
+                activity.project.as_str(),
+                activity.activity.as_str(),
+                activity.hours.to_string().as_str(),
+                activity.notes.as_str(),
+            ])?;
+        }
+    }
+    writer
+        .into_inner()
+        .map_err(|e| ServiceAppError::InternalServerError(e.to_string()))
+}
+
+/// Write the timesheet days as OpenDocument spreadsheet, with the same layout of the excel export.
+///
+/// The document is a zip archive with the mimetype as first uncompressed entry,
+/// the manifest and the content with a single table.
+fn write_timesheet_ods(export_days: &[ExportDay]) -> Result<Vec<u8>, ServiceAppError> {
+    fn escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;")
+    }
+    fn string_cell(value: &str) -> String {
+        format!(
+            r#"<table:table-cell office:value-type="string"><text:p>{}</text:p></table:table-cell>"#,
+            escape(value)
+        )
+    }
+    fn float_cell(value: u32, spanned_rows: usize) -> String {
+        format!(
+            r#"<table:table-cell table:number-rows-spanned="{spanned_rows}" office:value-type="float" office:value="{value}"><text:p>{value}</text:p></table:table-cell>"#
+        )
+    }
+
+    let mut rows = vec![format!(
+        "<table:table-row>{}</table:table-row>",
+        EXPORT_COLUMNS
+            .iter()
+            .map(|column| string_cell(column))
+            .collect::<String>()
+    )];
+    for export_day in export_days.iter() {
+        let spanned_rows = export_day.activities.len().max(1);
+        // date, work type and permission hours span over the rows of the day
+        let day_cells = format!(
+            r#"<table:table-cell table:number-rows-spanned="{spanned_rows}" office:value-type="date" office:date-value="{date}"><text:p>{date}</text:p></table:table-cell><table:table-cell table:number-rows-spanned="{spanned_rows}" office:value-type="string"><text:p>{working_type}</text:p></table:table-cell>{permit_hours}"#,
+            date = export_day.date.format("%Y-%m-%d"),
+            working_type = export_day.working_type,
+            permit_hours = float_cell(export_day.permit_hours, spanned_rows),
+        );
+        if export_day.activities.is_empty() {
+            rows.push(format!("<table:table-row>{day_cells}</table:table-row>"));
+        }
+        for (index, activity) in export_day.activities.iter().enumerate() {
+            rows.push(format!(
+                "<table:table-row>{}{}{}{}{}{}</table:table-row>",
+                if index == 0 {
+                    day_cells.clone()
+                } else {
+                    "<table:covered-table-cell/>".repeat(3)
+                },
+                string_cell(&activity.company),
+                string_cell(&activity.project),
+                string_cell(&activity.activity),
+                float_cell(activity.hours, 1),
+                string_cell(&activity.notes),
+            ));
+        }
+    }
+    let content = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" office:version="1.2"><office:body><office:spreadsheet><table:table table:name="Timesheet"><table:table-column table:number-columns-repeated="{}"/>{}</table:table></office:spreadsheet></office:body></office:document-content>"#,
+        EXPORT_COLUMNS.len(),
+        rows.concat()
+    );
+    let manifest = r#"<?xml version="1.0" encoding="UTF-8"?><manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2"><manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/><manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/></manifest:manifest>"#;
+
+    let mut archive = ZipWriter::new(Cursor::new(vec![]));
+    archive.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    archive
+        .write_all(TimesheetExportFormat::Ods.content_type().as_bytes())
+        .map_err(ZipError::from)?;
+    archive.start_file("META-INF/manifest.xml", SimpleFileOptions::default())?;
+    archive
+        .write_all(manifest.as_bytes())
+        .map_err(ZipError::from)?;
+    archive.start_file("content.xml", SimpleFileOptions::default())?;
+    archive
+        .write_all(content.as_bytes())
+        .map_err(ZipError::from)?;
+    Ok(archive.finish()?.into_inner())
 }
 
-/// Export the timesheet as excel file
+/// Export the timesheet of the month in the given format
 ///
 /// The file contains the columns:
 /// - date
@@ -107,156 +936,906 @@ pub async fn get_days(
 /// - hours
 /// - notes
 ///
-pub async fn export_as_excel(
+pub async fn export(
     user_id: &DocumentId,
     year: &i32,
     month: &u32,
+    format: TimesheetExportFormat,
 ) -> Result<Vec<u8>, ServiceAppError> {
+    let timesheet_days = get_days(
+        user_id,
+        &TimesheetPeriod::Month {
+            year: *year,
+            month: *month,
+        },
+    )
+    .await?;
+    let names = TimesheetNames::load(&timesheet_days).await?;
+    let tz = get_day_timezone(user_id, &[]).await?;
+    let export_days = to_export_days(&timesheet_days, |_| true, &names, &tz)?;
+
+    match format {
+        TimesheetExportFormat::Csv => write_timesheet_csv(&export_days),
+        TimesheetExportFormat::Ods => write_timesheet_ods(&export_days),
+        TimesheetExportFormat::Xlsx => {
+            let mut workbook = Workbook::new();
+            write_timesheet_worksheet(workbook.add_worksheet(), &export_days)?;
+            Ok(workbook.save_to_buffer()?)
+        }
+    }
+}
+
+/// Export the timesheets of the employees of the company as excel file
+///
+/// The file starts with a summary sheet with the hours per project and activity,
+/// followed by one sheet per employee with the same columns of [export].
+/// Only the activities of the company are exported. Days are taken between `from`
+/// and `to`, both included, in the timezone of the company and can be restricted
+/// to some projects and some users.
+pub async fn export_company_as_excel(
+    company_id: &DocumentId,
+    from: NaiveDate,
+    to: NaiveDate,
+    project_ids: Option<Vec<DocumentId>>,
+    user_ids: Option<Vec<DocumentId>>,
+) -> Result<Vec<u8>, ServiceAppError> {
+    let (from_date, to_date) = company_date_range(company_id, from, to).await?;
+    let tz = company::get_company_settings(company_id).await?.tz();
+
+    let mut assignment_query = doc! {"company_id": company_id};
+    if let Some(user_ids) = &user_ids {
+        assignment_query.insert("user_id", doc! {"$in": user_ids});
+    }
+    let employee_ids = db_entities::UserCompanyAssignment::find_many(assignment_query)
+        .await?
+        .into_iter()
+        .map(|assignment| *assignment.user_id())
+        .collect::<Vec<DocumentId>>();
+    let mut employees = db_entities::User::find_many(doc! {"_id": {"$in": &employee_ids}}).await?;
+    employees.sort_by(|a, b| (a.surname(), a.name()).cmp(&(b.surname(), b.name())));
+
+    let activity_filter = |activity: &db_entities::TimesheetActivityHours| {
+        activity.company_id() == company_id
+            && project_ids
+                .as_ref()
+                .is_none_or(|project_ids| project_ids.contains(activity.project_id()))
+    };
+
+    // days of all the employees are read at once, as well as the names they refer to.
+    // Days with activities of other companies only are left out, while days
+    // without activities (e.g. day off) are kept unless filtering by project
+    let mut days_by_employee: HashMap<DocumentId, Vec<db_entities::TimesheetDay>> = HashMap::new();
+    for day in db_entities::TimesheetDay::find_many(doc! {
+        "user_id": {"$in": &employee_ids},
+        "date": {"$gte": from_date, "$lt": to_date},
+    })
+    .await?
+    .into_iter()
+    .filter(|day| {
+        day.activities().iter().any(activity_filter)
+            || (day.activities().is_empty() && project_ids.is_none())
+    }) {
+        days_by_employee
+            .entry(*day.user_id())
+            .or_default()
+            .push(day);
+    }
+    let names = TimesheetNames::load(days_by_employee.values().flatten()).await?;
+
     let mut workbook = Workbook::new();
-    let worksheet = workbook.add_worksheet();
+    // the summary sheet is written at the end, once all the hours are known
+    workbook.add_worksheet().set_name("Summary")?;
+    // projects and activities with the same name are kept apart
+    let mut summary: HashMap<(DocumentId, DocumentId), u32> = HashMap::new();
+    let mut sheet_names: Vec<String> = vec!["Summary".into()];
+    for employee in employees.iter() {
+        let mut timesheet_days = employee
+            .get_id()
+            .and_then(|employee_id| days_by_employee.remove(employee_id))
+            .unwrap_or_default();
+        timesheet_days.sort_by_key(|day| *day.date());
+        for activity in timesheet_days
+            .iter()
+            .flat_map(|day| day.activities())
+            .filter(|activity| activity_filter(activity))
+        {
+            *summary
+                .entry((*activity.project_id(), *activity.activity_id()))
+                .or_default() += activity.hours();
+        }
+        let export_days = to_export_days(&timesheet_days, activity_filter, &names, &tz)?;
+
+        let sheet_name = unique_sheet_name(
+            &format!("{} {}", employee.surname(), employee.name()),
+            &sheet_names,
+        );
+        let worksheet = workbook.add_worksheet().set_name(&sheet_name)?;
+        sheet_names.push(sheet_name);
+        write_timesheet_worksheet(worksheet, &export_days)?;
+    }
+
+    let mut summary_rows = summary
+        .into_iter()
+        .map(|((project_id, activity_id), hours)| {
+            Ok((
+                (
+                    names.project_name(&project_id)?.clone(),
+                    names.activity_name(&activity_id)?.clone(),
+                ),
+                hours,
+            ))
+        })
+        .collect::<Result<Vec<((String, String), u32)>, ServiceAppError>>()?;
+    summary_rows.sort();
 
     let bold_format = Format::new().set_bold();
-    let date_format = Format::new().set_num_format("yyyy-mm-dd");
-    let merge_format = Format::new().set_align(FormatAlign::Center);
+    let worksheet = workbook.worksheet_from_index(0)?;
+    for (column_index, &column) in ["Project", "Activity", "Hours"].iter().enumerate() {
+        worksheet.write_with_format(0, column_index as u16, column, &bold_format)?;
+    }
+    let mut current_row = 1;
+    for ((project_name, activity_name), hours) in summary_rows.iter() {
+        worksheet.write(current_row, 0, project_name)?;
+        worksheet.write(current_row, 1, activity_name)?;
+        worksheet.write(current_row, 2, *hours)?;
+        current_row += 1;
+    }
+    worksheet.write_with_format(current_row, 0, "Total", &bold_format)?;
+    worksheet.write_with_format(
+        current_row,
+        2,
+        summary_rows.iter().map(|(_, hours)| hours).sum::<u32>(),
+        &bold_format,
+    )?;
+
+    Ok(workbook.save_to_buffer()?)
+}
+
+/// Returns a valid worksheet name from the given one, distinct from the ones already used.
+///
+/// Excel forbids the characters `[]:*?/\` and names longer than 31 characters,
+/// and compares names case insensitively.
+fn unique_sheet_name(name: &str, used_names: &[String]) -> String {
+    const MAX_LENGTH: usize = 31;
+    let sanitized = name
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .collect::<String>();
+    let sanitized = sanitized.trim_matches('\'').trim();
+    let base = if sanitized.is_empty() {
+        "Employee"
+    } else {
+        sanitized
+    };
+    let is_used = |candidate: &str| {
+        used_names
+            .iter()
+            .any(|used| used.to_lowercase() == candidate.to_lowercase())
+    };
+
+    let mut candidate = base.chars().take(MAX_LENGTH).collect::<String>();
+    let mut counter = 2;
+    while is_used(&candidate) {
+        let suffix = format!(" ({counter})");
+        candidate = base
+            .chars()
+            .take(MAX_LENGTH - suffix.chars().count())
+            .collect::<String>()
+            + &suffix;
+        counter += 1;
+    }
+    candidate
+}
+
+/// Activity of a row of an imported file, with company, project and activity names
+#[derive(Debug)]
+struct ImportActivity {
+    row: usize,
+    company: String,
+    project: String,
+    activity: String,
+    hours: u32,
+    notes: String,
+}
+
+/// Timesheet day read from an imported file
+#[derive(Debug)]
+struct ImportDay {
+    /// first row of the day in the file
+    row: usize,
+    date: NaiveDate,
+    working_type: WorkingDayType,
+    permit_hours: u32,
+    activities: Vec<ImportActivity>,
+}
+
+/// Returns the cells of the rows of the imported file as text, without the header row.
+///
+/// Spreadsheets are read from the first sheet and their date cells are written as `yyyy-mm-dd`.
+fn read_import_rows(
+    content: &[u8],
+    format: TimesheetExportFormat,
+) -> Result<Vec<Vec<String>>, ServiceAppError> {
+    match format {
+        TimesheetExportFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .has_headers(true)
+                .flexible(true)
+                .from_reader(content);
+            let mut rows = vec![];
+            for record in reader.records() {
+                rows.push(
+                    record
+                        .map_err(|e| ServiceAppError::InvalidRequest(e.to_string()))?
+                        .iter()
+                        .map(|cell| cell.to_string())
+                        .collect(),
+                );
+            }
+            Ok(rows)
+        }
+        TimesheetExportFormat::Xlsx | TimesheetExportFormat::Ods => {
+            let mut workbook = open_workbook_auto_from_rs(Cursor::new(content))
+                .map_err(|e| ServiceAppError::InvalidRequest(e.to_string()))?;
+            let range = workbook
+                .worksheet_range_at(0)
+                .ok_or(ServiceAppError::InvalidRequest(
+                    "The file does not contain any sheet".into(),
+                ))?
+                .map_err(|e| ServiceAppError::InvalidRequest(e.to_string()))?;
+            Ok(range
+                .rows()
+                .skip(1)
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| match cell {
+                            Data::DateTime(_) | Data::DateTimeIso(_) => cell
+                                .as_date()
+                                .map(|date| date.format("%Y-%m-%d").to_string())
+                                .unwrap_or_else(|| cell.to_string()),
+                            cell => cell.to_string(),
+                        })
+                        .collect()
+                })
+                .collect())
+        }
+    }
+}
+
+/// Group the rows of an imported file into days.
+///
+/// A row without date, or with the date of the previous row, adds its activity to the
+/// previous day, so that both the merged cells of the spreadsheets and the repeated
+/// dates of the csv export are accepted. Rows are numbered from 2, after the header.
+fn parse_import_rows(rows: Vec<Vec<String>>) -> (Vec<ImportDay>, Vec<TimesheetImportRowError>) {
+    let mut days: Vec<ImportDay> = vec![];
+    let mut errors = vec![];
+    let mut error = |row: usize, field: &str, rule: &str, message: String| {
+        errors.push(TimesheetImportRowError {
+            row,
+            violation: ValidationViolation::new(field, rule, message),
+        })
+    };
+
+    for (index, cells) in rows.into_iter().enumerate() {
+        let row = index + 2;
+        let cell = |column: usize| cells.get(column).map(|cell| cell.trim()).unwrap_or("");
+        if (0..EXPORT_COLUMNS.len()).all(|column| cell(column).is_empty()) {
+            continue;
+        }
+
+        let date = match cell(0) {
+            "" => None,
+            date => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                Ok(date) => Some(date),
+                Err(_) => {
+                    error(
+                        row,
+                        "date",
+                        "format",
+                        format!("Invalid date {date}, expected yyyy-mm-dd"),
+                    );
+                    continue;
+                }
+            },
+        };
+        let same_day = match (date, days.last()) {
+            (None, Some(_)) => true,
+            (None, None) => {
+                error(row, "date", "required", "The date is required".into());
+                continue;
+            }
+            (Some(date), Some(day)) => day.date == date,
+            (Some(_), None) => false,
+        };
+        if !same_day {
+            let date = date.expect("a new day always has a date");
+            if days.iter().any(|day| day.date == date) {
+                error(
+                    row,
+                    "date",
+                    "duplicate",
+                    format!("The day {date} is already in the file"),
+                );
+                continue;
+            }
+            let Ok(working_type) = WorkingDayType::from_str(cell(1)) else {
+                error(
+                    row,
+                    "workType",
+                    "format",
+                    format!("Invalid work type {}", cell(1)),
+                );
+                continue;
+            };
+            let Some(permit_hours) = parse_import_hours(cell(2)) else {
+                error(
+                    row,
+                    "permitHours",
+                    "format",
+                    format!("Invalid permission hours {}", cell(2)),
+                );
+                continue;
+            };
+            days.push(ImportDay {
+                row,
+                date,
+                working_type,
+                permit_hours,
+                activities: vec![],
+            });
+        }
+
+        if (3..6).all(|column| cell(column).is_empty()) && cell(6).is_empty() {
+            continue;
+        }
+        let mut valid = true;
+        for (column, field) in [(3, "company"), (4, "project"), (5, "activity")] {
+            if cell(column).is_empty() {
+                error(row, field, "required", format!("The {field} is required"));
+                valid = false;
+            }
+        }
+        let hours = parse_import_hours(cell(6));
+        if hours.is_none() {
+            error(row, "hours", "format", format!("Invalid hours {}", cell(6)));
+        }
+        if let (true, Some(hours), Some(day)) = (valid, hours, days.last_mut()) {
+            day.activities.push(ImportActivity {
+                row,
+                company: cell(3).into(),
+                project: cell(4).into(),
+                activity: cell(5).into(),
+                hours,
+                notes: cell(7).into(),
+            });
+        }
+    }
+    (days, errors)
+}
+
+/// Parse a number of hours, empty cells are zero hours and spreadsheets may write them as `2.0`
+fn parse_import_hours(value: &str) -> Option<u32> {
+    if value.is_empty() {
+        return Some(0);
+    }
+    value.parse::<u32>().ok().or_else(|| {
+        value
+            .parse::<f64>()
+            .ok()
+            .filter(|hours| *hours >= 0.0 && hours.fract() == 0.0 && *hours <= u32::MAX as f64)
+            .map(|hours| hours as u32)
+    })
+}
+
+/// Import the timesheet days of the user from a file with the columns of the export.
+///
+/// Companies are resolved among the companies of the user by id or by name, when the name
+/// is not shared by several of them. Project and activity names are resolved in the company.
+/// Days are validated like in [create_day], so the user must be allocated to the project and
+/// the activity assigned to it, and imported days replace the existing ones.
+/// Each error is reported with its row and the days are saved in a single transaction
+/// only when the file has no errors and `dry_run` is false.
+pub async fn import(
+    user_id: &DocumentId,
+    content: &[u8],
+    format: TimesheetExportFormat,
+    dry_run: bool,
+) -> Result<TimesheetImportReport, ServiceAppError> {
+    let (days, mut errors) = parse_import_rows(read_import_rows(content, format)?);
+
+    let company_ids = db_entities::UserCompanyAssignment::find_many(doc! {"user_id": user_id})
+        .await?
+        .into_iter()
+        .map(|assignment| *assignment.company_id())
+        .collect::<Vec<DocumentId>>();
+    let mut companies: HashMap<String, Vec<DocumentId>> = HashMap::new();
+    for company in db_entities::Company::find_many(doc! {"_id": {"$in": &company_ids}}).await? {
+        if let Some(company_id) = company.get_id() {
+            companies
+                .entry(company.name().to_lowercase())
+                .or_default()
+                .push(*company_id);
+        }
+    }
+    let projects =
+        db_entities::CompanyProject::find_many(doc! {"company_id": {"$in": &company_ids}})
+            .await?
+            .into_iter()
+            .filter_map(|project| {
+                Some((
+                    (*project.company_id(), project.name().to_lowercase()),
+                    *project.get_id()?,
+                ))
+            })
+            .collect::<HashMap<(DocumentId, String), DocumentId>>();
+    let activities =
+        db_entities::ProjectActivity::find_many(doc! {"company_id": {"$in": &company_ids}})
+            .await?
+            .into_iter()
+            .filter_map(|activity| {
+                Some((
+                    (*activity.company_id(), activity.name().to_lowercase()),
+                    *activity.get_id()?,
+                ))
+            })
+            .collect::<HashMap<(DocumentId, String), DocumentId>>();
+
+    let mut error = |row: usize, field: &str, rule: &str, message: String| {
+        errors.push(TimesheetImportRowError {
+            row,
+            violation: ValidationViolation::new(field, rule, message),
+        })
+    };
+    let mut timezones = HashMap::new();
+    let mut days_to_save = vec![];
+    let mut imported_activities = 0;
+    for day in days {
+        let mut day_activities = vec![];
+        for activity in day.activities.iter() {
+            let company_id = match DocumentId::parse_str(&activity.company) {
+                Ok(company_id) if company_ids.contains(&company_id) => company_id,
+                _ => match companies
+                    .get(&activity.company.to_lowercase())
+                    .map(Vec::as_slice)
+                {
+                    Some([company_id]) => *company_id,
+                    Some([_, _, ..]) => {
+                        error(
+                            activity.row,
+                            "company",
+                            "ambiguous",
+                            format!(
+                                "The user belongs to several companies named {}, use the company id",
+                                activity.company
+                            ),
+                        );
+                        continue;
+                    }
+                    _ => {
+                        error(
+                            activity.row,
+                            "company",
+                            "notFound",
+                            format!("The user does not belong to company {}", activity.company),
+                        );
+                        continue;
+                    }
+                },
+            };
+            let Some(project_id) = projects.get(&(company_id, activity.project.to_lowercase()))
+            else {
+                error(
+                    activity.row,
+                    "project",
+                    "notFound",
+                    format!(
+                        "Project {} does not exist in company {}",
+                        activity.project, activity.company
+                    ),
+                );
+                continue;
+            };
+            let Some(activity_id) = activities.get(&(company_id, activity.activity.to_lowercase()))
+            else {
+                error(
+                    activity.row,
+                    "activity",
+                    "notFound",
+                    format!(
+                        "Activity {} does not exist in company {}",
+                        activity.activity, activity.company
+                    ),
+                );
+                continue;
+            };
+            day_activities.push(TimesheetActivityHours {
+                company_id,
+                project_id: *project_id,
+                activity_id: *activity_id,
+                notes: activity.notes.clone(),
+                hours: activity.hours,
+            });
+        }
+        if day_activities.len() != day.activities.len() {
+            continue;
+        }
+
+        // the day starts at midnight in the timezone of its company
+        let Some(company_id) = day_activities
+            .first()
+            .map(|activity| activity.company_id)
+            .or(company_ids.first().copied())
+        else {
+            error(
+                day.row,
+                "company",
+                "required",
+                "The user does not belong to any company".into(),
+            );
+            continue;
+        };
+        if let Entry::Vacant(entry) = timezones.entry(company_id) {
+            entry.insert(company::get_company_settings(&company_id).await?.tz());
+        }
+        let Ok(date) = local_midnight(&timezones[&company_id], day.date) else {
+            error(
+                day.row,
+                "date",
+                "format",
+                format!("Invalid date {}", day.date),
+            );
+            continue;
+        };
+
+        // the file has no overtime column, so the flag of the replaced day is kept
+        let overtime = db_entities::TimesheetDay::find_one(doc! {"user_id": user_id, "date": date})
+            .await?
+            .is_some_and(|existing_day| *existing_day.overtime());
+        let violations =
+            validate_day(user_id, &date, day.working_type, &day_activities, overtime).await?;
+        if !violations.is_empty() {
+            for violation in violations {
+                // violations of an activity line are reported on the row of the activity
+                let row = violation
+                    .field
+                    .strip_prefix("activities[")
+                    .and_then(|field| field.split(']').next())
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .and_then(|index| day.activities.get(index))
+                    .map_or(day.row, |activity| activity.row);
+                error(row, &violation.field, &violation.rule, violation.message);
+            }
+            continue;
+        }
+        imported_activities += day_activities.len();
+        days_to_save.push((
+            date,
+            day.permit_hours,
+            day.working_type,
+            day_activities,
+            overtime,
+        ));
+    }
+    errors.sort_by_key(|error| error.row);
+
+    let committed = !dry_run && errors.is_empty() && !days_to_save.is_empty();
+    let imported_days = days_to_save.len();
+    if committed {
+        let project_ids = days_to_save
+            .iter()
+            .flat_map(|(_, _, _, activities, _)| {
+                activities.iter().map(|activity| activity.project_id)
+            })
+            .collect();
+        let db_service = get_database_service().await;
+        let mut transaction = db_service.new_transaction().await?;
+        transaction.start_transaction().await?;
+        for (date, permit_hours, working_type, activities, overtime) in days_to_save {
+            if let Err(e) = save_day(
+                *user_id,
+                date,
+                permit_hours,
+                working_type,
+                activities,
+                overtime,
+                Some(&mut transaction),
+            )
+            .await
+            {
+                transaction.abort_transaction().await?;
+                return Err(e);
+            }
+        }
+        transaction.commit_transaction().await?;
+        notify_budget_thresholds(project_ids).await;
+    }
+
+    Ok(TimesheetImportReport {
+        dry_run,
+        committed,
+        days: imported_days,
+        activities: imported_activities,
+        errors,
+    })
+}
+
+/// Create a timesheet template of the user, its activities must be loggable by the user
+pub async fn create_template(
+    user_id: DocumentId,
+    name: String,
+    working_type: WorkingDayType,
+    activities: Vec<TimesheetActivityHours>,
+) -> Result<String, ServiceAppError> {
+    let mut violations = check_activities(&user_id, &activities).await?;
+    if name.trim().is_empty() {
+        violations.push(ValidationViolation::new(
+            "name",
+            "required",
+            "The name of the template is required".into(),
+        ));
+    }
+    if !violations.is_empty() {
+        return Err(ServiceAppError::ValidationError(violations));
+    }
+    db_entities::TimesheetTemplate::new(
+        user_id,
+        name,
+        working_type,
+        activities
+            .into_iter()
+            .map(|e| e.into())
+            .collect::<Vec<db_entities::TimesheetActivityHours>>(),
+    )
+    .save(None)
+    .await
+}
+
+pub async fn get_templates(
+    user_id: &DocumentId,
+) -> Result<Vec<db_entities::TimesheetTemplate>, ServiceAppError> {
+    db_entities::TimesheetTemplate::find_many(doc! {"user_id": user_id}).await
+}
+
+async fn get_template(
+    user_id: &DocumentId,
+    template_id: &DocumentId,
+) -> Result<db_entities::TimesheetTemplate, ServiceAppError> {
+    db_entities::TimesheetTemplate::find_one(doc! {"_id": template_id, "user_id": user_id})
+        .await?
+        .ok_or(ServiceAppError::EntityDoesNotExist(format!(
+            "Timesheet template {template_id} does not exist"
+        )))
+}
+
+pub async fn delete_template(
+    user_id: &DocumentId,
+    template_id: &DocumentId,
+) -> Result<(), ServiceAppError> {
+    get_template(user_id, template_id).await?.delete(None).await
+}
+
+/// Maximum number of days that can be created at once
+const MAX_BULK_DAYS: u64 = 366;
+
+/// Timesheet day to create by a bulk operation
+struct BulkDay {
+    date: NaiveDate,
+    permit_hours: u32,
+    working_type: WorkingDayType,
+    activities: Vec<TimesheetActivityHours>,
+    overtime: bool,
+}
+
+/// Write the activities of the template in every day between `from` and `to`, both included.
+///
+/// See [create_days_in_bulk] for the days that are skipped.
+pub async fn apply_template(
+    user_id: &DocumentId,
+    template_id: &DocumentId,
+    from: NaiveDate,
+    to: NaiveDate,
+    overwrite: bool,
+) -> Result<TimesheetBulkReport, ServiceAppError> {
+    let template = get_template(user_id, template_id).await?;
+    if to < from || (to - from).num_days() as u64 >= MAX_BULK_DAYS {
+        return Err(ServiceAppError::InvalidRequest(format!(
+            "Invalid date range. Got from: {from} and to: {to}, at most {MAX_BULK_DAYS} days can be filled at once"
+        )));
+    }
+    let days = from
+        .iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| BulkDay {
+            date,
+            permit_hours: 0,
+            working_type: *template.working_type(),
+            activities: template
+                .activities()
+                .iter()
+                .cloned()
+                .map(|e| e.into())
+                .collect(),
+            overtime: false,
+        })
+        .collect();
+    create_days_in_bulk(user_id, days, overwrite).await
+}
+
+/// Copy the previous day or week of the user into the day or the week of `date`.
+///
+/// The previous day is the last day logged before `date`, while the previous week is copied
+/// day by day on the same weekdays. Holidays and closure days are never copied.
+/// See [create_days_in_bulk] for the days that are skipped.
+pub async fn copy_previous(
+    user_id: &DocumentId,
+    period: TimesheetCopyPeriod,
+    date: NaiveDate,
+    overwrite: bool,
+) -> Result<TimesheetBulkReport, ServiceAppError> {
+    let tz = get_day_timezone(user_id, &[]).await?;
+    let (source_from, target_start, shift) = match period {
+        // a month back is enough to find the last logged day
+        TimesheetCopyPeriod::Day => (date - Days::new(31), date, None),
+        TimesheetCopyPeriod::Week => {
+            let monday = date - Days::new(date.weekday().num_days_from_monday() as u64);
+            (monday - Days::new(7), monday, Some(Days::new(7)))
+        }
+    };
+    let mut source_days = db_entities::TimesheetDay::find_many(doc! {
+        "user_id": user_id,
+        "date": {
+            "$gte": local_midnight(&tz, source_from)?,
+            "$lt": local_midnight(&tz, target_start)?,
+        },
+    })
+    .await?
+    .into_iter()
+    .filter(|day| {
+        !matches!(
+            day.working_type(),
+            WorkingDayType::Holiday | WorkingDayType::CompanyClosure
+        )
+    })
+    .collect::<Vec<db_entities::TimesheetDay>>();
+    source_days.sort_by_key(|day| *day.date());
+    if shift.is_none() {
+        source_days = source_days.pop().into_iter().collect();
+    }
+
+    let mut days = vec![];
+    for source_day in source_days {
+        let activities = source_day
+            .activities()
+            .iter()
+            .cloned()
+            .map(|e| e.into())
+            .collect::<Vec<TimesheetActivityHours>>();
+        let source_date = source_day
+            .date()
+            .with_timezone(&get_day_timezone(user_id, &activities).await?)
+            .date_naive();
+        days.push(BulkDay {
+            date: shift.map_or(date, |shift| source_date + shift),
+            permit_hours: *source_day.permit_hours(),
+            working_type: *source_day.working_type(),
+            activities,
+            overtime: *source_day.overtime(),
+        });
+    }
+    create_days_in_bulk(user_id, days, overwrite).await
+}
 
-    // create columns
-    let columns = [
-        "Date",
-        "Work Type",
-        "Permission hours",
-        "Company",
-        "Project",
-        "Activity",
-        "Hours",
-        "Notes",
-    ];
-    for (column_index, &column) in columns.iter().enumerate() {
-        worksheet.write_with_format(0, column_index as u16, column, &bold_format)?;
+/// Returns the timezone of the day, it is the timezone of the first company of the day
+/// or UTC when the user does not belong to any company
+async fn get_day_timezone(
+    user_id: &DocumentId,
+    activities: &[TimesheetActivityHours],
+) -> Result<Tz, ServiceAppError> {
+    match get_day_company_ids(user_id, activities).await?.first() {
+        Some(company_id) => Ok(company::get_company_settings(company_id).await?.tz()),
+        None => Ok(Tz::UTC),
     }
+}
 
-    // create a caches to store temporary company, project and activity names read from db
-    // to avoid useless reads
-    let mut company_cache: HashMap<&DocumentId, db_entities::Company> = HashMap::new();
-    let mut project_cache: HashMap<&DocumentId, db_entities::CompanyProject> = HashMap::new();
-    let mut activity_cache: HashMap<&DocumentId, db_entities::ProjectActivity> = HashMap::new();
+/// Create the timesheet days in a single transaction.
+///
+/// Days in the calendar of their companies, i.e. holidays and closure days, are skipped,
+/// as well as the days that already exist unless `overwrite` is true and the days
+/// that are not valid, e.g. on a non working weekday.
+async fn create_days_in_bulk(
+    user_id: &DocumentId,
+    days: Vec<BulkDay>,
+    overwrite: bool,
+) -> Result<TimesheetBulkReport, ServiceAppError> {
+    let mut report = TimesheetBulkReport::default();
+    let mut skip =
+        |date: NaiveDate, reason: String| report.skipped.push(TimesheetSkippedDay { date, reason });
 
-    let mut current_row = 1;
-    let timesheet_days = get_days(user_id, year, month).await?;
-    for timesheet_day in timesheet_days.iter() {
-        let initial_activities_row = current_row;
-        for activity_doc in timesheet_day.activities() {
-            // Here, we cannot use or_insert_with because we cannot use async closure that are considered unsafe
-            // therefore, we check if the entry exist and if not we perform the query
-
-            if let Entry::Vacant(entry) = company_cache.entry(activity_doc.company_id()) {
-                entry.insert(
-                db_entities::Company::find_one(
-                    doc! {"_id": activity_doc.company_id()})
-                    .await
-                    .map_err(|e| ServiceAppError::InternalServerError(format!("An error occurred when retrieving the company document with id {}. Got error {}", activity_doc.company_id(), e)))?
-                    .ok_or(ServiceAppError::EntityDoesNotExist(format!("Company with id {} does not exist.", activity_doc.company_id())))?
-            );
-            }
-            let company_name = company_cache
-                .get(activity_doc.company_id())
-                .ok_or(ServiceAppError::InternalServerError(format!(
-                    "Company entry with id {} should exist in the cache",
-                    activity_doc.company_id()
-                )))?
-                .name();
-
-            if let Entry::Vacant(entry) = project_cache.entry(activity_doc.project_id()) {
-                entry.insert(
-                db_entities::CompanyProject::find_one(
-                    doc! {"_id": activity_doc.project_id()})
-                    .await
-                    .map_err(|e| ServiceAppError::InternalServerError(format!("An error occurred when retrieving the project document with id {}. Got error {}", activity_doc.project_id(), e)))?
-                    .ok_or(ServiceAppError::EntityDoesNotExist(format!("Project with id {} does not exist.", activity_doc.project_id())))?
-            );
-            }
-            let project_name = project_cache
-                .get(activity_doc.project_id())
-                .ok_or(ServiceAppError::InternalServerError(format!(
-                    "Project entry with id {} should exist in the cache",
-                    activity_doc.project_id()
-                )))?
-                .name();
-
-            if let Entry::Vacant(entry) = activity_cache.entry(activity_doc.activity_id()) {
-                entry.insert(
-                    db_entities::ProjectActivity::find_one(
-                        doc! {"_id": activity_doc.activity_id()})
-                        .await
-                        .map_err(|e| ServiceAppError::InternalServerError(format!("An error occurred when retrieving the activity document with id {}. Got error {}", activity_doc.activity_id(), e)))?
-                        .ok_or(ServiceAppError::EntityDoesNotExist(format!("Activity with id {} does not exist.", activity_doc.activity_id())))?
+    let mut days_to_save = vec![];
+    'days: for day in days {
+        for company_id in get_day_company_ids(user_id, &day.activities).await? {
+            if let Some(calendar_day) = calendar::get_calendar_day(&company_id, day.date).await? {
+                skip(
+                    day.date,
+                    format!(
+                        "The day is {} in the company calendar: {}",
+                        calendar_day.day_type(),
+                        calendar_day.name()
+                    ),
                 );
+                continue 'days;
             }
-            let activity_name = activity_cache
-                .get(activity_doc.activity_id())
-                .ok_or(ServiceAppError::InternalServerError(format!(
-                    "Activity entry with id {} should exist in the cache",
-                    activity_doc.activity_id()
-                )))?
-                .name();
-
-            worksheet.write(current_row, 3, company_name)?;
-            worksheet.write(current_row, 4, project_name)?;
-            worksheet.write(current_row, 5, activity_name)?;
-            worksheet.write(current_row, 6, *activity_doc.hours())?;
-            worksheet.write(current_row, 7, activity_doc.notes())?;
-
-            current_row += 1;
         }
-        // To write date we need to first merge cells with an empty string and then write with the right format
-        if initial_activities_row != (current_row - 1) {
-            worksheet.merge_range(
-                initial_activities_row,
-                0,
-                current_row - 1,
-                0,
-                "",
-                &merge_format,
-            )?;
+        let tz = get_day_timezone(user_id, &day.activities).await?;
+        let Ok(date) = local_midnight(&tz, day.date) else {
+            skip(day.date, format!("Invalid date {}", day.date));
+            continue;
+        };
+        if !overwrite
+            && db_entities::TimesheetDay::count_documents(doc! {"user_id": user_id, "date": date})
+                .await?
+                > 0
+        {
+            skip(day.date, "The day already exists".into());
+            continue;
         }
-        worksheet.write_with_format(
-            initial_activities_row,
-            0,
-            &timesheet_day.date().date_naive(),
-            &date_format,
-        )?;
-        // We can write working type directly as string
-        if initial_activities_row != (current_row - 1) {
-            worksheet.merge_range(
-                initial_activities_row,
-                1,
-                current_row - 1,
-                1,
-                &timesheet_day.working_type().to_string(),
-                &merge_format,
-            )?;
-        } else {
-            worksheet.write(
-                initial_activities_row,
-                1,
-                timesheet_day.working_type().to_string(),
-            )?;
+        let violations = validate_day(
+            user_id,
+            &date,
+            day.working_type,
+            &day.activities,
+            day.overtime,
+        )
+        .await?;
+        if !violations.is_empty() {
+            skip(
+                day.date,
+                violations
+                    .into_iter()
+                    .map(|violation| violation.message)
+                    .collect::<Vec<String>>()
+                    .join(". "),
+            );
+            continue;
         }
-        // To write hours we need to first merge cells with an empty string and then write with the right format
+        days_to_save.push((date, day));
+    }
 
-        if initial_activities_row != (current_row - 1) {
-            worksheet.merge_range(
-                initial_activities_row,
-                2,
-                current_row - 1,
-                2,
-                "",
-                &merge_format,
-            )?;
+    if !days_to_save.is_empty() {
+        let project_ids = days_to_save
+            .iter()
+            .flat_map(|(_, day)| day.activities.iter().map(|activity| activity.project_id))
+            .collect();
+        let db_service = get_database_service().await;
+        let mut transaction = db_service.new_transaction().await?;
+        transaction.start_transaction().await?;
+        for (date, day) in days_to_save {
+            if let Err(e) = save_day(
+                *user_id,
+                date,
+                day.permit_hours,
+                day.working_type,
+                day.activities,
+                day.overtime,
+                Some(&mut transaction),
+            )
+            .await
+            {
+                transaction.abort_transaction().await?;
+                return Err(e);
+            }
+            report.created.push(day.date);
         }
-        worksheet.write(initial_activities_row, 2, *timesheet_day.permit_hours())?;
+        transaction.commit_transaction().await?;
+        notify_budget_thresholds(project_ids).await;
     }
-
-    Ok(workbook.save_to_buffer()?)
+    report.created.sort();
+    report.skipped.sort_by_key(|skipped| skipped.date);
+    Ok(report)
 }
 
 #[cfg(test)]
@@ -264,23 +1843,675 @@ mod tests {
     use std::str::FromStr;
 
     use bson::{doc, oid::ObjectId};
-    use chrono::{TimeZone, Utc};
+    use chrono::{NaiveDate, TimeZone, Utc, Weekday};
+    use rust_xlsxwriter::Workbook;
 
     use crate::{
+        enums::{
+            CalendarDaySource, CompanyRole, TimesheetCopyPeriod, TimesheetExportFormat,
+            WorkingDayType,
+        },
+        error::{ServiceAppError, ValidationViolation},
         model::{
             db_entities::{self, Company, CompanyProject, ProjectActivity},
-            internal::TimesheetActivityHours,
+            internal::{TimesheetActivityHours, TimesheetPeriod},
         },
         service::{
+            calendar,
             db::{get_database_service, DatabaseDocument},
-            timesheet::{create_day, export_as_excel, get_days},
+            timesheet::{
+                apply_template, check_day, copy_previous, create_day, create_template, export,
+                get_consistency_report, get_days, parse_import_rows, read_import_rows,
+                unique_sheet_name, write_timesheet_csv, write_timesheet_ods,
+                write_timesheet_worksheet, ActivityReferences, ExportActivity, ExportDay,
+                TimesheetNames,
+            },
         },
         DocumentId,
     };
 
+    fn export_days() -> Vec<ExportDay> {
+        vec![
+            ExportDay {
+                date: NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(),
+                working_type: WorkingDayType::Office,
+                permit_hours: 2,
+                activities: vec![
+                    ExportActivity {
+                        company: "Company".into(),
+                        project: "Project".into(),
+                        activity: "Development".into(),
+                        hours: 4,
+                        notes: "new \"export\", csv & ods".into(),
+                    },
+                    ExportActivity {
+                        company: "Company".into(),
+                        project: "Project".into(),
+                        activity: "Meeting".into(),
+                        hours: 2,
+                        notes: "".into(),
+                    },
+                ],
+            },
+            ExportDay {
+                date: NaiveDate::from_ymd_opt(2025, 1, 3).unwrap(),
+                working_type: WorkingDayType::DayOff,
+                permit_hours: 0,
+                activities: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn csv_export_test() {
+        let content = String::from_utf8(write_timesheet_csv(&export_days()).unwrap()).unwrap();
+        assert_eq!(
+            content,
+            "Date,Work Type,Permission hours,Company,Project,Activity,Hours,Notes\n\
+            2025-01-02,Office,2,Company,Project,Development,4,\"new \"\"export\"\", csv & ods\"\n\
+            2025-01-02,Office,,Company,Project,Meeting,2,\n\
+            2025-01-03,DayOff,0,,,,,\n"
+        );
+    }
+
+    #[test]
+    fn ods_export_test() {
+        let content = write_timesheet_ods(&export_days()).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(content)).unwrap();
+        assert_eq!(archive.by_index(0).unwrap().name(), "mimetype");
+
+        let mut xml = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("content.xml").unwrap(), &mut xml)
+            .unwrap();
+        assert_eq!(xml.matches("<table:table-row>").count(), 4);
+        assert_eq!(xml.matches("<table:covered-table-cell/>").count(), 3);
+        assert!(xml.contains("new &quot;export&quot;, csv &amp; ods"));
+        assert!(xml.contains(r#"office:date-value="2025-01-03""#));
+    }
+
+    #[test]
+    fn import_round_trip_test() {
+        let csv = write_timesheet_csv(&export_days()).unwrap();
+        let ods = write_timesheet_ods(&export_days()).unwrap();
+        let mut workbook = Workbook::new();
+        write_timesheet_worksheet(workbook.add_worksheet(), &export_days()).unwrap();
+        let xlsx = workbook.save_to_buffer().unwrap();
+
+        for (content, format) in [
+            (csv, TimesheetExportFormat::Csv),
+            (xlsx, TimesheetExportFormat::Xlsx),
+            (ods, TimesheetExportFormat::Ods),
+        ] {
+            let (days, errors) = parse_import_rows(read_import_rows(&content, format).unwrap());
+            assert!(errors.is_empty(), "{format:?}");
+            assert_eq!(days.len(), 2, "{format:?}");
+            assert_eq!(days[0].date, NaiveDate::from_ymd_opt(2025, 1, 2).unwrap());
+            assert_eq!(days[0].permit_hours, 2);
+            assert_eq!(days[0].activities.len(), 2);
+            assert_eq!(days[0].activities[0].notes, "new \"export\", csv & ods");
+            assert_eq!(days[0].activities[1].row, 3);
+            assert_eq!(days[0].activities[1].hours, 2);
+            assert_eq!(days[1].working_type, WorkingDayType::DayOff);
+            assert!(days[1].activities.is_empty());
+        }
+    }
+
+    #[test]
+    fn import_row_errors_test() {
+        let rows = [
+            ",Office,,Company,Project,Development,4,",
+            "2025-01-32,Office,,,,,,",
+            "2025-01-02,Travel,,,,,,",
+            "2025-01-03,Office,1,Company,,Development,two,",
+            "2025-01-03,,,Company,Project,Meeting,2,",
+            "",
+            "2025-01-04,Remote,0,,,,,",
+            "2025-01-03,Office,0,,,,,",
+        ]
+        .iter()
+        .map(|row| row.split(',').map(String::from).collect())
+        .collect();
+        let (days, errors) = parse_import_rows(rows);
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].activities.len(), 1);
+        assert_eq!(days[0].activities[0].activity, "Meeting");
+        let errors = errors
+            .iter()
+            .map(|error| {
+                (
+                    error.row,
+                    error.violation.field.as_str(),
+                    error.violation.rule.as_str(),
+                )
+            })
+            .collect::<Vec<(usize, &str, &str)>>();
+        assert_eq!(
+            errors,
+            vec![
+                (2, "date", "required"),
+                (3, "date", "format"),
+                (4, "workType", "format"),
+                (5, "project", "required"),
+                (5, "hours", "format"),
+                (9, "date", "duplicate"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unique_sheet_name_test() {
+        let used = vec!["Summary".to_string(), "Rossi Mario".to_string()];
+        assert_eq!(unique_sheet_name("Bianchi Anna", &used), "Bianchi Anna");
+        assert_eq!(unique_sheet_name("rossi mario", &used), "rossi mario (2)");
+        assert_eq!(
+            unique_sheet_name("O'Neil [Dev]: a/b", &used),
+            "O'Neil _Dev__ a_b"
+        );
+        assert_eq!(unique_sheet_name("", &used), "Employee");
+
+        let long_name = "Wolfeschlegelsteinhausenbergerdorff Hubert";
+        let first = unique_sheet_name(long_name, &used);
+        assert_eq!(first.chars().count(), 31);
+        let second = unique_sheet_name(long_name, &[first]);
+        assert_eq!(second.chars().count(), 31);
+        assert!(second.ends_with(" (2)"));
+    }
+
+    #[test]
+    fn check_day_test() {
+        let settings = db_entities::CompanySettings::new(
+            DocumentId::new(),
+            8,
+            vec![Weekday::Mon, Weekday::Tue],
+            "Europe/Rome".into(),
+            10,
+            vec![WorkingDayType::Office, WorkingDayType::Holiday],
+        );
+        // 2025-01-05T23:30:00Z is Monday in Rome while it is still Sunday in UTC
+        let monday = Utc.with_ymd_and_hms(2025, 1, 5, 23, 30, 0).unwrap();
+        assert!(check_day(&settings, None, &monday, WorkingDayType::Office, 8, false).is_empty());
+
+        let sunday = Utc.with_ymd_and_hms(2025, 1, 5, 10, 0, 0).unwrap();
+        let rules = check_day(&settings, None, &sunday, WorkingDayType::Remote, 11, false)
+            .into_iter()
+            .map(|violation| violation.rule)
+            .collect::<Vec<String>>();
+        assert_eq!(
+            rules,
+            vec!["allowedWorkingTypes", "maxDailyHours", "workingWeekdays"]
+        );
+
+        // overtime allows to work on non working days
+        assert!(check_day(&settings, None, &sunday, WorkingDayType::Office, 4, true).is_empty());
+
+        let holiday = db_entities::CompanyCalendarDay::new(
+            *settings.company_id(),
+            Utc.with_ymd_and_hms(2025, 1, 6, 0, 0, 0).unwrap(),
+            WorkingDayType::Holiday,
+            "Epiphany".into(),
+            CalendarDaySource::NationalRuleSet,
+        );
+        let rules = check_day(
+            &settings,
+            Some(&holiday),
+            &monday,
+            WorkingDayType::Office,
+            8,
+            false,
+        )
+        .into_iter()
+        .map(|violation| violation.rule)
+        .collect::<Vec<String>>();
+        assert_eq!(rules, vec!["calendar"]);
+        assert!(check_day(
+            &settings,
+            Some(&holiday),
+            &monday,
+            WorkingDayType::Holiday,
+            0,
+            false
+        )
+        .is_empty());
+    }
+
+    async fn allocate_user(
+        user_id: DocumentId,
+        company_id: DocumentId,
+        project_ids: Vec<DocumentId>,
+    ) {
+        db_entities::UserCompanyAssignment::new(
+            user_id,
+            company_id,
+            CompanyRole::User,
+            "Developer".into(),
+            project_ids,
+        )
+        .save(None)
+        .await
+        .unwrap();
+    }
+
+    async fn assign_activities(project_id: DocumentId, activity_ids: Vec<DocumentId>) {
+        db_entities::ProjectActivityAssignment::new(project_id, activity_ids)
+            .save(None)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn activity_references_test() {
+        let user_id = DocumentId::new();
+        let company_id = DocumentId::new();
+        let project_id = DocumentId::new();
+        let other_project_id = DocumentId::new();
+        let activity_id = DocumentId::new();
+        let other_activity_id = DocumentId::new();
+
+        let mut references = ActivityReferences::default();
+        references.assignments.insert(
+            (user_id, company_id),
+            db_entities::UserCompanyAssignment::new(
+                user_id,
+                company_id,
+                CompanyRole::User,
+                "Developer".into(),
+                vec![project_id],
+            ),
+        );
+        for id in [project_id, other_project_id] {
+            let mut project =
+                CompanyProject::new("Project".into(), "code".into(), company_id, true, None);
+            project.set_id(&id.to_hex()).unwrap();
+            references.projects.insert(id, project);
+        }
+        for id in [activity_id, other_activity_id] {
+            let mut activity = ProjectActivity::new("Activity".into(), "".into(), company_id, true);
+            activity.set_id(&id.to_hex()).unwrap();
+            references.activities.insert(id, activity);
+        }
+        references
+            .project_activity_ids
+            .insert(project_id, vec![activity_id]);
+
+        assert!(references
+            .check(0, &user_id, &company_id, &project_id, &activity_id)
+            .is_empty());
+
+        let rules = |violations: Vec<ValidationViolation>| {
+            violations
+                .into_iter()
+                .map(|violation| (violation.field, violation.rule))
+                .collect::<Vec<(String, String)>>()
+        };
+        assert_eq!(
+            rules(references.check(1, &user_id, &company_id, &other_project_id, &activity_id)),
+            vec![
+                ("activities[1].projectId".into(), "allocation".into()),
+                ("activities[1].activityId".into(), "assignment".into()),
+            ]
+        );
+        assert_eq!(
+            rules(references.check(0, &user_id, &company_id, &project_id, &other_activity_id)),
+            vec![("activities[0].activityId".into(), "assignment".into())]
+        );
+        assert_eq!(
+            rules(references.check(
+                0,
+                &DocumentId::new(),
+                &DocumentId::new(),
+                &project_id,
+                &activity_id
+            )),
+            vec![
+                ("activities[0].companyId".into(), "companyAssignment".into()),
+                ("activities[0].projectId".into(), "notFound".into()),
+                ("activities[0].activityId".into(), "notFound".into()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn activity_validation_and_consistency_report_test() {
+        let user_id = DocumentId::new();
+        let mut company = Company::new("Company".into(), true);
+        let company_id = ObjectId::from_str(&company.save(None).await.unwrap()).unwrap();
+        let mut project =
+            CompanyProject::new("Project".into(), "code".into(), company_id, true, None);
+        let project_id = ObjectId::from_str(&project.save(None).await.unwrap()).unwrap();
+        let mut other_project = CompanyProject::new(
+            "Other project".into(),
+            "other".into(),
+            company_id,
+            true,
+            None,
+        );
+        let other_project_id =
+            ObjectId::from_str(&other_project.save(None).await.unwrap()).unwrap();
+        let mut activity =
+            ProjectActivity::new("Activity".into(), "description".into(), company_id, true);
+        let activity_id = ObjectId::from_str(&activity.save(None).await.unwrap()).unwrap();
+        allocate_user(user_id, company_id, vec![project_id]).await;
+        assign_activities(project_id, vec![activity_id]).await;
+        assign_activities(other_project_id, vec![activity_id]).await;
+
+        let date = Utc.with_ymd_and_hms(2025, 1, 2, 0, 0, 0).unwrap();
+        let result = create_day(
+            user_id,
+            date,
+            0,
+            WorkingDayType::Office,
+            vec![TimesheetActivityHours {
+                company_id,
+                project_id: other_project_id,
+                activity_id,
+                notes: "".into(),
+                hours: 4,
+            }],
+            false,
+        )
+        .await;
+        match result {
+            Err(ServiceAppError::ValidationError(violations)) => {
+                assert_eq!(violations.len(), 1);
+                assert_eq!(violations[0].rule, "allocation");
+            }
+            _ => panic!("the day should not be valid"),
+        }
+
+        // a day saved before the validation was introduced
+        db_entities::TimesheetDay::new(
+            user_id,
+            date,
+            0,
+            WorkingDayType::Office,
+            vec![db_entities::TimesheetActivityHours::new(
+                company_id,
+                other_project_id,
+                activity_id,
+                "".into(),
+                4,
+            )],
+            false,
+        )
+        .save(None)
+        .await
+        .unwrap();
+        let report = get_consistency_report(
+            &company_id,
+            NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 1, 31).unwrap(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].user_id, user_id);
+        assert_eq!(report[0].violations[0].field, "activities[0].projectId");
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn template_and_copy_test() {
+        let user_id = DocumentId::new();
+        let mut company = Company::new("Company".into(), true);
+        let company_id = ObjectId::from_str(&company.save(None).await.unwrap()).unwrap();
+        let mut project =
+            CompanyProject::new("Project".into(), "code".into(), company_id, true, None);
+        let project_id = ObjectId::from_str(&project.save(None).await.unwrap()).unwrap();
+        let mut activity =
+            ProjectActivity::new("Activity".into(), "description".into(), company_id, true);
+        let activity_id = ObjectId::from_str(&activity.save(None).await.unwrap()).unwrap();
+        allocate_user(user_id, company_id, vec![project_id]).await;
+        assign_activities(project_id, vec![activity_id]).await;
+        calendar::add_closure_day(
+            company_id,
+            NaiveDate::from_ymd_opt(2025, 1, 7).unwrap(),
+            "Inventory".into(),
+        )
+        .await
+        .unwrap();
+
+        let template_id = create_template(
+            user_id,
+            "Standard week".into(),
+            WorkingDayType::Office,
+            vec![TimesheetActivityHours {
+                company_id,
+                project_id,
+                activity_id,
+                notes: "development".into(),
+                hours: 8,
+            }],
+        )
+        .await
+        .unwrap();
+        let template_id = ObjectId::from_str(&template_id).unwrap();
+
+        // from Monday to Sunday, the closure day and the weekend are skipped
+        let report = apply_template(
+            &user_id,
+            &template_id,
+            NaiveDate::from_ymd_opt(2025, 1, 6).unwrap(),
+            NaiveDate::from_ymd_opt(2025, 1, 12).unwrap(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.created.len(), 4);
+        assert_eq!(report.skipped.len(), 3);
+
+        let report = copy_previous(
+            &user_id,
+            TimesheetCopyPeriod::Week,
+            NaiveDate::from_ymd_opt(2025, 1, 15).unwrap(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            report.created,
+            [13, 15, 16, 17]
+                .map(|day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap())
+                .to_vec()
+        );
+
+        // the day before is copied only when the target day does not exist yet
+        let report = copy_previous(
+            &user_id,
+            TimesheetCopyPeriod::Day,
+            NaiveDate::from_ymd_opt(2025, 1, 16).unwrap(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(report.created.is_empty());
+        assert_eq!(report.skipped[0].reason, "The day already exists");
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+
+    #[test]
+    fn period_date_range_test() {
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        assert_eq!(
+            TimesheetPeriod::Month {
+                year: 2024,
+                month: 12
+            }
+            .date_range(),
+            Some((date(2024, 12, 1), date(2024, 12, 31)))
+        );
+        assert_eq!(
+            TimesheetPeriod::Month {
+                year: 2024,
+                month: 2
+            }
+            .date_range(),
+            Some((date(2024, 2, 1), date(2024, 2, 29)))
+        );
+        assert_eq!(
+            TimesheetPeriod::Month {
+                year: 2024,
+                month: 13
+            }
+            .date_range(),
+            None
+        );
+        // the first ISO week of 2025 starts in 2024
+        assert_eq!(
+            TimesheetPeriod::IsoWeek {
+                year: 2025,
+                week: 1
+            }
+            .date_range(),
+            Some((date(2024, 12, 30), date(2025, 1, 5)))
+        );
+        // 2020 has 53 ISO weeks, the last one ends in 2021
+        assert_eq!(
+            TimesheetPeriod::IsoWeek {
+                year: 2020,
+                week: 53
+            }
+            .date_range(),
+            Some((date(2020, 12, 28), date(2021, 1, 3)))
+        );
+        assert_eq!(
+            TimesheetPeriod::IsoWeek {
+                year: 2021,
+                week: 53
+            }
+            .date_range(),
+            None
+        );
+        assert_eq!(
+            TimesheetPeriod::Range {
+                from: date(2025, 1, 2),
+                to: date(2025, 1, 1)
+            }
+            .date_range(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn year_boundary_days_test() {
+        let user_id = DocumentId::new();
+        let company_id = DocumentId::new();
+        allocate_user(user_id, company_id, vec![]).await;
+        db_entities::CompanySettings::new(
+            company_id,
+            8,
+            vec![Weekday::Mon, Weekday::Tue, Weekday::Wed],
+            "Europe/Rome".into(),
+            10,
+            vec![WorkingDayType::Remote],
+        )
+        .save(None)
+        .await
+        .unwrap();
+
+        // local midnight of Tuesday 31st December and Wednesday 1st January in Rome
+        for date in [
+            Utc.with_ymd_and_hms(2024, 12, 30, 23, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 12, 31, 23, 0, 0).unwrap(),
+        ] {
+            create_day(user_id, date, 0, WorkingDayType::Remote, vec![], false)
+                .await
+                .unwrap();
+        }
+
+        let december = get_days(
+            &user_id,
+            &TimesheetPeriod::Month {
+                year: 2024,
+                month: 12,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(december.len(), 1);
+        let january = get_days(
+            &user_id,
+            &TimesheetPeriod::Month {
+                year: 2025,
+                month: 1,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(january.len(), 1);
+        let first_week = get_days(
+            &user_id,
+            &TimesheetPeriod::IsoWeek {
+                year: 2025,
+                week: 1,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(first_week.len(), 2);
+        let range = get_days(
+            &user_id,
+            &TimesheetPeriod::Range {
+                from: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+                to: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(range.len(), 1);
+        assert!(get_days(
+            &user_id,
+            &TimesheetPeriod::Range {
+                from: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                to: NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+            },
+        )
+        .await
+        .is_err());
+
+        let drop_result = get_database_service().await.db.drop().await;
+        assert!(drop_result.is_ok());
+    }
+
     #[tokio::test]
     async fn create_and_get_day_test() {
         let user_id = DocumentId::new();
+        let mut company = Company::new("Company".into(), true);
+        let company_id = ObjectId::from_str(&company.save(None).await.unwrap()).unwrap();
+        let mut first_project = CompanyProject::new(
+            "First project".into(),
+            "first".into(),
+            company_id,
+            true,
+            None,
+        );
+        let first_project_id =
+            ObjectId::from_str(&first_project.save(None).await.unwrap()).unwrap();
+        let mut second_project = CompanyProject::new(
+            "Second project".into(),
+            "second".into(),
+            company_id,
+            true,
+            None,
+        );
+        let second_project_id =
+            ObjectId::from_str(&second_project.save(None).await.unwrap()).unwrap();
+        let mut activity =
+            ProjectActivity::new("Activity".into(), "description".into(), company_id, true);
+        let activity_id = ObjectId::from_str(&activity.save(None).await.unwrap()).unwrap();
+        allocate_user(
+            user_id,
+            company_id,
+            vec![first_project_id, second_project_id],
+        )
+        .await;
+        assign_activities(first_project_id, vec![activity_id]).await;
+        assign_activities(second_project_id, vec![activity_id]).await;
         let first_day = Utc
             .with_ymd_and_hms(2025, 1, 1, 0, 0, 0)
             .earliest()
@@ -300,20 +2531,21 @@ mod tests {
             crate::enums::WorkingDayType::Office,
             vec![
                 TimesheetActivityHours {
-                    company_id: DocumentId::new(),
-                    project_id: DocumentId::new(),
-                    activity_id: DocumentId::new(),
+                    company_id,
+                    project_id: first_project_id,
+                    activity_id,
                     notes: "this is my description".into(),
                     hours: 2,
                 },
                 TimesheetActivityHours {
-                    company_id: DocumentId::new(),
-                    project_id: DocumentId::new(),
-                    activity_id: DocumentId::new(),
+                    company_id,
+                    project_id: second_project_id,
+                    activity_id,
                     notes: "this is my second description".into(),
                     hours: 4,
                 },
             ],
+            false,
         )
         .await;
         assert!(result.is_ok());
@@ -331,20 +2563,21 @@ mod tests {
             crate::enums::WorkingDayType::Office,
             vec![
                 TimesheetActivityHours {
-                    company_id: DocumentId::new(),
-                    project_id: DocumentId::new(),
-                    activity_id: DocumentId::new(),
+                    company_id,
+                    project_id: first_project_id,
+                    activity_id,
                     notes: "this is my description".into(),
                     hours: 2,
                 },
                 TimesheetActivityHours {
-                    company_id: DocumentId::new(),
-                    project_id: DocumentId::new(),
-                    activity_id: DocumentId::new(),
+                    company_id,
+                    project_id: second_project_id,
+                    activity_id,
                     notes: "this is my second description".into(),
                     hours: 4,
                 },
             ],
+            false,
         )
         .await;
         assert!(result.is_ok());
@@ -356,31 +2589,56 @@ mod tests {
             crate::enums::WorkingDayType::Office,
             vec![
                 TimesheetActivityHours {
-                    company_id: DocumentId::new(),
-                    project_id: DocumentId::new(),
-                    activity_id: DocumentId::new(),
+                    company_id,
+                    project_id: first_project_id,
+                    activity_id,
                     notes: "this is my description".into(),
                     hours: 2,
                 },
                 TimesheetActivityHours {
-                    company_id: DocumentId::new(),
-                    project_id: DocumentId::new(),
-                    activity_id: DocumentId::new(),
+                    company_id,
+                    project_id: second_project_id,
+                    activity_id,
                     notes: "this is my second description".into(),
                     hours: 4,
                 },
             ],
+            true,
         )
         .await;
         assert!(result.is_ok());
 
-        let docs = get_days(&user_id, &2025, &1).await.unwrap();
+        let docs = get_days(
+            &user_id,
+            &TimesheetPeriod::Month {
+                year: 2025,
+                month: 1,
+            },
+        )
+        .await
+        .unwrap();
         assert_eq!(docs.len(), 2);
 
-        let docs = get_days(&user_id, &2025, &2).await.unwrap();
+        let docs = get_days(
+            &user_id,
+            &TimesheetPeriod::Month {
+                year: 2025,
+                month: 2,
+            },
+        )
+        .await
+        .unwrap();
         assert_eq!(docs.len(), 0);
 
-        let docs = get_days(&user_id, &2025, &3).await.unwrap();
+        let docs = get_days(
+            &user_id,
+            &TimesheetPeriod::Month {
+                year: 2025,
+                month: 3,
+            },
+        )
+        .await
+        .unwrap();
         assert_eq!(docs.len(), 1);
 
         let drop_result = get_database_service().await.db.drop().await;
@@ -401,6 +2659,7 @@ mod tests {
             "first project code".into(),
             first_company_id.clone(),
             true,
+            None,
         );
         let first_project_id =
             ObjectId::from_str(&first_project.save(None).await.unwrap()).unwrap();
@@ -410,15 +2669,32 @@ mod tests {
             "second project code".into(),
             second_company_id.clone(),
             true,
+            None,
         );
         let second_project_id =
             ObjectId::from_str(&second_project.save(None).await.unwrap()).unwrap();
 
-        let mut activity =
-            ProjectActivity::new("Activity".into(), "description".into(), first_company_id);
+        let mut activity = ProjectActivity::new(
+            "Activity".into(),
+            "description".into(),
+            first_company_id,
+            true,
+        );
         let activity_id = ObjectId::from_str(&activity.save(None).await.unwrap()).unwrap();
+        let mut second_activity = ProjectActivity::new(
+            "Activity".into(),
+            "description".into(),
+            second_company_id,
+            true,
+        );
+        let second_activity_id =
+            ObjectId::from_str(&second_activity.save(None).await.unwrap()).unwrap();
 
         let user_id = DocumentId::new();
+        allocate_user(user_id, first_company_id, vec![first_project_id]).await;
+        allocate_user(user_id, second_company_id, vec![second_project_id]).await;
+        assign_activities(first_project_id, vec![activity_id]).await;
+        assign_activities(second_project_id, vec![second_activity_id]).await;
         let first_day = Utc
             .with_ymd_and_hms(2025, 1, 1, 0, 0, 0)
             .earliest()
@@ -445,13 +2721,14 @@ mod tests {
                     hours: 2,
                 },
                 TimesheetActivityHours {
-                    company_id: first_company_id,
+                    company_id: second_company_id,
                     project_id: second_project_id,
-                    activity_id: activity_id,
+                    activity_id: second_activity_id,
                     notes: "this is my second description".into(),
                     hours: 4,
                 },
             ],
+            false,
         )
         .await;
         assert!(result.is_ok());
@@ -471,7 +2748,7 @@ mod tests {
                 TimesheetActivityHours {
                     company_id: second_company_id,
                     project_id: second_project_id,
-                    activity_id: activity_id,
+                    activity_id: second_activity_id,
                     notes: "this is my description".into(),
                     hours: 2,
                 },
@@ -483,6 +2760,7 @@ mod tests {
                     hours: 4,
                 },
             ],
+            false,
         )
         .await;
         assert!(result.is_ok());
@@ -503,17 +2781,49 @@ mod tests {
                 TimesheetActivityHours {
                     company_id: second_company_id,
                     project_id: second_project_id,
-                    activity_id: activity_id,
+                    activity_id: second_activity_id,
                     notes: "this is my second description".into(),
                     hours: 4,
                 },
             ],
+            true,
         )
         .await;
         assert!(result.is_ok());
 
-        let result = export_as_excel(&user_id, &2025, &1).await;
+        let result = export(&user_id, &2025, &1, TimesheetExportFormat::Xlsx).await;
+        assert!(result.is_ok());
+        let result = export(&user_id, &2025, &1, TimesheetExportFormat::Ods).await;
         assert!(result.is_ok());
+        let result = export(&user_id, &2025, &1, TimesheetExportFormat::Csv).await;
+        assert!(result.is_ok());
+        // one record per activity plus the header
+        assert_eq!(
+            String::from_utf8(result.unwrap()).unwrap().lines().count(),
+            5
+        );
+
+        // names of all the days are loaded at once
+        let days = db_entities::TimesheetDay::find_many(doc! {"user_id": user_id})
+            .await
+            .unwrap();
+        let names = TimesheetNames::load(&days).await.unwrap();
+        assert_eq!(
+            names.company_name(&second_company_id).unwrap(),
+            "Second company"
+        );
+        assert_eq!(
+            names.project_name(&first_project_id).unwrap(),
+            "First project"
+        );
+        assert_eq!(
+            names.activity_name(&second_activity_id).unwrap(),
+            "Activity"
+        );
+        assert!(matches!(
+            names.project_name(&DocumentId::new()),
+            Err(ServiceAppError::EntityDoesNotExist(_))
+        ));
 
         let drop_result = get_database_service().await.db.drop().await;
         assert!(drop_result.is_ok());
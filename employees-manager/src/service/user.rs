@@ -11,7 +11,10 @@ use crate::{
     DocumentId,
 };
 
-use super::db::{get_database_service, DatabaseDocument};
+use super::{
+    db::{get_database_service, DatabaseDocument},
+    password_policy,
+};
 
 pub async fn login(username: &str, password: &str) -> Result<db_entities::User, AppError> {
     let query_result: Option<db_entities::User> =
@@ -133,6 +136,9 @@ pub async fn get_user(user_id: &DocumentId) -> Result<db_entities::User, Service
 
 /// Create new user in database and returns his identifier
 /// Attribute `username` is unique therefore, before creating a user we verify it
+///
+/// The password must satisfy the password policy, otherwise
+/// `ServiceAppError::ValidationError` is returned with every violated rule
 pub async fn create_user(
     username: String,
     password: String,
@@ -140,12 +146,29 @@ pub async fn create_user(
     name: String,
     surname: String,
 ) -> Result<String, ServiceAppError> {
+    new_user(username, password, email, name, surname)
+        .await?
+        .save(None)
+        .await
+}
+
+/// Returns the new user, not saved yet, after the checks of `create_user`.
+/// It lets the caller save the user inside its own transaction
+pub async fn new_user(
+    username: String,
+    password: String,
+    email: String,
+    name: String,
+    surname: String,
+) -> Result<db_entities::User, ServiceAppError> {
     #[derive(Serialize, Deserialize, Debug)]
     struct QueryResult {
         username: String,
         email: String,
     }
 
+    password_policy::validate_password(&password, &username, &email)?;
+
     let usernames = db_entities::User::find_many_projection::<QueryResult>(
         doc! {},
         doc! {"username": 1, "email": 1},
@@ -166,7 +189,7 @@ pub async fn create_user(
             )));
         }
     }
-    let mut user_model = db_entities::User::new(
+    Ok(db_entities::User::new(
         email.trim().into(),
         username.trim().into(),
         hash_password(&password)?,
@@ -175,9 +198,7 @@ pub async fn create_user(
         None,
         false,
         true,
-    );
-
-    user_model.save(None).await
+    ))
 }
 
 /// Deactivate user
@@ -477,7 +498,7 @@ mod tests {
     #[tokio::test]
     async fn create_user_test() {
         let username = "johnsmith".into();
-        let password = "dfsf".into();
+        let password = "Horse-Battery-7".into();
         let name = "John".into();
         let surname = "Smith".into();
         let email = "john@smith.com".into();
@@ -485,7 +506,7 @@ mod tests {
         assert!(created_user_result.is_ok());
 
         let username = "johnsmith".into();
-        let password = "ollol".into();
+        let password = "Horse-Battery-8".into();
         let name = "John".into();
         let surname = "Smith".into();
         let email = "john@smith.com".into();